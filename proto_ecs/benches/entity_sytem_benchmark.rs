@@ -2,6 +2,7 @@ use criterion::{criterion_group, criterion_main, Criterion, Throughput};
 use proto_ecs::{
     app::App,
     entities::{
+        archetype::StorageMode,
         entity_spawn_desc::EntitySpawnDescription,
         entity_system::{EntitySystem, DEFAULT_WORLD},
     },
@@ -30,7 +31,7 @@ impl TestAdderLocalSystem for TestAdder {
         _entity_id: EntityID,
         test_number_data_group: &mut TestNumberDataGroup,
     ) {
-        test_number_data_group.num = test_number_data_group.num + 1
+        test_number_data_group.num += 1
     }
 }
 
@@ -49,7 +50,7 @@ impl TestMultiplierLocalSystem for TestMultiplier {
         _entity_id: EntityID,
         test_number_data_group: &mut TestNumberDataGroup,
     ) {
-        test_number_data_group.num = test_number_data_group.num * 2
+        test_number_data_group.num *= 2
     }
 }
 
@@ -57,7 +58,7 @@ impl TestMultiplierLocalSystem for TestMultiplier {
 
 fn entity_system_creation_benchmark(c: &mut Criterion) {
     if !App::is_initialized() {
-        App::initialize();
+        App::initialize().expect("Failed to initialize the app");
     }
 
     let es = EntitySystem::get();
@@ -84,7 +85,7 @@ fn entity_system_creation_benchmark(c: &mut Criterion) {
 
 fn entity_system_step_100_benchmark(c: &mut Criterion) {
     if !App::is_initialized() {
-        App::initialize();
+        App::initialize().expect("Failed to initialize the app");
     }
 
     let es = EntitySystem::get();
@@ -119,7 +120,7 @@ fn entity_system_step_100_benchmark(c: &mut Criterion) {
 
 fn entity_system_step_10k_benchmark(c: &mut Criterion) {
     if !App::is_initialized() {
-        App::initialize();
+        App::initialize().expect("Failed to initialize the app");
     }
 
     let es = EntitySystem::get();
@@ -152,9 +153,47 @@ fn entity_system_step_10k_benchmark(c: &mut Criterion) {
     });
 }
 
+fn entity_system_step_10k_archetype_benchmark(c: &mut Criterion) {
+    if !App::is_initialized() {
+        App::initialize().expect("Failed to initialize the app");
+    }
+
+    let es = EntitySystem::get();
+    es.reset(); // In case other tests happened
+    es.step(0.0, 0.0); // Process reset
+
+    let world_id = es.create_world_with_storage(StorageMode::Archetype);
+    es.step(0.0, 0.0); // Process world creation
+
+    const ENTITIES_NUM: usize = 10_000;
+
+    for _ in 0..ENTITIES_NUM {
+        let mut spawn_desc = EntitySpawnDescription::default();
+        let init_params = Box::new(TestNumberDataGroupArg { num: 1 });
+
+        TestNumberDataGroup::prepare_spawn(&mut spawn_desc, init_params);
+        TestAdder::simple_prepare(&mut spawn_desc);
+        TestMultiplier::simple_prepare(&mut spawn_desc);
+        spawn_desc.check_local_systems_panic();
+
+        spawn_desc.set_name("Test Name".to_owned());
+
+        es.create_entity(world_id, spawn_desc)
+            .expect("Failed to create entity!");
+    }
+
+    let mut group = c.benchmark_group("entity-system-throughput-10k-archetype");
+    group.throughput(Throughput::Elements(ENTITIES_NUM as u64));
+    group.bench_function("Entity System: Step 10k Archetype", |b| {
+        b.iter(|| {
+            es.step(0.0, 0.0);
+        });
+    });
+}
+
 criterion_group!(
     name = benches;
     config = Criterion::default().sample_size(50);
-    targets = entity_system_creation_benchmark, entity_system_step_100_benchmark, entity_system_step_10k_benchmark
+    targets = entity_system_creation_benchmark, entity_system_step_100_benchmark, entity_system_step_10k_benchmark, entity_system_step_10k_archetype_benchmark
 );
 criterion_main!(benches);
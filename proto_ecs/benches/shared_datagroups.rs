@@ -1,8 +1,5 @@
 use ecs_macros::register_datagroup;
-use proto_ecs::{
-    core::casting::CanCast,
-    data_group::{DataGroup, GenericDataGroupInitArgTrait},
-};
+use proto_ecs::{core::casting::CanCast, data_group::GenericDataGroupInitArgTrait};
 
 #[derive(CanCast, Default, Debug)]
 pub struct TestNumberDataGroup {
@@ -16,8 +13,8 @@ pub struct TestNumberDataGroupArg {
 
 impl GenericDataGroupInitArgTrait for TestNumberDataGroupArg {}
 
-fn test_num_factory() -> Box<dyn DataGroup> {
-    return Box::new(TestNumberDataGroup::default());
+fn test_num_factory() -> TestNumberDataGroup {
+    TestNumberDataGroup::default()
 }
 
 impl TestNumberDataGroupDesc for TestNumberDataGroup {
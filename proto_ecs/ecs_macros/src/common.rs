@@ -13,9 +13,9 @@ pub enum InitArgStyle
     /// Init with no Args
     NoArg,
     /// Init with Args
-    Arg(syn::Ident),
+    Arg(syn::Type),
     /// Init with optional Args
-    OptionalArg(syn::Ident),
+    OptionalArg(syn::Type),
 }
 
 impl syn::parse::Parse for InitArgStyle
@@ -29,13 +29,13 @@ impl syn::parse::Parse for InitArgStyle
             "Arg" => {
                 let content;
                 let _ : syn::token::Paren = syn::parenthesized!(content in input); // parenthesis
-                let arg_type : syn::Ident = content.parse()?;
+                let arg_type : syn::Type = content.parse()?;
                 Ok(InitArgStyle::Arg(arg_type))
             },
             "OptionalArg" => {
                 let content;
                 let _ : syn::token::Paren = syn::parenthesized!(content in input); // parenthesis
-                let arg_type : syn::Ident = content.parse()?;
+                let arg_type : syn::Type = content.parse()?;
                 Ok(InitArgStyle::OptionalArg(arg_type))
             },
             unknown => Err(
@@ -107,3 +107,35 @@ pub fn _ensure_struct_implements_trait(struct_id : syn::Ident, trait_id : syn::I
         };
     }.into());
 }
+
+/// Implement `CanCast` for a type. Casting goes through `dyn Any`, so type parameters
+/// get a `'static` bound
+pub fn implement_can_cast(ident : &syn::Ident, generics : &syn::Generics) -> proc_macro2::TokenStream
+{
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!('static));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics proto_ecs::core::casting::CanCast for #ident #ty_generics #where_clause
+        {
+            fn into_any(self: Box<Self>) -> Box<dyn std::any::Any>
+            {
+                self
+            }
+
+            fn as_any(&self) -> &dyn std::any::Any
+            {
+                self as &dyn std::any::Any
+            }
+
+            fn as_any_mut(&mut self) ->&mut dyn std::any::Any
+            {
+                self as &mut dyn std::any::Any
+            }
+
+        }
+    }
+}
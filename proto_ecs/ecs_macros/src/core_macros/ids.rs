@@ -1,4 +1,4 @@
-use quote::quote;
+use quote::{quote, ToTokens};
 use syn;
 
 /// Extend a TokenStream to add implementations for ID related
@@ -9,10 +9,20 @@ use syn;
 pub fn implement_id_traits(
     struct_ident: &syn::Ident,
     program: &mut proc_macro2::TokenStream,
+) -> syn::Ident {
+    implement_id_traits_for_type(struct_ident, struct_ident, program)
+}
+
+/// Like [implement_id_traits], for any type like `path::Struct<u32>`.
+/// The static variable is named after `name_ident`
+pub fn implement_id_traits_for_type(
+    struct_type: &impl ToTokens,
+    name_ident: &syn::Ident,
+    program: &mut proc_macro2::TokenStream,
 ) -> syn::Ident {
     let struct_id_magic_ident = {
-        let name_up = struct_ident.to_string().to_uppercase();
-        syn::Ident::new(&format!("{name_up}_STATIC_ID"), struct_ident.span())
+        let name_up = name_ident.to_string().to_uppercase();
+        syn::Ident::new(&format!("{name_up}_STATIC_ID"), name_ident.span())
     };
 
     program.extend::<proc_macro2::TokenStream>(quote!{
@@ -21,7 +31,7 @@ pub fn implement_id_traits(
 
         // Implement locator trait for registry,
         // it helps you to find the id for a struct using static function calls
-        impl proto_ecs::core::ids::IDLocator for #struct_ident
+        impl proto_ecs::core::ids::IDLocator for #struct_type
         {
             #[inline(always)]
             fn get_id() -> proto_ecs::core::ids::ID
@@ -33,7 +43,7 @@ pub fn implement_id_traits(
         // Implement metadata trait for this struct. It helps you to
         // get the id of a struct instance, so that you can find its
         // static data with the global registry
-        impl proto_ecs::core::ids::HasID for #struct_ident
+        impl proto_ecs::core::ids::HasID for #struct_type
         {
             #[inline(always)]
             fn get_id(&self) -> proto_ecs::core::ids::ID
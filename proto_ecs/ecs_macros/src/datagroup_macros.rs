@@ -4,6 +4,7 @@ use proc_macro;
 use quote::quote;
 use syn::{self, parse::Parse, parse_macro_input};
 use crate::common::*;
+use crate::systems::common::DependencyList;
 
 // -- < Datagroups > -----------------------------------
 
@@ -13,10 +14,46 @@ fn get_datagroup_desc_trait(datagroup: &syn::Ident) -> syn::Ident {
     syn::Ident::new(&format!("{datagroup_str}Desc"), datagroup.span())
 }
 
-/// Register the way a datagroup struct initializes
-fn register_datagroup_init(args: &DatagroupInput, result : &mut proc_macro2::TokenStream) {
-    let datagroup_desc_trait = get_datagroup_desc_trait(&args.datagroup);
+/// Names of a datagroup type: the name it's registered with, like `Foo<u32>` for
+/// `data::Foo<u32>`, and an identifier to name the items generated for it, like `FooU32`
+fn datagroup_names(datagroup: &syn::Type) -> syn::Result<(String, syn::Ident)> {
+    let segment = match datagroup {
+        syn::Type::Group(group) => return datagroup_names(&group.elem),
+        syn::Type::Path(type_path) if type_path.qself.is_none() => type_path.path.segments.last(),
+        _ => None,
+    };
+    let segment = segment.ok_or_else(|| {
+        syn::Error::new_spanned(
+            datagroup,
+            "Expected a datagroup struct, like `MyDataGroup` or `path::MyDataGroup<u32>`",
+        )
+    })?;
+
+    let name = quote!(#segment).to_string().replace(' ', "");
+    let mut ident_str = segment.ident.to_string();
+    if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+        // Foo<Vec<u8>> => FooVecU8
+        let args_str = quote!(#args).to_string();
+        let words = args_str
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty());
+        for word in words {
+            let mut chars = word.chars();
+            ident_str.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+            ident_str.extend(chars);
+        }
+    }
+
+    Ok((name, syn::Ident::new(&ident_str, segment.ident.span())))
+}
 
+/// Register the way a datagroup struct initializes
+fn register_datagroup_init(
+    args: &DatagroupInput,
+    datagroup_str: &str,
+    datagroup_desc_trait: &syn::Ident,
+    result : &mut proc_macro2::TokenStream,
+) {
     let init_fn_trait = args.init_style.to_signature();
 
     let init_fn_arg_trait_check = match &args.init_style {
@@ -86,7 +123,7 @@ fn register_datagroup_init(args: &DatagroupInput, result : &mut proc_macro2::Tok
         InitArgStyle::NoInit => {
             let msg = format!(
                 "Add data group {} to an entity being prepared to spawn",
-                datagroup
+                datagroup_str
             );
 
             quote!(
@@ -99,7 +136,7 @@ fn register_datagroup_init(args: &DatagroupInput, result : &mut proc_macro2::Tok
         InitArgStyle::NoArg => {
             let msg = format!(
                 "Add data group {} to an entity being prepared to spawn. It will init",
-                datagroup
+                datagroup_str
             );
 
             quote!(
@@ -112,7 +149,7 @@ fn register_datagroup_init(args: &DatagroupInput, result : &mut proc_macro2::Tok
         InitArgStyle::Arg(arg) => {
             let msg = format!(
                 "Add data group {} to an entity being prepared to spawn. It will init with arg {}",
-                datagroup, arg
+                datagroup_str, quote!(#arg).to_string().replace(' ', "")
             );
 
             quote!(
@@ -123,17 +160,33 @@ fn register_datagroup_init(args: &DatagroupInput, result : &mut proc_macro2::Tok
             )
         }
         InitArgStyle::OptionalArg(arg) => {
-            let msg = format!("Add data group {} to an entity being prepared to spawn. It will init with optional arg {}", datagroup, arg);
+            let msg = format!("Add data group {} to an entity being prepared to spawn. It will init with optional arg {}", datagroup_str, quote!(#arg).to_string().replace(' ', ""));
 
             quote!(
                 #[doc = #msg]
                 pub fn prepare_spawn(spawn_desc : &mut proto_ecs::entities::entity_spawn_desc::EntitySpawnDescription, arg : std::option::Option<std::boxed::Box<#arg>>) -> std::option::Option<proto_ecs::data_group::DataGroupInitType> {
-                    spawn_desc.add_datagroup::<#datagroup>(proto_ecs::data_group::DataGroupInitType::OptionalArg(arg))
+                    spawn_desc.add_datagroup::<#datagroup>(proto_ecs::data_group::DataGroupInitType::OptionalArg(
+                        arg.map(|arg| arg as proto_ecs::data_group::GenericDataGroupInitArg)
+                    ))
                 }
             )
         }
     };
 
+    let visit_entity_refs_fn = if args.entity_refs.is_empty() {
+        quote! {}
+    } else {
+        let fields = &args.entity_refs;
+        quote! {
+            fn __visit_entity_refs__(&mut self, visitor: &mut dyn FnMut(&mut proto_ecs::entities::entity_ref::EntityRef))
+            {
+                #(
+                    proto_ecs::entities::entity_ref::VisitEntityRefs::visit_entity_refs(&mut self.#fields, visitor);
+                )*
+            }
+        }
+    };
+
     result.extend(quote! {
         #init_fn_arg_trait_check
         trait #datagroup_desc_trait {
@@ -143,6 +196,8 @@ fn register_datagroup_init(args: &DatagroupInput, result : &mut proc_macro2::Tok
         impl proto_ecs::data_group::DataGroup for #datagroup
         {
             #init_fn_internal
+
+            #visit_entity_refs_fn
         }
 
         impl proto_ecs::data_group::DataGroupInitDescTrait for #datagroup
@@ -165,21 +220,25 @@ fn register_datagroup_init(args: &DatagroupInput, result : &mut proc_macro2::Tok
 
 #[derive(Clone)]
 struct DatagroupInput {
-    datagroup: syn::Ident,
-    factory: syn::Ident,
+    datagroup: syn::Type,
+    /// Function, or closure without captures, returning a new instance by value
+    factory: syn::Expr,
     init_style: InitArgStyle,
+    entity_refs: Vec<syn::Member>,
+    cloneable: bool,
+    reflect: bool,
 }
 
 impl Parse for DatagroupInput {
     fn parse(input: syn::parse::ParseStream) -> Result<Self, syn::Error> {
-        let datagroup = input.parse::<syn::Ident>().or_else(|_| 
+        let datagroup = input.parse::<syn::Type>().or_else(|_| 
         {
             return Err(syn::Error::new(input.span(), "Missing Datagroup Struct Identifier"));
         })?;
 
         let _ = input.parse::<syn::token::Comma>()?;
 
-        let factory = input.parse::<syn::Ident>().or_else(|_|
+        let factory = input.parse::<syn::Expr>().or_else(|_|
         {
             return Err(syn::Error::new(input.span(), "Missing factory function argument"));
         })?;
@@ -187,6 +246,9 @@ impl Parse for DatagroupInput {
         let _ = input.parse::<syn::token::Comma>()?;
 
         let mut init_style = None;
+        let mut entity_refs = None;
+        let mut cloneable = None;
+        let mut reflect = None;
 
         loop {
             let keyword_arg = input.parse::<syn::Ident>();
@@ -214,19 +276,68 @@ impl Parse for DatagroupInput {
                     init_style = Some(input.parse::<InitArgStyle>()?);
                 },
 
+                "entity_refs" => {
+
+                    if entity_refs.is_some()
+                    {
+                        return Err(syn::Error::new(
+                            keyword_arg.span(),
+                            "Duplicated keyword argument: entity_refs",
+                        ));
+                    }
+
+                    entity_refs = Some(
+                        input.parse::<DependencyList>()?.0.into_iter().map(syn::Member::Named).collect()
+                    );
+                },
+
+                "cloneable" => {
+
+                    if cloneable.is_some()
+                    {
+                        return Err(syn::Error::new(
+                            keyword_arg.span(),
+                            "Duplicated keyword argument: cloneable",
+                        ));
+                    }
+
+                    cloneable = Some(input.parse::<syn::LitBool>()?.value);
+                },
+
+                "reflect" => {
+
+                    if reflect.is_some()
+                    {
+                        return Err(syn::Error::new(
+                            keyword_arg.span(),
+                            "Duplicated keyword argument: reflect",
+                        ));
+                    }
+
+                    reflect = Some(input.parse::<syn::LitBool>()?.value);
+                },
+
                 _ => {
                     return Err(syn::Error::new(
                         keyword_arg.span(),
-                        "Unexpected keyword. Available keywords = {init_style}")
+                        "Unexpected keyword. Available keywords = {init_style, entity_refs, cloneable, reflect}")
                     )
                 }
             }
+
+            let comma = input.parse::<syn::Token![,]>();
+            if comma.is_err() {
+                break;
+            }
         }
 
         return Ok(
             DatagroupInput { 
                 datagroup, factory, 
-                init_style: init_style.unwrap_or(InitArgStyle::NoInit) 
+                init_style: init_style.unwrap_or(InitArgStyle::NoInit),
+                entity_refs: entity_refs.unwrap_or_default(),
+                cloneable: cloneable.unwrap_or(false),
+                reflect: reflect.unwrap_or(false),
             });
     }
 }
@@ -234,13 +345,46 @@ impl Parse for DatagroupInput {
 /// Register a datagroup struct as a new datagroup class in the global registry
 pub fn register_datagroup(args: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let args = parse_macro_input!(args as DatagroupInput);
-    let DatagroupInput { datagroup, factory , ..} = args.clone();
-    let datagroup_str = datagroup.to_string();
+    expand_datagroup(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generate the registration of a datagroup and its `DataGroup` implementation
+fn expand_datagroup(args: &DatagroupInput) -> syn::Result<proc_macro2::TokenStream> {
+    let DatagroupInput { datagroup, factory, cloneable, reflect, ..} = args;
+    let (datagroup_str, datagroup_name_ident) = datagroup_names(datagroup)?;
     let name_crc = crc32fast::hash(datagroup_str.as_bytes());
-    let datagroup_desc_trait = get_datagroup_desc_trait(&datagroup);
+    let datagroup_desc_trait = get_datagroup_desc_trait(&datagroup_name_ident);
+
+    let clone_const = if *cloneable {
+        quote! {
+            #[doc = "Function to copy instances of this datagroup"]
+            const CLONE : std::option::Option<proto_ecs::data_group::DataGroupCloneFn> = std::option::Option::Some(
+                |datagroup : &dyn proto_ecs::data_group::DataGroup| -> std::boxed::Box<dyn proto_ecs::data_group::DataGroup> {
+                    let datagroup : &#datagroup = proto_ecs::core::casting::cast(datagroup);
+                    std::boxed::Box::new(std::clone::Clone::clone(datagroup))
+                }
+            );
+        }
+    } else {
+        quote! {}
+    };
+
+    let reflection_const = if *reflect {
+        quote! {
+            #[doc = "Reflection of this datagroup's fields"]
+            const REFLECTION : std::option::Option<proto_ecs::data_group::DataGroupReflection> = std::option::Option::Some(
+                proto_ecs::data_group::DataGroupReflection::new::<#datagroup>()
+            );
+        }
+    } else {
+        quote! {}
+    };
 
     let mut result = quote!();
-    let datagroup_id_magic_ident = ids::implement_id_traits(&datagroup, &mut result);
+    let datagroup_id_magic_ident =
+        ids::implement_id_traits_for_type(datagroup, &datagroup_name_ident, &mut result);
 
     result.extend(quote! {
 
@@ -258,7 +402,22 @@ pub fn register_datagroup(args: proc_macro::TokenStream) -> proc_macro::TokenStr
             #[doc = "Name's crc"]
             const NAME_CRC : u32 = #name_crc;
             #[doc = "Factory to create new instances of this datagroup"]
-            const FACTORY : proto_ecs::data_group::DataGroupFactory = #factory;
+            const FACTORY : proto_ecs::data_group::DataGroupFactory =
+                || -> std::boxed::Box<dyn proto_ecs::data_group::DataGroup> {
+                    let factory : fn() -> #datagroup = #factory;
+                    std::boxed::Box::new(factory())
+                };
+            #[doc = "Factory to create new instances of this datagroup in place"]
+            const IN_PLACE_FACTORY : proto_ecs::data_group::DataGroupInPlaceFactory =
+                |dst : *mut u8| -> *mut dyn proto_ecs::data_group::DataGroup {
+                    // Write the new value straight to `dst`, without boxing it first
+                    let factory : fn() -> #datagroup = #factory;
+                    let dst = dst.cast::<#datagroup>();
+                    unsafe { dst.write(factory()) };
+                    dst
+                };
+            #clone_const
+            #reflection_const
         }
 
         // Registration in the global datagroup registry
@@ -269,14 +428,18 @@ pub fn register_datagroup(args: proc_macro::TokenStream) -> proc_macro::TokenStr
                 proto_ecs::data_group::DataGroupRegistry::register_lambda(
                     std::boxed::Box::new(
                         |registry| {
-                            let new_id = registry.register(proto_ecs::data_group::DataGroupRegistryEntry{
+                            registry.register(proto_ecs::data_group::DataGroupRegistryEntry{
                                 name: <#datagroup as proto_ecs::data_group::DatagroupDesc>::NAME,
                                 name_crc: <#datagroup as proto_ecs::data_group::DatagroupDesc>::NAME_CRC,
                                 factory_func: <#datagroup as proto_ecs::data_group::DatagroupDesc>::FACTORY,
+                                in_place_factory_func: <#datagroup as proto_ecs::data_group::DatagroupDesc>::IN_PLACE_FACTORY,
+                                clone_func: <#datagroup as proto_ecs::data_group::DatagroupDesc>::CLONE,
+                                reflection: <#datagroup as proto_ecs::data_group::DatagroupDesc>::REFLECTION,
+                                layout: proto_ecs::data_group::DataGroupLayout::new::<#datagroup>(),
                                 init_desc: <#datagroup as proto_ecs::data_group::DataGroupInitDescTrait>::INIT_DESC,
-                                id: proto_ecs::data_group::DataGroupID::MAX
+                                id: proto_ecs::data_group::DataGroupID::MAX,
+                                set_id_fn: |id| { #datagroup_id_magic_ident.set(id).expect("Failed to register DataGroup ID"); }
                             });
-                            #datagroup_id_magic_ident.set(new_id).expect("Failed to register DataGroup ID");
                         }
                     )
                 );
@@ -284,6 +447,141 @@ pub fn register_datagroup(args: proc_macro::TokenStream) -> proc_macro::TokenStr
         };
     });
 
-    register_datagroup_init(&args, &mut result);
-    return result.into();
+    register_datagroup_init(args, &datagroup_str, &datagroup_desc_trait, &mut result);
+    Ok(result)
+}
+
+// -- < Datagroup derive > -----------------------------------
+
+/// Parse the `#[datagroup(...)]` attributes of a struct deriving `DataGroup`
+fn parse_derive_input(input: &syn::DeriveInput) -> syn::Result<DatagroupInput> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "Generic datagroups can't be derived, register each concrete type with `register_datagroup!` instead",
+        ));
+    }
+
+    let fields = match &input.data {
+        syn::Data::Struct(data) => &data.fields,
+        syn::Data::Enum(data) => {
+            return Err(syn::Error::new_spanned(data.enum_token, "Datagroups should be structs"))
+        }
+        syn::Data::Union(data) => {
+            return Err(syn::Error::new_spanned(data.union_token, "Datagroups should be structs"))
+        }
+    };
+
+    let mut init_style = None;
+    let mut factory: Option<syn::Expr> = None;
+    let mut default_factory: Option<syn::Path> = None;
+    let mut cloneable = false;
+    let mut reflect = false;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("datagroup")) {
+        attr.parse_nested_meta(|meta| {
+            let duplicated = || {
+                let name = meta.path.get_ident().map(|ident| ident.to_string()).unwrap_or_default();
+                meta.error(format!("Duplicated datagroup attribute: {name}"))
+            };
+
+            if meta.path.is_ident("init") {
+                if init_style.is_some() {
+                    return Err(duplicated());
+                }
+                init_style = Some(meta.value()?.parse::<InitArgStyle>()?);
+            } else if meta.path.is_ident("factory") {
+                if factory.is_some() {
+                    return Err(duplicated());
+                }
+                factory = Some(meta.value()?.parse::<syn::Expr>()?);
+            } else if meta.path.is_ident("default_factory") {
+                if default_factory.is_some() {
+                    return Err(duplicated());
+                }
+                default_factory = Some(meta.path.clone());
+            } else if meta.path.is_ident("cloneable") {
+                if cloneable {
+                    return Err(duplicated());
+                }
+                cloneable = true;
+            } else if meta.path.is_ident("reflect") {
+                if reflect {
+                    return Err(duplicated());
+                }
+                reflect = true;
+            } else {
+                return Err(meta.error(
+                    "Unexpected datagroup attribute. Available attributes = {init, factory, default_factory, cloneable, reflect}",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+
+    let factory = match (factory, default_factory) {
+        (Some(_), Some(default_factory)) => {
+            return Err(syn::Error::new_spanned(
+                default_factory,
+                "A datagroup can't have both `factory` and `default_factory`",
+            ))
+        }
+        (Some(factory), None) => factory,
+        (None, _) => syn::parse2(quote::quote_spanned! {ident.span()=>
+            <#ident as std::default::Default>::default
+        })?,
+    };
+
+    let mut entity_refs = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("datagroup")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("entity_ref") {
+                    entity_refs.push(match &field.ident {
+                        Some(field_ident) => syn::Member::Named(field_ident.clone()),
+                        None => syn::Member::Unnamed(syn::Index::from(i)),
+                    });
+                    Ok(())
+                } else {
+                    Err(meta.error("Unexpected datagroup field attribute. Available attributes = {entity_ref}"))
+                }
+            })?;
+        }
+    }
+
+    Ok(DatagroupInput {
+        datagroup: syn::parse_quote!(#ident),
+        factory,
+        init_style: init_style.unwrap_or(InitArgStyle::NoInit),
+        entity_refs,
+        cloneable,
+        reflect,
+    })
+}
+
+fn expand_derive_datagroup(input: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let args = parse_derive_input(input)?;
+    let ident = &input.ident;
+
+    let mut result = implement_can_cast(ident, &input.generics);
+    result.extend(expand_datagroup(&args)?);
+
+    // Implement what register_datagroup! leaves to the user when there's nothing to write by hand.
+    // Init arg types can be shared by many datagroups, so they are marked by hand as well
+    if let InitArgStyle::NoInit = &args.init_style {
+        let datagroup_desc_trait = get_datagroup_desc_trait(ident);
+        result.extend(quote! {
+            impl #datagroup_desc_trait for #ident {}
+        });
+    }
+
+    Ok(result)
+}
+
+/// Implement `DataGroup` for a struct and register it, configured with `#[datagroup(...)]` attributes
+pub fn derive_datagroup(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(item as syn::DeriveInput);
+    expand_derive_datagroup(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
@@ -1,5 +1,4 @@
 use proc_macro;
-use quote::quote;
 use syn::{self, parse_macro_input, DeriveInput};
 
 mod core_macros;
@@ -10,6 +9,7 @@ mod common;
 
 // -- < Datagroups > -----------------------------------
 mod datagroup_macros;
+mod reflect_macros;
 
 /// Register a datagroup struct as a new datagroup class in the global registry
 #[proc_macro]
@@ -17,6 +17,44 @@ pub fn register_datagroup(args: proc_macro::TokenStream) -> proc_macro::TokenStr
     datagroup_macros::register_datagroup(args)
 }
 
+/// Register a struct as a new datagroup class in the global registry, implementing
+/// `CanCast` and everything `register_datagroup!` does.
+///
+/// Struct attributes, all of them optional:
+/// * `init`: How the datagroup initializes, like the `init_style` of `register_datagroup!`. `NoInit` by default
+/// * `factory`: Function returning a new instance of the datagroup
+/// * `default_factory`: Create new instances with `Default`, used when there's no `factory`
+/// * `cloneable`: The datagroup can be copied with `Clone`
+/// * `reflect`: The datagroup fields can be reflected with `Reflect`
+///
+/// Fields marked with `#[datagroup(entity_ref)]` are visited when remapping entity references.
+///
+/// Like with `register_datagroup!`, the init arg type has to implement `GenericDataGroupInitArgTrait`
+/// and the `init` function is implemented by hand.
+///
+/// Example usage:
+/// ```ignore
+/// #[derive(DataGroup, Default, Debug)]
+/// #[datagroup(init = Arg(ExampleArg))]
+/// struct Example {
+///     speed : f32,
+///     #[datagroup(entity_ref)]
+///     target : EntityRef,
+/// }
+///
+/// impl GenericDataGroupInitArgTrait for ExampleArg {}
+///
+/// impl ExampleDesc for Example
+/// {
+///     fn init(&mut self, init_data : Box<ExampleArg>)
+///     { todo!() }
+/// }
+/// ```
+#[proc_macro_derive(DataGroup, attributes(datagroup))]
+pub fn derive_datagroup(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    datagroup_macros::derive_datagroup(item)
+}
+
 // -- < Local systems > --------------------------------------
 
 /// Register a struct as a local system.
@@ -45,6 +83,33 @@ pub fn register_local_system(input: proc_macro::TokenStream) -> proc_macro::Toke
     systems::local_systems_macros::register_local_system(input)
 }
 
+/// Register a function as a local system named like the function.
+///
+/// Dependencies come from the parameters, which can be in any order:
+/// * `&mut DataGroup`: a datagroup the entity is required to have
+/// * `Option<&mut DataGroup>`: an optional datagroup, `None` if the entity doesn't have it
+/// * `&World`: the world the entity lives in
+/// * `EntityID`: the entity running the system
+///
+/// Attribute arguments:
+/// * `stage` or `stages`: stage, or list of stages, the function runs on
+/// * `before` : (optional) List of local systems that should run after this system
+/// * `after` : (optional) List of local systems that should run before this system
+///
+/// Example usage:
+/// ```ignore
+/// #[local_system(stage = 0, after = (Example))]
+/// fn Animate(animation : &mut AnimationDataGroup, mesh : Option<&mut MeshDataGroup>, entity : EntityID)
+/// { todo!() }
+/// ```
+#[proc_macro_attribute]
+pub fn local_system(
+    args: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    systems::local_systems_macros::local_system(args, item)
+}
+
 // -- < Global Systems Macros > ------------------------------
 
 #[proc_macro]
@@ -69,29 +134,15 @@ pub fn register_global_system(args : proc_macro::TokenStream) -> proc_macro::Tok
 
 // -- < Misc macros > ----------------------------------------
 
+/// Implement `Reflect` for a struct with named fields, or an enum without data in its variants.
+/// Fields marked with `#[reflect(skip)]` are not reflected
+#[proc_macro_derive(Reflect, attributes(reflect))]
+pub fn derive_reflect(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    reflect_macros::derive_reflect(item)
+}
+
 #[proc_macro_derive(CanCast)]
 pub fn derive_can_cast(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let DeriveInput { ident, .. } = parse_macro_input!(item);
-
-    return quote! {
-        impl proto_ecs::core::casting::CanCast for #ident
-        {
-            fn into_any(self: Box<Self>) -> Box<dyn std::any::Any>
-            {
-                self
-            }
-
-            fn as_any(&self) -> &dyn std::any::Any
-            {
-                self as &dyn std::any::Any
-            }
-
-            fn as_any_mut(&mut self) ->&mut dyn std::any::Any
-            {
-                self as &mut dyn std::any::Any
-            }
-
-        }
-    }
-    .into();
+    let DeriveInput { ident, generics, .. } = parse_macro_input!(item);
+    common::implement_can_cast(&ident, &generics).into()
 }
@@ -0,0 +1,171 @@
+use quote::quote;
+use syn::{self, parse_macro_input, DeriveInput};
+
+// -- < Reflection > -----------------------------------
+
+/// If a field is marked with `#[reflect(skip)]`
+fn is_skipped(field: &syn::Field) -> syn::Result<bool> {
+    let mut skip = false;
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("reflect")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+                Ok(())
+            } else {
+                Err(meta.error("Unexpected reflect attribute. Available attributes = {skip}"))
+            }
+        })?;
+    }
+    Ok(skip)
+}
+
+fn derive_struct(ident: &syn::Ident, fields: &syn::FieldsNamed) -> syn::Result<proc_macro2::TokenStream> {
+    let ident_str = ident.to_string();
+
+    let mut field_idents = Vec::new();
+    let mut field_types = Vec::new();
+    for field in fields.named.iter() {
+        if !is_skipped(field)? {
+            field_idents.push(field.ident.clone().unwrap());
+            field_types.push(field.ty.clone());
+        }
+    }
+
+    let field_names: Vec<String> = field_idents.iter().map(|field| field.to_string()).collect();
+    let type_names: Vec<String> = field_types
+        .iter()
+        .map(|ty| quote!(#ty).to_string().replace(' ', ""))
+        .collect();
+
+    Ok(quote! {
+        impl proto_ecs::core::reflection::Reflect for #ident
+        {
+            fn type_info() -> &'static proto_ecs::core::reflection::TypeInfo
+            {
+                static INFO : proto_ecs::core::reflection::TypeInfo = proto_ecs::core::reflection::TypeInfo::Struct {
+                    type_name: #ident_str,
+                    fields: &[
+                        #(
+                            proto_ecs::core::reflection::FieldInfo {
+                                name: #field_names,
+                                type_name: #type_names,
+                                offset: std::mem::offset_of!(#ident, #field_idents),
+                                type_info: <#field_types as proto_ecs::core::reflection::Reflect>::type_info,
+                            },
+                        )*
+                    ],
+                };
+                &INFO
+            }
+
+            fn reflect_type_info(&self) -> &'static proto_ecs::core::reflection::TypeInfo
+            {
+                <Self as proto_ecs::core::reflection::Reflect>::type_info()
+            }
+
+            fn field(&self, name: &str) -> std::option::Option<&dyn proto_ecs::core::reflection::Reflect>
+            {
+                match name {
+                    #( #field_names => std::option::Option::Some(&self.#field_idents), )*
+                    _ => std::option::Option::None,
+                }
+            }
+
+            fn field_mut(&mut self, name: &str) -> std::option::Option<&mut dyn proto_ecs::core::reflection::Reflect>
+            {
+                match name {
+                    #( #field_names => std::option::Option::Some(&mut self.#field_idents), )*
+                    _ => std::option::Option::None,
+                }
+            }
+        }
+    })
+}
+
+fn derive_enum(ident: &syn::Ident, data: &syn::DataEnum) -> syn::Result<proc_macro2::TokenStream> {
+    let ident_str = ident.to_string();
+
+    if data.variants.is_empty() {
+        return Err(syn::Error::new(ident.span(), "Reflect can't be derived for enums without variants"));
+    }
+
+    let mut variants = Vec::new();
+    for variant in data.variants.iter() {
+        if !matches!(variant.fields, syn::Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "Reflect can only be derived for enums without data in their variants",
+            ));
+        }
+        variants.push(variant.ident.clone());
+    }
+    let variant_names: Vec<String> = variants.iter().map(|variant| variant.to_string()).collect();
+
+    Ok(quote! {
+        impl proto_ecs::core::reflection::Reflect for #ident
+        {
+            fn type_info() -> &'static proto_ecs::core::reflection::TypeInfo
+            {
+                static INFO : proto_ecs::core::reflection::TypeInfo = proto_ecs::core::reflection::TypeInfo::Enum {
+                    type_name: #ident_str,
+                    variants: &[ #( #variant_names, )* ],
+                };
+                &INFO
+            }
+
+            fn reflect_type_info(&self) -> &'static proto_ecs::core::reflection::TypeInfo
+            {
+                <Self as proto_ecs::core::reflection::Reflect>::type_info()
+            }
+
+            fn get_value(&self) -> std::option::Option<proto_ecs::core::reflection::ReflectValue>
+            {
+                let variant = match self {
+                    #( Self::#variants => #variant_names, )*
+                };
+                std::option::Option::Some(proto_ecs::core::reflection::ReflectValue::Enum(variant.to_owned()))
+            }
+
+            fn set_value(&mut self, value: proto_ecs::core::reflection::ReflectValue) -> std::result::Result<(), proto_ecs::core::reflection::ReflectError>
+            {
+                match value {
+                    proto_ecs::core::reflection::ReflectValue::Enum(variant) => {
+                        *self = match variant.as_str() {
+                            #( #variant_names => Self::#variants, )*
+                            _ => return std::result::Result::Err(
+                                proto_ecs::core::reflection::ReflectError::UnknownVariant { type_name: #ident_str, variant }
+                            ),
+                        };
+                        std::result::Result::Ok(())
+                    }
+                    value => std::result::Result::Err(
+                        proto_ecs::core::reflection::ReflectError::TypeMismatch { type_name: #ident_str, value }
+                    ),
+                }
+            }
+        }
+    })
+}
+
+/// Implement `Reflect` for a struct with named fields, or an enum without data in its variants
+pub fn derive_reflect(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let DeriveInput { ident, generics, data, .. } = parse_macro_input!(item);
+
+    let result = if !generics.params.is_empty() {
+        Err(syn::Error::new_spanned(generics, "Reflect can't be derived for generic types"))
+    } else {
+        match &data {
+            syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(fields), .. }) => derive_struct(&ident, fields),
+            syn::Data::Enum(data) => derive_enum(&ident, data),
+            _ => Err(syn::Error::new(
+                ident.span(),
+                "Reflect can only be derived for structs with named fields and enums",
+            )),
+        }
+    };
+
+    match result {
+        Ok(result) => result.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
@@ -71,7 +71,8 @@ pub fn register_global_system(args: proc_macro::TokenStream) -> proc_macro::Toke
     let struct_id_str = struct_id.to_string();
     let name_crc = crc32fast::hash(struct_id_str.as_bytes());
     let trait_function_signatures = trait_function_ids.clone().map(|id| {
-        quote!(fn #id(&mut self, world: &proto_ecs::entities::entity_system::World, entity_map : &proto_ecs::entities::entity_system::EntityMap, registered_entities : &Vec<proto_ecs::entities::entity_system::EntityPtr>);)
+        // Existing global systems implement this signature with a &Vec, keep it
+        quote!(#[allow(clippy::ptr_arg)] fn #id(&mut self, world: &proto_ecs::entities::entity_system::World, entity_map : &proto_ecs::entities::entity_system::EntityMap, registered_entities : &Vec<proto_ecs::entities::entity_system::EntityPtr>);)
     });
 
     let init_fn_signature = init_style.to_signature();
@@ -441,11 +442,11 @@ fn create_glue_function(
             global_system : &mut std::boxed::Box<dyn proto_ecs::systems::global_systems::GlobalSystem>, 
             world : &proto_ecs::entities::entity_system::World,
             entity_map : &proto_ecs::entities::entity_system::EntityMap, 
-            registered_entities : &proto_ecs::entities::entity_system::EntitiesVec
+            registered_entities : &std::vec::Vec<proto_ecs::entities::entity_allocator::EntityPtr>
         )
         {
             let mut global_system = global_system.as_any_mut().downcast_mut::<#struct_id>().unwrap();
-            global_system.#function_id (world, entity_map, &registered_entities.read());
+            global_system.#function_id (world, entity_map, registered_entities);
         }
     };
 
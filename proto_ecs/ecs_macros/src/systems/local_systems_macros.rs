@@ -108,13 +108,47 @@ impl syn::parse::Parse for LocalSystemArgs {
     }
 }
 
+/// Argument passed by a glue function to the user defined function
+enum GlueArg {
+    World,
+    Entity,
+    /// Index of the datagroup in the dependencies of the local system
+    DataGroup(usize),
+}
+
+/// Arguments of the functions written for `register_local_system!`: world, entity
+/// and then every dependency in order
+fn trait_function_args(deps: &[OptionalDep]) -> Vec<GlueArg> {
+    let mut args = vec![GlueArg::World, GlueArg::Entity];
+    args.extend((0..deps.len()).map(GlueArg::DataGroup));
+    args
+}
+
+/// Values of `call_args` inside a glue function, where datagroup `i` is stored in `arg{i}`
+fn glue_call_values(call_args: &[GlueArg], span: proc_macro2::Span) -> Vec<proc_macro2::TokenStream> {
+    call_args
+        .iter()
+        .map(|arg| match arg {
+            GlueArg::World => quote! { world },
+            GlueArg::Entity => quote! { entity },
+            GlueArg::DataGroup(i) => {
+                let arg_id = syn::Ident::new(format!("arg{i}").as_str(), span);
+                quote! { #arg_id }
+            }
+        })
+        .collect()
+}
+
 /// Create a new glue function to call user defined functions.
+/// `callee` is the function to call and `call_args` the arguments it takes.
 /// Return the ident of the new generated function and the function itself
 /// as a token stream
 fn create_glue_function(
     struct_id: &syn::Ident,
     function_id: &syn::Ident,
-    args: &Vec<OptionalDep>,
+    callee: &proc_macro2::TokenStream,
+    args: &[OptionalDep],
+    call_args: &[GlueArg],
 ) -> (syn::Ident, proc_macro2::TokenStream) {
     let new_function_id = syn::Ident::new(
         format!(
@@ -133,14 +167,13 @@ fn create_glue_function(
             syn::Ident::new(format!("arg{i}").as_str(), function_id.span())
         );
 
-    // required to prevent use-after-move error later on this function
-    let arg_ids_copy = arg_ids.clone();
+    let call_values = glue_call_values(call_args, function_id.span());
 
     let arg_values = args.iter().enumerate().map(|(i, arg)| {
         let index = syn::Index::from(i);
         let type_id = arg.unwrap();
         let arg_value = quote! {
-            (&mut *entity_datagroups_ptr.add(indices[#index] as usize))
+            (&mut *entity_datagroups[indices[#index] as usize])
             .as_any_mut()
             .downcast_mut::<#type_id>()
             .expect("Couldn't perform cast")
@@ -164,7 +197,8 @@ fn create_glue_function(
     });
 
     let new_function = quote! {
-        fn #new_function_id(world : &proto_ecs::entities::entity_system::World, entity : proto_ecs::entities::entity::EntityID, indices : &[proto_ecs::entities::entity::DataGroupIndexingType], entity_datagroups : &mut [std::boxed::Box<dyn proto_ecs::data_group::DataGroup>])
+        #[allow(unused_variables, unused_unsafe)] // Not every local system takes the world, the entity or datagroups
+        fn #new_function_id(world : &proto_ecs::entities::entity_system::World, entity : proto_ecs::entities::entity::EntityID, indices : &[proto_ecs::entities::entity::DataGroupIndexingType], entity_datagroups : &[*mut (dyn proto_ecs::data_group::DataGroup + 'static)])
         {
             debug_assert!({
                 let mut unique_set = std::collections::HashSet::new();
@@ -172,9 +206,8 @@ fn create_glue_function(
             }, "Overlapping indices or index out of range");
 
             unsafe {
-                let entity_datagroups_ptr = entity_datagroups.as_mut_ptr();
                 #(let #arg_ids = #arg_values;)*
-                #struct_id :: #function_id (&world, entity, #( #arg_ids_copy, )*);
+                #callee (#( #call_values, )*);
             }
         }
     };
@@ -182,31 +215,89 @@ fn create_glue_function(
     return (new_function_id, new_function);
 }
 
+/// Create a glue function to call user defined functions over all the entities of an archetype chunk.
+/// Return the ident of the new generated function and the function itself
+/// as a token stream
+fn create_chunk_glue_function(
+    struct_id: &syn::Ident,
+    function_id: &syn::Ident,
+    callee: &proc_macro2::TokenStream,
+    args: &[OptionalDep],
+    call_args: &[GlueArg],
+) -> (syn::Ident, proc_macro2::TokenStream) {
+    let new_function_id = syn::Ident::new(
+        format!(
+            "_{}_{}_chunk_", 
+                to_snake_case(
+                    struct_id.to_string().as_str()
+                ), 
+                function_id
+            ).as_str(),
+        function_id.span(),
+    );
+
+    let arg_ids =
+        (0..args.len()).map(
+            |i| 
+            syn::Ident::new(format!("arg{i}").as_str(), function_id.span())
+        );
+
+    let call_values = glue_call_values(call_args, function_id.span());
+
+    let arg_values = args.iter().enumerate().map(|(i, arg)| {
+        let index = syn::Index::from(i);
+        let type_id = arg.unwrap();
+        let arg_value = quote! {
+            &mut *columns[#index].cast::<#type_id>().add(row)
+        };
+
+        match arg {
+            OptionalDep::OptionalDep(_) => {
+                quote! {
+                    if columns[#index].is_null()
+                    {
+                        None
+                    }
+                    else
+                    {
+                        Some(#arg_value)
+                    }
+                }
+            }
+            OptionalDep::Dependency(_) => arg_value,
+        }
+    });
+
+    let args_len = args.len();
+    let new_function = quote! {
+        #[allow(unused_variables, unused_unsafe)] // Local systems without dependencies don't use the columns
+        fn #new_function_id(world : &proto_ecs::entities::entity_system::World, entities : &[proto_ecs::entities::entity::EntityID], columns : &[*mut u8])
+        {
+            debug_assert_eq!(columns.len(), #args_len, "Wrong number of columns");
+
+            for (row, &entity) in entities.iter().enumerate()
+            {
+                unsafe {
+                    #(let #arg_ids = #arg_values;)*
+                    #callee (#( #call_values, )*);
+                }
+            }
+        }
+    };
+
+    (new_function_id, new_function)
+}
+
 pub fn register_local_system(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let args = parse_macro_input!(input as LocalSystemArgs);
     let deps = args.dependencies.0;
     let struct_id_str = args.struct_id.to_string();
-    let name_crc = crc32fast::hash(struct_id_str.as_bytes());
     let stages = args.stages.0;
     let new_trait_id = syn::Ident::new(
         format!("{}LocalSystem", struct_id_str).as_str(),
         args.struct_id.span(),
     );
 
-    // Generate the simple spawn preparation for dependency datagroups
-    let datagroups_simple_prepare: Vec<proc_macro2::TokenStream> = deps.iter().filter_map(|dep| {
-        match dep {
-            OptionalDep::OptionalDep(_) => None,
-            OptionalDep::Dependency(d) => {
-                let msg = format!("Local System '{}' added Datagroup dependency '{d}'", args.struct_id);
-
-                Some(quote!{
-                    proto_ecs::entities::entity_spawn_desc::helpers::local_system_try_add_datagroup::<#d>(spawn_desc, #msg);
-                })
-            },
-        }
-    }).collect();
-
     // Generate function arguments for trait functions
     let function_args = 
         {
@@ -241,57 +332,127 @@ pub fn register_local_system(input: proc_macro::TokenStream) -> proc_macro::Toke
         };
     let function_ids = stages
         .iter()
-        .map(|stage| {
-            let stage_name = format!("stage_{}", stage.base10_digits());
-            let function_id = syn::Ident::new(stage_name.as_str(), stage.span());
-            function_id
-        })
+        .map(stage_function_id)
         .collect::<Vec<syn::Ident>>();
 
     let function_signatures = function_ids.iter().map(|ident| {
         quote! { fn #ident(#(#function_args),*) }
     });
 
-    let glue_functions = function_ids
+    let struct_id = &args.struct_id;
+    let stage_functions = stages
         .iter()
-        .map(|function_id| create_glue_function(&args.struct_id, function_id, &deps));
+        .zip(function_ids.iter())
+        .map(|(stage, function_id)| (stage.clone(), quote! { #struct_id :: #function_id }))
+        .collect();
+    let call_args = trait_function_args(&deps);
+
+    let mut result = quote!{
+        // For static assertions
+        const _ : fn() = || {
+            fn check_implements_traits<T : #new_trait_id>(){};
+            check_implements_traits::<#struct_id>();
+        };
+
+        // Generate the trait to be implemented by the user 
+        pub trait #new_trait_id 
+        {
+           #(#function_signatures;)*
+        }
+    };
+
+    result.extend(expand_local_system(LocalSystemDef {
+        struct_id: struct_id.clone(),
+        deps,
+        stage_functions,
+        call_args,
+        before: args.before.0,
+        after: args.after.0,
+    }));
+
+    return result.into();
+}
+
+/// Everything needed to register a local system, whether it comes from
+/// `register_local_system!` or from `#[local_system]`
+struct LocalSystemDef {
+    struct_id: syn::Ident,
+    deps: Vec<OptionalDep>,
+    /// Stage and the function to call in that stage
+    stage_functions: Vec<(syn::LitInt, proc_macro2::TokenStream)>,
+    /// Arguments taken by the stage functions
+    call_args: Vec<GlueArg>,
+    before: Vec<syn::Ident>,
+    after: Vec<syn::Ident>,
+}
+
+fn stage_function_id(stage: &syn::LitInt) -> syn::Ident {
+    let stage_name = format!("stage_{}", stage.base10_digits());
+    syn::Ident::new(stage_name.as_str(), stage.span())
+}
+
+/// Generate the glue functions, ids, descriptions and registration of a local system
+fn expand_local_system(def: LocalSystemDef) -> proc_macro2::TokenStream {
+    let LocalSystemDef {
+        struct_id,
+        deps,
+        stage_functions,
+        call_args,
+        before,
+        after,
+    } = def;
+    let struct_id_str = struct_id.to_string();
+    let name_crc = crc32fast::hash(struct_id_str.as_bytes());
+
+    // Generate the simple spawn preparation for dependency datagroups
+    let datagroups_simple_prepare: Vec<proc_macro2::TokenStream> = deps.iter().filter_map(|dep| {
+        match dep {
+            OptionalDep::OptionalDep(_) => None,
+            OptionalDep::Dependency(d) => {
+                let msg = format!("Local System '{}' added Datagroup dependency '{d}'", struct_id);
+
+                Some(quote!{
+                    proto_ecs::entities::entity_spawn_desc::helpers::local_system_try_add_datagroup::<#d>(spawn_desc, #msg);
+                })
+            },
+        }
+    }).collect();
+
+    let glue_functions = stage_functions.iter().map(|(stage, callee)| {
+        create_glue_function(&struct_id, &stage_function_id(stage), callee, &deps, &call_args)
+    });
 
     let glue_function_bodies = glue_functions.clone().map(|(_, body)| body);
     let glue_function_ids = glue_functions.map(|(id, _)| id);
-    let stage_indices = stages
+
+    let chunk_glue_functions = stage_functions.iter().map(|(stage, callee)| {
+        create_chunk_glue_function(&struct_id, &stage_function_id(stage), callee, &deps, &call_args)
+    });
+
+    let chunk_glue_function_bodies = chunk_glue_functions.clone().map(|(_, body)| body);
+    let chunk_glue_function_ids = chunk_glue_functions.map(|(id, _)| id);
+    let stage_indices = stage_functions
         .iter()
-        .map(|lit| syn::Index::from(lit.base10_parse::<usize>().unwrap()));
-    let struct_id = &args.struct_id;
+        .map(|(lit, _)| syn::Index::from(lit.base10_parse::<usize>().unwrap()));
+    let chunk_stage_indices = stage_indices.clone();
 
     let mut result = quote!{};
-    let id_magic_ident = ids::implement_id_traits(struct_id, &mut result);
-    let before = args.before.0;
-    let after = args.after.0;
+    let id_magic_ident = ids::implement_id_traits(&struct_id, &mut result);
     let id_set_up_fn_id = syn::Ident::new(
         format!("__{}_id_register__", to_snake_case(struct_id_str.as_str())).as_str(), 
         struct_id.span());
 
     result.extend(quote!{
 
-        // For static assertions
-        const _ : fn() = || {
-            fn check_implements_traits<T : #new_trait_id>(){};
-            check_implements_traits::<#struct_id>();
-        };
-
         fn #id_set_up_fn_id (new_id : proto_ecs::systems::local_systems::SystemClassID)
         {
             #id_magic_ident.set(new_id).expect("Can't set id twice");
         }
 
-        // Generate the trait to be implemented by the user 
-        pub trait #new_trait_id 
-        {
-           #(#function_signatures;)*
-        }
-
         #(#glue_function_bodies)*
 
+        #(#chunk_glue_function_bodies)*
+
         impl #struct_id
         {
             #[doc = "Simple preparation of this local system. Dependencies that require init args are left uninitialized. Dependencies with optional args are left empty"]
@@ -324,6 +485,8 @@ pub fn register_local_system(input: proc_macro::TokenStream) -> proc_macro::Toke
                             let mut func_map  = proto_ecs::systems::local_systems::EMPTY_STAGE_MAP;
                             #( dependencies.push(#deps);)*
                             #( func_map[#stage_indices] = Some(#glue_function_ids);)*
+                            let mut chunk_func_map = proto_ecs::systems::local_systems::EMPTY_CHUNK_STAGE_MAP;
+                            #( chunk_func_map[#chunk_stage_indices] = Some(#chunk_glue_function_ids);)*
 
                             assert!(
                                 dependencies.len() <= proto_ecs::entities::entity::MAX_DATAGROUP_LEN as usize,
@@ -340,6 +503,7 @@ pub fn register_local_system(input: proc_macro::TokenStream) -> proc_macro::Toke
                                     name_crc : #name_crc,
                                     dependencies : dependencies,
                                     functions : func_map,
+                                    chunk_functions : chunk_func_map,
                                     before : vec![
                                         #(<#before as proto_ecs::systems::local_systems::LocalSystemDesc>::NAME_CRC),*
                                     ],
@@ -356,5 +520,318 @@ pub fn register_local_system(input: proc_macro::TokenStream) -> proc_macro::Toke
         };
     });
 
-    return result.into();
+    result
+}
+
+// -- < Local system attribute > ------------------------------
+
+const PARAM_HELP: &str =
+    "Local system parameters can be `&mut DataGroup`, `Option<&mut DataGroup>`, `&World` or `EntityID`";
+
+/// Arguments of `#[local_system(...)]`
+struct LocalSystemAttrArgs {
+    stages: Vec<syn::LitInt>,
+    before: DependencyList,
+    after: DependencyList,
+}
+
+impl syn::parse::Parse for LocalSystemAttrArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut stages: Option<Vec<syn::LitInt>> = None;
+        let mut before: Option<DependencyList> = None;
+        let mut after: Option<DependencyList> = None;
+
+        // Parse a list of keyword arguments: stage = 0, before = (A, B), ...
+        while !input.is_empty() {
+            let keyword_arg = input.parse::<syn::Ident>()?;
+            let _ = input.parse::<syn::Token![=]>()?;
+            let duplicated = || {
+                syn::Error::new(
+                    keyword_arg.span(),
+                    format!("Duplicated keyword argument: {keyword_arg}"),
+                )
+            };
+
+            match keyword_arg.to_string().as_str() {
+                "stage" | "stages" => {
+                    if stages.is_some() {
+                        return Err(duplicated());
+                    }
+
+                    stages = Some(if keyword_arg == "stage" {
+                        vec![input.parse::<syn::LitInt>()?]
+                    } else {
+                        input.parse::<Stages>()?.0
+                    });
+                }
+                "before" => {
+                    if before.is_some() {
+                        return Err(duplicated());
+                    }
+
+                    before = Some(input.parse::<DependencyList>()?);
+                }
+                "after" => {
+                    if after.is_some() {
+                        return Err(duplicated());
+                    }
+
+                    after = Some(input.parse::<DependencyList>()?);
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        keyword_arg.span(),
+                        "Unexpected keyword. Available keywords = {stage, stages, before, after}",
+                    ));
+                }
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            let _ = input.parse::<syn::Token![,]>()?;
+        }
+
+        let stages = stages.ok_or_else(|| {
+            syn::Error::new(
+                proc_macro2::Span::call_site(),
+                "Missing stage, add one with `#[local_system(stage = 0)]`",
+            )
+        })?;
+        if stages.is_empty() {
+            return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                "A local system should run in at least one stage",
+            ));
+        }
+
+        let mut seen_stages = Vec::with_capacity(stages.len());
+        for stage in stages.iter() {
+            let stage_id = stage.base10_parse::<u8>().map_err(|_| {
+                syn::Error::new(
+                    stage.span(),
+                    format!("Invalid stage, stages go from 0 to {}", u8::MAX),
+                )
+            })?;
+            if seen_stages.contains(&stage_id) {
+                return Err(syn::Error::new(stage.span(), "Duplicated stage"));
+            }
+            seen_stages.push(stage_id);
+        }
+
+        Ok(LocalSystemAttrArgs {
+            stages,
+            before: before.unwrap_or(DependencyList(vec![])),
+            after: after.unwrap_or(DependencyList(vec![])),
+        })
+    }
+}
+
+/// What a parameter of a `#[local_system]` function receives
+enum SystemParam {
+    World,
+    Entity,
+    DataGroup(OptionalDep),
+}
+
+/// Remove the invisible groups macro_rules! wraps types in
+fn ungroup(ty: &syn::Type) -> &syn::Type {
+    match ty {
+        syn::Type::Group(group) => ungroup(&group.elem),
+        syn::Type::Paren(paren) => ungroup(&paren.elem),
+        _ => ty,
+    }
+}
+
+/// Last segment of a type path, like `World` in `entity_system::World`
+fn last_path_segment(ty: &syn::Type) -> Option<&syn::PathSegment> {
+    match ungroup(ty) {
+        syn::Type::Path(type_path) if type_path.qself.is_none() => type_path.path.segments.last(),
+        _ => None,
+    }
+}
+
+fn is_plain_type_named(ty: &syn::Type, name: &str) -> bool {
+    last_path_segment(ty)
+        .is_some_and(|segment| segment.ident == name && segment.arguments.is_none())
+}
+
+/// Datagroup named by `ty`, which should be a single identifier
+fn datagroup_ident(ty: &syn::Type) -> syn::Result<syn::Ident> {
+    match ungroup(ty) {
+        syn::Type::Path(type_path) if type_path.qself.is_none() => {
+            type_path.path.get_ident().cloned().ok_or_else(|| {
+                syn::Error::new_spanned(
+                    ty,
+                    "Datagroup types should be named directly, like `&mut MyDataGroup`. Bring the type into scope with `use`",
+                )
+            })
+        }
+        _ => Err(syn::Error::new_spanned(ty, PARAM_HELP)),
+    }
+}
+
+fn parse_system_param(arg: &syn::FnArg) -> syn::Result<SystemParam> {
+    let ty = match arg {
+        syn::FnArg::Receiver(receiver) => {
+            return Err(syn::Error::new_spanned(
+                receiver,
+                "Local systems can't take `self`",
+            ))
+        }
+        syn::FnArg::Typed(pat_type) => ungroup(&pat_type.ty),
+    };
+
+    match ty {
+        syn::Type::Reference(reference) => {
+            let is_world = is_plain_type_named(&reference.elem, "World");
+            match (reference.mutability, is_world) {
+                (None, true) => Ok(SystemParam::World),
+                (Some(_), true) => Err(syn::Error::new_spanned(
+                    ty,
+                    "The world can't be borrowed mutably, use `&World`",
+                )),
+                (Some(_), false) => Ok(SystemParam::DataGroup(OptionalDep::Dependency(
+                    datagroup_ident(&reference.elem)?,
+                ))),
+                (None, false) => match datagroup_ident(&reference.elem) {
+                    Ok(datagroup) => Err(syn::Error::new_spanned(
+                        ty,
+                        format!("Datagroups have to be borrowed mutably, use `&mut {datagroup}`"),
+                    )),
+                    Err(_) => Err(syn::Error::new_spanned(ty, PARAM_HELP)),
+                },
+            }
+        }
+        _ if is_plain_type_named(ty, "EntityID") => Ok(SystemParam::Entity),
+        _ => {
+            // Only Option<&mut DataGroup> is left
+            let optional_ref = last_path_segment(ty)
+                .filter(|segment| segment.ident == "Option")
+                .and_then(|segment| match &segment.arguments {
+                    syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => {
+                        match args.args.first() {
+                            Some(syn::GenericArgument::Type(syn::Type::Reference(reference))) => {
+                                Some(reference)
+                            }
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                });
+
+            match optional_ref {
+                Some(reference) if reference.mutability.is_some() => Ok(SystemParam::DataGroup(
+                    OptionalDep::OptionalDep(datagroup_ident(&reference.elem)?),
+                )),
+                Some(reference) => Err(syn::Error::new_spanned(
+                    reference,
+                    "Optional datagroups have to be borrowed mutably, use `Option<&mut DataGroup>`",
+                )),
+                None => Err(syn::Error::new_spanned(ty, PARAM_HELP)),
+            }
+        }
+    }
+}
+
+/// Dependencies of a `#[local_system]` function and the arguments its glue functions pass to it
+fn parse_system_params(sig: &syn::Signature) -> syn::Result<(Vec<OptionalDep>, Vec<GlueArg>)> {
+    let mut deps: Vec<OptionalDep> = vec![];
+    let mut call_args = vec![];
+
+    for input in sig.inputs.iter() {
+        let call_arg = match parse_system_param(input)? {
+            SystemParam::World => GlueArg::World,
+            SystemParam::Entity => GlueArg::Entity,
+            SystemParam::DataGroup(dep) => {
+                // Two mutable references to the same datagroup would alias
+                if deps.iter().any(|other| other.unwrap() == dep.unwrap()) {
+                    return Err(syn::Error::new(
+                        dep.unwrap().span(),
+                        format!("Datagroup '{}' is already a parameter of this local system", dep.unwrap()),
+                    ));
+                }
+                deps.push(dep);
+                GlueArg::DataGroup(deps.len() - 1)
+            }
+        };
+
+        let is_repeated = call_args.iter().any(|other| {
+            matches!(
+                (other, &call_arg),
+                (GlueArg::World, GlueArg::World) | (GlueArg::Entity, GlueArg::Entity)
+            )
+        });
+        if is_repeated {
+            return Err(syn::Error::new_spanned(input, "Parameter already taken by this local system"));
+        }
+        call_args.push(call_arg);
+    }
+
+    Ok((deps, call_args))
+}
+
+fn local_system_from_function(
+    args: LocalSystemAttrArgs,
+    function: &mut syn::ItemFn,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let sig = &function.sig;
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(syn::Error::new_spanned(asyncness, "Local systems can't be async"));
+    }
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(&sig.generics, "Local systems can't be generic"));
+    }
+    if let syn::ReturnType::Type(_, ty) = &sig.output {
+        return Err(syn::Error::new_spanned(ty, "Local systems can't return a value"));
+    }
+
+    let (deps, call_args) = parse_system_params(sig)?;
+    let function_id = sig.ident.clone();
+    let vis = function.vis.clone();
+
+    // The function is named like a type, since it's also the name of the local system
+    function.attrs.push(syn::parse_quote!(#[allow(non_snake_case)]));
+
+    // The local system struct only takes the type namespace, so it can share its name
+    // with the function. It's used to refer to the system in `before`, `after` and spawn descriptions
+    let mut result = quote! {
+        #[doc = "Local system that runs the function with the same name"]
+        #[allow(non_camel_case_types)]
+        #vis struct #function_id {}
+    };
+
+    let stage_functions = args
+        .stages
+        .into_iter()
+        .map(|stage| (stage, quote! { #function_id }))
+        .collect();
+
+    result.extend(expand_local_system(LocalSystemDef {
+        struct_id: function_id,
+        deps,
+        stage_functions,
+        call_args,
+        before: args.before.0,
+        after: args.after.0,
+    }));
+
+    Ok(result)
+}
+
+pub fn local_system(
+    args: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let mut function = parse_macro_input!(item as syn::ItemFn);
+    let system = syn::parse::<LocalSystemAttrArgs>(args)
+        .and_then(|args| local_system_from_function(args, &mut function));
+
+    // Keep the function on errors, so its uses don't report errors of their own
+    let system = system.unwrap_or_else(|err| err.to_compile_error());
+    quote! {
+        #function
+        #system
+    }
+    .into()
 }
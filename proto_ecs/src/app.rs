@@ -1,18 +1,28 @@
-use std::time::Instant;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
+use crate::core::input_recording::{
+    InputPlayback, InputRecorder, InputRecordingError, PlaybackSettings, RecordedFrame,
+};
 use crate::core::layer::{LayerManager, LayerPtr};
 use crate::core::locking::RwLock;
+use crate::core::logging;
+use crate::core::platform::Platforms;
+use crate::core::registry_manifest::{ManifestMismatch, RegistryManifest};
 use crate::core::rendering::render_thread::RenderThread;
+use crate::core::rendering::Render;
 use crate::core::time::Time;
 use crate::core::windowing::events::{Event, Type};
 use crate::core::windowing::window_manager::WindowManager;
 use crate::data_group::DataGroupRegistry;
 use crate::entities::entity_system::{EntitySystem, WorldID};
+use crate::systems::common::SystemOrderError;
 use crate::systems::global_systems::GlobalSystemRegistry;
 use crate::systems::local_systems::LocalSystemRegistry;
 /// This module implements the entire Application workflow.
 /// Put any glue code between parts of our application here
 use lazy_static::lazy_static;
+use tracing::{error, info, warn};
 
 pub type LayerID = u32;
 
@@ -21,7 +31,9 @@ pub struct App {
     time: Time,
     running: bool,
     pub(crate) layer_manager: LayerManager,
-    world: WorldID
+    world: WorldID,
+    recorder: Option<InputRecorder>,
+    playback: Option<InputPlayback>,
 }
 
 lazy_static! {
@@ -35,43 +47,71 @@ impl App {
             time: Time::new(Instant::now()),
             running: false,
             layer_manager: Default::default(),
-            world: 0
+            world: 0,
+            recorder: None,
+            playback: None,
         }
     }
 
     /// Initialize internal systems (like datagroup registry).
-    pub fn initialize() {
+    ///
+    /// Fails if local or global systems can't be ordered by their `before` and `after`
+    /// dependencies, in which case the app stays uninitialized and initializing it again
+    /// fails the same way
+    pub fn initialize() -> Result<(), SystemOrderError> {
+        App::initialize_internal(None)
+    }
+
+    /// Initialize internal systems, giving datagroups and systems the ids they have in
+    /// `manifest` when possible, see [RegistryManifest].
+    ///
+    /// Returns the differences between the manifest and this build, which are also logged as
+    /// warnings. Fails like [App::initialize] if systems can't be ordered
+    pub fn initialize_with_manifest(
+        manifest: &RegistryManifest,
+    ) -> Result<Vec<ManifestMismatch>, SystemOrderError> {
+        App::initialize_internal(Some(manifest))?;
+
+        let mismatches = manifest.diff(&RegistryManifest::from_global_registries());
+        for mismatch in mismatches.iter() {
+            warn!(target: logging::APP, "Registry manifest mismatch: {}", mismatch);
+        }
+        Ok(mismatches)
+    }
+
+    fn initialize_internal(manifest: Option<&RegistryManifest>) -> Result<(), SystemOrderError> {
         let mut global_app = APP.write();
         if global_app.is_initialized {
             // Already initialized
-            return;
+            return Ok(());
         }
 
-        println!("Initializing app!");
+        info!(target: logging::APP, "Initializing app!");
 
         // Put any initialization logic here, mind the expected initialization order.
-        debug_assert!(
-            !DataGroupRegistry::get_global_registry()
-                .read()
-                .is_initialized(),
-            "DataGroupRegistry should not initialize before app"
-        );
-        DataGroupRegistry::initialize();
+        // Datagroup and system ids can only be set once, so registries initialized by a
+        // previous attempt that failed to order systems are kept as they are
+        if !DataGroupRegistry::get_global_registry()
+            .read()
+            .is_initialized()
+        {
+            DataGroupRegistry::initialize_with_manifest(manifest);
+        }
 
         // Local system should update after datagroups since they rely on datagroup ids for
         // initialization
-        debug_assert!(
-            !LocalSystemRegistry::get_global_registry()
-                .read()
-                .is_initialized(),
-            "LocalSystemRegistry should not initialize before app"
-        );
-        LocalSystemRegistry::initialize();
+        if !LocalSystemRegistry::get_global_registry()
+            .read()
+            .is_initialized()
+        {
+            LocalSystemRegistry::initialize_with_manifest(manifest)?;
+        }
 
         // Global systems can initialize at any point
-        GlobalSystemRegistry::initialize();
+        GlobalSystemRegistry::initialize_with_manifest(manifest)?;
 
         global_app.init();
+        Ok(())
     }
 
     pub fn is_initialized() -> bool {
@@ -83,12 +123,16 @@ impl App {
     }
 
     pub fn run_application() {
-        println!("Waiting for render thread...");
-        while !RenderThread::is_started() {}
+        // Headless apps have no display to render to, and apps that don't render
+        // have no render thread, so there's nothing to wait for
+        if !APP.read().is_headless() && Render::is_initialized() {
+            info!(target: logging::APP, "Waiting for render thread...");
+            while !RenderThread::is_started() {}
+        }
         // TODO Ask Chris
         // Will we leave this lock on during the entire application?
         let mut global_app = APP.write();
-        println!("Starting to run application!");
+        info!(target: logging::APP, "Starting to run application!");
         global_app.run();
     }
 
@@ -102,6 +146,64 @@ impl App {
         global_app.layer_manager.attach_overlays(overlay)
     }
 
+    /// Record every event and frame delta of this application into the file at `path`.
+    /// Call this before `run_application`, recording stops when the application closes.
+    /// Fails if the app is playing back a recording
+    pub fn start_recording<P: AsRef<Path>>(path: P) -> Result<(), InputRecordingError> {
+        let mut global_app = APP.write();
+        if global_app.playback.is_some() {
+            return Err(InputRecordingError::RecordingAndPlayback);
+        }
+        global_app.recorder = Some(InputRecorder::create(path)?);
+        Ok(())
+    }
+
+    /// Stop recording and flush the recording file
+    pub fn stop_recording() -> Result<(), InputRecordingError> {
+        let mut global_app = APP.write();
+        match global_app.recorder.take() {
+            Some(mut recorder) => recorder.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Play back a recording made with `start_recording`. Events and frame deltas
+    /// come from the recording instead of the window, and the application closes
+    /// when the recording is over.
+    /// Call this before `run_application`. Fails if the app is recording
+    pub fn start_playback<P: AsRef<Path>>(
+        path: P,
+        settings: PlaybackSettings,
+    ) -> Result<(), InputRecordingError> {
+        let mut global_app = APP.write();
+        if global_app.recorder.is_some() {
+            return Err(InputRecordingError::RecordingAndPlayback);
+        }
+        global_app.playback = Some(InputPlayback::open(path, settings)?);
+        Ok(())
+    }
+
+    /// If this app is playing back a recording
+    pub fn is_playing_back(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// If this app runs without a display, either on the headless platform
+    /// or playing back a recording in headless mode
+    #[inline(always)]
+    fn is_headless(&self) -> bool {
+        self.is_headless_playback() || WindowManager::get_platform() == Platforms::Headless
+    }
+
+    /// If this app plays back a recording without a window.
+    /// The headless platform still has a window to poll for injected events
+    #[inline(always)]
+    fn is_headless_playback(&self) -> bool {
+        self.playback
+            .as_ref()
+            .is_some_and(|playback| playback.get_settings().headless)
+    }
+
     fn init(&mut self) {
         self.is_initialized = true;
         self.running = true;
@@ -112,15 +214,32 @@ impl App {
 
     fn run(&mut self) {
         while self.running {
-            // Time update
-            self.time.step(Instant::now());
-            let delta_time = self.time.delta_seconds();
+            let frame_start = Instant::now();
+
+            // Time update. When playing back, both time and events come from the recording
+            let (delta_time, recorded_events) = if self.playback.is_some() {
+                match self.next_playback_frame() {
+                    Some(frame) => {
+                        self.time
+                            .step_by(Duration::from_secs_f32(frame.delta_seconds));
+                        (frame.delta_seconds, frame.events)
+                    }
+                    None => break,
+                }
+            } else {
+                self.time.step(Instant::now());
+                (self.time.delta_seconds(), vec![])
+            };
+            self.record_frame(delta_time);
 
             // Event polling
-            {
+            if !self.is_headless_playback() {
                 let mut window_manager = WindowManager::get().write();
                 window_manager.get_window_mut().handle_window_events(self);
             }
+            for mut event in recorded_events {
+                self.dispatch_event(&mut event);
+            }
 
             // If layers were requested in runtime, add them just before the next frame.
             // Must of the time this returns immediately
@@ -135,7 +254,7 @@ impl App {
                 layer.layer.update(delta_time);
             }
 
-            // Update the entity system 
+            // Update the entity system
             // TODO Compute actual fixed delta time
             // TODO Compute delta time with f64 precision
             {
@@ -145,12 +264,25 @@ impl App {
 
             self.layer_manager.detach_pending_layers();
             self.layer_manager.detach_pending_overlays();
-            {
+            if !self.is_headless_playback() {
                 let mut window_manager = WindowManager::get().write();
                 window_manager.get_window_mut().on_update();
             }
+
+            // Keep the recorded frame rate when playing back in real time
+            if let Some(playback) = &self.playback {
+                let recorded_frame_time = Duration::from_secs_f32(delta_time);
+                let frame_time = frame_start.elapsed();
+                if playback.get_settings().real_time && frame_time < recorded_frame_time {
+                    std::thread::sleep(recorded_frame_time - frame_time);
+                }
+            }
         }
 
+        // Dropping the recorder flushes it
+        self.recorder = None;
+        self.playback = None;
+
         // Closing the application, detach all layers
         for layer in self.layer_manager.layers_iter_mut() {
             layer.layer.on_detach();
@@ -177,6 +309,18 @@ impl App {
     }
 
     pub fn on_event(&mut self, event: &mut Event) {
+        // Live events are ignored while playing back a recording,
+        // but the user can still close the window
+        if self.playback.is_some() {
+            self.handle_event(event);
+            return;
+        }
+
+        self.record_event(event);
+        self.dispatch_event(event);
+    }
+
+    fn dispatch_event(&mut self, event: &mut Event) {
         // Event is handled, ignore it.
         // Handled events are no propagated later in the event stack
         if event.is_handled() {
@@ -188,7 +332,7 @@ impl App {
             layer.layer.on_event(event);
         }
 
-        for layer in self.layer_manager.layers_iter_mut() {
+        for layer in self.layer_manager.overlays_iter_mut() {
             layer.layer.on_event(event);
         }
     }
@@ -198,4 +342,33 @@ impl App {
             self.running = false;
         }
     }
+
+    fn next_playback_frame(&mut self) -> Option<RecordedFrame> {
+        let playback = self.playback.as_mut()?;
+        match playback.next_frame() {
+            Ok(frame) => frame,
+            Err(err) => {
+                error!(target: logging::APP, "Stopping playback, failed to read recording: {}", err);
+                None
+            }
+        }
+    }
+
+    fn record_frame(&mut self, delta_time: f32) {
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(err) = recorder.record_frame(delta_time) {
+                error!(target: logging::APP, "Stopping recording, failed to record frame: {}", err);
+                self.recorder = None;
+            }
+        }
+    }
+
+    fn record_event(&mut self, event: &Event) {
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(err) = recorder.record_event(event) {
+                error!(target: logging::APP, "Stopping recording, failed to record event: {}", err);
+                self.recorder = None;
+            }
+        }
+    }
 }
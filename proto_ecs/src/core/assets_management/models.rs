@@ -1,17 +1,19 @@
-use proto_ecs::core::utils::handle::Allocator;
 use std::collections::HashMap;
 use tobj;
+use tracing::debug;
 
 use std::fs::canonicalize;
 use std::path::{PathBuf, Path};
 
+use crate::core::logging::ASSETS;
 use crate::core::utils::handle::Handle;
+use crate::core::utils::slot_map::{SlotMap, SlotRef};
 
-pub type ModelHandle = Handle;
+pub type ModelHandle = Handle<Model>;
 
 #[derive(Default)]
 pub struct ModelManager {
-    model_allocator: Allocator<Model>,
+    model_allocator: SlotMap<ModelHandle, Model>,
     loaded_models: HashMap<PathBuf, Vec<ModelHandle>>,
 }
 
@@ -35,16 +37,24 @@ impl ModelManager {
     }
 
     #[inline(always)]
-    pub fn get(&self, model_handle: ModelHandle) -> &mut Model {
+    pub fn get(&self, model_handle: ModelHandle) -> SlotRef<'_, Model> {
         self.model_allocator.get(model_handle)
     }
 
+    #[inline(always)]
+    pub fn try_get(&self, model_handle: ModelHandle) -> Option<SlotRef<'_, Model>> {
+        self.model_allocator.try_get(model_handle)
+    }
+
     pub fn get_or_load(&mut self, model_path: &PathBuf) -> Vec<ModelHandle> {
         let canon_path = canonicalize(model_path).expect("Not a valid model path");
         if let Some(handles) = self.loaded_models.get(&canon_path) {
             let mut result = vec![];
             for handle in handles {
-                let model = self.model_allocator.get(*handle);
+                debug_assert!(
+                    self.model_allocator.is_live(*handle),
+                    "Loaded model was freed"
+                );
                 result.push(*handle);
             }
             result
@@ -60,6 +70,7 @@ impl ModelManager {
         );
 
         let canon_path = canonicalize(model_path).expect("Invalid model file");
+        debug!(target: ASSETS, "Loading model {:?}", canon_path);
 
         // Actually load the model
         let load_options = tobj::LoadOptions {
@@ -78,7 +89,7 @@ impl ModelManager {
         let mut result = vec![];
 
         for model in models {
-            let handle = self.model_allocator.allocate(Model {
+            let handle = self.model_allocator.insert(Model {
                 internal_model: model,
             });
 
@@ -95,7 +106,7 @@ impl ModelManager {
         );
 
         // Clear from allocator, will free this model from memory
-        self.model_allocator.free(model_handle);
+        self.model_allocator.remove(model_handle);
 
         // Clear from map
         let mut model_path = None;
@@ -125,12 +136,13 @@ impl ModelManager {
         }
     }
 
+    #[allow(unused)]
     fn unload_from_path(&mut self, model_path : &Path) {
         debug_assert!(self.loaded_models.contains_key(model_path), "Trying to unload unloaded model");
         let models = self.loaded_models.get(model_path);
         let handles = models.as_ref().unwrap();
         for handle in handles.iter() {
-            self.model_allocator.free(*handle);
+            self.model_allocator.remove(*handle);
         }
 
         self.loaded_models.remove(model_path);
@@ -23,7 +23,12 @@ impl HetSingleMemBlock {
 
         let block_layout = block_layout.pad_to_align();
 
-        let base = unsafe { System.alloc(block_layout) };
+        let base = if block_layout.size() == 0 {
+            // Nothing to allocate for zero sized data, a well aligned pointer is enough
+            block_layout.align() as *mut u8
+        } else {
+            unsafe { System.alloc(block_layout) }
+        };
         assert!(!base.is_null(), "Failed to allocate!");
 
         Self {
@@ -58,7 +63,7 @@ impl HetSingleMemBlock {
 
 impl Drop for HetSingleMemBlock {
     fn drop(&mut self) {
-        if !self.base.is_null() {
+        if !self.base.is_null() && self.layout.size() != 0 {
             unsafe {
                 System.dealloc(self.base, self.layout);
             }
@@ -217,4 +222,18 @@ mod tests {
         assert_eq!(A_COUNTER.load(Ordering::Acquire), 0);
         assert_eq!(B_COUNTER.load(Ordering::Acquire), 0);
     }
+
+    #[test]
+    fn test_het_mem_block_zero_sized() {
+        let layouts = [Layout::new::<()>(), Layout::new::<[u64; 0]>()];
+
+        let block = HetSingleMemBlock::alloc(&layouts);
+        assert_eq!(block.get_layout().size(), 0);
+
+        let offsets = unsafe { block.get_layout_iter(&layouts).collect::<Vec<*mut u8>>() };
+        assert_eq!(offsets.len(), 2);
+        assert!(offsets
+            .iter()
+            .all(|ptr| !ptr.is_null() && ptr.align_offset(std::mem::align_of::<u64>()) == 0));
+    }
 }
@@ -13,7 +13,7 @@ pub trait HasID {
 
 #[macro_export]
 macro_rules! get_id {
-    ($i:ident) => {
+    ($i:ty) => {
         <$i as proto_ecs::core::ids::IDLocator>::get_id()
     };
 }
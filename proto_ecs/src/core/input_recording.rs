@@ -0,0 +1,527 @@
+/// Input recording and playback for the application loop.
+///
+/// A recording captures every [Event] that goes through `App::on_event` and every
+/// frame delta fed to the entity system, so a session can be replayed exactly.
+///
+/// The log is a compact little endian binary stream:
+/// ```text
+/// header : MAGIC (8 bytes) | VERSION (u16)
+/// record : FRAME_TAG (u8) | delta_seconds (f32)
+///        | EVENT_TAG (u8) | handled (u8) | event type tag (u8) | payload
+/// ```
+/// Every frame starts with a frame record, followed by the events dispatched during that frame.
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use tracing::error;
+
+use crate::core::logging::APP;
+use crate::core::windowing::events::{Event, KeyState, MouseButton, Type};
+use crate::core::windowing::keys::Keycode;
+
+const MAGIC: &[u8; 8] = b"PECSINPT";
+const VERSION: u16 = 1;
+
+const FRAME_TAG: u8 = 0;
+const EVENT_TAG: u8 = 1;
+
+/// Longest frame a recording can have. Longer frames are considered corrupted,
+/// as stepping the app clock by them could overflow it
+const MAX_FRAME_SECONDS: f32 = 60.0 * 60.0;
+
+/// Writes frames and events into a binary input log
+pub struct InputRecorder {
+    writer: Box<dyn Write + Send + Sync>,
+}
+
+/// A single recorded frame: the delta time used for this frame and the
+/// events dispatched during it, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedFrame {
+    pub delta_seconds: f32,
+    pub events: Vec<Event>,
+}
+
+/// Reads back a log written by an [InputRecorder], one frame at a time
+#[derive(Debug)]
+pub struct InputPlayback {
+    data: Vec<u8>,
+    cursor: usize,
+    settings: PlaybackSettings,
+}
+
+/// How a recording should be played back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaybackSettings {
+    /// Don't poll or update the window. Rendering is skipped entirely
+    pub headless: bool,
+    /// Wait between frames so that playback matches the recorded frame times.
+    /// When false, frames run as fast as possible
+    pub real_time: bool,
+}
+
+#[derive(Debug)]
+pub enum InputRecordingError {
+    /// Failed to read or write the log
+    Io(std::io::Error),
+    /// The log does not start with the expected header
+    InvalidHeader,
+    /// The log was written with an unsupported format version
+    UnsupportedVersion(u16),
+    /// The log contains an unexpected value at the specified byte offset
+    Corrupted { offset: usize },
+    /// Tried to record while playing back a recording, or the other way around
+    RecordingAndPlayback,
+}
+
+// -- < Implementations > --------------------------------
+
+impl InputRecorder {
+    /// Create a new recorder writing to a file at `path`.
+    /// The file is truncated if it already exists
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, InputRecordingError> {
+        let file = File::create(path)?;
+        Self::from_writer(Box::new(BufWriter::new(file)))
+    }
+
+    /// Create a new recorder writing to an arbitrary writer
+    pub fn from_writer(
+        mut writer: Box<dyn Write + Send + Sync>,
+    ) -> Result<Self, InputRecordingError> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        Ok(InputRecorder { writer })
+    }
+
+    /// Start a new frame. Events recorded after this call belong to this frame
+    pub fn record_frame(&mut self, delta_seconds: f32) -> Result<(), InputRecordingError> {
+        let mut buffer = Vec::with_capacity(5);
+        buffer.push(FRAME_TAG);
+        buffer.extend_from_slice(&delta_seconds.to_le_bytes());
+        self.writer.write_all(&buffer)?;
+        Ok(())
+    }
+
+    /// Record an event in the current frame
+    pub fn record_event(&mut self, event: &Event) -> Result<(), InputRecordingError> {
+        let mut buffer = Vec::with_capacity(16);
+        buffer.push(EVENT_TAG);
+        buffer.push(event.is_handled() as u8);
+        encode_event_type(event.get_type(), &mut buffer);
+        self.writer.write_all(&buffer)?;
+        Ok(())
+    }
+
+    /// Flush any buffered data into the underlying writer
+    pub fn flush(&mut self) -> Result<(), InputRecordingError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for InputRecorder {
+    fn drop(&mut self) {
+        if let Err(err) = self.writer.flush() {
+            error!(target: APP, "Failed to flush input recording: {}", err);
+        }
+    }
+}
+
+impl std::fmt::Debug for InputRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InputRecorder").finish_non_exhaustive()
+    }
+}
+
+impl Default for PlaybackSettings {
+    fn default() -> Self {
+        PlaybackSettings {
+            headless: false,
+            real_time: true,
+        }
+    }
+}
+
+impl InputPlayback {
+    /// Load a recording from a file
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        settings: PlaybackSettings,
+    ) -> Result<Self, InputRecordingError> {
+        Self::from_bytes(std::fs::read(path)?, settings)
+    }
+
+    /// Load a recording from an in-memory buffer
+    pub fn from_bytes(
+        data: Vec<u8>,
+        settings: PlaybackSettings,
+    ) -> Result<Self, InputRecordingError> {
+        let header_len = MAGIC.len() + std::mem::size_of::<u16>();
+        if data.len() < header_len || &data[..MAGIC.len()] != MAGIC {
+            return Err(InputRecordingError::InvalidHeader);
+        }
+
+        let version = u16::from_le_bytes([data[MAGIC.len()], data[MAGIC.len() + 1]]);
+        if version != VERSION {
+            return Err(InputRecordingError::UnsupportedVersion(version));
+        }
+
+        Ok(InputPlayback {
+            data,
+            cursor: header_len,
+            settings,
+        })
+    }
+
+    #[inline(always)]
+    pub fn get_settings(&self) -> &PlaybackSettings {
+        &self.settings
+    }
+
+    /// If every frame in this recording was already played
+    #[inline(always)]
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.data.len()
+    }
+
+    /// Read the next frame in the recording, or `None` if the recording is over
+    pub fn next_frame(&mut self) -> Result<Option<RecordedFrame>, InputRecordingError> {
+        if self.is_finished() {
+            return Ok(None);
+        }
+
+        if self.read_u8()? != FRAME_TAG {
+            return Err(self.corrupted(1));
+        }
+        let delta_seconds = f32::from_le_bytes(self.read_array()?);
+        // Also rejects negative and NaN deltas
+        if !(0.0..=MAX_FRAME_SECONDS).contains(&delta_seconds) {
+            return Err(self.corrupted(4));
+        }
+
+        let mut events = vec![];
+        while !self.is_finished() && self.data[self.cursor] == EVENT_TAG {
+            self.cursor += 1;
+            let handled = self.read_u8()? != 0;
+            let mut event = Event::new(self.read_event_type()?);
+            if handled {
+                event.make_handled();
+            }
+            events.push(event);
+        }
+
+        Ok(Some(RecordedFrame {
+            delta_seconds,
+            events,
+        }))
+    }
+
+    fn read_event_type(&mut self) -> Result<Type, InputRecordingError> {
+        let tag_offset = self.cursor;
+        let event_type = match self.read_u8()? {
+            0 => Type::WindowClose,
+            1 => Type::WindowResize {
+                new_width: u32::from_le_bytes(self.read_array()?),
+                new_height: u32::from_le_bytes(self.read_array()?),
+            },
+            2 => Type::WindowFocus,
+            3 => Type::WindowLostFocus,
+            4 => Type::WindowMoved {
+                new_x: i32::from_le_bytes(self.read_array()?),
+                new_y: i32::from_le_bytes(self.read_array()?),
+            },
+            5 => Type::AppTick,
+            6 => Type::AppUpdate,
+            7 => Type::AppRender,
+            8 => Type::KeyEvent {
+                key: Keycode::from_u16(u16::from_le_bytes(self.read_array()?)),
+                state: self.read_key_state()?,
+                repeat: self.read_u8()? != 0,
+            },
+            9 => Type::MouseButtonEvent {
+                button: self.read_mouse_button()?,
+                state: self.read_key_state()?,
+            },
+            10 => Type::MouseMoved {
+                x: f32::from_le_bytes(self.read_array()?),
+                y: f32::from_le_bytes(self.read_array()?),
+            },
+            11 => Type::MouseScrolled {
+                x: f32::from_le_bytes(self.read_array()?),
+                y: f32::from_le_bytes(self.read_array()?),
+            },
+            12 => Type::Unknown,
+            _ => {
+                return Err(InputRecordingError::Corrupted { offset: tag_offset });
+            }
+        };
+
+        Ok(event_type)
+    }
+
+    fn read_key_state(&mut self) -> Result<KeyState, InputRecordingError> {
+        match self.read_u8()? {
+            0 => Ok(KeyState::Pressed),
+            1 => Ok(KeyState::Released),
+            2 => Ok(KeyState::Repeat),
+            _ => Err(self.corrupted(1)),
+        }
+    }
+
+    fn read_mouse_button(&mut self) -> Result<MouseButton, InputRecordingError> {
+        match self.read_u8()? {
+            0 => Ok(MouseButton::Left),
+            1 => Ok(MouseButton::Right),
+            2 => Ok(MouseButton::Middle),
+            3 => Ok(MouseButton::Back),
+            4 => Ok(MouseButton::Forward),
+            5 => Ok(MouseButton::Other(u16::from_le_bytes(self.read_array()?))),
+            _ => Err(self.corrupted(1)),
+        }
+    }
+
+    #[inline(always)]
+    fn read_u8(&mut self) -> Result<u8, InputRecordingError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], InputRecordingError> {
+        let end = self.cursor + N;
+        if end > self.data.len() {
+            return Err(InputRecordingError::Corrupted {
+                offset: self.cursor,
+            });
+        }
+
+        let mut result = [0; N];
+        result.copy_from_slice(&self.data[self.cursor..end]);
+        self.cursor = end;
+        Ok(result)
+    }
+
+    /// Error pointing to a value of `size` bytes that was just read
+    #[inline(always)]
+    fn corrupted(&self, size: usize) -> InputRecordingError {
+        InputRecordingError::Corrupted {
+            offset: self.cursor - size,
+        }
+    }
+}
+
+fn encode_event_type(event_type: &Type, buffer: &mut Vec<u8>) {
+    match event_type {
+        Type::WindowClose => buffer.push(0),
+        Type::WindowResize {
+            new_width,
+            new_height,
+        } => {
+            buffer.push(1);
+            buffer.extend_from_slice(&new_width.to_le_bytes());
+            buffer.extend_from_slice(&new_height.to_le_bytes());
+        }
+        Type::WindowFocus => buffer.push(2),
+        Type::WindowLostFocus => buffer.push(3),
+        Type::WindowMoved { new_x, new_y } => {
+            buffer.push(4);
+            buffer.extend_from_slice(&new_x.to_le_bytes());
+            buffer.extend_from_slice(&new_y.to_le_bytes());
+        }
+        Type::AppTick => buffer.push(5),
+        Type::AppUpdate => buffer.push(6),
+        Type::AppRender => buffer.push(7),
+        Type::KeyEvent { key, state, repeat } => {
+            buffer.push(8);
+            buffer.extend_from_slice(&(*key as u16).to_le_bytes());
+            buffer.push(encode_key_state(state));
+            buffer.push(*repeat as u8);
+        }
+        Type::MouseButtonEvent { button, state } => {
+            buffer.push(9);
+            match button {
+                MouseButton::Left => buffer.push(0),
+                MouseButton::Right => buffer.push(1),
+                MouseButton::Middle => buffer.push(2),
+                MouseButton::Back => buffer.push(3),
+                MouseButton::Forward => buffer.push(4),
+                MouseButton::Other(code) => {
+                    buffer.push(5);
+                    buffer.extend_from_slice(&code.to_le_bytes());
+                }
+            }
+            buffer.push(encode_key_state(state));
+        }
+        Type::MouseMoved { x, y } => {
+            buffer.push(10);
+            buffer.extend_from_slice(&x.to_le_bytes());
+            buffer.extend_from_slice(&y.to_le_bytes());
+        }
+        Type::MouseScrolled { x, y } => {
+            buffer.push(11);
+            buffer.extend_from_slice(&x.to_le_bytes());
+            buffer.extend_from_slice(&y.to_le_bytes());
+        }
+        Type::Unknown => buffer.push(12),
+    }
+}
+
+#[inline(always)]
+fn encode_key_state(state: &KeyState) -> u8 {
+    match state {
+        KeyState::Pressed => 0,
+        KeyState::Released => 1,
+        KeyState::Repeat => 2,
+    }
+}
+
+impl From<std::io::Error> for InputRecordingError {
+    fn from(value: std::io::Error) -> Self {
+        InputRecordingError::Io(value)
+    }
+}
+
+impl std::fmt::Display for InputRecordingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputRecordingError::Io(err) => write!(f, "IO error: {}", err),
+            InputRecordingError::InvalidHeader => write!(f, "Invalid input recording header"),
+            InputRecordingError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported input recording version: {}", version)
+            }
+            InputRecordingError::Corrupted { offset } => {
+                write!(f, "Corrupted input recording at byte {}", offset)
+            }
+            InputRecordingError::RecordingAndPlayback => {
+                write!(f, "Can't record and play back a recording at the same time")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InputRecordingError {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::core::locking::RwLock;
+
+    use super::*;
+
+    /// Writer that shares its buffer so the test can read it after recording
+    struct SharedBuffer(Arc<RwLock<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.write().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_record_and_playback() {
+        let buffer = Arc::new(RwLock::new(vec![]));
+        let mut handled = Event::new(Type::MouseButtonEvent {
+            button: MouseButton::Other(42),
+            state: KeyState::Released,
+        });
+        handled.make_handled();
+
+        let frames = [
+            RecordedFrame {
+                delta_seconds: 0.016,
+                events: vec![
+                    Event::new(Type::KeyEvent {
+                        key: Keycode::Space,
+                        state: KeyState::Pressed,
+                        repeat: false,
+                    }),
+                    Event::new(Type::MouseMoved { x: 1.5, y: -3.0 }),
+                ],
+            },
+            RecordedFrame {
+                delta_seconds: 0.033,
+                events: vec![],
+            },
+            RecordedFrame {
+                delta_seconds: 0.0,
+                events: vec![
+                    handled,
+                    Event::new(Type::WindowResize {
+                        new_width: 720,
+                        new_height: 480,
+                    }),
+                    Event::new(Type::WindowClose),
+                ],
+            },
+        ];
+
+        {
+            let mut recorder =
+                InputRecorder::from_writer(Box::new(SharedBuffer(buffer.clone()))).unwrap();
+            for frame in frames.iter() {
+                recorder.record_frame(frame.delta_seconds).unwrap();
+                for event in frame.events.iter() {
+                    recorder.record_event(event).unwrap();
+                }
+            }
+        }
+
+        let data = buffer.read().clone();
+        let mut playback = InputPlayback::from_bytes(data, PlaybackSettings::default()).unwrap();
+        for frame in frames.iter() {
+            let played = playback.next_frame().unwrap().expect("Missing frame");
+            assert_eq!(&played, frame);
+        }
+        assert!(playback.is_finished());
+        assert!(playback.next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_playback_rejects_invalid_data() {
+        assert!(matches!(
+            InputPlayback::from_bytes(b"not a recording".to_vec(), PlaybackSettings::default()),
+            Err(InputRecordingError::InvalidHeader)
+        ));
+
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        data.extend_from_slice(&[FRAME_TAG, 0, 0, 0, 0, EVENT_TAG, 0, 200]);
+        let mut playback = InputPlayback::from_bytes(data, PlaybackSettings::default()).unwrap();
+        assert!(matches!(
+            playback.next_frame(),
+            Err(InputRecordingError::Corrupted { offset: 17 })
+        ));
+    }
+
+    #[test]
+    fn test_playback_rejects_invalid_delta() {
+        for delta_seconds in [-0.016, f32::NAN, f32::INFINITY, 1e30] {
+            let mut data = MAGIC.to_vec();
+            data.extend_from_slice(&VERSION.to_le_bytes());
+            data.push(FRAME_TAG);
+            data.extend_from_slice(&delta_seconds.to_le_bytes());
+            let mut playback =
+                InputPlayback::from_bytes(data, PlaybackSettings::default()).unwrap();
+            assert!(
+                matches!(
+                    playback.next_frame(),
+                    Err(InputRecordingError::Corrupted { offset: 11 })
+                ),
+                "Delta {delta_seconds} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_keycode_roundtrip() {
+        for code in 0..Keycode::COUNT {
+            assert_eq!(Keycode::from_u16(code) as u16, code);
+        }
+        assert_eq!(Keycode::from_u16(Keycode::COUNT), Keycode::Unknown);
+    }
+}
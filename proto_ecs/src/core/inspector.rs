@@ -0,0 +1,192 @@
+/// Built-in ImGui inspector for the entity system.
+///
+/// Attach it as an overlay with `App::add_overlay(Box::new(InspectorLayer::new()))`.
+/// It lists every world with its spatial hierarchy, lets you pause and step
+/// each world, and shows the datagroups and systems of the selected entity.
+use imgui::{TreeNodeFlags, Ui};
+
+use crate::core::layer::Layer;
+use crate::core::windowing::events::Event;
+use crate::data_group::DataGroupRegistry;
+use crate::entities::entity::{Entity, EntityID};
+use crate::entities::entity_system::{EntityPtr, EntitySystem, World, WorldID};
+use crate::systems::global_systems::GlobalSystemRegistry;
+use crate::systems::local_systems::LocalSystemRegistry;
+
+/// Overlay layer drawing the inspector windows
+#[derive(Debug, Default)]
+pub struct InspectorLayer {
+    /// Entity shown in the entity window
+    selection: Option<(WorldID, EntityID)>,
+}
+
+// -- < Implementations > --------------------------------
+
+impl InspectorLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pause controls and entity hierarchy of a world
+    fn world_ui(&mut self, ui: &Ui, world: &World) {
+        let world_id = world.get_id();
+        let _id = ui.push_id_usize(world_id as usize);
+
+        if !ui.collapsing_header(format!("World {}", world_id), TreeNodeFlags::DEFAULT_OPEN) {
+            return;
+        }
+
+        let mut paused = world.is_paused();
+        if ui.checkbox("Paused", &mut paused) {
+            world.set_paused(paused);
+        }
+        if paused {
+            ui.same_line();
+            if ui.button("Step") {
+                world.request_step();
+            }
+        }
+
+        let entities = world.get_all_entities();
+        ui.text(format!(
+            "Entities: {}, Storage: {:?}",
+            entities.len(),
+            world.get_storage_mode()
+        ));
+
+        // Children are drawn by their parents
+        for entity_ptr in entities {
+            let is_child = {
+                let entity = entity_ptr.read();
+                entity.is_spatial_entity() && !entity.is_root()
+            };
+            if !is_child {
+                self.entity_tree_ui(ui, world_id, entity_ptr);
+            }
+        }
+    }
+
+    /// Tree node for an entity and its spatial children
+    fn entity_tree_ui(&mut self, ui: &Ui, world_id: WorldID, entity_ptr: EntityPtr) {
+        // Don't keep the entity locked while drawing its children
+        let (entity_id, label, children) = {
+            let entity = entity_ptr.read();
+            let children = entity
+                .get_transform()
+                .map_or_else(Vec::new, |transform| transform.get_children().to_vec());
+            let label = format!(
+                "{} [{}]##{}",
+                entity.get_name(),
+                entity.get_id(),
+                entity.get_id()
+            );
+            (entity.get_id(), label, children)
+        };
+
+        let node = ui
+            .tree_node_config(label)
+            .open_on_arrow(true)
+            .leaf(children.is_empty())
+            .selected(self.selection == Some((world_id, entity_id)))
+            .push();
+        if ui.is_item_clicked() {
+            self.selection = Some((world_id, entity_id));
+        }
+
+        if let Some(_node) = node {
+            for child in children {
+                self.entity_tree_ui(ui, world_id, child);
+            }
+        }
+    }
+
+    /// Window with the details of the selected entity
+    fn entity_window(&mut self, ui: &Ui) {
+        let Some((world_id, entity_id)) = self.selection else {
+            return;
+        };
+
+        let entity_ptr = EntitySystem::get()
+            .with_world(world_id, |world| world.get_entity_ref(entity_id).get())
+            .flatten();
+        let Some(entity_ptr) = entity_ptr else {
+            // The entity or its world were destroyed
+            self.selection = None;
+            return;
+        };
+
+        let mut opened = true;
+        ui.window(format!("Entity {}", entity_id))
+            .opened(&mut opened)
+            .build(|| entity_ui(ui, &entity_ptr.read()));
+        if !opened {
+            self.selection = None;
+        }
+    }
+}
+
+/// Name, datagroups and systems of an entity
+fn entity_ui(ui: &Ui, entity: &Entity) {
+    ui.text(format!("Name: {}", entity.get_name()));
+    ui.text(format!("ID: {}", entity.get_id()));
+    if !entity.get_debug_info().is_empty() {
+        ui.text_wrapped(format!("Debug info: {}", entity.get_debug_info()));
+    }
+
+    if ui.collapsing_header("DataGroups", TreeNodeFlags::DEFAULT_OPEN) {
+        let dg_registry = DataGroupRegistry::get_global_registry().read();
+        for datagroup in entity.get_all_datagroups() {
+            let entry = dg_registry.get_entry_by_id(datagroup.get_id());
+            ui.bullet_text(entry.name);
+        }
+    }
+
+    if ui.collapsing_header("Local Systems", TreeNodeFlags::DEFAULT_OPEN) {
+        let ls_registry = LocalSystemRegistry::get_global_registry().read();
+        for system_id in entity.get_local_systems() {
+            let entry = ls_registry.get_entry_by_id(*system_id);
+            let stages: Vec<String> = entry
+                .functions
+                .iter()
+                .enumerate()
+                .filter(|(_, function)| function.is_some())
+                .map(|(stage_id, _)| stage_id.to_string())
+                .collect();
+            ui.bullet_text(format!("{} (stages: {})", entry.name, stages.join(", ")));
+        }
+    }
+
+    if ui.collapsing_header("Global Systems", TreeNodeFlags::DEFAULT_OPEN) {
+        let gs_registry = GlobalSystemRegistry::get_global_registry().read();
+        for system_id in entity.get_global_systems() {
+            ui.bullet_text(gs_registry.get_entry_by_id(*system_id).name);
+        }
+    }
+
+    if ui.collapsing_header("Debug", TreeNodeFlags::empty()) {
+        ui.text_wrapped(format!("{:#?}", entity));
+    }
+}
+
+impl Layer for InspectorLayer {
+    fn on_attach(&mut self) {}
+
+    fn on_detach(&mut self) {}
+
+    fn update(&mut self, _delta_time: f32) {}
+
+    fn on_event(&mut self, _event: &mut Event) {}
+
+    fn imgui_update(&mut self, _delta_time: f32, ui: &mut Ui) {
+        let es = EntitySystem::get();
+        let mut worlds = es.get_worlds_list();
+        worlds.sort_unstable();
+
+        ui.window("Inspector").build(|| {
+            for world_id in worlds {
+                es.with_world(world_id, |world| self.world_ui(ui, world));
+            }
+        });
+        self.entity_window(ui);
+    }
+}
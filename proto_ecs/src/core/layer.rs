@@ -115,19 +115,19 @@ impl LayerManager {
         self.layers.retain(|layer| !to_detach.contains(&layer.id));
     }
 
-    pub fn layers_iter(&self) -> Iter<LayerContainer> {
+    pub fn layers_iter(&self) -> Iter<'_, LayerContainer> {
         self.layers.iter()
     }
 
-    pub fn layers_iter_mut(&mut self) -> IterMut<LayerContainer> {
+    pub fn layers_iter_mut(&mut self) -> IterMut<'_, LayerContainer> {
         self.layers.iter_mut()
     }
 
-    pub fn overlays_iter(&mut self) -> Iter<LayerContainer> {
+    pub fn overlays_iter(&mut self) -> Iter<'_, LayerContainer> {
         self.overlays.iter()
     }
 
-    pub fn overlays_iter_mut(&mut self) -> IterMut<LayerContainer> {
+    pub fn overlays_iter_mut(&mut self) -> IterMut<'_, LayerContainer> {
         self.overlays.iter_mut()
     }
 }
@@ -0,0 +1,22 @@
+//! Targets of the engine diagnostics.
+//!
+//! The engine reports through the `tracing` facade instead of printing, so nothing
+//! is shown until the application installs a subscriber, like `tracing_subscriber::fmt::init()`.
+//! Every event uses one of these targets, so engine output can be filtered per subsystem,
+//! for example with `RUST_LOG=entity_system=warn,render=info`. Events about worlds and
+//! entities carry their ids in the `world_id` and `entity_id` fields.
+
+/// Application lifetime, input recording and playback
+pub const APP: &str = "app";
+
+/// Worlds, entities and systems
+pub const ENTITY_SYSTEM: &str = "entity_system";
+
+/// Render thread and render backends
+pub const RENDER: &str = "render";
+
+/// Windows and platform events
+pub const WINDOW: &str = "window";
+
+/// Asset loading
+pub const ASSETS: &str = "assets";
@@ -2,10 +2,16 @@ pub mod casting;
 pub mod common;
 pub mod het_single_mem_block;
 pub mod ids;
+pub mod input_recording;
+pub mod inspector;
 pub mod layer;
 pub mod locking;
+pub mod logging;
 pub mod math;
 pub mod platform;
+pub mod profiling;
+pub mod reflection;
+pub mod registry_manifest;
 pub mod rendering;
 pub mod time;
 pub mod windowing;
@@ -0,0 +1,152 @@
+use std::any::Any;
+use std::rc::Rc;
+
+use proto_ecs::core::casting::CanCast;
+use proto_ecs::core::windowing::events::{self, Event};
+use proto_ecs::core::windowing::window_manager::WindowManager;
+use proto_ecs::core::windowing::{Window, WindowBuilder, WindowDyn, WindowPtr};
+use tracing::debug;
+
+use crate::core::logging::WINDOW;
+use crate::core::rendering::render_thread::RenderThread;
+use crate::prelude::App;
+
+/// A window that is never shown.
+///
+/// It has no events of its own: push them with [HeadlessWindow::push_event] or
+/// [HeadlessWindow::push_event_at_frame], and they are sent to the app when it
+/// polls events. Frames are counted from 1, every time the app polls events.
+///
+/// Use [HeadlessWindow::with_instance] to access the window managed by the [WindowManager]
+#[derive(CanCast)]
+pub struct HeadlessWindow {
+    width: u32,
+    height: u32,
+    title: String,
+    use_vsync: bool,
+    /// Frames started so far
+    frame: u64,
+    /// Close the app during this frame
+    frame_limit: Option<u64>,
+    /// Events to send and the frame to send them in, in the order they were pushed
+    pending_events: Vec<(u64, Event)>,
+}
+
+impl WindowDyn for HeadlessWindow {
+    fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    fn get_heigth(&self) -> u32 {
+        self.height
+    }
+
+    fn set_vsync(&mut self, is_vsync_active: bool) {
+        // There's no display to sync with, just remember it
+        self.use_vsync = is_vsync_active;
+    }
+
+    fn get_vsync(&self) -> bool {
+        self.use_vsync
+    }
+
+    fn get_native_window(&self) -> Rc<dyn Any> {
+        Rc::new(())
+    }
+
+    fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    fn handle_window_events(&mut self, app: &mut App) {
+        self.frame += 1;
+
+        let (ready, pending) = std::mem::take(&mut self.pending_events)
+            .into_iter()
+            .partition(|(frame, _)| *frame <= self.frame);
+        self.pending_events = pending;
+
+        for (_, event) in ready {
+            self.send_event(app, event);
+        }
+
+        if self.frame_limit.is_some_and(|limit| self.frame >= limit) {
+            debug!(target: WINDOW, "Frame limit reached, closing headless window");
+            self.send_event(app, Event::new(events::Type::WindowClose));
+        }
+
+        // Same as a real window, let the render thread start the next frame
+        // when it's done with the last one
+        if RenderThread::is_last_frame_finished() {
+            RenderThread::next_frame_updated();
+        }
+    }
+
+    fn on_update(&mut self) {}
+}
+
+impl Window for HeadlessWindow {
+    fn create(window_builder: WindowBuilder) -> WindowPtr {
+        Box::new(HeadlessWindow {
+            width: window_builder.width,
+            height: window_builder.height,
+            title: window_builder.title,
+            use_vsync: false,
+            frame: 0,
+            frame_limit: None,
+            pending_events: vec![],
+        })
+    }
+}
+
+impl HeadlessWindow {
+    /// Run `f` with the window of the [WindowManager].
+    ///
+    /// Panics if the window manager was not initialized with [crate::core::platform::Platforms::Headless]
+    pub fn with_instance<R>(f: impl FnOnce(&mut HeadlessWindow) -> R) -> R {
+        let mut window_manager = WindowManager::get().write();
+        let window = window_manager
+            .get_window_mut()
+            .as_any_mut()
+            .downcast_mut::<HeadlessWindow>()
+            .expect("The current window is not a headless window");
+        f(window)
+    }
+
+    /// Send `event` to the app the next time it polls events
+    pub fn push_event(&mut self, event: Event) {
+        self.pending_events.push((self.frame + 1, event));
+    }
+
+    /// Send `event` to the app when it polls events in frame `frame`, or in the next
+    /// frame if that one already started
+    pub fn push_event_at_frame(&mut self, frame: u64, event: Event) {
+        self.pending_events.push((frame, event));
+    }
+
+    /// Close the app during frame `frame`, so it runs exactly that many frames.
+    /// Pass `None` to run until a close event is pushed
+    pub fn set_frame_limit(&mut self, frame: Option<u64>) {
+        debug_assert!(frame != Some(0), "Frames are counted from 1");
+        self.frame_limit = frame;
+    }
+
+    /// Frames started so far
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame
+    }
+
+    fn send_event(&mut self, app: &mut App, mut event: Event) {
+        // Resizing this window is up to the events, since there's no user to do it
+        if let events::Type::WindowResize {
+            new_width,
+            new_height,
+        } = event.get_type()
+        {
+            self.width = *new_width;
+            self.height = *new_height;
+        }
+
+        app.on_event(&mut event);
+    }
+}
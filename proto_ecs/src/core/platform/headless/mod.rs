@@ -0,0 +1,6 @@
+/// Platform without a display or a GPU, for running the app in CI machines and tests.
+///
+/// The window is never shown: its events are injected by the user, and rendering
+/// goes to a render backend that keeps track of resources but doesn't draw anything
+pub mod headless_window;
+pub mod null_render_backend;
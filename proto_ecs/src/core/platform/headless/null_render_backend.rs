@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use proto_ecs::core::rendering::render_api::{
+    RenderAPIBackend, RenderAPIBackendDyn, RenderAPIBackendPtr,
+};
+use tracing::info;
+
+use crate::core::logging::RENDER;
+use crate::core::math::Colorf32;
+use crate::core::rendering::buffer::BufferLayout;
+use crate::core::rendering::render_api::API;
+use crate::core::rendering::render_api::{
+    IndexBuffer, IndexBufferHandle, Shader, ShaderHandle, VertexArray, VertexArrayHandle,
+    VertexBuffer, VertexBufferHandle,
+};
+use crate::core::rendering::shader::{DataType, ShaderDataType, ShaderError, ShaderSrc};
+use crate::core::utils::handle::Allocator;
+
+/// Render backend that draws nothing.
+///
+/// Resources are allocated and checked like in a real backend, so code using
+/// handles the wrong way still fails in headless runs, but no GPU is required
+pub struct NullRenderBackend {
+    clear_color: Colorf32,
+    shader_allocator: Allocator<Shader, NullShader>,
+    vertex_array_allocator: Allocator<VertexArray, NullVertexArray>,
+    index_buffer_allocator: Allocator<IndexBuffer, NullIndexBuffer>,
+    vertex_buffer_allocator: Allocator<VertexBuffer, NullVertexBuffer>,
+}
+
+struct NullShader {
+    name: String,
+    uniforms: HashMap<String, ShaderDataType>,
+}
+
+struct NullVertexArray {
+    vertex_buffer: Option<VertexBufferHandle>,
+    index_buffer: Option<IndexBufferHandle>,
+}
+
+struct NullIndexBuffer {
+    element_count: usize,
+}
+
+struct NullVertexBuffer {
+    buffer_layout: BufferLayout,
+}
+
+impl RenderAPIBackend for NullRenderBackend {
+    fn create() -> RenderAPIBackendPtr {
+        let mut result = Box::new(NullRenderBackend {
+            clear_color: Colorf32::new(0.0, 0.0, 0.0, 1.0),
+            shader_allocator: Allocator::new(),
+            vertex_array_allocator: Allocator::new(),
+            index_buffer_allocator: Allocator::new(),
+            vertex_buffer_allocator: Allocator::new(),
+        });
+        result.init();
+        result
+    }
+}
+
+impl RenderAPIBackendDyn for NullRenderBackend {
+    fn init(&mut self) {
+        info!(target: RENDER, "Null render backend initialized, nothing will be drawn");
+    }
+
+    fn clear_color(&self) {}
+
+    fn set_clear_color(&mut self, color: Colorf32) {
+        self.clear_color = color;
+    }
+
+    fn get_api(&self) -> API {
+        API::None
+    }
+
+    fn set_viewport(&mut self, _x: u32, _y: u32, _width: u32, _height: u32) {}
+
+    fn draw_indexed(&mut self, handle: VertexArrayHandle) {
+        let vertex_array = self.vertex_array_allocator.get(handle);
+        debug_assert!(
+            vertex_array.index_buffer.is_some(),
+            "Can't draw-indexed over array with no index"
+        );
+    }
+
+    fn finish(&self) {}
+
+    // Resource creation and destruction
+    fn create_vertex_buffer(&mut self, _vertex_data: &[f32]) -> VertexBufferHandle {
+        self.vertex_buffer_allocator.allocate(NullVertexBuffer {
+            buffer_layout: BufferLayout::default(),
+        })
+    }
+    fn destroy_vertex_buffer(&mut self, handle: VertexBufferHandle) {
+        self.vertex_buffer_allocator.free(handle);
+    }
+    fn create_index_buffer(&mut self, indices: &[u32]) -> IndexBufferHandle {
+        self.index_buffer_allocator.allocate(NullIndexBuffer {
+            element_count: indices.len(),
+        })
+    }
+    fn destroy_index_buffer(&mut self, handle: IndexBufferHandle) {
+        self.index_buffer_allocator.free(handle);
+    }
+    fn create_vertex_array(&mut self) -> VertexArrayHandle {
+        self.vertex_array_allocator.allocate(NullVertexArray {
+            vertex_buffer: None,
+            index_buffer: None,
+        })
+    }
+    fn destroy_vertex_array(&mut self, handle: VertexArrayHandle) {
+        self.vertex_array_allocator.free(handle);
+    }
+    fn create_shader(
+        &mut self,
+        name: &str,
+        _vertex_src: ShaderSrc,
+        _fragment_src: ShaderSrc,
+    ) -> Result<ShaderHandle, ShaderError> {
+        // Sources are not compiled, so every shader is valid
+        Ok(self.shader_allocator.allocate(NullShader {
+            name: name.to_string(),
+            uniforms: HashMap::new(),
+        }))
+    }
+    fn destroy_shader(&mut self, handle: ShaderHandle) {
+        debug_assert!(
+            self.shader_allocator.is_live(handle),
+            "Trying to destroy unexistent shader"
+        );
+        self.shader_allocator.free(handle);
+    }
+
+    // Bindings. Only check that the resources are alive
+    fn bind_vertex_buffer(&self, handle: VertexBufferHandle) {
+        debug_assert!(self.vertex_buffer_allocator.is_live(handle));
+    }
+    fn unbind_vertex_buffer(&self) {}
+    fn bind_vertex_array(&self, handle: VertexArrayHandle) {
+        debug_assert!(self.vertex_array_allocator.is_live(handle));
+    }
+    fn unbind_vertex_array(&self) {}
+    fn bind_index_buffer(&self, handle: IndexBufferHandle) {
+        debug_assert!(self.index_buffer_allocator.is_live(handle));
+    }
+    fn unbind_index_buffer(&self) {}
+    fn bind_shader(&self, handle: ShaderHandle) {
+        debug_assert!(self.shader_allocator.is_live(handle));
+    }
+    fn unbind_shader(&self) {}
+
+    // Operations: Index buffer
+    fn get_index_buffer_count(&self, handle: IndexBufferHandle) -> u32 {
+        self.index_buffer_allocator.get(handle).element_count as u32
+    }
+
+    // Operations: Vertex Buffer
+    fn get_vertex_buffer_layout(&self, handle: VertexBufferHandle) -> &BufferLayout {
+        &self.vertex_buffer_allocator.get(handle).buffer_layout
+    }
+    fn set_vertex_buffer_layout(&mut self, handle: VertexBufferHandle, layout: BufferLayout) {
+        self.vertex_buffer_allocator.get_mut(handle).buffer_layout = layout;
+    }
+
+    // Operations: Vertex Array
+    fn set_vertex_array_vertex_buffer(
+        &mut self,
+        va_handle: VertexArrayHandle,
+        vb_handle: VertexBufferHandle,
+    ) {
+        debug_assert!(self.vertex_buffer_allocator.is_live(vb_handle));
+        self.vertex_array_allocator.get_mut(va_handle).vertex_buffer = Some(vb_handle);
+    }
+    fn set_vertex_array_index_buffer(
+        &mut self,
+        va_handle: VertexArrayHandle,
+        ib_handle: IndexBufferHandle,
+    ) {
+        debug_assert!(self.index_buffer_allocator.is_live(ib_handle));
+        self.vertex_array_allocator.get_mut(va_handle).index_buffer = Some(ib_handle);
+    }
+    fn get_vertex_array_vertex_buffer(
+        &self,
+        va_handle: VertexArrayHandle,
+    ) -> Option<VertexBufferHandle> {
+        self.vertex_array_allocator.get(va_handle).vertex_buffer
+    }
+    fn get_vertex_array_index_buffer(
+        &self,
+        va_handle: VertexArrayHandle,
+    ) -> Option<IndexBufferHandle> {
+        self.vertex_array_allocator.get(va_handle).index_buffer
+    }
+
+    // Operations: Shaders
+    fn get_shader_name(&self, handle: ShaderHandle) -> &str {
+        &self.shader_allocator.get(handle).name
+    }
+    fn shader_exists(&self, handle: ShaderHandle) -> bool {
+        self.shader_allocator.is_live(handle)
+    }
+    fn set_shader_uniform_f32(&mut self, handle: ShaderHandle, name: &str, _value: f32) {
+        self.check_uniform(handle, name, DataType::Float);
+    }
+    fn set_shader_uniform_i32(&mut self, handle: ShaderHandle, name: &str, _value: i32) {
+        self.check_uniform(handle, name, DataType::Int);
+    }
+    fn set_shader_uniform_fvec2(&mut self, handle: ShaderHandle, name: &str, _value: &macaw::Vec2) {
+        self.check_uniform(handle, name, DataType::Float2);
+    }
+    fn set_shader_uniform_fvec3(&mut self, handle: ShaderHandle, name: &str, _value: &macaw::Vec3) {
+        self.check_uniform(handle, name, DataType::Float3);
+    }
+    fn set_shader_uniform_fvec4(&mut self, handle: ShaderHandle, name: &str, _value: &macaw::Vec4) {
+        self.check_uniform(handle, name, DataType::Float4);
+    }
+    fn set_shader_uniform_fmat3(&mut self, handle: ShaderHandle, name: &str, _value: &macaw::Mat3) {
+        self.check_uniform(handle, name, DataType::Mat3);
+    }
+    fn set_shader_uniform_fmat4(&mut self, handle: ShaderHandle, name: &str, _value: &macaw::Mat4) {
+        self.check_uniform(handle, name, DataType::Mat4);
+    }
+    fn add_shader_uniform(
+        &mut self,
+        handle: ShaderHandle,
+        name: &str,
+        data_type: ShaderDataType,
+    ) -> Result<(), ShaderError> {
+        let shader = self.shader_allocator.get_mut(handle);
+
+        if let Some(prev_type) = shader.uniforms.get(name) {
+            return Err(ShaderError::UniformAlreadyExists {
+                uniform_name: name.to_string(),
+                prev_type: *prev_type,
+            });
+        };
+        shader.uniforms.insert(name.to_string(), data_type);
+        Ok(())
+    }
+}
+
+impl NullRenderBackend {
+    /// Same checks the OpenGL backend does before setting a uniform
+    #[inline(always)]
+    fn check_uniform(&self, handle: ShaderHandle, name: &str, data_type: DataType) {
+        let shader = self.shader_allocator.get(handle);
+        let uniform_type = shader
+            .uniforms
+            .get(name)
+            .expect("Trying to access unexistent uniform");
+        debug_assert!(uniform_type.data_type == data_type, "Wrong uniform type");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::rendering::buffer::BufferElement;
+    use crate::core::rendering::shader::Precision;
+
+    #[test]
+    fn test_null_backend_resources() {
+        let mut backend = NullRenderBackend::create();
+        assert!(matches!(backend.get_api(), API::None));
+
+        let vbo = backend.create_vertex_buffer(&[0.0, 1.0, 2.0]);
+        let layout = BufferLayout::from_elements(vec![BufferElement::new(
+            "a_Position".into(),
+            ShaderDataType::new(Precision::P32, DataType::Float3),
+            false,
+        )]);
+        backend.set_vertex_buffer_layout(vbo, layout);
+        let stored_layout = backend.get_vertex_buffer_layout(vbo);
+        assert_eq!(stored_layout.get_buffer_elements().len(), 1);
+        assert_eq!(stored_layout.get_stride(), 12);
+
+        let ibo = backend.create_index_buffer(&[0, 1, 2]);
+        assert_eq!(backend.get_index_buffer_count(ibo), 3);
+
+        let vao = backend.create_vertex_array();
+        backend.set_vertex_array_vertex_buffer(vao, vbo);
+        backend.set_vertex_array_index_buffer(vao, ibo);
+        assert_eq!(backend.get_vertex_array_vertex_buffer(vao), Some(vbo));
+        assert_eq!(backend.get_vertex_array_index_buffer(vao), Some(ibo));
+        backend.draw_indexed(vao);
+
+        backend.destroy_vertex_array(vao);
+        backend.destroy_index_buffer(ibo);
+        backend.destroy_vertex_buffer(vbo);
+    }
+
+    #[test]
+    fn test_null_backend_shaders() {
+        let mut backend = NullRenderBackend::create();
+        let shader = backend
+            .create_shader(
+                "test",
+                ShaderSrc::Code("not even glsl"),
+                ShaderSrc::Code("not even glsl"),
+            )
+            .expect("The null backend should accept any shader");
+        assert!(backend.shader_exists(shader));
+        assert_eq!(backend.get_shader_name(shader), "test");
+
+        let mat4 = ShaderDataType::new(Precision::P32, DataType::Mat4);
+        backend
+            .add_shader_uniform(shader, "u_Transform", mat4)
+            .expect("Should be able to add a new uniform");
+        assert!(matches!(
+            backend.add_shader_uniform(shader, "u_Transform", mat4),
+            Err(ShaderError::UniformAlreadyExists { .. })
+        ));
+        backend.set_shader_uniform_fmat4(shader, "u_Transform", &macaw::Mat4::IDENTITY);
+
+        backend.destroy_shader(shader);
+        assert!(!backend.shader_exists(shader));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod headless;
 pub mod opengl;
 pub mod winit_window;
 
@@ -6,4 +7,6 @@ pub mod winit_window;
 pub enum Platforms {
     None,
     Windows,
+    /// No display nor GPU, see [headless]
+    Headless,
 }
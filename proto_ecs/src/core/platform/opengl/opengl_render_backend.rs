@@ -7,7 +7,9 @@ use proto_ecs::core::rendering::render_api::{
 };
 use proto_ecs::core::windowing::window_manager;
 use raw_window_handle::HasRawWindowHandle;
+use tracing::{error, info};
 
+use crate::core::logging::RENDER;
 use crate::core::math::Colorf32;
 use crate::core::platform::opengl::opengl_buffer::{OpenGLIndexBuffer, OpenGLVertexBuffer};
 use crate::core::platform::opengl::opengl_shader::{OpenGLShader, UniformData};
@@ -16,7 +18,8 @@ use crate::core::platform::winit_window::WinitWindow;
 use crate::core::rendering::buffer::BufferLayout;
 use crate::core::rendering::render_api::API;
 use crate::core::rendering::render_api::{
-    IndexBufferHandle, ShaderHandle, VertexArrayHandle, VertexBufferHandle,
+    IndexBuffer, IndexBufferHandle, Shader, ShaderHandle, VertexArray, VertexArrayHandle,
+    VertexBuffer, VertexBufferHandle,
 };
 use crate::core::rendering::shader::{DataType, ShaderDataType, ShaderError, ShaderSrc};
 use crate::core::utils::handle::Allocator;
@@ -31,10 +34,10 @@ use std::mem::size_of;
 /// the window
 pub struct OpenGLRenderBackend {
     pub(super) clear_color: Colorf32,
-    shader_allocator: Allocator<OpenGLShader>,
-    vertex_array_allocator: Allocator<OpenGLVertexArray>,
-    index_buffer_allocator: Allocator<OpenGLIndexBuffer>,
-    vertex_buffer_allocator: Allocator<OpenGLVertexBuffer>,
+    shader_allocator: Allocator<Shader, OpenGLShader>,
+    vertex_array_allocator: Allocator<VertexArray, OpenGLVertexArray>,
+    index_buffer_allocator: Allocator<IndexBuffer, OpenGLIndexBuffer>,
+    vertex_buffer_allocator: Allocator<VertexBuffer, OpenGLVertexBuffer>,
     _context: RwLock<PossiblyCurrentContext>,
     gl: RwLock<Context>,
 }
@@ -125,15 +128,17 @@ impl RenderAPIBackendDyn for OpenGLRenderBackend {
     }
 
     fn init(&mut self) {
-        println!("Glow OpenGL successfully initialized!");
         let opengl_version = self.get_string(glow::VERSION);
         let opengl_renderer = self.get_string(glow::RENDERER);
         let opengl_vendor = self.get_string(glow::VENDOR);
 
-
-        println!("\tOpenGL Version: {}", opengl_version);
-        println!("\tOpenGL Renderer: {}", opengl_renderer);
-        println!("\tOpenGL Vendor: {}", opengl_vendor);
+        info!(
+            target: RENDER,
+            version = %opengl_version,
+            renderer = %opengl_renderer,
+            vendor = %opengl_vendor,
+            "Glow OpenGL successfully initialized!"
+        );
     }
 
     fn set_clear_color(&mut self, color: Colorf32) {
@@ -338,8 +343,8 @@ impl RenderAPIBackendDyn for OpenGLRenderBackend {
         let vertex_buffer = self.vertex_buffer_allocator.get(handle);
         &vertex_buffer.buffer_layout
     }
-    fn set_vertex_buffer_layout(&self, handle: VertexBufferHandle, layout: BufferLayout) {
-        let vertex_buffer = self.vertex_buffer_allocator.get(handle);
+    fn set_vertex_buffer_layout(&mut self, handle: VertexBufferHandle, layout: BufferLayout) {
+        let vertex_buffer = self.vertex_buffer_allocator.get_mut(handle);
         vertex_buffer.buffer_layout = layout;
     }
 
@@ -393,7 +398,7 @@ impl RenderAPIBackendDyn for OpenGLRenderBackend {
             }
         }
         self.unbind_vertex_buffer();
-        let vertex_array = self.vertex_array_allocator.get(va_handle);
+        let vertex_array = self.vertex_array_allocator.get_mut(va_handle);
         vertex_array.vertex_buffer = Some(vb_handle);
     }
     fn set_vertex_array_index_buffer(
@@ -401,7 +406,7 @@ impl RenderAPIBackendDyn for OpenGLRenderBackend {
         va_handle: VertexArrayHandle,
         ib_handle: IndexBufferHandle,
     ) {
-        let va = self.vertex_array_allocator.get(va_handle);
+        let va = self.vertex_array_allocator.get_mut(va_handle);
         va.index_buffer = Some(ib_handle);
     }
     fn get_vertex_array_vertex_buffer(
@@ -567,7 +572,7 @@ impl RenderAPIBackendDyn for OpenGLRenderBackend {
         name: &str,
         data_type: ShaderDataType,
     ) -> Result<(), ShaderError> {
-        let shader = self.shader_allocator.get(handle);
+        let shader = self.shader_allocator.get_mut(handle);
 
         if let Some(uniform_data) = shader.uniforms.get(name) {
             return Err(ShaderError::UniformAlreadyExists {
@@ -634,7 +639,7 @@ impl OpenGLRenderBackend {
                     // Delete program in progress
                     gl.delete_program(program);
 
-                    eprintln!("Error creating shader: {}", info_log);
+                    error!(target: RENDER, "Error creating shader: {}", info_log);
                     return Err(ShaderError::CompilationError(info_log));
                 }
 
@@ -654,7 +659,7 @@ impl OpenGLRenderBackend {
                     gl.delete_shader(shader);
                 }
 
-                eprintln!("Error linking program: {}", info_log);
+                error!(target: RENDER, "Error linking program: {}", info_log);
                 return Err(ShaderError::CompilationError(info_log));
             }
 
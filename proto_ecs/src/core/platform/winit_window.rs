@@ -14,6 +14,7 @@ use proto_ecs::core::windowing::events;
 use proto_ecs::core::windowing::events::Event;
 use proto_ecs::core::windowing::{Window, WindowDyn, WindowPtr};
 use raw_window_handle::HasRawWindowHandle;
+use tracing::debug;
 use winit::dpi::LogicalSize;
 use winit::event::{MouseButton, MouseScrollDelta};
 use winit::event_loop::EventLoop;
@@ -23,6 +24,7 @@ use winit::platform::pump_events::EventLoopExtPumpEvents;
 use winit::window::{Window as winit_Window, WindowBuilder};
 
 use crate::core::casting::CanCast;
+use crate::core::logging::WINDOW;
 use crate::core::rendering::render_thread::RenderThread;
 use crate::core::windowing::keys::Keycode;
 use crate::prelude::App;
@@ -179,7 +181,7 @@ impl Window for WinitWindow {
                     })
                     .unwrap();
                 let samples = config.num_samples();
-                println!("Num samples: {samples}");
+                debug!(target: WINDOW, "Num samples: {samples}");
                 config
             })
             .expect("Failed to create Winit Window");
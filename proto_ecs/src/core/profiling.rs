@@ -0,0 +1,285 @@
+/// Low overhead profiling of the entity system, exported as Chrome trace events.
+///
+/// Timed sections are recorded with [ProfileScope] guards while profiling is enabled.
+/// Enable it at runtime with `Profiler::set_enabled`, or from startup with the `profiling`
+/// cargo feature. When disabled, a scope costs a single atomic load.
+///
+/// Save the trace with `Profiler::write_chrome_trace` and open it in chrome://tracing
+/// or https://ui.perfetto.dev. Threads keep the names of the threads that recorded
+/// the events, like the "Entity System Thread {i}" threads of the entity system pool.
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+use crate::core::locking::RwLock;
+use crate::core::utils::json::escape_json;
+use crate::systems::local_systems::{LocalSystemRegistry, SystemClassID};
+
+/// Category of the events recorded by the entity system
+pub const ENTITY_SYSTEM_CATEGORY: &str = "entity_system";
+
+/// A timed section of a thread
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    pub name: Cow<'static, str>,
+    pub category: &'static str,
+    /// Thread that recorded this event
+    pub thread_id: u64,
+    /// Start of the event since the profiler started, in microseconds
+    pub start_us: f64,
+    pub duration_us: f64,
+    pub args: Vec<(Cow<'static, str>, f64)>,
+}
+
+/// Collects the events of all the threads
+#[derive(Debug)]
+pub struct Profiler {
+    enabled: AtomicBool,
+    start: Instant,
+    events: RwLock<Vec<TraceEvent>>,
+    /// Name of each thread that recorded events, by thread id
+    thread_names: RwLock<Vec<(u64, String)>>,
+    next_thread_id: AtomicU64,
+}
+
+/// Records the time from its creation to its drop as a [TraceEvent], if profiling is enabled
+#[derive(Debug)]
+#[must_use = "The scope is recorded when dropped"]
+pub struct ProfileScope {
+    name: Cow<'static, str>,
+    category: &'static str,
+    start: Option<Instant>,
+    args: Vec<(Cow<'static, str>, f64)>,
+}
+
+/// Time spent by each local system during a stage, added up over all the entities running it
+#[derive(Debug)]
+pub struct LocalSystemTimings {
+    /// Nanoseconds and entities run per local system, by local system id
+    timings: Vec<(AtomicU64, AtomicU64)>,
+}
+
+lazy_static! {
+    static ref PROFILER: Profiler = Profiler::new();
+}
+
+thread_local! {
+    /// Id of the current thread in the trace, 0 until it records its first event
+    static THREAD_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+// -- < Implementations > --------------------------------
+
+impl Profiler {
+    fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(cfg!(feature = "profiling")),
+            start: Instant::now(),
+            events: Default::default(),
+            thread_names: Default::default(),
+            next_thread_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn get() -> &'static Profiler {
+        &PROFILER
+    }
+
+    /// If timed sections are being recorded
+    #[inline(always)]
+    pub fn is_enabled() -> bool {
+        PROFILER.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Start or stop recording timed sections. Already recorded events are kept
+    pub fn set_enabled(enabled: bool) {
+        PROFILER.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Id of the current thread in the trace, registering its name the first time
+    fn current_thread_id(&self) -> u64 {
+        THREAD_ID.with(|thread_id| {
+            if thread_id.get() == 0 {
+                let id = self.next_thread_id.fetch_add(1, Ordering::Relaxed);
+                let thread = std::thread::current();
+                let name = match thread.name() {
+                    Some(name) => name.to_owned(),
+                    None => format!("Thread {id}"),
+                };
+                self.thread_names.write().push((id, name));
+                thread_id.set(id);
+            }
+            thread_id.get()
+        })
+    }
+
+    /// Record a section of the current thread that started at `start` and ends now
+    pub fn record(
+        &self,
+        name: Cow<'static, str>,
+        category: &'static str,
+        start: Instant,
+        args: Vec<(Cow<'static, str>, f64)>,
+    ) {
+        let start_us = start.saturating_duration_since(self.start).as_secs_f64() * 1e6;
+        let event = TraceEvent {
+            name,
+            category,
+            thread_id: self.current_thread_id(),
+            start_us,
+            duration_us: start.elapsed().as_secs_f64() * 1e6,
+            args,
+        };
+        self.events.write().push(event);
+    }
+
+    /// Take all the events recorded so far
+    pub fn take_events() -> Vec<TraceEvent> {
+        std::mem::take(&mut *PROFILER.events.write())
+    }
+
+    /// Write the events recorded so far as Chrome trace event JSON to `writer`, and forget them
+    pub fn write_chrome_trace_to<W: Write>(mut writer: W) -> std::io::Result<()> {
+        let events = Profiler::take_events();
+        let thread_names = PROFILER.thread_names.read().clone();
+
+        writer.write_all(b"{\"traceEvents\":[")?;
+        let mut first = true;
+        for (thread_id, name) in thread_names.iter() {
+            if !first {
+                writer.write_all(b",")?;
+            }
+            first = false;
+            write!(
+                writer,
+                "\n{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{},\"args\":{{\"name\":\"{}\"}}}}",
+                thread_id,
+                escape_json(name)
+            )?;
+        }
+
+        for event in events.iter() {
+            if !first {
+                writer.write_all(b",")?;
+            }
+            first = false;
+            write!(
+                writer,
+                "\n{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"pid\":1,\"tid\":{},\"ts\":{:.3},\"dur\":{:.3},\"args\":{{",
+                escape_json(&event.name),
+                escape_json(event.category),
+                event.thread_id,
+                event.start_us,
+                event.duration_us
+            )?;
+            for (i, (name, value)) in event.args.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",")?;
+                }
+                write!(writer, "\"{}\":{}", escape_json(name), value)?;
+            }
+            writer.write_all(b"}}")?;
+        }
+        writer.write_all(b"\n],\"displayTimeUnit\":\"ms\"}\n")?;
+        writer.flush()
+    }
+
+    /// Write the events recorded so far as a Chrome trace file at `path`, and forget them
+    pub fn write_chrome_trace<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+        Profiler::write_chrome_trace_to(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl ProfileScope {
+    #[inline(always)]
+    pub fn new(name: impl Into<Cow<'static, str>>, category: &'static str) -> Self {
+        let start = Profiler::is_enabled().then(Instant::now);
+        Self {
+            name: if start.is_some() {
+                name.into()
+            } else {
+                Cow::Borrowed("")
+            },
+            category,
+            start,
+            args: Vec::new(),
+        }
+    }
+
+    /// If this scope will be recorded
+    #[inline(always)]
+    pub fn is_recording(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// Add an argument shown with the event. Ignored if the scope is not recording
+    #[inline(always)]
+    pub fn add_arg(&mut self, name: impl Into<Cow<'static, str>>, value: f64) {
+        if self.is_recording() {
+            self.args.push((name.into(), value));
+        }
+    }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            Profiler::get().record(
+                std::mem::take(&mut self.name),
+                self.category,
+                start,
+                std::mem::take(&mut self.args),
+            );
+        }
+    }
+}
+
+impl LocalSystemTimings {
+    pub fn new() -> Self {
+        let count = LocalSystemRegistry::get_global_registry()
+            .read()
+            .get_local_system_count();
+        Self {
+            timings: (0..count)
+                .map(|_| (AtomicU64::new(0), AtomicU64::new(0)))
+                .collect(),
+        }
+    }
+
+    /// Add the time it took to run local system `id` on `entities` entities
+    #[inline(always)]
+    pub fn add(&self, id: SystemClassID, elapsed: Duration, entities: u64) {
+        if let Some((nanos, count)) = self.timings.get(id as usize) {
+            nanos.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+            count.fetch_add(entities, Ordering::Relaxed);
+        }
+    }
+
+    /// Add the timings of the local systems that ran as arguments of `scope`, and reset them
+    pub fn drain_into(&self, scope: &mut ProfileScope) {
+        let ls_registry = LocalSystemRegistry::get_global_registry().read();
+        for (id, (nanos, count)) in self.timings.iter().enumerate() {
+            let count = count.swap(0, Ordering::Relaxed);
+            let nanos = nanos.swap(0, Ordering::Relaxed);
+            if count == 0 {
+                continue;
+            }
+
+            let name = ls_registry.get_entry_by_id(id as SystemClassID).name;
+            scope.add_arg(format!("{name} us"), nanos as f64 / 1e3);
+            scope.add_arg(format!("{name} entities"), count as f64);
+        }
+    }
+}
+
+impl Default for LocalSystemTimings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
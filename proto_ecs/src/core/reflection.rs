@@ -0,0 +1,325 @@
+/// Runtime reflection of datagroup fields.
+///
+/// Types deriving [Reflect] describe their fields with a static [TypeInfo], and their
+/// fields can be read and written by path, like `"camera.position"`, without knowing
+/// the type. Leaf values are exchanged as [ReflectValue]s. Used by tools like inspectors,
+/// serialization and scripting.
+///
+/// Datagroups registered with `reflect = true` store their reflection in the registry,
+/// see `DataGroupRegistryEntry::reflection`.
+pub use ecs_macros::Reflect;
+use macaw::{Quat, Vec2, Vec3, Vec4};
+
+/// Static description of a reflected type
+#[derive(Debug)]
+pub enum TypeInfo {
+    /// Read and written as a whole, like numbers or strings
+    Value { type_name: &'static str },
+    /// Struct with named fields
+    Struct {
+        type_name: &'static str,
+        fields: &'static [FieldInfo],
+    },
+    /// Enum without data in its variants
+    Enum {
+        type_name: &'static str,
+        variants: &'static [&'static str],
+    },
+}
+
+/// Static description of a field of a reflected struct
+#[derive(Debug)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    pub type_name: &'static str,
+    /// Offset of the field from the start of the struct, in bytes
+    pub offset: usize,
+    pub type_info: fn() -> &'static TypeInfo,
+}
+
+/// A leaf value read from or written to a reflected type
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReflectValue {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    Quat(Quat),
+    /// Name of an enum variant
+    Enum(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReflectError {
+    /// No field at this path
+    UnknownField(String),
+    /// The type is not read and written as a whole. Structs have to be accessed field by field
+    NotAValue(&'static str),
+    /// The value can't be written to this type
+    TypeMismatch {
+        type_name: &'static str,
+        value: ReflectValue,
+    },
+    /// The number doesn't fit in this type
+    OutOfRange {
+        type_name: &'static str,
+        value: ReflectValue,
+    },
+    /// The enum has no variant with this name
+    UnknownVariant {
+        type_name: &'static str,
+        variant: String,
+    },
+}
+
+/// Types whose fields can be inspected and edited at runtime. Derive it with `#[derive(Reflect)]`,
+/// fields marked with `#[reflect(skip)]` are left out.
+///
+/// Implemented for numbers, `bool`, `String`, and macaw vectors and quaternions
+pub trait Reflect {
+    /// Description of this type
+    fn type_info() -> &'static TypeInfo
+    where
+        Self: Sized;
+
+    /// Description of the type of this value
+    fn reflect_type_info(&self) -> &'static TypeInfo;
+
+    /// This value as a whole, or `None` for structs
+    fn get_value(&self) -> Option<ReflectValue> {
+        None
+    }
+
+    /// Overwrite this value as a whole
+    fn set_value(&mut self, _value: ReflectValue) -> Result<(), ReflectError> {
+        Err(ReflectError::NotAValue(
+            self.reflect_type_info().type_name(),
+        ))
+    }
+
+    /// Field with name `name`, for structs
+    fn field(&self, _name: &str) -> Option<&dyn Reflect> {
+        None
+    }
+
+    /// Mutable field with name `name`, for structs
+    fn field_mut(&mut self, _name: &str) -> Option<&mut dyn Reflect> {
+        None
+    }
+}
+
+// -- < Implementations > --------------------------------
+
+impl TypeInfo {
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            TypeInfo::Value { type_name }
+            | TypeInfo::Struct { type_name, .. }
+            | TypeInfo::Enum { type_name, .. } => type_name,
+        }
+    }
+
+    /// Fields of a struct, empty for other types
+    #[inline]
+    pub fn fields(&self) -> &'static [FieldInfo] {
+        match self {
+            TypeInfo::Struct { fields, .. } => fields,
+            _ => &[],
+        }
+    }
+}
+
+impl<'a> dyn Reflect + 'a {
+    /// Value at `path`, a list of field names separated by dots. An empty path is this value
+    pub fn path(&self, path: &str) -> Result<&dyn Reflect, ReflectError> {
+        let mut current: &dyn Reflect = self;
+        for name in path.split('.').filter(|name| !name.is_empty()) {
+            current = current
+                .field(name)
+                .ok_or_else(|| ReflectError::UnknownField(path.to_owned()))?;
+        }
+        Ok(current)
+    }
+
+    /// Mutable value at `path`, see [Self::path]
+    pub fn path_mut(&mut self, path: &str) -> Result<&mut dyn Reflect, ReflectError> {
+        let mut current: &mut dyn Reflect = self;
+        for name in path.split('.').filter(|name| !name.is_empty()) {
+            current = current
+                .field_mut(name)
+                .ok_or_else(|| ReflectError::UnknownField(path.to_owned()))?;
+        }
+        Ok(current)
+    }
+
+    /// Read the value at `path`, see [Self::path]
+    pub fn get_path(&self, path: &str) -> Result<ReflectValue, ReflectError> {
+        let target = self.path(path)?;
+        target.get_value().ok_or(ReflectError::NotAValue(
+            target.reflect_type_info().type_name(),
+        ))
+    }
+
+    /// Write the value at `path`, see [Self::path]
+    pub fn set_path(&mut self, path: &str, value: ReflectValue) -> Result<(), ReflectError> {
+        self.path_mut(path)?.set_value(value)
+    }
+}
+
+/// Implement [Reflect] for types read and written as a whole
+macro_rules! impl_reflect_value {
+    ($t:ty, |$self_:ident| $get:expr, |$value:ident| $set:expr) => {
+        impl Reflect for $t {
+            fn type_info() -> &'static TypeInfo {
+                static INFO: TypeInfo = TypeInfo::Value {
+                    type_name: stringify!($t),
+                };
+                &INFO
+            }
+
+            #[inline]
+            fn reflect_type_info(&self) -> &'static TypeInfo {
+                <$t as Reflect>::type_info()
+            }
+
+            #[inline]
+            fn get_value(&self) -> Option<ReflectValue> {
+                let $self_ = self;
+                Some($get)
+            }
+
+            fn set_value(&mut self, $value: ReflectValue) -> Result<(), ReflectError> {
+                *self = $set?;
+                Ok(())
+            }
+        }
+    };
+}
+
+/// Convert an integer value to `T`
+fn int_from_value<T>(type_name: &'static str, value: ReflectValue) -> Result<T, ReflectError>
+where
+    T: TryFrom<i64> + TryFrom<u64>,
+{
+    let converted = match &value {
+        ReflectValue::Int(v) => T::try_from(*v).ok(),
+        ReflectValue::UInt(v) => T::try_from(*v).ok(),
+        _ => return Err(ReflectError::TypeMismatch { type_name, value }),
+    };
+    converted.ok_or(ReflectError::OutOfRange { type_name, value })
+}
+
+/// Convert a number value to a float
+fn float_from_value(type_name: &'static str, value: ReflectValue) -> Result<f64, ReflectError> {
+    match value {
+        ReflectValue::Float(v) => Ok(v),
+        ReflectValue::Int(v) => Ok(v as f64),
+        ReflectValue::UInt(v) => Ok(v as f64),
+        value => Err(ReflectError::TypeMismatch { type_name, value }),
+    }
+}
+
+macro_rules! impl_reflect_int {
+    ($variant:ident, $($t:ty),*) => {
+        $(impl_reflect_value!(
+            $t,
+            |v| ReflectValue::$variant(*v as _),
+            |value| int_from_value::<$t>(stringify!($t), value)
+        );)*
+    };
+}
+
+impl_reflect_int!(Int, i8, i16, i32, i64, isize);
+impl_reflect_int!(UInt, u8, u16, u32, u64, usize);
+
+impl_reflect_value!(f32, |v| ReflectValue::Float(*v as f64), |value| {
+    float_from_value("f32", value).map(|v| v as f32)
+});
+impl_reflect_value!(f64, |v| ReflectValue::Float(*v), |value| float_from_value(
+    "f64", value
+));
+
+/// Implement [Reflect] for types stored in a single [ReflectValue] variant
+macro_rules! impl_reflect_variant {
+    ($t:ty, $variant:ident) => {
+        impl_reflect_value!(
+            $t,
+            |v| ReflectValue::$variant(v.clone()),
+            |value| match value {
+                ReflectValue::$variant(v) => Ok(v),
+                value => Err(ReflectError::TypeMismatch {
+                    type_name: stringify!($t),
+                    value,
+                }),
+            }
+        );
+    };
+}
+
+impl_reflect_variant!(bool, Bool);
+impl_reflect_variant!(String, String);
+impl_reflect_variant!(Vec2, Vec2);
+impl_reflect_variant!(Vec3, Vec3);
+impl_reflect_variant!(Vec4, Vec4);
+impl_reflect_variant!(Quat, Quat);
+
+impl std::fmt::Display for ReflectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReflectError::UnknownField(path) => write!(f, "Unknown field: {}", path),
+            ReflectError::NotAValue(type_name) => {
+                write!(f, "'{}' can't be accessed as a single value", type_name)
+            }
+            ReflectError::TypeMismatch { type_name, value } => {
+                write!(f, "Can't write {:?} to '{}'", value, type_name)
+            }
+            ReflectError::OutOfRange { type_name, value } => {
+                write!(f, "{:?} is out of range for '{}'", value, type_name)
+            }
+            ReflectError::UnknownVariant { type_name, variant } => {
+                write!(f, "'{}' has no variant '{}'", type_name, variant)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReflectError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reflect_values() {
+        let mut num: u8 = 1;
+        let num_ref: &mut dyn Reflect = &mut num;
+        assert_eq!(num_ref.get_path(""), Ok(ReflectValue::UInt(1)));
+        num_ref.set_path("", ReflectValue::Int(200)).unwrap();
+        assert!(matches!(
+            num_ref.set_value(ReflectValue::Int(-1)),
+            Err(ReflectError::OutOfRange { .. })
+        ));
+        assert!(matches!(
+            num_ref.set_value(ReflectValue::Bool(true)),
+            Err(ReflectError::TypeMismatch { .. })
+        ));
+        assert_eq!(num, 200);
+
+        let mut float: f32 = 0.0;
+        float.set_value(ReflectValue::Int(3)).unwrap();
+        assert_eq!(float.get_value(), Some(ReflectValue::Float(3.0)));
+
+        let mut position = Vec3::ZERO;
+        position.set_value(ReflectValue::Vec3(Vec3::ONE)).unwrap();
+        assert_eq!(position, Vec3::ONE);
+        assert!(matches!(
+            (&position as &dyn Reflect).get_path("x"),
+            Err(ReflectError::UnknownField(_))
+        ));
+    }
+}
@@ -0,0 +1,398 @@
+/// Manifest pinning the ids of datagroups and systems across builds.
+///
+/// Datagroup ids follow the order of their name crcs and system ids follow their running
+/// order, so adding or removing one of them can shift the ids of the others. Anything that
+/// stores ids, like saved worlds or input recordings, then breaks in the next build.
+///
+/// A manifest saved from one build maps the `NAME` and `NAME_CRC` of every datagroup and
+/// system to its id. Initializing with `App::initialize_with_manifest` gives the same ids to
+/// the same names whenever their `before` and `after` dependencies allow it, and reports
+/// everything that differs from the manifest as a [ManifestMismatch].
+///
+/// Manifests are plain text so they're easy to review in version control:
+/// ```text
+/// # proto_ecs registry manifest
+/// [datagroups]
+/// 0 0xa6fa2c3e AnimationDataGroup
+/// 1 0xce479a0c MeshDataGroup
+/// [local_systems]
+/// 0 0x784dd132 Test
+/// 1 0x4b7127c9 TestOpt
+/// [global_systems]
+/// ```
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::data_group::DataGroupRegistry;
+use crate::systems::global_systems::GlobalSystemRegistry;
+use crate::systems::local_systems::LocalSystemRegistry;
+
+/// Registry an id belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistryKind {
+    DataGroup,
+    LocalSystem,
+    GlobalSystem,
+}
+
+/// Id of a datagroup or system in the build a manifest was saved from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub id: u32,
+    pub name_crc: u32,
+    pub name: String,
+}
+
+/// Ids of every datagroup and system of a build, sorted by id
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RegistryManifest {
+    datagroups: Vec<ManifestEntry>,
+    local_systems: Vec<ManifestEntry>,
+    global_systems: Vec<ManifestEntry>,
+}
+
+/// Difference between a manifest and the current build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestMismatch {
+    /// In the manifest, but not in this build
+    Missing { kind: RegistryKind, name: String },
+    /// In this build, but not in the manifest
+    New { kind: RegistryKind, name: String },
+    /// Got a different id than the one in the manifest
+    IdChanged {
+        kind: RegistryKind,
+        name: String,
+        manifest_id: u32,
+        id: u32,
+    },
+}
+
+/// Errors reading a manifest
+#[derive(Debug)]
+pub enum ManifestError {
+    Io(std::io::Error),
+    /// A line that is not a section header nor an entry
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+// -- < Implementations > --------------------------------
+
+impl RegistryKind {
+    const ALL: [RegistryKind; 3] = [
+        RegistryKind::DataGroup,
+        RegistryKind::LocalSystem,
+        RegistryKind::GlobalSystem,
+    ];
+
+    /// Name of the section of this registry in a manifest file
+    fn section(self) -> &'static str {
+        match self {
+            RegistryKind::DataGroup => "datagroups",
+            RegistryKind::LocalSystem => "local_systems",
+            RegistryKind::GlobalSystem => "global_systems",
+        }
+    }
+}
+
+impl std::fmt::Display for RegistryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryKind::DataGroup => write!(f, "Datagroup"),
+            RegistryKind::LocalSystem => write!(f, "Local system"),
+            RegistryKind::GlobalSystem => write!(f, "Global system"),
+        }
+    }
+}
+
+impl RegistryManifest {
+    /// Manifest of initialized registries
+    pub fn from_registries(
+        dg_registry: &DataGroupRegistry,
+        ls_registry: &LocalSystemRegistry,
+        gs_registry: &GlobalSystemRegistry,
+    ) -> RegistryManifest {
+        let entry = |id, name_crc, name: &str| ManifestEntry {
+            id,
+            name_crc,
+            name: name.to_owned(),
+        };
+
+        RegistryManifest {
+            datagroups: dg_registry
+                .into_iter()
+                .map(|e| entry(e.id, e.name_crc, e.name))
+                .collect(),
+            local_systems: ls_registry
+                .get_entries()
+                .iter()
+                .map(|e| entry(e.id, e.name_crc, e.name))
+                .collect(),
+            global_systems: gs_registry
+                .get_entries()
+                .iter()
+                .map(|e| entry(e.id, e.name_crc, e.name))
+                .collect(),
+        }
+    }
+
+    /// Manifest of the global registries. The app should be initialized
+    pub fn from_global_registries() -> RegistryManifest {
+        RegistryManifest::from_registries(
+            &DataGroupRegistry::get_global_registry().read(),
+            &LocalSystemRegistry::get_global_registry().read(),
+            &GlobalSystemRegistry::get_global_registry().read(),
+        )
+    }
+
+    /// Entries of a registry, sorted by id
+    #[inline]
+    pub fn get_entries(&self, kind: RegistryKind) -> &[ManifestEntry] {
+        match kind {
+            RegistryKind::DataGroup => &self.datagroups,
+            RegistryKind::LocalSystem => &self.local_systems,
+            RegistryKind::GlobalSystem => &self.global_systems,
+        }
+    }
+
+    fn get_entries_mut(&mut self, kind: RegistryKind) -> &mut Vec<ManifestEntry> {
+        match kind {
+            RegistryKind::DataGroup => &mut self.datagroups,
+            RegistryKind::LocalSystem => &mut self.local_systems,
+            RegistryKind::GlobalSystem => &mut self.global_systems,
+        }
+    }
+
+    /// Id the manifest gives to the datagroup or system with name crc `name_crc`
+    pub fn get_id(&self, kind: RegistryKind, name_crc: u32) -> Option<u32> {
+        self.get_entries(kind)
+            .iter()
+            .find(|entry| entry.name_crc == name_crc)
+            .map(|entry| entry.id)
+    }
+
+    /// Differences between this manifest and the manifest of another build, like
+    /// the one from [RegistryManifest::from_global_registries]
+    pub fn diff(&self, build: &RegistryManifest) -> Vec<ManifestMismatch> {
+        let mut mismatches = vec![];
+        for kind in RegistryKind::ALL {
+            for entry in self.get_entries(kind) {
+                match build.get_id(kind, entry.name_crc) {
+                    None => mismatches.push(ManifestMismatch::Missing {
+                        kind,
+                        name: entry.name.clone(),
+                    }),
+                    Some(id) if id != entry.id => mismatches.push(ManifestMismatch::IdChanged {
+                        kind,
+                        name: entry.name.clone(),
+                        manifest_id: entry.id,
+                        id,
+                    }),
+                    Some(_) => {}
+                }
+            }
+
+            for entry in build.get_entries(kind) {
+                if self.get_id(kind, entry.name_crc).is_none() {
+                    mismatches.push(ManifestMismatch::New {
+                        kind,
+                        name: entry.name.clone(),
+                    });
+                }
+            }
+        }
+        mismatches
+    }
+
+    /// Write this manifest as text to `writer`
+    pub fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "# proto_ecs registry manifest")?;
+        for kind in RegistryKind::ALL {
+            writeln!(writer, "[{}]", kind.section())?;
+            for entry in self.get_entries(kind) {
+                writeln!(
+                    writer,
+                    "{} {:#010x} {}",
+                    entry.id, entry.name_crc, entry.name
+                )?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// Write this manifest to a file at `path`
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Read a manifest written by [RegistryManifest::write_to]
+    pub fn read_from<R: BufRead>(reader: R) -> Result<RegistryManifest, ManifestError> {
+        let mut manifest = RegistryManifest::default();
+        let mut section = None;
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(ManifestError::Io)?;
+            let line = line.trim();
+            let parse_error = |message: String| ManifestError::Parse {
+                line: i + 1,
+                message,
+            };
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let kind = RegistryKind::ALL
+                    .into_iter()
+                    .find(|kind| kind.section() == name)
+                    .ok_or_else(|| parse_error(format!("Unknown section '{name}'")))?;
+                section = Some(kind);
+                continue;
+            }
+
+            let kind =
+                section.ok_or_else(|| parse_error("Entry outside of a section".to_owned()))?;
+            let mut fields = line.splitn(3, ' ');
+            let (Some(id), Some(name_crc), Some(name)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(parse_error(format!(
+                    "Expected '<id> <name crc> <name>', found '{line}'"
+                )));
+            };
+            let id = id
+                .parse()
+                .map_err(|_| parse_error(format!("Invalid id '{id}'")))?;
+            let name_crc = name_crc
+                .strip_prefix("0x")
+                .and_then(|crc| u32::from_str_radix(crc, 16).ok())
+                .ok_or_else(|| parse_error(format!("Invalid name crc '{name_crc}'")))?;
+
+            manifest.get_entries_mut(kind).push(ManifestEntry {
+                id,
+                name_crc,
+                name: name.to_owned(),
+            });
+        }
+
+        for kind in RegistryKind::ALL {
+            manifest.get_entries_mut(kind).sort_by_key(|entry| entry.id);
+        }
+        Ok(manifest)
+    }
+
+    /// Read a manifest file at `path`
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<RegistryManifest, ManifestError> {
+        let file = File::open(path).map_err(ManifestError::Io)?;
+        RegistryManifest::read_from(BufReader::new(file))
+    }
+}
+
+impl std::fmt::Display for ManifestMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestMismatch::Missing { kind, name } => {
+                write!(
+                    f,
+                    "{kind} '{name}' is in the manifest but not in this build"
+                )
+            }
+            ManifestMismatch::New { kind, name } => {
+                write!(f, "{kind} '{name}' is not in the manifest")
+            }
+            ManifestMismatch::IdChanged {
+                kind,
+                name,
+                manifest_id,
+                id,
+            } => write!(
+                f,
+                "{kind} '{name}' has id {id} instead of {manifest_id} from the manifest"
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestError::Io(err) => write!(f, "Failed to read manifest: {err}"),
+            ManifestError::Parse { line, message } => {
+                write!(f, "Invalid manifest at line {line}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = "# proto_ecs registry manifest
+[datagroups]
+1 0x00000014 Mesh
+0 0x0000000a Animation
+[local_systems]
+0 0x0000001e Move
+[global_systems]
+";
+
+    #[test]
+    fn test_manifest_read_write() {
+        let manifest = RegistryManifest::read_from(MANIFEST.as_bytes()).unwrap();
+        assert_eq!(manifest.get_id(RegistryKind::DataGroup, 10), Some(0));
+        assert_eq!(manifest.get_id(RegistryKind::DataGroup, 20), Some(1));
+        assert_eq!(manifest.get_id(RegistryKind::LocalSystem, 30), Some(0));
+        assert_eq!(manifest.get_id(RegistryKind::GlobalSystem, 30), None);
+
+        let mut text = Vec::new();
+        manifest.write_to(&mut text).unwrap();
+        let read_back = RegistryManifest::read_from(text.as_slice()).unwrap();
+        assert_eq!(manifest, read_back);
+
+        let err = RegistryManifest::read_from("0 0x1 Outside".as_bytes()).unwrap_err();
+        assert!(matches!(err, ManifestError::Parse { line: 1, .. }));
+        let err = RegistryManifest::read_from("[datagroups]\n0 12 Mesh".as_bytes()).unwrap_err();
+        assert!(matches!(err, ManifestError::Parse { line: 2, .. }));
+    }
+
+    #[test]
+    fn test_manifest_diff() {
+        let manifest = RegistryManifest::read_from(MANIFEST.as_bytes()).unwrap();
+        let build = RegistryManifest::read_from(
+            "[datagroups]
+0 0x00000014 Mesh
+1 0x00000028 Camera
+[local_systems]
+0 0x0000001e Move"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest.diff(&build),
+            [
+                ManifestMismatch::Missing {
+                    kind: RegistryKind::DataGroup,
+                    name: "Animation".to_owned()
+                },
+                ManifestMismatch::IdChanged {
+                    kind: RegistryKind::DataGroup,
+                    name: "Mesh".to_owned(),
+                    manifest_id: 1,
+                    id: 0
+                },
+                ManifestMismatch::New {
+                    kind: RegistryKind::DataGroup,
+                    name: "Camera".to_owned()
+                },
+            ]
+        );
+        assert!(manifest.diff(&manifest).is_empty());
+    }
+}
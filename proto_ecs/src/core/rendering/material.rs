@@ -1,4 +1,5 @@
-use crate::core::utils::handle::{Allocator, Handle};
+use crate::core::utils::handle::Handle;
+use crate::core::utils::slot_map::SlotMap;
 
 use super::{render_api::ShaderHandle, shader::ShaderDataTypeValue};
 use std::collections::HashMap;
@@ -23,5 +24,5 @@ impl Material {
     }
 }
 
-pub type MaterialAllocator = Allocator<Material>;
-pub type MaterialHandle = Handle;
\ No newline at end of file
+pub type MaterialAllocator = SlotMap<MaterialHandle, Material>;
+pub type MaterialHandle = Handle<Material>;
\ No newline at end of file
@@ -4,8 +4,10 @@ use lazy_static::lazy_static;
 use proto_ecs::core::locking::RwLock;
 use proto_ecs::core::rendering::render_api::RenderCommand;
 use proto_ecs::core::windowing::window_manager::WindowManager;
+use tracing::{error, info};
 
 use crate::core::assets_management::models::{ModelHandle, ModelManager};
+use crate::core::logging;
 use crate::core::rendering::material::MaterialAllocator;
 use crate::core::utils::handle::Handle;
 
@@ -62,7 +64,8 @@ impl Render {
             render_thread: None,
         });
 
-        println!("Starting Render Thread...");
+        info!(target: logging::RENDER, "Starting Render Thread...");
+        RenderThread::set_running();
         let render_handle = std::thread::Builder::new()
             .name("RenderThread".into())
             .spawn(|| {
@@ -77,6 +80,11 @@ impl Render {
         render.render_thread = Some(render_handle);
     }
 
+    /// If [Render::init] was called, so there's a render thread
+    pub fn is_initialized() -> bool {
+        RENDER.read().is_some()
+    }
+
     pub fn shutdown() {
         // Send a stop signal to the render thread
         RenderThread::stop();
@@ -87,10 +95,10 @@ impl Render {
             .take()
             .expect("Render thread is not initialized");
 
-        println!("Shutting down Render Thread...");
+        info!(target: logging::RENDER, "Shutting down Render Thread...");
         match handle.join() {
-            Ok(()) => println!("Render thread successfully finished"),
-            Err(e) => eprintln!("Error in render thread: {:?}", e),
+            Ok(()) => info!(target: logging::RENDER, "Render thread successfully finished"),
+            Err(e) => error!(target: logging::RENDER, "Error in render thread: {:?}", e),
         }
     }
 
@@ -136,15 +144,16 @@ impl Render {
         // Check that the material has a valid shader
         if !RenderCommand::shader_exists(shader) {
             return Err(RenderError::InvalidAsset {
-                handle: shader,
+                handle: shader.untyped(),
                 asset_type: AssetType::Shader,
             });
         }
 
-        let mut render_lock = RENDER.write();
-        let render = render_lock.as_mut().expect("Render not initialized");
+        // Materials are thread safe, so the render thread can keep reading them meanwhile
+        let render_lock = RENDER.read();
+        let render = render_lock.as_ref().expect("Render not initialized");
 
-        Ok(render.materials.allocate(Material {
+        Ok(render.materials.insert(Material {
             shader,
             parameters: params,
         }))
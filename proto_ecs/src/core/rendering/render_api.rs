@@ -3,15 +3,32 @@ use super::shader::{ShaderDataType, ShaderSrc};
 use lazy_static::lazy_static;
 use proto_ecs::core::locking::RwLock;
 use proto_ecs::core::math::Colorf32;
+use proto_ecs::core::platform::headless::null_render_backend::NullRenderBackend;
 use proto_ecs::core::platform::opengl::opengl_render_backend::OpenGLRenderBackend;
 use proto_ecs::core::platform::Platforms;
 use proto_ecs::core::rendering::shader::ShaderError;
 use proto_ecs::core::utils::handle::Handle;
 
-pub type VertexBufferHandle = Handle;
-pub type IndexBufferHandle = Handle;
-pub type VertexArrayHandle = Handle;
-pub type ShaderHandle = Handle;
+/// Marker for handles to vertex buffers
+#[derive(Debug)]
+pub enum VertexBuffer {}
+
+/// Marker for handles to index buffers
+#[derive(Debug)]
+pub enum IndexBuffer {}
+
+/// Marker for handles to vertex arrays
+#[derive(Debug)]
+pub enum VertexArray {}
+
+/// Marker for handles to shaders
+#[derive(Debug)]
+pub enum Shader {}
+
+pub type VertexBufferHandle = Handle<VertexBuffer>;
+pub type IndexBufferHandle = Handle<IndexBuffer>;
+pub type VertexArrayHandle = Handle<VertexArray>;
+pub type ShaderHandle = Handle<Shader>;
 
 pub enum API {
     OpenGL,
@@ -60,7 +77,7 @@ pub trait RenderAPIBackendDyn: Send + Sync {
 
     // Operations: Vertex Buffer
     fn get_vertex_buffer_layout(&self, handle: VertexBufferHandle) -> &BufferLayout;
-    fn set_vertex_buffer_layout(&self, handle: VertexBufferHandle, layout: BufferLayout);
+    fn set_vertex_buffer_layout(&mut self, handle: VertexBufferHandle, layout: BufferLayout);
 
     // Operations: Vertex Array
     fn set_vertex_array_vertex_buffer(
@@ -134,6 +151,9 @@ impl RenderCommand {
             Platforms::Windows => {
                 render_api.backend = Some(OpenGLRenderBackend::create());
             }
+            Platforms::Headless => {
+                render_api.backend = Some(NullRenderBackend::create());
+            }
             _ => panic!("Platform Render API backend not yet implemented"),
         }
     }
@@ -16,7 +16,7 @@ use crate::{
 use super::{
     buffer::{BufferElement, BufferLayout},
     camera::Camera,
-    material::{Material, MaterialHandle},
+    material::MaterialHandle,
     render_api::{
         IndexBufferHandle, RenderCommand, ShaderHandle, VertexArrayHandle, VertexBufferHandle,
     },
@@ -88,9 +88,6 @@ impl RenderThread {
         RENDER_THREAD_SHARED_STORAGE
             .last_frame_finished
             .store(true, Ordering::SeqCst);
-        RENDER_THREAD_SHARED_STORAGE
-            .running
-            .store(false, Ordering::SeqCst);
         RENDER_THREAD_SHARED_STORAGE
             .started
             .store(false, Ordering::SeqCst);
@@ -113,9 +110,6 @@ impl RenderThread {
     }
 
     pub fn run(&mut self) {
-        RENDER_THREAD_SHARED_STORAGE
-            .running
-            .store(true, Ordering::SeqCst);
         RENDER_THREAD_SHARED_STORAGE
             .started
             .store(true, Ordering::SeqCst);
@@ -145,6 +139,14 @@ impl RenderThread {
             .store(false, Ordering::SeqCst);
     }
 
+    /// Mark the render thread as running before spawning it, so a [RenderThread::stop]
+    /// sent before the thread starts is not overwritten
+    pub(super) fn set_running() {
+        RENDER_THREAD_SHARED_STORAGE
+            .running
+            .store(true, Ordering::SeqCst);
+    }
+
     /// Stop the render thread
     pub fn stop() {
         RENDER_THREAD_SHARED_STORAGE
@@ -187,7 +189,7 @@ impl RenderThread {
     fn send_models_to_gpu(&mut self) {
         let mut models_to_load = vec![];
         for proxy in self.current_frame_desc.render_proxies.iter() {
-            if self.models_in_gpu.get(&proxy.model).is_none() {
+            if !self.models_in_gpu.contains_key(&proxy.model) {
                 models_to_load.push(proxy.model);
             }
         }
@@ -218,7 +220,7 @@ impl RenderThread {
         };
 
         for proxy in self.current_frame_desc.render_proxies.iter() {
-            let material = render.materials.get(proxy.material) as &Material;
+            let material = render.materials.get(proxy.material);
             let gpu_model_data = self
                 .models_in_gpu
                 .get(&proxy.model)
@@ -28,4 +28,11 @@ impl Time {
         self.delta_time = instant - self.last_time;
         self.last_time = instant;
     }
+
+    /// Step by a fixed amount of time instead of measuring it.
+    /// Used when time does not come from the wall clock, like when playing back recordings
+    pub fn step_by(&mut self, delta_time: Duration) {
+        self.delta_time = delta_time;
+        self.last_time += delta_time;
+    }
 }
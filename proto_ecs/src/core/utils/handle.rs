@@ -1,42 +1,57 @@
-use std::{cell::RefCell, fmt::Debug, mem::MaybeUninit};
+use std::{
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
 
-use num::{Integer, Zero};
-use scc::Queue;
+use num::{Bounded, Integer, One, Zero};
 
 /// Handles for resources like buffers and shaders.
 /// We use a concrete type to ensure that resource handles are always of the
-/// same type no matter the backend
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct GenericHandle<IndexType, GenType>
+/// same type no matter the backend.
+///
+/// `Tag` is the kind of resource this handle refers to, so handles of different
+/// resources can't be mixed up. It's only a marker, it doesn't need to be constructible
+pub struct GenericHandle<IndexType, GenType, Tag = ()>
 where
     IndexType: Integer,
     GenType: Integer + Zero,
 {
     pub(super) index: IndexType,
     pub(super) generation: GenType,
+    _tag: PhantomData<fn() -> Tag>,
 }
 
-pub type Handle = GenericHandle<u32, u32>;
+/// Handle to a resource of type `T`. `Handle` without a type is an untyped handle,
+/// use it when the kind of resource is not known, like in errors
+pub type Handle<T = ()> = GenericHandle<u32, u32, T>;
 
 pub trait IsHandle: Clone + Copy + Debug {
     type Index: Integer + TryInto<usize> + TryFrom<usize> + Clone + Copy + Debug;
     type Generation: Integer + Zero + Clone + Copy + Debug;
+    /// Kind of resource this handle refers to
+    type Tag;
     fn index(&self) -> Self::Index;
     fn generation(&self) -> Self::Generation;
     fn new(index: Self::Index, generation: Self::Generation) -> Self;
     fn array_index(&self) -> usize;
 }
 
-impl<IndexType, GenType> IsHandle for GenericHandle<IndexType, GenType>
+impl<IndexType, GenType, Tag> IsHandle for GenericHandle<IndexType, GenType, Tag>
 where
     IndexType: Integer + TryInto<usize> + TryFrom<usize> + Clone + Copy + Debug,
     GenType: Integer + Clone + Copy + Debug,
 {
     type Generation = GenType;
     type Index = IndexType;
+    type Tag = Tag;
 
     fn new(index: Self::Index, generation: Self::Generation) -> Self {
-        GenericHandle { index, generation }
+        GenericHandle {
+            index,
+            generation,
+            _tag: PhantomData,
+        }
     }
 
     fn generation(&self) -> Self::Generation {
@@ -52,91 +67,250 @@ where
     }
 }
 
-/// Use this allocator in most cases. If you really need
+impl<IndexType, GenType, Tag> GenericHandle<IndexType, GenType, Tag>
+where
+    IndexType: Integer,
+    GenType: Integer + Zero,
+{
+    /// Forget the kind of resource this handle refers to
+    #[inline(always)]
+    pub fn untyped(self) -> GenericHandle<IndexType, GenType> {
+        GenericHandle {
+            index: self.index,
+            generation: self.generation,
+            _tag: PhantomData,
+        }
+    }
+}
+
+// Manual implementations, derives would require them from `Tag` too
+
+impl<IndexType, GenType, Tag> Clone for GenericHandle<IndexType, GenType, Tag>
+where
+    IndexType: Integer + Copy,
+    GenType: Integer + Zero + Copy,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<IndexType, GenType, Tag> Copy for GenericHandle<IndexType, GenType, Tag>
+where
+    IndexType: Integer + Copy,
+    GenType: Integer + Zero + Copy,
+{
+}
+
+impl<IndexType, GenType, Tag> PartialEq for GenericHandle<IndexType, GenType, Tag>
+where
+    IndexType: Integer,
+    GenType: Integer + Zero,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<IndexType, GenType, Tag> Eq for GenericHandle<IndexType, GenType, Tag>
+where
+    IndexType: Integer,
+    GenType: Integer + Zero,
+{
+}
+
+impl<IndexType, GenType, Tag> Hash for GenericHandle<IndexType, GenType, Tag>
+where
+    IndexType: Integer + Hash,
+    GenType: Integer + Zero + Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<IndexType, GenType, Tag> Debug for GenericHandle<IndexType, GenType, Tag>
+where
+    IndexType: Integer + Debug,
+    GenType: Integer + Zero + Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GenericHandle")
+            .field("tag", &std::any::type_name::<Tag>())
+            .field("index", &self.index)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+/// Use this allocator in most cases, with `T` the kind of resource of its handles. If you really need
 /// one with a specific type of pointer, you can use [GenerationalIndexAllocator] with
 /// your specific type of Handle
-pub type Allocator<V> = GenerationalIndexAllocator<Handle, V>;
+pub type Allocator<T, V> = GenerationalIndexAllocator<Handle<T>, V>;
 
-/// Basic allocator type that can work for most cases
+/// Basic allocator type that can work for most cases.
+/// Allocating, freeing and writing need exclusive access, so keep it behind a lock to share it
+/// between threads, like the render backend does. Values accessed from many threads at once
+/// should go in a [super::slot_map::SlotMap] instead
 pub struct GenerationalIndexAllocator<K: IsHandle, V> {
-    free: Queue<usize>,
+    /// Indices of empty entries that can be reused
+    free: Vec<usize>,
     entries: Vec<AllocatorEntry<V, K::Generation>>,
 }
 
 struct AllocatorEntry<V, G> {
-    value: RefCell<MaybeUninit<V>>,
+    /// Generation of the value in this entry, or of the next one if empty
     generation: G,
+    value: Option<V>,
 }
 
-// TODO Actual send + sync implementation
-unsafe impl<K: Send + IsHandle, V: Send> Send for GenerationalIndexAllocator<K, V> {}
-unsafe impl<K: Sync + IsHandle, V: Sync> Sync for GenerationalIndexAllocator<K, V> {}
-
-impl<K: IsHandle + Debug, V> Default for GenerationalIndexAllocator<K, V> {
+impl<K: IsHandle, V> Default for GenerationalIndexAllocator<K, V>
+where
+    K::Generation: Bounded,
+{
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<K: IsHandle, V> GenerationalIndexAllocator<K, V> {
+impl<K: IsHandle, V> GenerationalIndexAllocator<K, V>
+where
+    K::Generation: Bounded,
+{
     const INITIAL_SIZE: usize = 1_000;
 
     pub fn new() -> Self {
         GenerationalIndexAllocator {
-            free: Queue::default(),
+            free: Vec::new(),
             entries: Vec::with_capacity(Self::INITIAL_SIZE),
         }
     }
 
     pub fn allocate(&mut self, value: V) -> K {
-        if self.free.is_empty() {
-            let next_index = self.entries.len();
-            // Allocate a new entry
-            self.entries.push(AllocatorEntry {
-                value: RefCell::new(MaybeUninit::new(value)),
-                generation: <K as IsHandle>::Generation::zero(),
-            });
-
-            // Create handle:
-            let index = unsafe {
-                // This will only crash in non 32 or 64 bits architectures
-                K::Index::try_from(next_index).unwrap_unchecked()
-            };
-            return K::new(index, K::Generation::zero());
+        if let Some(index) = self.free.pop() {
+            let entry = &mut self.entries[index];
+            debug_assert!(entry.value.is_none(), "Free entry is in use");
+            entry.value = Some(value);
+            return Self::make_key(index, entry.generation);
         }
 
-        let next_index = **self.free.pop().unwrap();
-        let next_generation = self.entries[next_index].generation;
-        self.entries[next_index].value.borrow_mut().write(value);
-
-        let index = unsafe { next_index.try_into().unwrap_unchecked() };
-        K::new(index, next_generation)
+        let index = self.entries.len();
+        self.entries.push(AllocatorEntry {
+            generation: K::Generation::zero(),
+            value: Some(value),
+        });
+        Self::make_key(index, K::Generation::zero())
     }
 
     #[inline(always)]
     pub fn is_live(&self, key: K) -> bool {
-        let index: usize = key.array_index();
-        self.entries[index].generation == key.generation()
+        self.entry(key).is_some()
+    }
+
+    /// Free the value of `key`, returning it. Handles to it won't be valid anymore,
+    /// even if its entry gets reused
+    ///
+    /// # Panics
+    /// If `key` is not live
+    pub fn free(&mut self, key: K) -> V {
+        let index = key.array_index();
+        let entry = self
+            .entries
+            .get_mut(index)
+            .filter(|entry| entry.generation == key.generation())
+            .and_then(|entry| Some((entry.value.take()?, entry)));
+        let Some((value, entry)) = entry else {
+            panic!("Trying to free dead handle {key:?}");
+        };
+
+        // Entries that run out of generations are not reused, so old handles never match them
+        if entry.generation != K::Generation::max_value() {
+            entry.generation = entry.generation + K::Generation::one();
+            self.free.push(index);
+        }
+
+        value
     }
 
-    pub fn free(&mut self, key: K) {
-        debug_assert!(self.is_live(key), "Trying to access dead handle");
+    /// # Panics
+    /// If `key` is not live
+    #[inline]
+    pub fn get(&self, key: K) -> &V {
+        self.entry(key)
+            .unwrap_or_else(|| panic!("Trying to access dead handle {key:?}"))
+    }
 
-        // Reset the entry
-        let index: usize = key.array_index();
-        unsafe {
-            self.entries[index].value.borrow_mut().assume_init_drop();
+    /// # Panics
+    /// If `key` is not live
+    #[inline]
+    pub fn get_mut(&mut self, key: K) -> &mut V {
+        self.entries
+            .get_mut(key.array_index())
+            .filter(|entry| entry.generation == key.generation())
+            .and_then(|entry| entry.value.as_mut())
+            .unwrap_or_else(|| panic!("Trying to access dead handle {key:?}"))
+    }
+
+    #[inline]
+    fn entry(&self, key: K) -> Option<&V> {
+        self.entries
+            .get(key.array_index())
+            .filter(|entry| entry.generation == key.generation())?
+            .value
+            .as_ref()
+    }
+
+    #[inline]
+    fn make_key(index: usize, generation: K::Generation) -> K {
+        match K::Index::try_from(index) {
+            Ok(index) => K::new(index, generation),
+            Err(_) => panic!("Allocator index {index} doesn't fit in its handle type"),
         }
-        self.entries[index].generation.inc();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type SmallHandle = GenericHandle<u32, u8>;
+
+    #[test]
+    fn test_allocator_generations() {
+        let mut allocator: GenerationalIndexAllocator<SmallHandle, String> =
+            GenerationalIndexAllocator::new();
 
-        // Add to the free stack again
-        self.free.push(index);
+        let first = allocator.allocate("first".to_string());
+        assert_eq!(allocator.get(first), "first");
+        allocator.get_mut(first).push('!');
+        assert_eq!(allocator.free(first), "first!");
+        assert!(!allocator.is_live(first));
+
+        // The entry is reused with a new generation
+        let second = allocator.allocate("second".to_string());
+        assert_eq!(second.index(), first.index());
+        assert!(!allocator.is_live(first));
+        assert_eq!(allocator.get(second), "second");
     }
 
-    pub fn get(&self, key: K) -> &mut V {
-        debug_assert!(self.is_live(key), "Trying to access dead handle");
-        let index: usize = key.array_index();
-        let entry = &self.entries[index];
-        return unsafe { entry.value.borrow_mut().as_mut_ptr().as_mut().unwrap() };
+    #[test]
+    fn test_allocator_retires_exhausted_entries() {
+        let mut allocator: GenerationalIndexAllocator<SmallHandle, u32> =
+            GenerationalIndexAllocator::new();
+
+        let mut key = allocator.allocate(0);
+        for i in 1..=u8::MAX as u32 {
+            allocator.free(key);
+            key = allocator.allocate(i);
+            assert_eq!(key.index(), 0);
+        }
+        assert_eq!(key.generation(), u8::MAX);
+
+        // The entry ran out of generations, so it's never handed out again
+        allocator.free(key);
+        let next = allocator.allocate(0);
+        assert_eq!(next.index(), 1);
+        assert!(!allocator.is_live(key));
     }
 }
@@ -0,0 +1,60 @@
+/// Helpers to write JSON by hand, for the few places that export it
+use std::borrow::Cow;
+use std::io::Write;
+
+/// Escape quotes, backslashes and control characters in a JSON string
+pub fn escape_json(value: &str) -> Cow<'_, str> {
+    if !value
+        .chars()
+        .any(|c| c == '"' || c == '\\' || c.is_control())
+    {
+        return Cow::Borrowed(value);
+    }
+
+    let mut escaped = String::with_capacity(value.len() + 2);
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Write `values` as a JSON array of strings
+pub fn write_json_strings<W: Write, S: AsRef<str>>(
+    writer: &mut W,
+    values: &[S],
+) -> std::io::Result<()> {
+    writer.write_all(b"[")?;
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        write!(writer, "\"{}\"", escape_json(value.as_ref()))?;
+    }
+    writer.write_all(b"]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_json() {
+        assert_eq!(
+            escape_json("Entity System Thread 0"),
+            "Entity System Thread 0"
+        );
+        assert_eq!(escape_json("a \"b\" \\ c\n"), "a \\\"b\\\" \\\\ c\\u000a");
+    }
+
+    #[test]
+    fn test_write_json_strings() {
+        let mut json = Vec::new();
+        write_json_strings(&mut json, &["a", "\"b\""]).unwrap();
+        assert_eq!(String::from_utf8(json).unwrap(), "[\"a\",\"\\\"b\\\"\"]");
+    }
+}
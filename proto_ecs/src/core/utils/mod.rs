@@ -1 +1,3 @@
-pub mod handle;
\ No newline at end of file
+pub mod handle;
+pub mod json;
+pub mod slot_map;
\ No newline at end of file
@@ -0,0 +1,339 @@
+/// Thread safe generational slot map.
+///
+/// Values are found with handles holding their index and the generation of their slot,
+/// so handles to removed values are detected instead of reading a new value in the same
+/// slot. Access goes through guards: shared guards can be held by many threads at once,
+/// exclusive guards lock the whole map.
+use std::fmt::Debug;
+
+use num::{Bounded, One, Zero};
+use parking_lot::{
+    MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLockReadGuard, RwLockWriteGuard,
+};
+
+use crate::core::locking::RwLock;
+
+use super::handle::IsHandle;
+
+/// Shared access to a value in a [SlotMap]
+pub type SlotRef<'a, V> = MappedRwLockReadGuard<'a, V>;
+
+/// Exclusive access to a value in a [SlotMap]
+pub type SlotRefMut<'a, V> = MappedRwLockWriteGuard<'a, V>;
+
+pub struct SlotMap<K: IsHandle, V> {
+    inner: RwLock<SlotMapInner<K, V>>,
+}
+
+struct SlotMapInner<K: IsHandle, V> {
+    slots: Vec<Slot<K::Generation, V>>,
+    /// Indices of empty slots that can be reused
+    free: Vec<usize>,
+    len: usize,
+}
+
+struct Slot<G, V> {
+    /// Generation of the value in this slot, or of the next one if empty
+    generation: G,
+    value: Option<V>,
+}
+
+/// Shared access to all the values in a [SlotMap]
+pub struct SlotMapReadGuard<'a, K: IsHandle, V> {
+    inner: RwLockReadGuard<'a, SlotMapInner<K, V>>,
+}
+
+// -- < Implementations > --------------------------------
+
+impl<K: IsHandle, V> Default for SlotMap<K, V>
+where
+    K::Generation: Bounded,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: IsHandle, V> SlotMapInner<K, V> {
+    #[inline]
+    fn slot(&self, key: K) -> Option<&V> {
+        self.slots
+            .get(key.array_index())
+            .filter(|slot| slot.generation == key.generation())?
+            .value
+            .as_ref()
+    }
+
+    #[inline]
+    fn slot_mut(&mut self, key: K) -> Option<&mut V> {
+        self.slots
+            .get_mut(key.array_index())
+            .filter(|slot| slot.generation == key.generation())?
+            .value
+            .as_mut()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (K, &V)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let value = slot.value.as_ref()?;
+            Some((make_key::<K>(index, slot.generation), value))
+        })
+    }
+}
+
+#[inline]
+fn make_key<K: IsHandle>(index: usize, generation: K::Generation) -> K {
+    match K::Index::try_from(index) {
+        Ok(index) => K::new(index, generation),
+        Err(_) => panic!("Slot map index {index} doesn't fit in its handle type"),
+    }
+}
+
+impl<K: IsHandle, V> SlotMap<K, V>
+where
+    K::Generation: Bounded,
+{
+    pub fn new() -> Self {
+        Self {
+            inner: RwLock::new(SlotMapInner {
+                slots: Vec::new(),
+                free: Vec::new(),
+                len: 0,
+            }),
+        }
+    }
+
+    /// Store a value, returning the handle to access it
+    pub fn insert(&self, value: V) -> K {
+        let mut inner = self.inner.write();
+        inner.len += 1;
+
+        if let Some(index) = inner.free.pop() {
+            let slot = &mut inner.slots[index];
+            debug_assert!(slot.value.is_none(), "Free slot is in use");
+            slot.value = Some(value);
+            return make_key(index, slot.generation);
+        }
+
+        let index = inner.slots.len();
+        let key = make_key(index, K::Generation::zero());
+        inner.slots.push(Slot {
+            generation: K::Generation::zero(),
+            value: Some(value),
+        });
+        key
+    }
+
+    /// Remove the value of `key`, returning it if it was live.
+    /// Handles to it won't be valid anymore, even if its slot gets reused
+    pub fn remove(&self, key: K) -> Option<V> {
+        let mut inner = self.inner.write();
+        let index = key.array_index();
+        let slot = inner
+            .slots
+            .get_mut(index)
+            .filter(|slot| slot.generation == key.generation())?;
+        let value = slot.value.take()?;
+
+        // Slots that run out of generations are not reused, so old handles never match them
+        let retired = slot.generation == K::Generation::max_value();
+        if !retired {
+            slot.generation = slot.generation + K::Generation::one();
+            inner.free.push(index);
+        }
+        inner.len -= 1;
+
+        Some(value)
+    }
+
+    /// If `key` refers to a value still in this map
+    #[inline]
+    pub fn is_live(&self, key: K) -> bool {
+        self.inner.read().slot(key).is_some()
+    }
+
+    /// Shared access to the value of `key`, if it's live
+    #[inline]
+    pub fn try_get(&self, key: K) -> Option<SlotRef<'_, V>> {
+        RwLockReadGuard::try_map(self.inner.read(), |inner| inner.slot(key)).ok()
+    }
+
+    /// Exclusive access to the value of `key`, if it's live.
+    /// Blocks every other access to the map while held
+    #[inline]
+    pub fn try_get_mut(&self, key: K) -> Option<SlotRefMut<'_, V>> {
+        RwLockWriteGuard::try_map(self.inner.write(), |inner| inner.slot_mut(key)).ok()
+    }
+
+    /// Shared access to the value of `key`.
+    ///
+    /// # Panics
+    /// If `key` is not live
+    #[inline]
+    pub fn get(&self, key: K) -> SlotRef<'_, V> {
+        self.try_get(key)
+            .unwrap_or_else(|| panic!("Trying to access dead handle {key:?}"))
+    }
+
+    /// Exclusive access to the value of `key`.
+    ///
+    /// # Panics
+    /// If `key` is not live
+    #[inline]
+    pub fn get_mut(&self, key: K) -> SlotRefMut<'_, V> {
+        self.try_get_mut(key)
+            .unwrap_or_else(|| panic!("Trying to access dead handle {key:?}"))
+    }
+
+    /// Shared access to all the live values, to iterate over them
+    #[inline]
+    pub fn read(&self) -> SlotMapReadGuard<'_, K, V> {
+        SlotMapReadGuard {
+            inner: self.inner.read(),
+        }
+    }
+
+    /// Iterate over the live values without locking, since the map is borrowed mutably
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (K, &mut V)> {
+        self.inner
+            .get_mut()
+            .slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let value = slot.value.as_mut()?;
+                Some((make_key::<K>(index, slot.generation), value))
+            })
+    }
+
+    /// Number of live values
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.read().len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a, K: IsHandle, V> SlotMapReadGuard<'a, K, V> {
+    /// Iterate over the live values and their handles
+    pub fn iter(&self) -> impl Iterator<Item = (K, &V)> {
+        self.inner.iter()
+    }
+
+    /// Shared access to the value of `key`, if it's live
+    #[inline]
+    pub fn try_get(&self, key: K) -> Option<&V> {
+        self.inner.slot(key)
+    }
+
+    /// Number of live values
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.inner.len == 0
+    }
+}
+
+impl<K: IsHandle, V: Debug> Debug for SlotMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.inner.read().iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::utils::handle::{GenericHandle, Handle};
+
+    #[test]
+    fn test_slot_map_insert_remove() {
+        let map: SlotMap<Handle, String> = SlotMap::new();
+        let a = map.insert("a".to_owned());
+        let b = map.insert("b".to_owned());
+        assert_eq!(map.len(), 2);
+        assert_eq!(*map.get(a), "a");
+
+        map.get_mut(b).push('!');
+        assert_eq!(*map.get(b), "b!");
+
+        assert_eq!(map.remove(a).as_deref(), Some("a"));
+        assert_eq!(map.remove(a), None);
+        assert!(!map.is_live(a));
+        assert!(map.try_get(a).is_none());
+
+        // The slot gets reused, but the old handle doesn't match the new value
+        let c = map.insert("c".to_owned());
+        assert_eq!(c.index(), a.index());
+        assert!(map.try_get(a).is_none());
+        assert_eq!(*map.get(c), "c");
+
+        // Out of range handles are not live
+        assert!(!map.is_live(Handle::new(100, 0)));
+    }
+
+    #[test]
+    fn test_slot_map_iteration() {
+        let mut map: SlotMap<Handle, u32> = SlotMap::new();
+        let handles: Vec<Handle> = (0..10).map(|i| map.insert(i)).collect();
+        for handle in handles.iter().step_by(2) {
+            map.remove(*handle);
+        }
+
+        {
+            let values = map.read();
+            assert_eq!(values.len(), 5);
+            for (handle, value) in values.iter() {
+                assert_eq!(value % 2, 1);
+                assert_eq!(values.try_get(handle), Some(value));
+            }
+        }
+
+        for (_, value) in map.iter_mut() {
+            *value *= 10;
+        }
+        assert_eq!(*map.get(handles[3]), 30);
+    }
+
+    #[test]
+    fn test_slot_map_generation_overflow() {
+        let map: SlotMap<GenericHandle<u32, u8>, u32> = SlotMap::new();
+        let mut handle = map.insert(0);
+        for i in 1..=u8::MAX as u32 {
+            map.remove(handle);
+            handle = map.insert(i);
+            assert_eq!(handle.index(), 0);
+        }
+        assert_eq!(handle.generation(), u8::MAX);
+
+        // The slot is retired once it runs out of generations
+        map.remove(handle);
+        let new_handle = map.insert(0);
+        assert_ne!(new_handle.index(), 0);
+        assert!(!map.is_live(handle));
+    }
+
+    #[test]
+    fn test_slot_map_threads() {
+        let map: SlotMap<Handle, u32> = SlotMap::new();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for i in 0..100 {
+                        let handle = map.insert(i);
+                        assert_eq!(*map.get(handle), i);
+                        map.remove(handle);
+                    }
+                });
+            }
+        });
+        assert!(map.is_empty());
+    }
+}
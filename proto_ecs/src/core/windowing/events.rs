@@ -2,13 +2,13 @@
 /// but translated to this canonical Event data types to abstract platform-specific APIs
 use proto_ecs::core::windowing::keys::Keycode;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Event {
     handled: bool,
     event_type: Type,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     WindowClose,
     WindowResize {
@@ -45,14 +45,14 @@ pub enum Type {
     Unknown,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyState {
     Pressed,
     Released,
     Repeat,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MouseButton {
     Left,
     Right,
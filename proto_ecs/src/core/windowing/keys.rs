@@ -1,6 +1,7 @@
 // Key codes stolen from here:
 // https://rust-sdl2.github.io/rust-sdl2/sdl2/keyboard/enum.Keycode.html
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
 pub enum Keycode {
     Backspace,
     Tab,
@@ -240,3 +241,20 @@ pub enum Keycode {
     Sleep,
     Unknown,
 }
+
+impl Keycode {
+    /// Number of key codes. `Unknown` is always the last variant
+    pub const COUNT: u16 = Keycode::Unknown as u16 + 1;
+
+    /// Build a key code from its numeric representation, as returned by `key as u16`.
+    /// Out of range values are mapped to `Keycode::Unknown`
+    pub fn from_u16(value: u16) -> Self {
+        if value >= Self::COUNT {
+            return Keycode::Unknown;
+        }
+
+        // SAFETY: Keycode is `repr(u16)` with implicit discriminants, so every value
+        // in `0..COUNT` is a valid variant
+        unsafe { std::mem::transmute::<u16, Keycode>(value) }
+    }
+}
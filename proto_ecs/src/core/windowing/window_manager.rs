@@ -1,6 +1,7 @@
 /// This module implements management of the window instance
 use lazy_static::lazy_static;
 use proto_ecs::core::locking::RwLock;
+use proto_ecs::core::platform::headless::headless_window::HeadlessWindow;
 use proto_ecs::core::platform::{winit_window, Platforms};
 
 use super::window::{Window, WindowBuilder, WindowPtr};
@@ -37,6 +38,10 @@ impl WindowManager {
                 self.window = Some(winit_window::WinitWindow::create(window_builder));
                 self.platform = platform;
             }
+            Platforms::Headless => {
+                self.window = Some(HeadlessWindow::create(window_builder));
+                self.platform = platform;
+            }
             _ => panic!("Unimplemented platform"),
         }
     }
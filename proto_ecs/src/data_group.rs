@@ -10,14 +10,18 @@
 // use static dispatching. To access this information, user-defined datagroups
 // can use their datagroup id as key with the global registry to get its own data
 
-pub use ecs_macros::register_datagroup;
+pub use ecs_macros::{register_datagroup, DataGroup};
 use lazy_static::lazy_static;
-use proto_ecs::core::casting::CanCast;
+use proto_ecs::core::casting::{cast, cast_mut, into_any, CanCast};
+use proto_ecs::core::reflection::{Reflect, TypeInfo};
 use proto_ecs::core::{ids, locking::RwLock};
 use proto_ecs::get_id;
+use std::alloc::Layout;
 use std::fmt::Debug;
 
 use crate::core::common::InitDesc;
+use crate::core::registry_manifest::{RegistryKind, RegistryManifest};
+use crate::entities::entity_ref::EntityRef;
 
 pub type DataGroupID = u32;
 
@@ -64,15 +68,20 @@ pub enum DataGroupInitType {
 ///     { }
 /// }
 ///
-/// pub fn factory() -> Box<dyn DataGroup>
+/// pub fn factory() -> MyDatagroup
 /// {
-///     return Box::from(MyDatagroup{})
+///     return MyDatagroup{}
 /// }
 ///
 /// register_datagroup!(MyDatagroup, factory)
 /// ```
 pub trait DataGroup: ids::HasID + CanCast + std::fmt::Debug + Send + Sync {
     fn __init__(&mut self, init_data: std::option::Option<Box<dyn GenericDataGroupInitArgTrait>>);
+
+    /// Visit every [EntityRef] stored in this datagroup.
+    /// Generated by `register_datagroup!` when using the `entity_refs` argument
+    #[allow(unused)]
+    fn __visit_entity_refs__(&mut self, visitor: &mut dyn FnMut(&mut EntityRef)) {}
 }
 
 /// Trait to implement the description of the init step for a datagroup
@@ -88,6 +97,84 @@ pub trait DataGroupInitDescTrait {
 /// Factory function to create default Data Groups
 pub type DataGroupFactory = fn() -> Box<dyn DataGroup>;
 
+/// Factory function to create default Data Groups in place, at memory with the layout
+/// of the datagroup class. Returns the new datagroup
+pub type DataGroupInPlaceFactory = unsafe fn(*mut u8) -> *mut dyn DataGroup;
+
+/// Function to create a copy of an existing Data Group, including its state
+pub type DataGroupCloneFn = fn(&dyn DataGroup) -> Box<dyn DataGroup>;
+
+/// Memory layout of a datagroup class, and how to move its instances in and out of boxes.
+/// Used to store datagroups inline, like in archetype chunks
+#[derive(Debug, Clone, Copy)]
+pub struct DataGroupLayout {
+    pub layout: Layout,
+    /// Move a boxed datagroup of this class to `dst`
+    pub move_to: unsafe fn(Box<dyn DataGroup>, *mut u8),
+    /// Move the datagroup of this class stored at `src` to a new box
+    pub move_from: unsafe fn(*mut u8) -> Box<dyn DataGroup>,
+    /// Get the datagroup of this class stored at `ptr`
+    pub as_dyn: unsafe fn(*mut u8) -> *mut dyn DataGroup,
+}
+
+impl DataGroupLayout {
+    pub const fn new<D: DataGroup + 'static>() -> Self {
+        Self {
+            layout: Layout::new::<D>(),
+            move_to: move_datagroup_to::<D>,
+            move_from: move_datagroup_from::<D>,
+            as_dyn: datagroup_as_dyn::<D>,
+        }
+    }
+}
+
+unsafe fn move_datagroup_to<D: DataGroup + 'static>(datagroup: Box<dyn DataGroup>, dst: *mut u8) {
+    let datagroup: Box<D> = into_any(datagroup);
+    dst.cast::<D>().write(*datagroup);
+}
+
+unsafe fn move_datagroup_from<D: DataGroup + 'static>(src: *mut u8) -> Box<dyn DataGroup> {
+    Box::new(src.cast::<D>().read())
+}
+
+unsafe fn datagroup_as_dyn<D: DataGroup + 'static>(ptr: *mut u8) -> *mut dyn DataGroup {
+    ptr.cast::<D>()
+}
+
+/// Reflection of a datagroup class, to inspect and edit the fields of its instances.
+/// See [crate::core::reflection]
+#[derive(Debug, Clone, Copy)]
+pub struct DataGroupReflection {
+    /// Description of the fields of this datagroup class
+    pub type_info: fn() -> &'static TypeInfo,
+    /// Reflect a datagroup of this class
+    pub as_reflect: fn(&dyn DataGroup) -> &dyn Reflect,
+    /// Reflect a datagroup of this class mutably
+    pub as_reflect_mut: fn(&mut dyn DataGroup) -> &mut dyn Reflect,
+}
+
+impl DataGroupReflection {
+    pub const fn new<D: DataGroup + Reflect + 'static>() -> Self {
+        Self {
+            type_info: <D as Reflect>::type_info,
+            as_reflect: datagroup_as_reflect::<D>,
+            as_reflect_mut: datagroup_as_reflect_mut::<D>,
+        }
+    }
+}
+
+fn datagroup_as_reflect<D: DataGroup + Reflect + 'static>(
+    datagroup: &dyn DataGroup,
+) -> &dyn Reflect {
+    cast::<_, D>(datagroup)
+}
+
+fn datagroup_as_reflect_mut<D: DataGroup + Reflect + 'static>(
+    datagroup: &mut dyn DataGroup,
+) -> &mut dyn Reflect {
+    cast_mut::<_, D>(datagroup)
+}
+
 /// Datagroup's static description
 pub trait DatagroupDesc {
     /// Name of this datagroup
@@ -96,6 +183,12 @@ pub trait DatagroupDesc {
     const NAME_CRC: u32;
     /// Factory to create new instances of this datagroup
     const FACTORY: DataGroupFactory;
+    /// Factory to create new instances of this datagroup in place
+    const IN_PLACE_FACTORY: DataGroupInPlaceFactory;
+    /// Function to copy instances of this datagroup, if it's cloneable
+    const CLONE: Option<DataGroupCloneFn> = None;
+    /// Reflection of this datagroup's fields, if it's reflected
+    const REFLECTION: Option<DataGroupReflection> = None;
 }
 
 /// Entry for the datagroup Registry
@@ -106,8 +199,13 @@ pub struct DataGroupRegistryEntry {
     pub name: &'static str,
     pub name_crc: u32,
     pub factory_func: DataGroupFactory,
+    pub in_place_factory_func: DataGroupInPlaceFactory,
+    pub clone_func: Option<DataGroupCloneFn>,
+    pub reflection: Option<DataGroupReflection>,
+    pub layout: DataGroupLayout,
     pub init_desc: InitDesc,
     pub id: DataGroupID,
+    pub set_id_fn: fn(DataGroupID), // Only used for init, don't use it manually
 }
 
 lazy_static! {
@@ -122,39 +220,73 @@ lazy_static! {
 #[derive(Debug, Default)]
 pub struct DataGroupRegistry {
     entries: Vec<DataGroupRegistryEntry>,
+    /// Position in `entries` of each datagroup id, `None` for ids left free by a manifest
+    entry_positions: Vec<Option<usize>>,
     is_initialized: bool,
 }
 
 impl DataGroupRegistry {
     /// Call this first thing before running game play code.
     pub fn init(&mut self) {
+        self.init_with_manifest(None);
+    }
+
+    /// Give ids to the registered datagroups. Datagroups in `manifest` keep their ids there,
+    /// leaving holes for the ones missing in this build. New datagroups get the ids after the
+    /// highest id in `manifest`, sorted by name crc
+    pub fn init_with_manifest(&mut self, manifest: Option<&RegistryManifest>) {
         assert!(
             !self.is_initialized,
             "Data Group Registry got double initialized!"
         );
+        let pinned_id = |entry: &DataGroupRegistryEntry| {
+            manifest.and_then(|manifest| manifest.get_id(RegistryKind::DataGroup, entry.name_crc))
+        };
         self.entries
-            .sort_by(|entry1, entry2| entry1.id.cmp(&entry2.id));
+            .sort_by_key(|entry| (pinned_id(entry).unwrap_or(DataGroupID::MAX), entry.name_crc));
+
+        let mut next_id = manifest
+            .and_then(|manifest| {
+                let manifest_entries = manifest.get_entries(RegistryKind::DataGroup);
+                manifest_entries.iter().map(|entry| entry.id).max()
+            })
+            .map_or(0, |max_id| max_id + 1);
+        self.entry_positions.clear();
+        for (pos, entry) in self.entries.iter_mut().enumerate() {
+            entry.id = match pinned_id(entry) {
+                // A broken manifest might pin two datagroups to the same id
+                Some(id) if self.entry_positions.get(id as usize).copied().flatten().is_none() => id,
+                _ => {
+                    let id = next_id;
+                    next_id += 1;
+                    id
+                }
+            };
+
+            let id = entry.id as usize;
+            if self.entry_positions.len() <= id {
+                self.entry_positions.resize(id + 1, None);
+            }
+            self.entry_positions[id] = Some(pos);
+            (entry.set_id_fn)(entry.id);
+        }
         self.is_initialized = true;
     }
 
     /// Initialize global registry
     pub fn initialize() {
-        let mut locals: TempRegistryLambdas = TempRegistryLambdas::new();
+        DataGroupRegistry::initialize_with_manifest(None);
+    }
+
+    /// Initialize global registry, keeping the datagroup ids of `manifest` when possible
+    pub fn initialize_with_manifest(manifest: Option<&RegistryManifest>) {
         let mut registry = DataGroupRegistry::get_global_registry().write();
         assert!(
             !registry.is_initialized,
-            "Local System registry was already initialized!"
+            "Data Group registry was already initialized!"
         );
 
-        let mut globals = DataGroupRegistry::get_temp_global_registry().write();
-
-        // Clear globals
-        std::mem::swap(&mut locals, &mut globals);
-
-        // Consume locals
-        locals.into_iter().for_each(|lambda| lambda(&mut registry));
-
-        registry.init();
+        *registry = DataGroupRegistry::load_registered_datagroups(manifest);
     }
 
     #[inline]
@@ -179,16 +311,27 @@ impl DataGroupRegistry {
     }
 
     #[inline]
-    ///  Add a new entry to the registry
-    pub fn register(&mut self, mut entry: DataGroupRegistryEntry) -> DataGroupID {
-        let new_id = self.entries.len() as u32;
-        entry.id = new_id;
+    ///  Add a new entry to the registry. Its id is set when the registry is initialized
+    pub fn register(&mut self, entry: DataGroupRegistryEntry) {
         self.entries.push(entry);
-        new_id
     }
 
-    pub fn load_registered_datagroups() -> DataGroupRegistry {
-        unimplemented!("not yet implemented");
+    /// Create an initialized registry with the datagroups registered with the
+    /// `register_datagroup!` macro, which can only be loaded once.
+    /// Datagroups keep the ids they have in `manifest` when possible
+    pub fn load_registered_datagroups(manifest: Option<&RegistryManifest>) -> DataGroupRegistry {
+        let mut locals = TempRegistryLambdas::new();
+        {
+            let mut globals = DataGroupRegistry::get_temp_global_registry().write();
+            // Clear globals
+            std::mem::swap(&mut locals, &mut globals);
+        }
+
+        let mut registry = DataGroupRegistry::new();
+        // Consume locals
+        locals.into_iter().for_each(|lambda| lambda(&mut registry));
+        registry.init_with_manifest(manifest);
+        registry
     }
 
     #[inline(always)]
@@ -202,8 +345,24 @@ impl DataGroupRegistry {
 
     #[inline]
     pub fn get_entry_by_id(&self, id: DataGroupID) -> &DataGroupRegistryEntry {
-        debug_assert!((id as usize) < self.entries.len(), "Invalid id");
-        &self.entries[id as usize]
+        self.try_get_entry_by_id(id).expect("Invalid id")
+    }
+
+    /// Entry of datagroup `id`, or `None` if no datagroup is registered with that id
+    #[inline]
+    pub fn try_get_entry_by_id(&self, id: DataGroupID) -> Option<&DataGroupRegistryEntry> {
+        let pos = (*self.entry_positions.get(id as usize)?)?;
+        Some(&self.entries[pos])
+    }
+
+    /// Entry of the datagroup named `name`, if it's registered
+    pub fn get_entry_by_name(&self, name: &str) -> Option<&DataGroupRegistryEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    #[inline(always)]
+    pub fn get_datagroup_count(&self) -> usize {
+        self.entries.len()
     }
 
     #[inline(always)]
@@ -220,6 +379,20 @@ impl DataGroupRegistry {
         (entry.factory_func)()
     }
 
+    /// Create a datagroup at `dst`, which should fit the layout of its datagroup class.
+    ///
+    /// # Safety
+    /// `dst` should be valid for writes and aligned for the datagroup class
+    #[inline]
+    pub unsafe fn create_in_place_by_id(
+        &self,
+        id: DataGroupID,
+        dst: *mut u8,
+    ) -> *mut dyn DataGroup {
+        let entry = self.get_entry_by_id(id);
+        (entry.in_place_factory_func)(dst)
+    }
+
     #[inline(always)]
     pub fn create<D>(&self) -> Box<dyn DataGroup>
     where
@@ -228,6 +401,34 @@ impl DataGroupRegistry {
         self.create_by_id(get_id!(D))
     }
 
+    /// Create a copy of `datagroup`, including its state.
+    /// Returns `None` if its datagroup class is not cloneable
+    #[inline]
+    pub fn try_clone(&self, datagroup: &dyn DataGroup) -> Option<Box<dyn DataGroup>> {
+        let entry = self.get_entry_by_id(datagroup.get_id());
+        entry.clone_func.map(|clone_func| clone_func(datagroup))
+    }
+
+    /// Reflect `datagroup` to inspect its fields.
+    /// Returns `None` if its datagroup class is not reflected
+    #[inline]
+    pub fn reflect<'a>(&self, datagroup: &'a dyn DataGroup) -> Option<&'a dyn Reflect> {
+        let entry = self.get_entry_by_id(datagroup.get_id());
+        entry
+            .reflection
+            .map(|reflection| (reflection.as_reflect)(datagroup))
+    }
+
+    /// Reflect `datagroup` to edit its fields.
+    /// Returns `None` if its datagroup class is not reflected
+    #[inline]
+    pub fn reflect_mut<'a>(&self, datagroup: &'a mut dyn DataGroup) -> Option<&'a mut dyn Reflect> {
+        let entry = self.get_entry_by_id(datagroup.get_id());
+        entry
+            .reflection
+            .map(|reflection| (reflection.as_reflect_mut)(datagroup))
+    }
+
     pub fn register_lambda(lambda: TempRegistryLambda) {
         DataGroupRegistry::get_temp_global_registry()
             .write()
@@ -242,7 +443,7 @@ impl DataGroupRegistry {
 #[macro_export]
 /// Create a new datagroup registered in the global registry.
 macro_rules! create_datagroup {
-    ($dg:ident) => {{
+    ($dg:ty) => {{
         let global_registry =
             proto_ecs::data_group::DataGroupRegistry::get_global_registry().read();
         global_registry.create::<$dg>()
@@ -257,7 +458,7 @@ impl<'a> IntoIterator for &'a DataGroupRegistry {
     type IntoIter = std::slice::Iter<'a, DataGroupRegistryEntry>;
 
     fn into_iter(self) -> Self::IntoIter {
-        return self.entries.iter();
+        self.entries.iter()
     }
 }
 
@@ -1,11 +1,27 @@
+pub mod archetype;
+pub mod change_detection;
+pub mod datagroup_block;
 pub mod entity;
-mod entity_allocator;
+pub mod entity_allocator;
+pub mod entity_id_allocator;
+pub mod entity_index;
+pub mod entity_ref;
 pub mod entity_spawn_desc;
 pub mod entity_system;
+pub mod relationships;
 pub mod transform_datagroup;
 
+#[cfg(test)]
+mod test_change_detection;
+
 #[cfg(test)]
 mod test_entities;
 
 #[cfg(test)]
 mod test_entity_allocator;
+
+#[cfg(test)]
+mod test_entity_id_allocator;
+
+#[cfg(test)]
+mod test_entity_index;
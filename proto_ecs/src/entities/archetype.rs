@@ -0,0 +1,544 @@
+/// Archetype storage for datagroups.
+///
+/// By default every datagroup of an entity lives in its own box. Worlds created with
+/// [StorageMode::Archetype] store the datagroups of their non-spatial entities in archetypes instead:
+/// entities with the same datagroups and systems share an archetype, and their datagroups
+/// are stored column by column in fixed size chunks. Local systems of those entities
+/// run chunk by chunk, without going through each entity. The entities of a chunk stay locked
+/// for writing while its systems run, so those systems can't lock them again.
+/// Global systems get the entities stored in archetypes chunk by chunk, after the boxed ones,
+/// so the datagroups they visit are next to each other in memory.
+///
+/// Spatial entities are always boxed, as they have to run in hierarchy order.
+use std::alloc::Layout;
+use std::collections::HashMap;
+use std::time::Instant;
+
+use nohash_hasher::IntSet;
+use parking_lot::RwLockWriteGuard;
+use rayon::prelude::*;
+
+use crate::core::het_single_mem_block::HetSingleMemBlock;
+use crate::core::ids::IDLocator;
+use crate::core::profiling::Profiler;
+use crate::data_group::{DataGroup, DataGroupID, DataGroupLayout, DataGroupRegistry};
+use crate::get_id;
+use crate::systems::common::{Dependency, StageID, STAGE_COUNT};
+use crate::systems::global_systems::GlobalSystemID;
+use crate::systems::local_systems::{ChunkSystemFn, LocalSystemRegistry, SystemClassID};
+
+use super::change_detection::{current_change_tick, ChangeTick};
+use super::entity::{DataGroupIndexingType, DataGroupVec, Entity, EntityID, LocalSystemMap};
+use super::entity_allocator::EntityPtr;
+use super::entity_system::World;
+
+/// Max number of entities per chunk
+pub const CHUNK_CAPACITY: usize = 256;
+
+/// How a world stores the datagroups of its entities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageMode {
+    /// Every datagroup lives in its own box
+    #[default]
+    Boxed,
+    /// Datagroups of non-spatial entities live in archetype chunks
+    Archetype,
+}
+
+/// Index of an archetype inside the storage of a world
+pub type ArchetypeID = usize;
+
+/// Identifies an archetype: the sorted datagroups, local systems and global systems of its entities
+type ArchetypeKey = (Vec<DataGroupID>, Vec<SystemClassID>, Vec<GlobalSystemID>);
+
+/// Pointer to a datagroup stored in an archetype chunk
+#[derive(Debug, Clone, Copy)]
+pub(super) struct DataGroupPtr(*mut dyn DataGroup);
+
+/// Where the datagroups of an entity are stored inside the archetypes of its world
+#[derive(Debug)]
+pub(super) struct ArchetypeRow {
+    archetype: ArchetypeID,
+    chunk: usize,
+    row: usize,
+    /// Datagroups of the entity, sorted by id
+    datagroups: Vec<DataGroupPtr>,
+}
+
+/// A local system stage function and the archetype columns of its dependencies
+#[derive(Debug)]
+struct ChunkSystem {
+    id: SystemClassID,
+    function: ChunkSystemFn,
+    /// Column of each dependency, `None` for missing optional dependencies
+    columns: Vec<Option<usize>>,
+}
+
+/// Fixed size block of entities of an archetype, stored column by column
+#[derive(Debug)]
+struct Chunk {
+    /// Holds the entity ids column, followed by one column per datagroup
+    _block: HetSingleMemBlock,
+    ids: *mut EntityID,
+    columns: Vec<*mut u8>,
+    entities: Vec<EntityPtr>,
+}
+
+/// All the entities with the same datagroups and systems
+#[derive(Debug)]
+pub struct Archetype {
+    datagroups: Vec<DataGroupID>,
+    /// Global systems required by the entities of this archetype, sorted by id
+    global_systems: Vec<GlobalSystemID>,
+    layouts: Vec<DataGroupLayout>,
+    /// Layouts used to allocate each chunk
+    chunk_layouts: Vec<Layout>,
+    stages: [Vec<ChunkSystem>; STAGE_COUNT],
+    /// Columns the local systems of each stage get mutable access to
+    written_columns: [Vec<DataGroupIndexingType>; STAGE_COUNT],
+    /// Every chunk is full except for the last one
+    chunks: Vec<Chunk>,
+}
+
+/// Archetypes of a world
+#[derive(Debug, Default)]
+pub struct ArchetypeStorage {
+    archetypes: Vec<Archetype>,
+    index: HashMap<ArchetypeKey, ArchetypeID>,
+}
+
+// -- < Implementations > --------------------------------
+
+// Datagroups are Send + Sync, and chunks are only accessed by the world that owns them
+unsafe impl Send for DataGroupPtr {}
+unsafe impl Sync for DataGroupPtr {}
+unsafe impl Send for Chunk {}
+unsafe impl Sync for Chunk {}
+
+impl ArchetypeRow {
+    /// Get the datagroup with id `id` in this row, if any
+    pub(super) fn get_by_id(&self, id: DataGroupID) -> Option<*mut dyn DataGroup> {
+        self.position(id).map(|pos| self.datagroups[pos].0)
+    }
+
+    /// Position of the datagroup with id `id` in this row, if any
+    #[inline]
+    pub(super) fn position(&self, id: DataGroupID) -> Option<usize> {
+        self.datagroups
+            .binary_search_by_key(&id, |ptr| unsafe { (*ptr.0).get_id() })
+            .ok()
+    }
+
+    /// Datagroup at `pos` in this row
+    #[inline(always)]
+    pub(super) fn get(&self, pos: usize) -> *mut dyn DataGroup {
+        self.datagroups[pos].0
+    }
+
+    /// Pointers to all the datagroups in this row, sorted by id
+    #[inline(always)]
+    pub(super) fn get_datagroups(&self) -> impl Iterator<Item = *mut dyn DataGroup> + '_ {
+        self.datagroups.iter().map(|ptr| ptr.0)
+    }
+}
+
+impl Chunk {
+    fn new(chunk_layouts: &[Layout]) -> Self {
+        let block = HetSingleMemBlock::alloc(chunk_layouts);
+        let mut columns = unsafe {
+            block
+                .get_layout_iter(chunk_layouts)
+                .collect::<Vec<*mut u8>>()
+        };
+        let ids = columns.remove(0).cast::<EntityID>();
+
+        Self {
+            _block: block,
+            ids,
+            columns,
+            entities: Vec::with_capacity(CHUNK_CAPACITY),
+        }
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    #[inline(always)]
+    fn get_ids(&self) -> &[EntityID] {
+        unsafe { std::slice::from_raw_parts(self.ids, self.len()) }
+    }
+
+    /// Lock every entity of this chunk for writing. Datagroups stored in the chunk
+    /// belong to these entities, so holding the guards gives exclusive access to the chunk columns
+    fn lock_entities(&self) -> Vec<RwLockWriteGuard<'_, Entity>> {
+        self.entities
+            .iter()
+            .map(|entity_ptr| entity_ptr.write())
+            .collect()
+    }
+
+    /// Mark `columns` as changed in every locked entity of this chunk.
+    /// Columns match the positions of the datagroups in the entities
+    fn mark_changed(
+        entities: &[RwLockWriteGuard<'_, Entity>],
+        columns: &[DataGroupIndexingType],
+        tick: ChangeTick,
+    ) {
+        if columns.is_empty() {
+            return;
+        }
+
+        for entity in entities {
+            entity.mark_changed(columns, tick);
+        }
+    }
+}
+
+impl Archetype {
+    fn new(
+        datagroups: Vec<DataGroupID>,
+        local_systems: &[SystemClassID],
+        global_systems: Vec<GlobalSystemID>,
+    ) -> Self {
+        let dg_registry = DataGroupRegistry::get_global_registry().read();
+        let layouts: Vec<DataGroupLayout> = datagroups
+            .iter()
+            .map(|&id| dg_registry.get_entry_by_id(id).layout)
+            .collect();
+
+        // The ids column is never empty, so chunks always have something to allocate
+        let mut chunk_layouts =
+            vec![Layout::array::<EntityID>(CHUNK_CAPACITY).expect("Invalid ids column layout")];
+        chunk_layouts.extend(layouts.iter().map(|dg_layout| {
+            let layout = dg_layout.layout;
+            Layout::from_size_align(layout.size() * CHUNK_CAPACITY, layout.align())
+                .expect("Invalid datagroup column layout")
+        }));
+
+        let mut stages: [Vec<ChunkSystem>; STAGE_COUNT] = std::array::from_fn(|_| Vec::new());
+        let ls_registry = LocalSystemRegistry::get_global_registry().read();
+        for &ls_id in local_systems {
+            let entry = ls_registry.get_entry_by_id(ls_id);
+            let columns: Vec<Option<usize>> = entry
+                .dependencies
+                .iter()
+                .map(|dep| match dep {
+                    Dependency::DataGroup(dg_id) => Some(
+                        datagroups
+                            .binary_search(dg_id)
+                            .expect("Local System is missing datagroup dependency!"),
+                    ),
+                    Dependency::OptionalDG(dg_id) => datagroups.binary_search(dg_id).ok(),
+                })
+                .collect();
+
+            for (stage, function) in stages.iter_mut().zip(entry.chunk_functions.iter()) {
+                if let Some(function) = function {
+                    stage.push(ChunkSystem {
+                        id: ls_id,
+                        function: *function,
+                        columns: columns.clone(),
+                    });
+                }
+            }
+        }
+
+        let written_columns = std::array::from_fn(|stage_id| {
+            let mut columns: Vec<DataGroupIndexingType> = stages[stage_id]
+                .iter()
+                .flat_map(|system| system.columns.iter().flatten())
+                .map(|&column| column as DataGroupIndexingType)
+                .collect();
+            columns.sort();
+            columns.dedup();
+            columns
+        });
+
+        Self {
+            datagroups,
+            global_systems,
+            layouts,
+            chunk_layouts,
+            stages,
+            written_columns,
+            chunks: Vec::new(),
+        }
+    }
+
+    /// Number of entities in this archetype
+    pub fn len(&self) -> usize {
+        self.chunks.last().map_or(0, |last| {
+            (self.chunks.len() - 1) * CHUNK_CAPACITY + last.len()
+        })
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// If the entities in this archetype have local systems to run in `stage_id`
+    #[inline(always)]
+    pub fn runs_in_stage(&self, stage_id: StageID) -> bool {
+        !self.stages[stage_id as usize].is_empty()
+    }
+
+    /// Datagroups stored in this archetype, sorted by id
+    #[inline(always)]
+    pub fn get_datagroups(&self) -> &[DataGroupID] {
+        &self.datagroups
+    }
+
+    /// Pointer to the datagroup in `column` for a row in a chunk
+    #[inline(always)]
+    unsafe fn get_ptr(&self, chunk: usize, row: usize, column: usize) -> *mut u8 {
+        self.chunks[chunk].columns[column].add(row * self.layouts[column].layout.size())
+    }
+
+    fn get_row_datagroups(&self, chunk: usize, row: usize) -> Vec<DataGroupPtr> {
+        self.layouts
+            .iter()
+            .enumerate()
+            .map(|(column, dg_layout)| unsafe {
+                DataGroupPtr((dg_layout.as_dyn)(self.get_ptr(chunk, row, column)))
+            })
+            .collect()
+    }
+
+    /// Add an entity at the end of this archetype. `datagroups` should be sorted by id
+    fn push(
+        &mut self,
+        id: EntityID,
+        entity_ptr: EntityPtr,
+        datagroups: DataGroupVec,
+    ) -> (usize, usize) {
+        debug_assert_eq!(datagroups.len(), self.layouts.len());
+
+        if self
+            .chunks
+            .last()
+            .is_none_or(|last| last.len() == CHUNK_CAPACITY)
+        {
+            self.chunks.push(Chunk::new(&self.chunk_layouts));
+        }
+
+        let chunk = self.chunks.len() - 1;
+        let row = self.chunks[chunk].len();
+        unsafe {
+            self.chunks[chunk].ids.add(row).write(id);
+            for (column, datagroup) in datagroups.into_iter().enumerate() {
+                (self.layouts[column].move_to)(datagroup, self.get_ptr(chunk, row, column));
+            }
+        }
+        self.chunks[chunk].entities.push(entity_ptr);
+
+        (chunk, row)
+    }
+
+    /// Remove a row and return its datagroups boxed. The last row of the archetype
+    /// is moved to fill the gap, and its entity is updated to point to its new row
+    fn remove(&mut self, chunk: usize, row: usize) -> DataGroupVec {
+        let datagroups: DataGroupVec = self
+            .layouts
+            .iter()
+            .enumerate()
+            .map(|(column, dg_layout)| unsafe {
+                (dg_layout.move_from)(self.get_ptr(chunk, row, column))
+            })
+            .collect();
+
+        let last_chunk = self.chunks.len() - 1;
+        let last_row = self.chunks[last_chunk].len() - 1;
+        if (chunk, row) != (last_chunk, last_row) {
+            unsafe {
+                let last_id = self.chunks[last_chunk].ids.add(last_row).read();
+                self.chunks[chunk].ids.add(row).write(last_id);
+
+                for (column, dg_layout) in self.layouts.iter().enumerate() {
+                    std::ptr::copy_nonoverlapping(
+                        self.get_ptr(last_chunk, last_row, column),
+                        self.get_ptr(chunk, row, column),
+                        dg_layout.layout.size(),
+                    );
+                }
+            }
+
+            let moved_ptr = self.chunks[last_chunk].entities[last_row];
+            self.chunks[chunk].entities[row] = moved_ptr;
+
+            let mut moved_entity = moved_ptr.write();
+            let moved_row = moved_entity
+                .get_archetype_row_mut()
+                .expect("Entity in an archetype chunk should have an archetype row");
+            moved_row.chunk = chunk;
+            moved_row.row = row;
+            moved_row.datagroups = self.get_row_datagroups(chunk, row);
+        }
+
+        self.chunks[last_chunk].entities.pop();
+        if self.chunks[last_chunk].entities.is_empty() {
+            self.chunks.pop();
+        }
+
+        datagroups
+    }
+
+    fn run_stage(&self, world: &World, stage_id: StageID) {
+        let systems = &self.stages[stage_id as usize];
+        if systems.is_empty() {
+            return;
+        }
+
+        let written_columns = &self.written_columns[stage_id as usize];
+        let tick = current_change_tick();
+        let profiling = Profiler::is_enabled();
+        self.chunks.par_iter().for_each(|chunk| {
+            let entities = chunk.lock_entities();
+            Chunk::mark_changed(&entities, written_columns, tick);
+
+            let ids = chunk.get_ids();
+            let mut columns = Vec::new();
+            for system in systems {
+                columns.clear();
+                columns.extend(system.columns.iter().map(|column| match column {
+                    Some(column) => chunk.columns[*column],
+                    None => std::ptr::null_mut(),
+                }));
+
+                let start = profiling.then(Instant::now);
+                (system.function)(world, ids, &columns);
+                if let Some(start) = start {
+                    world.get_local_system_timings().add(
+                        system.id,
+                        start.elapsed(),
+                        ids.len() as u64,
+                    );
+                }
+            }
+        });
+    }
+}
+
+impl ArchetypeStorage {
+    /// Store the datagroups of an entity in the archetype matching its datagroups and systems.
+    /// `datagroups` should be sorted by id
+    pub(super) fn insert(
+        &mut self,
+        id: EntityID,
+        entity_ptr: EntityPtr,
+        datagroups: DataGroupVec,
+        local_systems: &LocalSystemMap,
+        global_systems: &IntSet<GlobalSystemID>,
+    ) -> ArchetypeRow {
+        let datagroup_ids: Vec<DataGroupID> = datagroups.iter().map(|dg| dg.get_id()).collect();
+        let mut local_system_ids: Vec<SystemClassID> = local_systems.iter().copied().collect();
+        local_system_ids.sort();
+        let mut global_system_ids: Vec<GlobalSystemID> = global_systems.iter().copied().collect();
+        global_system_ids.sort();
+
+        let archetypes = &mut self.archetypes;
+        let archetype_id = *self
+            .index
+            .entry((datagroup_ids, local_system_ids, global_system_ids))
+            .or_insert_with_key(|(datagroup_ids, local_system_ids, global_system_ids)| {
+                archetypes.push(Archetype::new(
+                    datagroup_ids.clone(),
+                    local_system_ids,
+                    global_system_ids.clone(),
+                ));
+                archetypes.len() - 1
+            });
+
+        let archetype = &mut self.archetypes[archetype_id];
+        let (chunk, row) = archetype.push(id, entity_ptr, datagroups);
+
+        ArchetypeRow {
+            archetype: archetype_id,
+            chunk,
+            row,
+            datagroups: archetype.get_row_datagroups(chunk, row),
+        }
+    }
+
+    /// Remove the datagroups in `row` from their archetype, returning them boxed
+    pub(super) fn remove(&mut self, row: ArchetypeRow) -> DataGroupVec {
+        self.archetypes[row.archetype].remove(row.chunk, row.row)
+    }
+
+    /// Run the local systems of every archetype for a stage
+    pub(super) fn run_stage(&self, world: &World, stage_id: StageID) {
+        self.archetypes
+            .par_iter()
+            .for_each(|archetype| archetype.run_stage(world, stage_id));
+    }
+
+    /// Call `f` with the entity ids and the `DG` column of every chunk storing datagroup `DG`.
+    /// The entities of each chunk are locked for writing while `f` visits it
+    pub(super) fn for_each_column<DG, F>(&self, mut f: F)
+    where
+        DG: IDLocator + DataGroup + 'static,
+        F: FnMut(&[EntityID], &mut [DG]),
+    {
+        let dg_id = get_id!(DG);
+        let tick = current_change_tick();
+        for archetype in self.archetypes.iter() {
+            let Ok(column) = archetype.datagroups.binary_search(&dg_id) else {
+                continue;
+            };
+
+            for chunk in archetype.chunks.iter() {
+                let entities = chunk.lock_entities();
+                Chunk::mark_changed(&entities, &[column as DataGroupIndexingType], tick);
+                // The column belongs to the locked entities, so no one else can access it
+                let values = unsafe {
+                    std::slice::from_raw_parts_mut(chunk.columns[column].cast::<DG>(), chunk.len())
+                };
+                f(chunk.get_ids(), values);
+            }
+        }
+    }
+
+    /// Add the entities requiring global system `gs_id` to `entities`, chunk by chunk
+    pub(super) fn extend_global_system_entities(
+        &self,
+        gs_id: GlobalSystemID,
+        entities: &mut Vec<EntityPtr>,
+    ) {
+        let archetypes = self
+            .archetypes
+            .iter()
+            .filter(|archetype| archetype.global_systems.binary_search(&gs_id).is_ok());
+        for archetype in archetypes {
+            for chunk in archetype.chunks.iter() {
+                entities.extend_from_slice(&chunk.entities);
+            }
+        }
+    }
+
+    /// If there are no entities stored in archetypes
+    pub fn is_empty(&self) -> bool {
+        self.archetypes.iter().all(Archetype::is_empty)
+    }
+
+    /// All the archetypes in this storage
+    #[inline(always)]
+    pub fn get_archetypes(&self) -> &[Archetype] {
+        &self.archetypes
+    }
+}
+
+impl Drop for ArchetypeStorage {
+    fn drop(&mut self) {
+        // Give the datagroups back to their entities, so they are dropped with them
+        for archetype_id in 0..self.archetypes.len() {
+            while let Some(last_chunk) = self.archetypes[archetype_id].chunks.last() {
+                let entity_ptr = *last_chunk.entities.last().unwrap();
+                // The world is being destroyed, no one else can access its entities
+                let entity = unsafe { &mut *entity_ptr.data_ptr() };
+                entity.move_out_of_archetype(self);
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+/// Change detection for datagroups.
+///
+/// Every datagroup of an entity remembers the tick when it was added, and the last tick
+/// when mutable access to it was handed out, be it through `Entity::get_datagroup_mut`
+/// or to a local system. Ticks advance once per stage, so a system can store the current
+/// tick when it looks at the datagroups, and next time only look at the ones changed since.
+///
+/// Datagroups of destroyed entities are kept in a per world log for one world step,
+/// see `World::removed_since`.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::core::ids::IDLocator;
+use crate::core::locking::RwLock;
+use crate::data_group::{DataGroup, DataGroupID};
+use crate::get_id;
+
+use super::entity::EntityID;
+use super::entity_allocator::EntityPtr;
+
+/// Point in time used to compare changes. Increases every stage of every world
+pub type ChangeTick = u64;
+
+/// Tick of the stage being run, shared by all the worlds so ticks of different worlds can be compared
+static CHANGE_TICK: AtomicU64 = AtomicU64::new(1);
+
+/// Change ticks of a datagroup in an entity
+#[derive(Debug)]
+pub struct DataGroupTicks {
+    added: ChangeTick,
+    changed: AtomicU64,
+}
+
+/// A datagroup that was removed from a world when its entity was destroyed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemovedDataGroup {
+    pub entity: EntityID,
+    pub datagroup: DataGroupID,
+    pub tick: ChangeTick,
+}
+
+/// Datagroups removed from a world, kept until the end of the world step after their removal
+#[derive(Debug, Default)]
+pub(super) struct RemovedDataGroups {
+    entries: RwLock<Vec<RemovedDataGroup>>,
+    /// Tick when the current world step started
+    step_start: AtomicU64,
+}
+
+/// Filters over the entities of a global system by the change ticks of their datagroups
+pub trait ChangeFilter<'a>: Iterator<Item = &'a EntityPtr> + Sized {
+    /// Keep only the entities whose `DG` datagroup changed at `tick` or later
+    fn changed_since<DG>(self, tick: ChangeTick) -> impl Iterator<Item = &'a EntityPtr>
+    where
+        DG: IDLocator + DataGroup + 'static,
+    {
+        let id = get_id!(DG);
+        self.filter(move |entity| {
+            entity
+                .read()
+                .get_datagroup_ticks_by_id(id)
+                .is_some_and(|ticks| ticks.changed_since(tick))
+        })
+    }
+
+    /// Keep only the entities whose `DG` datagroup was added at `tick` or later
+    fn added_since<DG>(self, tick: ChangeTick) -> impl Iterator<Item = &'a EntityPtr>
+    where
+        DG: IDLocator + DataGroup + 'static,
+    {
+        let id = get_id!(DG);
+        self.filter(move |entity| {
+            entity
+                .read()
+                .get_datagroup_ticks_by_id(id)
+                .is_some_and(|ticks| ticks.added_since(tick))
+        })
+    }
+}
+
+// -- < Implementations > --------------------------------
+
+impl<'a, I: Iterator<Item = &'a EntityPtr>> ChangeFilter<'a> for I {}
+
+/// Tick of the stage currently running.
+///
+/// Changes are compared inclusively: store this tick when looking at some datagroups,
+/// and pass it to `changed_since` next time. Changes made later in the same stage
+/// might be reported twice, but never missed
+#[inline(always)]
+pub fn current_change_tick() -> ChangeTick {
+    CHANGE_TICK.load(Ordering::Acquire)
+}
+
+/// Start a new tick, returning it
+#[inline(always)]
+pub(super) fn advance_change_tick() -> ChangeTick {
+    CHANGE_TICK.fetch_add(1, Ordering::AcqRel) + 1
+}
+
+impl DataGroupTicks {
+    #[inline(always)]
+    pub(super) fn new(tick: ChangeTick) -> Self {
+        Self {
+            added: tick,
+            changed: AtomicU64::new(tick),
+        }
+    }
+
+    /// Tick when this datagroup was added to its entity
+    #[inline(always)]
+    pub fn added(&self) -> ChangeTick {
+        self.added
+    }
+
+    /// Last tick when this datagroup was accessed mutably
+    #[inline(always)]
+    pub fn changed(&self) -> ChangeTick {
+        self.changed.load(Ordering::Acquire)
+    }
+
+    #[inline(always)]
+    pub fn added_since(&self, tick: ChangeTick) -> bool {
+        self.added >= tick
+    }
+
+    #[inline(always)]
+    pub fn changed_since(&self, tick: ChangeTick) -> bool {
+        self.changed() >= tick
+    }
+
+    /// Mark this datagroup as changed at `tick`.
+    /// Only takes `&self` so local systems running chunk by chunk can mark their entities
+    #[inline(always)]
+    pub(super) fn mark_changed(&self, tick: ChangeTick) {
+        self.changed.fetch_max(tick, Ordering::AcqRel);
+    }
+}
+
+impl RemovedDataGroups {
+    /// Log the datagroups of an entity being destroyed
+    pub(super) fn push(&self, entity: EntityID, datagroups: impl Iterator<Item = DataGroupID>) {
+        let tick = current_change_tick();
+        self.entries
+            .write()
+            .extend(datagroups.map(|datagroup| RemovedDataGroup {
+                entity,
+                datagroup,
+                tick,
+            }));
+    }
+
+    /// Forget the removals from before the previous step
+    pub(super) fn on_step_start(&self, tick: ChangeTick) {
+        let prev_step_start = self.step_start.swap(tick, Ordering::AcqRel);
+        self.entries
+            .write()
+            .retain(|removed| removed.tick >= prev_step_start);
+    }
+
+    /// Datagroups removed at `tick` or later, still in the log
+    pub(super) fn since(&self, tick: ChangeTick) -> Vec<RemovedDataGroup> {
+        self.entries
+            .read()
+            .iter()
+            .filter(|removed| removed.tick >= tick)
+            .copied()
+            .collect()
+    }
+}
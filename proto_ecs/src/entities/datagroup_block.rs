@@ -0,0 +1,184 @@
+/// Storage for the datagroups of an entity.
+///
+/// Instead of boxing every datagroup, an entity constructs all of them in place
+/// in a single memory block, using the layouts and in place factories from the
+/// datagroup registry. This means one allocation per entity when spawning, and
+/// datagroups of the same entity being close in memory when running its stages.
+use std::alloc::Layout;
+use std::fmt::Debug;
+
+use crate::core::het_single_mem_block::HetSingleMemBlock;
+use crate::data_group::{DataGroup, DataGroupID, DataGroupInitType, DataGroupRegistry};
+
+use super::entity::DataGroupVec;
+
+/// Datagroups of an entity sharing a single memory block, sorted by id
+#[derive(Default)]
+pub struct DataGroupBlock {
+    /// Memory of the datagroups, `None` if there are none
+    _block: Option<HetSingleMemBlock>,
+    datagroups: Vec<*mut dyn DataGroup>,
+}
+
+// -- < Implementations > --------------------------------
+
+// Datagroups are Send + Sync, and the block owns them
+unsafe impl Send for DataGroupBlock {}
+unsafe impl Sync for DataGroupBlock {}
+
+impl DataGroupBlock {
+    /// Create and initialize datagroups in place in a new block
+    pub(super) fn new(
+        data_groups: impl IntoIterator<Item = (DataGroupID, DataGroupInitType)>,
+    ) -> Self {
+        let mut data_groups: Vec<_> = data_groups.into_iter().collect();
+        if data_groups.is_empty() {
+            return Self::default();
+        }
+
+        // Sort them to be able to use binary search
+        data_groups.sort_by_key(|(id, _)| *id);
+
+        let dg_registry = DataGroupRegistry::get_global_registry().read();
+        for (id, init_params) in &data_groups {
+            if let DataGroupInitType::Uninitialized(msg) = init_params {
+                panic!(
+                    "Uninitialized DataGroup '{}': {msg}",
+                    dg_registry.get_entry_by_id(*id).name
+                );
+            }
+        }
+
+        let layouts: Vec<Layout> = data_groups
+            .iter()
+            .map(|(id, _)| dg_registry.get_entry_by_id(*id).layout.layout)
+            .collect();
+        let block = HetSingleMemBlock::alloc(&layouts);
+        let dsts: Vec<*mut u8> = unsafe { block.get_layout_iter(&layouts).collect() };
+
+        let mut result = Self {
+            _block: Some(block),
+            datagroups: Vec::with_capacity(data_groups.len()),
+        };
+
+        for ((id, init_params), dst) in data_groups.into_iter().zip(dsts) {
+            let new_dg = unsafe { dg_registry.create_in_place_by_id(id, dst) };
+            // Store it before init, so it gets dropped if init panics
+            result.datagroups.push(new_dg);
+
+            let new_dg = unsafe { &mut *new_dg };
+            match init_params {
+                DataGroupInitType::Uninitialized(_) => unreachable!(),
+                DataGroupInitType::NoInit => (),
+                DataGroupInitType::NoArg => new_dg.__init__(None),
+                DataGroupInitType::Arg(param) => new_dg.__init__(Some(param)),
+                DataGroupInitType::OptionalArg(param) => new_dg.__init__(param),
+            }
+        }
+
+        result
+    }
+
+    /// Move boxed datagroups to a new block. `datagroups` should be sorted by id
+    pub(super) fn from_boxed(datagroups: DataGroupVec) -> Self {
+        if datagroups.is_empty() {
+            return Self::default();
+        }
+
+        let dg_registry = DataGroupRegistry::get_global_registry().read();
+        let dg_layouts: Vec<_> = datagroups
+            .iter()
+            .map(|dg| dg_registry.get_entry_by_id(dg.get_id()).layout)
+            .collect();
+        let layouts: Vec<Layout> = dg_layouts
+            .iter()
+            .map(|dg_layout| dg_layout.layout)
+            .collect();
+        let block = HetSingleMemBlock::alloc(&layouts);
+        let dsts: Vec<*mut u8> = unsafe { block.get_layout_iter(&layouts).collect() };
+
+        let datagroups = datagroups
+            .into_iter()
+            .zip(dg_layouts.iter().zip(dsts))
+            .map(|(datagroup, (dg_layout, dst))| unsafe {
+                (dg_layout.move_to)(datagroup, dst);
+                (dg_layout.as_dyn)(dst)
+            })
+            .collect();
+
+        Self {
+            _block: Some(block),
+            datagroups,
+        }
+    }
+
+    /// Move the datagroups out of this block, to boxes
+    pub(super) fn into_boxed(mut self) -> DataGroupVec {
+        let dg_registry = DataGroupRegistry::get_global_registry().read();
+
+        // Once moved out, the block must not drop them
+        std::mem::take(&mut self.datagroups)
+            .into_iter()
+            .map(|datagroup| unsafe {
+                let entry = dg_registry.get_entry_by_id((*datagroup).get_id());
+                (entry.layout.move_from)(datagroup.cast())
+            })
+            .collect()
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.datagroups.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.datagroups.is_empty()
+    }
+
+    /// Position of the datagroup with id `id`, if any
+    #[inline]
+    pub fn position(&self, id: DataGroupID) -> Option<usize> {
+        self.datagroups
+            .binary_search_by_key(&id, |dg| unsafe { (**dg).get_id() })
+            .ok()
+    }
+
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> &dyn DataGroup {
+        unsafe { &*self.datagroups[index] }
+    }
+
+    #[inline(always)]
+    pub fn get_mut(&mut self, index: usize) -> &mut dyn DataGroup {
+        unsafe { &mut *self.datagroups[index] }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(dyn DataGroup + 'static)> {
+        self.datagroups.iter().map(|dg| unsafe { &**dg })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut (dyn DataGroup + 'static)> {
+        self.datagroups.iter().map(|dg| unsafe { &mut **dg })
+    }
+
+    /// Pointers to the datagroups in this block, as used by local systems
+    #[inline(always)]
+    pub(super) fn as_ptrs(&mut self) -> &[*mut (dyn DataGroup + 'static)] {
+        &self.datagroups
+    }
+}
+
+impl Drop for DataGroupBlock {
+    fn drop(&mut self) {
+        for datagroup in self.datagroups.drain(..) {
+            unsafe { std::ptr::drop_in_place(datagroup) };
+        }
+    }
+}
+
+impl Debug for DataGroupBlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
@@ -1,11 +1,13 @@
 use std::sync::atomic::Ordering;
+use std::time::Instant;
 
 use crate::{
     core::{
         casting::{cast, cast_mut, CanCast},
         ids::IDLocator,
+        profiling::Profiler,
     },
-    data_group::{DataGroup, DataGroupID, DataGroupInitType, DataGroupRegistry},
+    data_group::{DataGroup, DataGroupID, DataGroupRegistry},
     entities::entity_spawn_desc::EntitySpawnDescription,
     get_id,
     systems::common::Dependency,
@@ -23,6 +25,9 @@ use rayon::prelude::*;
 use vector_map::{set::VecSet, VecMap};
 
 use super::{
+    archetype::{ArchetypeRow, ArchetypeStorage},
+    change_detection::{current_change_tick, ChangeTick, DataGroupTicks},
+    datagroup_block::DataGroupBlock,
     entity_system::{EntityPtr, World},
     transform_datagroup::Transform,
 };
@@ -32,7 +37,36 @@ pub type EntityID = u64;
 /// The invalid entity ID
 pub const INVALID_ENTITY_ID: EntityID = 0;
 
-/// Map type used by entities to store datagroups
+/// Number of low bits of an [EntityID] used for its index. The high bits hold its generation,
+/// which is only non zero for recycled ids
+pub const ENTITY_INDEX_BITS: u32 = 40;
+
+/// Mask to get the index of an [EntityID]
+pub const ENTITY_INDEX_MASK: EntityID = (1 << ENTITY_INDEX_BITS) - 1;
+
+/// Max generation an [EntityID] can have before its index is retired
+pub const MAX_ENTITY_GENERATION: u32 = (1 << (EntityID::BITS - ENTITY_INDEX_BITS)) - 1;
+
+/// Index part of an entity id
+#[inline(always)]
+pub const fn entity_id_index(id: EntityID) -> EntityID {
+    id & ENTITY_INDEX_MASK
+}
+
+/// Generation part of an entity id
+#[inline(always)]
+pub const fn entity_id_generation(id: EntityID) -> u32 {
+    (id >> ENTITY_INDEX_BITS) as u32
+}
+
+/// Pack an index and a generation into an entity id
+#[inline(always)]
+pub const fn make_entity_id(index: EntityID, generation: u32) -> EntityID {
+    debug_assert!(index <= ENTITY_INDEX_MASK && generation <= MAX_ENTITY_GENERATION);
+    ((generation as EntityID) << ENTITY_INDEX_BITS) | index
+}
+
+/// Boxed datagroups, used when moving datagroups between storages
 pub type DataGroupVec = Vec<Box<dyn DataGroup>>;
 
 /// Type for use when indexing datagroups in entities
@@ -55,7 +89,7 @@ pub type StageEnabledMap = BitArr!(for STAGE_COUNT);
 type LocalSystemIndexingVec = Vec<DataGroupIndexingType>;
 
 /// Map type used by entities to store local systems' execution functions per stage
-pub type StageMap = VecMap<StageID, Vec<(DataGroupIndexingType, SystemFn)>>;
+pub type StageMap = VecMap<StageID, Vec<(SystemClassID, DataGroupIndexingType, SystemFn)>>;
 
 /// Map type used by entities to store the reference to its children
 pub type ChildrenMap = VecSet<EntityID>;
@@ -66,7 +100,9 @@ pub struct Entity {
     name: String,
     debug_info: String,
 
-    datagroups: DataGroupVec,
+    datagroups: DataGroupBlock,
+    /// Change ticks of the datagroups, in the same order as the datagroups
+    datagroup_ticks: Vec<DataGroupTicks>,
 
     local_systems_indices: LocalSystemIndexingVec,
     local_systems_map: LocalSystemMap,
@@ -75,8 +111,12 @@ pub struct Entity {
 
     global_systems: IntSet<GlobalSystemID>,
 
-    // Index of the transform datagroup in the `datagroups` vector
+    // Index of the transform datagroup in the `datagroups` block
     transform_index: DataGroupIndexingType,
+
+    // Where the datagroups are stored when using archetype storage.
+    // In that case `datagroups` is empty
+    archetype: Option<ArchetypeRow>,
 }
 
 impl Entity {
@@ -93,45 +133,14 @@ impl Entity {
             global_systems,
         } = spawn_desc;
 
-        // Init Datagroups
-        let dg_registry = DataGroupRegistry::get_global_registry().read();
-        let mut datagroups = DataGroupVec::new();
-
-        let transform_dg_id = Transform::get_id();
-        let mut transform_requested = false;
-        for (id, init_params) in data_groups {
-            let entry = dg_registry.get_entry_by_id(id);
-
-            let mut new_dg = (entry.factory_func)();
+        // Init Datagroups, all of them in the same block sorted by id
+        assert!(data_groups.len() <= MAX_DATAGROUP_LEN as usize);
+        let datagroups = DataGroupBlock::new(data_groups);
+        let datagroup_ticks = new_ticks(datagroups.len());
 
-            match init_params {
-                DataGroupInitType::Uninitialized(msg) => {
-                    panic!("Uninitialized DataGroup '{}': {msg}", entry.name);
-                }
-                DataGroupInitType::NoInit => (),
-                DataGroupInitType::NoArg => new_dg.__init__(None),
-                DataGroupInitType::Arg(param) => new_dg.__init__(Some(param)),
-                DataGroupInitType::OptionalArg(param) => new_dg.__init__(param),
-            }
-
-            datagroups.push(new_dg);
-
-            transform_requested = transform_requested || id == transform_dg_id;
-        }
-        assert!(datagroups.len() <= MAX_DATAGROUP_LEN as usize);
-
-        // Sort them to be able to use binary search
-        datagroups.sort_by_key(|dg| dg.get_id());
-        let mut transform_index = INVALID_DATAGROUP_INDEX;
-        if transform_requested {
-            transform_index = datagroups
-                .binary_search_by_key(&Transform::get_id(), |dg| dg.get_id())
-                .unwrap() as DataGroupIndexingType;
-            debug_assert_ne!(
-                transform_index, INVALID_DATAGROUP_INDEX,
-                "Failed to find transform DG!"
-            );
-        }
+        let transform_index = datagroups
+            .position(Transform::get_id())
+            .map_or(INVALID_DATAGROUP_INDEX, |pos| pos as DataGroupIndexingType);
 
         // Build temp map for their positions (for Local Systems lookup)
         let mut dg_to_pos_map: IntMap<DataGroupID, DataGroupIndexingType> = IntMap::default();
@@ -184,7 +193,11 @@ impl Entity {
                             }
 
                             let stage = stage_map.get_mut(&stage_id).unwrap();
-                            stage.push((entry.dependencies.len() as DataGroupIndexingType, *fun));
+                            stage.push((
+                                id,
+                                entry.dependencies.len() as DataGroupIndexingType,
+                                *fun,
+                            ));
                         }
                     }
                 });
@@ -197,12 +210,14 @@ impl Entity {
             name,
             debug_info,
             datagroups,
+            datagroup_ticks,
             local_systems_indices,
             local_systems_map: local_systems,
             ls_stage_enabled_map,
             stage_map,
             global_systems,
             transform_index,
+            archetype: None,
         };
 
         // Remember to initialize transform
@@ -213,6 +228,51 @@ impl Entity {
         entity
     }
 
+    /// Create a copy of this entity with a new id. The copy has the same local and global systems,
+    /// and a clone of each of its datagroups. The copy is not part of any hierarchy.
+    ///
+    /// Panics if any of its datagroups is not cloneable, see [Entity::find_uncloneable_datagroup]
+    pub(super) fn duplicate(&self, id: EntityID, self_ptr: EntityPtr) -> Self {
+        let dg_registry = DataGroupRegistry::get_global_registry().read();
+        let datagroups: DataGroupVec = self
+            .get_all_datagroups()
+            .map(|dg| {
+                dg_registry.try_clone(dg).unwrap_or_else(|| {
+                    panic!(
+                        "Can't duplicate entity '{}': DataGroup '{}' is not cloneable",
+                        self.name,
+                        dg_registry.get_entry_by_id(dg.get_id()).name
+                    )
+                })
+            })
+            .collect();
+        drop(dg_registry);
+        let datagroups = DataGroupBlock::from_boxed(datagroups);
+        let datagroup_ticks = new_ticks(datagroups.len());
+
+        let mut entity = Self {
+            id,
+            self_ptr,
+            name: self.name.clone(),
+            debug_info: self.debug_info.clone(),
+            datagroups,
+            datagroup_ticks,
+            local_systems_indices: self.local_systems_indices.clone(),
+            local_systems_map: self.local_systems_map.clone(),
+            ls_stage_enabled_map: self.ls_stage_enabled_map,
+            stage_map: self.stage_map.clone(),
+            global_systems: self.global_systems.clone(),
+            transform_index: self.transform_index,
+            archetype: None,
+        };
+
+        if entity.is_spatial_entity() {
+            entity.init_transform();
+        }
+
+        entity
+    }
+
     #[inline(always)]
     pub fn get_id(&self) -> EntityID {
         self.id
@@ -228,26 +288,128 @@ impl Entity {
         &self.debug_info
     }
 
+    /// Datagroups stored in the block of this entity. Empty if the entity uses archetype storage,
+    /// see `get_all_datagroups`
     #[inline(always)]
-    pub fn get_datagroups(&self) -> &DataGroupVec {
+    pub fn get_datagroups(&self) -> &DataGroupBlock {
         &self.datagroups
     }
 
+    /// All the datagroups of this entity sorted by id, wherever they are stored
+    pub fn get_all_datagroups(&self) -> impl Iterator<Item = &(dyn DataGroup + 'static)> {
+        let in_block = self.datagroups.iter();
+        let in_archetype = self
+            .archetype
+            .iter()
+            .flat_map(|row| row.get_datagroups())
+            .map(|dg| unsafe { &*dg });
+        in_block.chain(in_archetype)
+    }
+
+    /// All the datagroups of this entity sorted by id, wherever they are stored
+    pub(super) fn get_all_datagroups_mut(
+        &mut self,
+    ) -> impl Iterator<Item = &mut (dyn DataGroup + 'static)> {
+        let in_block = self.datagroups.iter_mut();
+        let in_archetype = self
+            .archetype
+            .iter()
+            .flat_map(|row| row.get_datagroups())
+            .map(|dg| unsafe { &mut *dg });
+        in_block.chain(in_archetype)
+    }
+
+    /// Name of the first datagroup of this entity that can't be cloned, if any.
+    /// Entities with such datagroups can't be duplicated
+    pub(super) fn find_uncloneable_datagroup(&self) -> Option<&'static str> {
+        let dg_registry = DataGroupRegistry::get_global_registry().read();
+        self.get_all_datagroups()
+            .map(|dg| dg_registry.get_entry_by_id(dg.get_id()))
+            .find(|entry| entry.clone_func.is_none())
+            .map(|entry| entry.name)
+    }
+
     #[inline]
     pub fn get_datagroup_by_id(&self, id: DataGroupID) -> Option<&dyn DataGroup> {
-        let pos = self.datagroups.binary_search_by_key(&id, |dg| dg.get_id());
-        match pos {
-            Ok(pos) => Some(self.datagroups[pos].as_ref()),
-            Err(_) => None,
+        if let Some(row) = &self.archetype {
+            return row.get_by_id(id).map(|dg| unsafe { &*dg });
         }
+
+        self.datagroups
+            .position(id)
+            .map(|pos| self.datagroups.get(pos))
     }
 
+    /// Marks the datagroup as changed, see [DataGroupTicks]
     #[inline]
     pub fn get_datagroup_by_id_mut(&mut self, id: DataGroupID) -> Option<&mut dyn DataGroup> {
-        let pos = self.datagroups.binary_search_by_key(&id, |dg| dg.get_id());
-        match pos {
-            Ok(pos) => Some(self.datagroups[pos].as_mut()),
-            Err(_) => None,
+        let pos = self.datagroup_position(id)?;
+        self.datagroup_ticks[pos].mark_changed(current_change_tick());
+
+        if let Some(row) = &self.archetype {
+            return Some(unsafe { &mut *row.get(pos) });
+        }
+        Some(self.datagroups.get_mut(pos))
+    }
+
+    /// Position of the datagroup with id `id` among the datagroups of this entity
+    #[inline]
+    fn datagroup_position(&self, id: DataGroupID) -> Option<usize> {
+        match &self.archetype {
+            Some(row) => row.position(id),
+            None => self.datagroups.position(id),
+        }
+    }
+
+    /// Change ticks of the datagroup with id `id`, if this entity has it
+    #[inline]
+    pub fn get_datagroup_ticks_by_id(&self, id: DataGroupID) -> Option<&DataGroupTicks> {
+        self.datagroup_position(id)
+            .map(|pos| &self.datagroup_ticks[pos])
+    }
+
+    #[inline(always)]
+    pub fn get_datagroup_ticks<DG>(&self) -> Option<&DataGroupTicks>
+    where
+        DG: IDLocator + DataGroup + Sized + 'static,
+    {
+        self.get_datagroup_ticks_by_id(get_id!(DG))
+    }
+
+    /// If the `DG` datagroup of this entity changed at `tick` or later
+    #[inline(always)]
+    pub fn datagroup_changed_since<DG>(&self, tick: ChangeTick) -> bool
+    where
+        DG: IDLocator + DataGroup + Sized + 'static,
+    {
+        self.get_datagroup_ticks::<DG>()
+            .is_some_and(|ticks| ticks.changed_since(tick))
+    }
+
+    /// If the `DG` datagroup of this entity was added at `tick` or later
+    #[inline(always)]
+    pub fn datagroup_added_since<DG>(&self, tick: ChangeTick) -> bool
+    where
+        DG: IDLocator + DataGroup + Sized + 'static,
+    {
+        self.get_datagroup_ticks::<DG>()
+            .is_some_and(|ticks| ticks.added_since(tick))
+    }
+
+    /// If any datagroup of this entity changed at `tick` or later
+    pub fn changed_since(&self, tick: ChangeTick) -> bool {
+        self.datagroup_ticks
+            .iter()
+            .any(|ticks| ticks.changed_since(tick))
+    }
+
+    /// Mark the datagroups in `positions` as changed. Invalid positions are skipped
+    #[inline]
+    pub(super) fn mark_changed(&self, positions: &[DataGroupIndexingType], tick: ChangeTick) {
+        for &pos in positions {
+            if let Some(ticks) = self.datagroup_ticks.get(pos as usize) {
+                ticks.mark_changed(tick);
+            }
         }
     }
 
@@ -268,6 +430,44 @@ impl Entity {
             .map(|dg| cast_mut(dg))
     }
 
+    /// If the datagroups of this entity are stored in an archetype chunk.
+    /// Its local systems are run by the archetype instead of by the entity
+    #[inline(always)]
+    pub fn is_in_archetype(&self) -> bool {
+        self.archetype.is_some()
+    }
+
+    /// Move the datagroups of this entity to the archetype storage of its world.
+    /// Only non-spatial entities can be stored in archetypes
+    pub(super) fn move_to_archetype(&mut self, archetypes: &mut ArchetypeStorage) {
+        debug_assert!(
+            !self.is_spatial_entity(),
+            "Spatial entities can't be stored in archetypes"
+        );
+        debug_assert!(!self.is_in_archetype(), "Entity is already in an archetype");
+
+        let datagroups = std::mem::take(&mut self.datagroups).into_boxed();
+        self.archetype = Some(archetypes.insert(
+            self.id,
+            self.self_ptr,
+            datagroups,
+            &self.local_systems_map,
+            &self.global_systems,
+        ));
+    }
+
+    /// Move the datagroups of this entity out of the archetype storage of its world, back to boxes
+    pub(super) fn move_out_of_archetype(&mut self, archetypes: &mut ArchetypeStorage) {
+        if let Some(row) = self.archetype.take() {
+            self.datagroups = DataGroupBlock::from_boxed(archetypes.remove(row));
+        }
+    }
+
+    #[inline(always)]
+    pub(super) fn get_archetype_row_mut(&mut self) -> Option<&mut ArchetypeRow> {
+        self.archetype.as_mut()
+    }
+
     /// Use this function to mark this as without transform.
     ///
     /// Useful when you want an entity to forget about its transform.
@@ -288,7 +488,7 @@ impl Entity {
             self.is_spatial_entity(),
             "Can't get transform from non spatial entity!"
         );
-        cast(&self.datagroups[self.transform_index as usize])
+        cast(self.datagroups.get(self.transform_index as usize))
     }
 
     #[inline(always)]
@@ -310,7 +510,7 @@ impl Entity {
             self.is_spatial_entity(),
             "Can't get transform from non spatial entity!"
         );
-        cast_mut(&mut self.datagroups[self.transform_index as usize])
+        cast_mut(self.datagroups.get_mut(self.transform_index as usize))
     }
 
     #[inline(always)]
@@ -318,6 +518,7 @@ impl Entity {
         if !self.is_spatial_entity() {
             None
         } else {
+            self.mark_changed(&[self.transform_index], current_change_tick());
             Some(unsafe { self.get_transform_mut_unsafe() })
         }
     }
@@ -377,6 +578,11 @@ impl Entity {
     /// Note: this function is used by the engine to check if this entity
     /// should be included in the list of entities to run per stage
     pub(super) fn should_run_in_stage(&self, stage_id: StageID) -> bool {
+        // Entities in archetypes are run by their archetype
+        if self.is_in_archetype() {
+            return false;
+        }
+
         // Check if we are non-spatial
         if !self.is_spatial_entity() {
             // Non-spatial entities only need to check themselves if they need to run
@@ -411,15 +617,27 @@ impl Entity {
             .expect("Uninitialized Entity or Entity in undefined state!");
 
         let mut indices_start: usize = 0;
+        let tick = current_change_tick();
+        let profiling = Profiler::is_enabled();
 
-        for (indices_num, local_sys_fun) in stage {
+        for (local_sys_id, indices_num, local_sys_fun) in stage {
             let indices_num = *indices_num as usize;
-            (local_sys_fun)(
-                world,
-                self.id,
-                &self.local_systems_indices[indices_start..(indices_start + indices_num)],
-                &mut self.datagroups,
-            );
+            let indices = &self.local_systems_indices[indices_start..(indices_start + indices_num)];
+
+            // Local systems get mutable access to all their datagroups
+            for &pos in indices {
+                if let Some(ticks) = self.datagroup_ticks.get(pos as usize) {
+                    ticks.mark_changed(tick);
+                }
+            }
+
+            let start = profiling.then(Instant::now);
+            (local_sys_fun)(world, self.id, indices, self.datagroups.as_ptrs());
+            if let Some(start) = start {
+                world
+                    .get_local_system_timings()
+                    .add(*local_sys_id, start.elapsed(), 1);
+            }
             indices_start += indices_num;
         }
     }
@@ -474,6 +692,7 @@ impl Entity {
 
                         // Update parent position to calculate current position
                         transform.set_parent_transform_mat(new_parent_transform_mat);
+                        child.mark_changed(&[child.transform_index], current_change_tick());
                         recurse(child, world, stage_id);
                     }
                 });
@@ -637,6 +856,8 @@ impl Entity {
                 }
             }
         }
+
+        unsafe { self.get_transform_mut_unsafe() }.parent = None;
     }
 
     /// Initializes the transform datagroup for this entity.
@@ -659,6 +880,12 @@ impl Entity {
     }
 }
 
+/// Ticks for `len` datagroups added in the current tick
+fn new_ticks(len: usize) -> Vec<DataGroupTicks> {
+    let tick = current_change_tick();
+    (0..len).map(|_| DataGroupTicks::new(tick)).collect()
+}
+
 impl std::fmt::Debug for Entity {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let dg_registry = DataGroupRegistry::get_global_registry().read();
@@ -782,7 +1009,7 @@ impl std::fmt::Debug for Entity {
             .field("id", &self.id)
             .field("name", &self.name)
             .field("debug_info", &self.debug_info)
-            .field("datagroups", &self.datagroups)
+            .field("datagroups", &self.get_all_datagroups().collect::<Vec<_>>())
             .field("local_systems", &local_system_map.values())
             .field("ls_stage_enabled_map", &ls_stage_enabled_map)
             .field("stages", &stage_map)
@@ -2,33 +2,53 @@ use crate::core::locking::RwLock;
 use crate::entities::entity::Entity;
 use bitvec::store::BitStore;
 use lazy_static::lazy_static;
+use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
 use scc::Queue;
 use std::fmt::Debug;
 use std::mem::MaybeUninit;
 use std::ops::{Deref, DerefMut};
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 
-use super::entity::EntityID;
+use super::entity::{EntityID, INVALID_ENTITY_ID};
 use super::entity_spawn_desc::EntitySpawnDescription;
 
+/// If entity pointers check their generation before every access. Always on in debug builds,
+/// and in release builds with the `safe-entities` feature
+pub const CHECKED_ENTITY_ACCESS: bool = cfg!(any(debug_assertions, feature = "safe-entities"));
+
 /// Manage entity allocation and storage.
 /// There should be just one global instance of this struct,
-/// accessible with `EntityAllocator::get_global()`
+/// accessible with `EntityAllocator::get_global()`.
+///
+/// Entities are stored in chunks of [EntityAllocator::CHUNK_LEN] entries. Allocating and
+/// freeing only need shared access: free entries are kept in a lock-free queue, and the
+/// chunk list is only locked when a new chunk is needed. Memory of chunks without live
+/// entities can be released with `trim`, which needs exclusive access.
 #[derive(Debug, Default)]
 pub struct EntityAllocator {
-    #[allow(clippy::vec_box)] // Entities are big and non-memcpy'ed
-    entries: RwLock<Vec<Box<EntityEntry>>>,
+    chunks: RwLock<Vec<Chunk>>,
     free: FreeQueue,
+    live: AtomicUsize,
 }
 
-/// A lock-free queue of pointers to locked entities
-type FreeQueue = Queue<*mut EntityLock>;
+/// A lock-free queue of free entries
+type FreeQueue = Queue<FreeEntry>;
+
+/// A free entry, with pointers to its header and to its memory
+#[derive(Debug, Clone, Copy)]
+struct FreeEntry {
+    header: *const EntryHeader,
+    mem: *mut EntityLock,
+    chunk: usize,
+}
 
+/// A chunk of entries. Headers are never released, so stale pointers can still be
+/// checked after their memory is trimmed
 #[derive(Debug)]
-#[repr(C)]
-struct EntityEntry {
-    header: EntryHeader,
-    mem: MaybeUninit<EntityLock>,
+struct Chunk {
+    headers: Box<[EntryHeader]>,
+    /// Memory of the entities in this chunk, `None` if trimmed
+    mem: Option<Box<[MaybeUninit<EntityLock>]>>,
 }
 
 /// A Locked entity
@@ -36,14 +56,41 @@ pub type EntityLock = RwLock<Entity>;
 
 #[derive(Debug)]
 struct EntryHeader {
-    // It's important that this is atomic since it might be accessed from
+    /// Used to validate pointers to headers
+    magic: u32,
+    // It's important that these are atomic since they might be accessed from
     // multiple threads
     generation: AtomicGeneration,
-    is_initialized: bool,
+    is_initialized: AtomicBool,
+    /// Id of the last entity stored in this entry, for diagnostics
+    entity_id: AtomicU64,
+    /// Index of the chunk of this entry
+    chunk: usize,
 }
 
 type AtomicGeneration = AtomicU32;
 
+/// Memory usage of an [EntityAllocator]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EntityAllocatorStats {
+    /// Entries holding an allocated entity
+    pub live_entries: usize,
+    /// Entries ready to be allocated
+    pub free_entries: usize,
+    /// Entries with memory, live or free
+    pub total_entries: usize,
+    /// Chunks with memory
+    pub chunks: usize,
+    /// Chunks whose memory was released by `trim`
+    pub trimmed_chunks: usize,
+    /// Bytes used by entries with memory
+    pub total_bytes: usize,
+    /// Bytes used by live entries
+    pub live_bytes: usize,
+    /// Bytes used by the headers of all the chunks, which are never released
+    pub header_bytes: usize,
+}
+
 /// A not owning reference to an [Entity]. Use this to access an entity allocated
 /// by the [EntityAllocator]. Note that since this pointer does not own the memory,
 /// dereferencing it would cause a segfault if the allocator that returned this
@@ -57,6 +104,7 @@ type AtomicGeneration = AtomicU32;
 #[derive(Clone, Copy, PartialEq)]
 pub struct EntityPtr {
     ptr: *mut EntityLock,
+    header: *const EntryHeader,
     generation: Generation,
 }
 
@@ -65,8 +113,8 @@ type Generation = u32;
 // -- < Implementations > --------------------------------
 
 lazy_static! {
-    // I use a RwLock for the global allocator because otherwise we can't get mutable
-    // references to it.
+    // The allocator only needs shared access to allocate and free,
+    // the lock is for exclusive operations like `trim`
     static ref GLOBAL_ALLOCATOR : RwLock<EntityAllocator> = RwLock::new(EntityAllocator::new());
 }
 
@@ -74,48 +122,55 @@ unsafe impl Send for EntityAllocator {}
 unsafe impl Sync for EntityAllocator {}
 
 impl EntityAllocator {
-    /// Initial capacity of the [EntityAllocator]
-    const INITIAL_CAPACITY: usize = 10_000;
+    /// Number of entries in each chunk
+    pub const CHUNK_LEN: usize = 256;
 
     /// Create a new empty allocator
     pub fn new() -> Self {
-        Self {
-            entries: RwLock::new(Vec::with_capacity(EntityAllocator::INITIAL_CAPACITY)),
-            free: FreeQueue::default(),
-        }
+        Default::default()
     }
 
     /// Allocate an entity and get a pointer for such entity.
     ///
     /// The entity will be uninitialized, you can initialize it by
     /// calling: `ptr.init(id, spawn_desc)` with the result from this function
-    pub fn allocate(&mut self) -> EntityPtr {
-        if self.free.is_empty() {
-            // Allocate a new entry
-            let mut new_entry = Box::new(EntityEntry {
-                header: EntryHeader {
-                    generation: AtomicGeneration::ZERO,
-                    is_initialized: false,
-                },
-                mem: MaybeUninit::uninit(),
-            });
-
-            // Pointer to return
-            let mut entries = self.entries.write();
-            let ptr = new_entry.mem.as_mut_ptr();
-            entries.push(new_entry);
-
-            // Create pointer:
-            return EntityPtr { ptr, generation: 0 };
+    pub fn allocate(&self) -> EntityPtr {
+        let entry = match self.free.pop() {
+            Some(entry) => **entry,
+            None => self.grow(),
+        };
+        self.live.fetch_add(1, Ordering::Relaxed);
+
+        let header = unsafe { &*entry.header };
+        EntityPtr {
+            ptr: entry.mem,
+            header: entry.header,
+            generation: header.generation.load(Ordering::Acquire),
         }
+    }
 
-        let ptr = self.free.pop().unwrap().cast();
-        let entry = unsafe { EntityEntry::from_ptr(ptr) };
-
-        EntityPtr {
-            ptr,
-            generation: entry.header.generation.load(Ordering::Acquire),
+    /// Give memory to a chunk, reusing trimmed chunks before creating new ones.
+    /// Returns one of its entries, the rest are added to the free queue
+    #[cold]
+    fn grow(&self) -> FreeEntry {
+        let mut chunks = self.chunks.write();
+        let chunk_index = match chunks.iter().position(|chunk| chunk.mem.is_none()) {
+            Some(index) => index,
+            None => {
+                let index = chunks.len();
+                chunks.push(Chunk::new(index));
+                index
+            }
+        };
+
+        let chunk = &mut chunks[chunk_index];
+        chunk.mem = Some(Box::new_uninit_slice(Self::CHUNK_LEN));
+        let mut entries = chunk.entries(chunk_index);
+        let result = entries.next().unwrap();
+        for entry in entries {
+            self.free.push(entry);
         }
+        result
     }
 
     /// Free an entity.
@@ -126,21 +181,78 @@ impl EntityAllocator {
     ///
     /// You can check if a pointer is valid using `ptr.is_live()`
     /// And you can check if the entity is initialized using `ptr.is_initialized()`
-    pub fn free(&mut self, entity_ptr: &EntityPtr) {
+    pub fn free(&self, entity_ptr: &EntityPtr) {
         if !entity_ptr.is_live() {
             panic!("Trying to free already unused index");
         }
 
-        let entry = unsafe { EntityEntry::from_ptr(entity_ptr.ptr) };
-        entry.header.generation.fetch_add(1, Ordering::Release);
+        let header = entity_ptr.header();
+        header.generation.fetch_add(1, Ordering::Release);
 
-        if entry.header.is_initialized {
+        if header.is_initialized.swap(false, Ordering::AcqRel) {
             // Don't drop if not initialized
-            entry.header.is_initialized = false;
-            unsafe { entry.mem.assume_init_drop() };
+            unsafe { std::ptr::drop_in_place(entity_ptr.ptr) };
         }
 
-        self.free.push(entity_ptr.ptr);
+        self.live.fetch_sub(1, Ordering::Relaxed);
+        self.free.push(FreeEntry {
+            header: entity_ptr.header,
+            mem: entity_ptr.ptr,
+            chunk: header.chunk,
+        });
+    }
+
+    /// Release the memory of chunks without live entities.
+    /// Call it after destroying many entities, like when changing levels.
+    ///
+    /// Returns the number of chunks released
+    pub fn trim(&mut self) -> usize {
+        let chunks = self.chunks.get_mut();
+
+        // Free entries are pushed again if their chunk is kept
+        let mut free_per_chunk = vec![0usize; chunks.len()];
+        let mut free_entries = Vec::new();
+        while let Some(entry) = self.free.pop() {
+            let entry = **entry;
+            free_per_chunk[entry.chunk] += 1;
+            free_entries.push(entry);
+        }
+
+        let mut released = 0;
+        for (chunk, free_count) in chunks.iter_mut().zip(free_per_chunk.iter()) {
+            if chunk.mem.is_some() && *free_count == Self::CHUNK_LEN {
+                chunk.mem = None;
+                released += 1;
+            }
+        }
+
+        for entry in free_entries {
+            if chunks[entry.chunk].mem.is_some() {
+                self.free.push(entry);
+            }
+        }
+
+        released
+    }
+
+    /// Current memory usage of this allocator
+    pub fn stats(&self) -> EntityAllocatorStats {
+        let chunks = self.chunks.read();
+        let with_mem = chunks.iter().filter(|chunk| chunk.mem.is_some()).count();
+        let live_entries = self.live.load(Ordering::Relaxed);
+        let total_entries = with_mem * Self::CHUNK_LEN;
+        let entry_size = std::mem::size_of::<EntityLock>();
+
+        EntityAllocatorStats {
+            live_entries,
+            free_entries: total_entries - live_entries,
+            total_entries,
+            chunks: with_mem,
+            trimmed_chunks: chunks.len() - with_mem,
+            total_bytes: total_entries * entry_size,
+            live_bytes: live_entries * entry_size,
+            header_bytes: chunks.len() * Self::CHUNK_LEN * std::mem::size_of::<EntryHeader>(),
+        }
     }
 
     /// Get a reference to the global allocator
@@ -149,61 +261,181 @@ impl EntityAllocator {
     }
 }
 
+impl Drop for EntityAllocator {
+    fn drop(&mut self) {
+        // Drop the entities that are still live
+        for chunk in self.chunks.get_mut().iter_mut() {
+            let Some(mem) = chunk.mem.as_mut() else {
+                continue;
+            };
+            for (header, entity) in chunk.headers.iter().zip(mem.iter_mut()) {
+                if header.is_initialized.load(Ordering::Acquire) {
+                    unsafe { entity.assume_init_drop() };
+                }
+            }
+        }
+    }
+}
+
+impl Chunk {
+    fn new(index: usize) -> Self {
+        let headers = (0..EntityAllocator::CHUNK_LEN)
+            .map(|_| EntryHeader {
+                magic: EntryHeader::MAGIC,
+                generation: AtomicGeneration::ZERO,
+                is_initialized: AtomicBool::new(false),
+                entity_id: AtomicU64::new(INVALID_ENTITY_ID),
+                chunk: index,
+            })
+            .collect();
+        Chunk { headers, mem: None }
+    }
+
+    /// All the entries of this chunk, it must have memory
+    fn entries(&mut self, chunk: usize) -> impl Iterator<Item = FreeEntry> + '_ {
+        let mem = self.mem.as_mut().expect("Chunk should have memory");
+        self.headers
+            .iter()
+            .zip(mem.iter_mut())
+            .map(move |(header, mem)| FreeEntry {
+                header,
+                mem: mem.as_mut_ptr(),
+                chunk,
+            })
+    }
+}
+
 impl Deref for EntityPtr {
     type Target = EntityLock;
 
     fn deref(&self) -> &Self::Target {
-        debug_assert!(self.is_live(), "Trying to deref invalid entity ptr");
+        self.check_live();
         unsafe { self.ptr.as_ref().unwrap() }
     }
 }
 
 impl DerefMut for EntityPtr {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        debug_assert!(self.is_live(), "Trying to deref invalid entity ptr");
+        self.check_live();
         unsafe { self.ptr.as_mut().unwrap() }
     }
 }
 
-impl<'a> EntityEntry {
-    /// Create an entity entry from an entity ptr
-    ///
-    /// # Safety
-    /// This function assumes that the `ptr` comes from an [EntityEntry]
-    /// allocated by an [EntityAllocator]. Also the allocator will free all allocated
-    /// entities after dying, so this pointer can only be safely used when you are sure that
-    /// the allocator is still alive.
-    unsafe fn from_ptr(ptr: *mut EntityLock) -> &'a mut Self {
-        ptr.cast::<u8>()
-            .sub(std::mem::size_of::<EntityEntry>() - std::mem::size_of::<EntityLock>())
-            .cast::<EntityEntry>()
-            .as_mut()
-            .unwrap()
-    }
+impl EntryHeader {
+    /// Value of `magic` in every valid header
+    const MAGIC: u32 = 0x454E_5459;
 }
 
 impl EntityPtr {
     /// If the entity pointed to by this pointer is still valid and live
     #[inline(always)]
     pub fn is_live(&self) -> bool {
-        let entry = unsafe { EntityEntry::from_ptr(self.ptr) };
-        entry.header.generation.load(Ordering::Acquire) == self.generation
+        self.header().generation.load(Ordering::Acquire) == self.generation
+    }
+
+    /// Header of the entry of this pointer. Headers are never released while the allocator lives
+    #[inline(always)]
+    fn header(&self) -> &EntryHeader {
+        assert!(!self.header.is_null(), "Null entity pointer");
+        let header = unsafe { &*self.header };
+        if CHECKED_ENTITY_ACCESS {
+            assert_eq!(
+                header.magic,
+                EntryHeader::MAGIC,
+                "Entity pointer {:?} doesn't point to an entity allocated by an EntityAllocator",
+                self.ptr
+            );
+        }
+        header
+    }
+
+    /// Lock the entity for reading, or `None` if it was freed or never initialized.
+    ///
+    /// Use this instead of `read` when the pointer might be stale. Note that it doesn't keep
+    /// the entity alive: entities are only freed when worlds process their deletions,
+    /// so don't hold the guard across those
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, Entity>> {
+        if !self.is_accessible() {
+            return None;
+        }
+
+        let guard = unsafe { self.ptr.as_ref().unwrap() }.read();
+        // The slot might have been freed or reused while waiting for the lock
+        self.is_accessible().then_some(guard)
+    }
+
+    /// Lock the entity for writing, or `None` if it was freed or never initialized.
+    /// See `try_read`
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, Entity>> {
+        if !self.is_accessible() {
+            return None;
+        }
+
+        let guard = unsafe { self.ptr.as_ref().unwrap() }.write();
+        // The slot might have been freed or reused while waiting for the lock
+        self.is_accessible().then_some(guard)
+    }
+
+    #[inline(always)]
+    fn is_accessible(&self) -> bool {
+        let header = self.header();
+        header.is_initialized.load(Ordering::Acquire)
+            && header.generation.load(Ordering::Acquire) == self.generation
+    }
+
+    /// Panic with a diagnostic if this pointer is stale, when checks are enabled
+    #[inline(always)]
+    fn check_live(&self) {
+        if CHECKED_ENTITY_ACCESS && !self.is_live() {
+            self.stale_access_panic();
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn stale_access_panic(&self) -> ! {
+        let header = self.header();
+        let current_generation = header.generation.load(Ordering::Acquire);
+        let entity_id = header.entity_id.load(Ordering::Acquire);
+        let occupant = if header.is_initialized.load(Ordering::Acquire) {
+            format!("entity {entity_id}")
+        } else {
+            format!("nothing, it last held entity {entity_id}")
+        };
+        panic!(
+            "Trying to deref invalid entity ptr {:?}: pointer generation is {}, but the slot is at generation {} and holds {}",
+            self.ptr, self.generation, current_generation, occupant
+        );
     }
 
     /// Initializes this entity using the same init function
     /// as the entity struct
     pub fn init(&mut self, id: EntityID, spawn_desc: EntitySpawnDescription) {
-        let entry = unsafe { EntityEntry::from_ptr(self.ptr) };
-        entry
-            .mem
-            .write(RwLock::new(Entity::init(id, *self, spawn_desc)));
-        entry.header.is_initialized = true;
+        let entity = Entity::init(id, *self, spawn_desc);
+        self.write_entity(id, entity);
+    }
+
+    /// Initializes this entity as a copy of `source` with a new id.
+    /// See `Entity::duplicate`
+    pub fn init_duplicate(&mut self, id: EntityID, source: &Entity) {
+        let entity = source.duplicate(id, *self);
+        self.write_entity(id, entity);
+    }
+
+    fn write_entity(&mut self, id: EntityID, entity: Entity) {
+        let header = self.header();
+        debug_assert!(
+            !header.is_initialized.load(Ordering::Acquire),
+            "Entity {id} initialized twice"
+        );
+        unsafe { self.ptr.write(RwLock::new(entity)) };
+        header.entity_id.store(id, Ordering::Release);
+        header.is_initialized.store(true, Ordering::Release);
     }
 
     #[inline(always)]
     pub fn is_initialized(&self) -> bool {
-        let entry = unsafe { EntityEntry::from_ptr(self.ptr) };
-        entry.header.is_initialized
+        self.header().is_initialized.load(Ordering::Acquire)
     }
 }
 
@@ -233,6 +465,20 @@ impl PartialEq<*mut EntityLock> for &EntityPtr {
 
 impl Debug for EntityPtr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.is_accessible() {
+            // Don't deref stale or uninitialized pointers, just report what's in their slot
+            let header = self.header();
+            return f
+                .debug_struct("EntityPtr")
+                .field("ptr", &self.ptr)
+                .field("generation", &self.generation)
+                .field(
+                    "slot_generation",
+                    &header.generation.load(Ordering::Acquire),
+                )
+                .field("slot_entity_id", &header.entity_id.load(Ordering::Acquire))
+                .finish();
+        }
         self.deref().fmt(f)
     }
 }
@@ -0,0 +1,213 @@
+/// Allocation of entity ids.
+///
+/// Fresh indices come from a global counter, but threads take them in blocks so
+/// spawning from many threads doesn't fight over the same atomic. When recycling is
+/// enabled, destroyed ids are reused with their generation bumped, so stale ids
+/// never match a new entity. Ranges of ids can also be reserved up front, for example
+/// by a server that assigns the ids of networked entities.
+use std::cell::Cell;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use lazy_static::lazy_static;
+use scc::Queue;
+
+use super::entity::{
+    entity_id_generation, entity_id_index, make_entity_id, EntityID, ENTITY_INDEX_MASK,
+    INVALID_ENTITY_ID, MAX_ENTITY_GENERATION,
+};
+
+/// Range of entity ids, all of them with generation 0
+pub type EntityIDRange = Range<EntityID>;
+
+/// Manage the ids of entities.
+/// Worlds use the global instance of this struct,
+/// accessible with `EntityIDAllocator::get_global()`
+#[derive(Debug)]
+pub struct EntityIDAllocator {
+    /// Unique id of this allocator, to know who owns the block of a thread
+    instance: u64,
+    /// Next index that was never handed out
+    next_index: AtomicU64,
+    /// Destroyed ids with their next generation, ready to be reused
+    recycled: Queue<EntityID>,
+    recycling: AtomicBool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EntityIDError {
+    /// Some of the ids in the range were already handed out by this allocator
+    AlreadyAllocated {
+        range: EntityIDRange,
+        next_free: EntityID,
+    },
+    /// The range contains the invalid id or doesn't fit in the index bits of an id
+    InvalidRange(EntityIDRange),
+    /// There are not enough indices left
+    OutOfIndices,
+}
+
+// -- < Implementations > --------------------------------
+
+impl Default for EntityIDAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static! {
+    static ref GLOBAL_ID_ALLOCATOR: EntityIDAllocator = EntityIDAllocator::new();
+}
+
+/// Counter of allocator instances, starting at 1 so no allocator owns the initial thread blocks
+static INSTANCE_COUNT: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// Block of indices owned by the current thread, and the instance of the allocator it came from
+    static THREAD_BLOCK: Cell<(u64, EntityIDRange)> = const { Cell::new((0, 0..0)) };
+}
+
+impl EntityIDAllocator {
+    /// Number of indices a thread takes at once from the global counter
+    pub const THREAD_BLOCK_LEN: u64 = 256;
+
+    /// Create a new allocator. Use the global one for entities in worlds
+    pub fn new() -> Self {
+        Self {
+            instance: INSTANCE_COUNT.fetch_add(1, Ordering::Relaxed),
+            next_index: AtomicU64::new(INVALID_ENTITY_ID + 1),
+            recycled: Queue::default(),
+            recycling: AtomicBool::new(false),
+        }
+    }
+
+    /// Get the global id allocator
+    #[inline(always)]
+    pub fn get_global() -> &'static Self {
+        &GLOBAL_ID_ALLOCATOR
+    }
+
+    /// Reuse the ids of destroyed entities, tagged with a new generation.
+    /// Disabled by default, so ids are never repeated
+    pub fn set_recycling(&self, enabled: bool) {
+        self.recycling.store(enabled, Ordering::Release);
+    }
+
+    #[inline(always)]
+    pub fn is_recycling(&self) -> bool {
+        self.recycling.load(Ordering::Acquire)
+    }
+
+    /// Allocate a new entity id
+    pub fn allocate(&self) -> EntityID {
+        if self.is_recycling() {
+            if let Some(id) = self.recycled.pop() {
+                return **id;
+            }
+        }
+
+        THREAD_BLOCK.with(|block| {
+            let (owner, mut current) = block.take();
+            if owner != self.instance || current.is_empty() {
+                current = self
+                    .take_indices(Self::THREAD_BLOCK_LEN)
+                    .expect("Ran out of entity ids");
+            }
+
+            let id = current.start;
+            current.start += 1;
+            block.set((self.instance, current));
+            id
+        })
+    }
+
+    /// Release an entity id. If recycling is enabled, it will be handed out again with
+    /// its generation bumped
+    pub fn deallocate(&self, id: EntityID) {
+        debug_assert!(
+            id != INVALID_ENTITY_ID
+                && entity_id_index(id) < self.next_index.load(Ordering::Acquire),
+            "Deallocating entity id {id} that was never allocated"
+        );
+
+        if !self.is_recycling() {
+            return;
+        }
+
+        let generation = entity_id_generation(id);
+        if generation < MAX_ENTITY_GENERATION {
+            self.recycled
+                .push(make_entity_id(entity_id_index(id), generation + 1));
+        }
+        // Otherwise the index is retired, so old ids are never confused with new ones
+    }
+
+    /// Reserve `count` consecutive ids that this allocator will never hand out.
+    /// Use this on the side that assigns ids, like a server spawning networked entities
+    pub fn reserve(&self, count: u64) -> Result<EntityIDRange, EntityIDError> {
+        self.take_indices(count)
+    }
+
+    /// Mark the ids in `range` as used, so this allocator never hands them out.
+    /// Use this on the side that receives ids assigned somewhere else, like a client
+    /// receiving the range of a server. Fails if any id in the range was already handed out
+    pub fn reserve_range(&self, range: EntityIDRange) -> Result<(), EntityIDError> {
+        if range.start == INVALID_ENTITY_ID || range.end > ENTITY_INDEX_MASK + 1 {
+            return Err(EntityIDError::InvalidRange(range));
+        }
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        let mut next_free = self.next_index.load(Ordering::Acquire);
+        loop {
+            if range.start < next_free {
+                return Err(EntityIDError::AlreadyAllocated { range, next_free });
+            }
+
+            match self.next_index.compare_exchange_weak(
+                next_free,
+                range.end,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Ok(()),
+                Err(current) => next_free = current,
+            }
+        }
+    }
+
+    /// Take `count` fresh indices from the global counter
+    fn take_indices(&self, count: u64) -> Result<EntityIDRange, EntityIDError> {
+        let mut start = self.next_index.load(Ordering::Acquire);
+        loop {
+            let end = start
+                .checked_add(count)
+                .filter(|end| *end <= ENTITY_INDEX_MASK + 1)
+                .ok_or(EntityIDError::OutOfIndices)?;
+
+            match self.next_index.compare_exchange_weak(
+                start,
+                end,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Ok(start..end),
+                Err(current) => start = current,
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for EntityIDError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntityIDError::AlreadyAllocated { range, next_free } => write!(
+                f,
+                "Entity ids {range:?} overlap with ids already allocated, next free id is {next_free}"
+            ),
+            EntityIDError::InvalidRange(range) => write!(f, "Invalid entity id range {range:?}"),
+            EntityIDError::OutOfIndices => write!(f, "Out of entity ids"),
+        }
+    }
+}
@@ -0,0 +1,253 @@
+/// Dense index of the entities in a world.
+///
+/// Entities are stored contiguously in a dense array, and found by the index part of
+/// their id through a paged sparse array, so lookups don't need hashing. The full id is
+/// kept in the dense array, so ids of a different generation are not found. Stage lists hold the entities the world
+/// has to run in each stage. Every entity remembers its slot in the dense array and its
+/// position in each stage list it's in, so removals are swap-removes instead of scans.
+use crate::core::locking::RwLock;
+use crate::systems::common::{StageID, STAGE_COUNT};
+use parking_lot::{MappedRwLockReadGuard, RwLockReadGuard};
+use vector_map::VecMap;
+
+use super::entity::{entity_id_index, EntityID};
+use super::entity_allocator::EntityPtr;
+
+/// Position of an entity in the dense array or in a stage list
+type SlotIndex = u32;
+
+/// Marks ids without an entity in the sparse pages
+const INVALID_SLOT: SlotIndex = SlotIndex::MAX;
+
+/// Number of entity ids covered by each sparse page
+const PAGE_LEN: usize = 1024;
+
+/// Thread safe dense index from entity ids to entities, and of the entities to run per stage
+#[derive(Debug, Default)]
+pub struct EntityIndex {
+    inner: RwLock<EntityIndexInner>,
+}
+
+#[derive(Debug)]
+struct EntityIndexInner {
+    /// Pages mapping entity ids to dense slots. Pages are allocated when first needed,
+    /// and freed when they don't hold any entity
+    sparse: Vec<Option<Box<SparsePage>>>,
+
+    dense: Vec<EntityPtr>,
+    dense_ids: Vec<EntityID>,
+    /// Position of each entity in the stage lists it's in
+    dense_stages: Vec<VecMap<StageID, SlotIndex>>,
+
+    stages: [Vec<EntityPtr>; STAGE_COUNT],
+    stage_ids: [Vec<EntityID>; STAGE_COUNT],
+}
+
+#[derive(Debug)]
+struct SparsePage {
+    slots: [SlotIndex; PAGE_LEN],
+    len: usize,
+}
+
+// -- < Implementations > --------------------------------
+
+impl Default for EntityIndexInner {
+    fn default() -> Self {
+        Self {
+            sparse: Vec::new(),
+            dense: Vec::new(),
+            dense_ids: Vec::new(),
+            dense_stages: Vec::new(),
+            stages: std::array::from_fn(|_| Vec::new()),
+            stage_ids: std::array::from_fn(|_| Vec::new()),
+        }
+    }
+}
+
+impl EntityIndexInner {
+    #[inline(always)]
+    fn page_and_offset(id: EntityID) -> (usize, usize) {
+        let index = entity_id_index(id) as usize;
+        (index / PAGE_LEN, index % PAGE_LEN)
+    }
+
+    /// Slot of the entity with the same index as `id`, whatever its generation
+    #[inline]
+    fn slot_of_index(&self, id: EntityID) -> Option<usize> {
+        let (page, offset) = Self::page_and_offset(id);
+        let slot = self.sparse.get(page)?.as_ref()?.slots[offset];
+        (slot != INVALID_SLOT).then_some(slot as usize)
+    }
+
+    #[inline]
+    fn slot_of(&self, id: EntityID) -> Option<usize> {
+        self.slot_of_index(id)
+            .filter(|slot| self.dense_ids[*slot] == id)
+    }
+
+    fn set_slot(&mut self, id: EntityID, slot: SlotIndex) {
+        let (page, offset) = Self::page_and_offset(id);
+        if page >= self.sparse.len() {
+            self.sparse.resize_with(page + 1, || None);
+        }
+
+        let sparse_page = self.sparse[page].get_or_insert_with(|| {
+            Box::new(SparsePage {
+                slots: [INVALID_SLOT; PAGE_LEN],
+                len: 0,
+            })
+        });
+
+        let prev = std::mem::replace(&mut sparse_page.slots[offset], slot);
+        match (prev == INVALID_SLOT, slot == INVALID_SLOT) {
+            (true, false) => sparse_page.len += 1,
+            (false, true) => sparse_page.len -= 1,
+            _ => (),
+        }
+
+        if sparse_page.len == 0 {
+            self.sparse[page] = None;
+        }
+    }
+
+    fn remove_from_stage(&mut self, stage_id: StageID, slot: usize) {
+        let Some(pos) = self.dense_stages[slot].remove(&stage_id) else {
+            return;
+        };
+
+        let pos = pos as usize;
+        let stage = stage_id as usize;
+        self.stages[stage].swap_remove(pos);
+        self.stage_ids[stage].swap_remove(pos);
+
+        // Update the back pointer of the entity that took its place
+        if let Some(&moved_id) = self.stage_ids[stage].get(pos) {
+            let moved_slot = self.slot_of(moved_id).unwrap();
+            self.dense_stages[moved_slot].insert(stage_id, pos as SlotIndex);
+        }
+    }
+
+    fn remove_from_all_stages(&mut self, slot: usize) {
+        let stages: Vec<StageID> = self.dense_stages[slot].keys().copied().collect();
+        for stage_id in stages {
+            self.remove_from_stage(stage_id, slot);
+        }
+    }
+}
+
+impl EntityIndex {
+    /// Create a new empty index
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add an entity to the index. Returns `false` if there was already an entity with the
+    /// index of this id, leaving the index unchanged
+    pub(super) fn insert(&self, id: EntityID, entity_ptr: EntityPtr) -> bool {
+        let mut inner = self.inner.write();
+        if inner.slot_of_index(id).is_some() {
+            return false;
+        }
+
+        let slot = inner.dense.len();
+        assert!(slot < INVALID_SLOT as usize, "Too many entities in world");
+        inner.dense.push(entity_ptr);
+        inner.dense_ids.push(id);
+        inner.dense_stages.push(VecMap::new());
+        inner.set_slot(id, slot as SlotIndex);
+        true
+    }
+
+    /// Remove an entity from the index and from all the stage lists it's in
+    pub(super) fn remove(&self, id: &EntityID) -> Option<EntityPtr> {
+        let mut inner = self.inner.write();
+        let slot = inner.slot_of(*id)?;
+
+        inner.remove_from_all_stages(slot);
+
+        let entity_ptr = inner.dense.swap_remove(slot);
+        inner.dense_ids.swap_remove(slot);
+        inner.dense_stages.swap_remove(slot);
+        inner.set_slot(*id, INVALID_SLOT);
+
+        // Update the slot of the entity that took its place
+        if let Some(&moved_id) = inner.dense_ids.get(slot) {
+            inner.set_slot(moved_id, slot as SlotIndex);
+        }
+
+        Some(entity_ptr)
+    }
+
+    #[inline]
+    pub fn get(&self, id: &EntityID) -> Option<EntityPtr> {
+        let inner = self.inner.read_recursive();
+        inner.slot_of(*id).map(|slot| inner.dense[slot])
+    }
+
+    #[inline]
+    pub fn contains_key(&self, id: &EntityID) -> bool {
+        self.inner.read_recursive().slot_of(*id).is_some()
+    }
+
+    /// Whether there is an entity with the index of `id`, whatever its generation
+    #[inline]
+    pub fn contains_index(&self, id: &EntityID) -> bool {
+        self.inner.read_recursive().slot_of_index(*id).is_some()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.read_recursive().dense.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Schedule entity `id` to run in a stage. Does nothing if it's already in the stage list
+    pub(super) fn add_to_stage(&self, stage_id: StageID, id: EntityID) {
+        let mut inner = self.inner.write();
+        let slot = inner
+            .slot_of(id)
+            .expect("Entity should be in the index before running in a stage");
+        if inner.dense_stages[slot].contains_key(&stage_id) {
+            return;
+        }
+
+        let entity_ptr = inner.dense[slot];
+        let stage = stage_id as usize;
+        let pos = inner.stages[stage].len() as SlotIndex;
+        inner.stages[stage].push(entity_ptr);
+        inner.stage_ids[stage].push(id);
+        inner.dense_stages[slot].insert(stage_id, pos);
+    }
+
+    /// Stop running entity `id` in a stage. Does nothing if it's not in the stage list
+    pub(super) fn remove_from_stage(&self, stage_id: StageID, id: EntityID) {
+        let mut inner = self.inner.write();
+        if let Some(slot) = inner.slot_of(id) {
+            inner.remove_from_stage(stage_id, slot);
+        }
+    }
+
+    /// Stop running entity `id` in any stage
+    pub(super) fn remove_from_all_stages(&self, id: EntityID) {
+        let mut inner = self.inner.write();
+        if let Some(slot) = inner.slot_of(id) {
+            inner.remove_from_all_stages(slot);
+        }
+    }
+
+    /// All the entities in the index, in no particular order
+    pub fn read_all(&self) -> MappedRwLockReadGuard<'_, [EntityPtr]> {
+        RwLockReadGuard::map(self.inner.read(), |inner| inner.dense.as_slice())
+    }
+
+    /// The entities to run in a stage, in no particular order
+    pub fn read_stage(&self, stage_id: StageID) -> MappedRwLockReadGuard<'_, [EntityPtr]> {
+        RwLockReadGuard::map(self.inner.read(), |inner| {
+            inner.stages[stage_id as usize].as_slice()
+        })
+    }
+}
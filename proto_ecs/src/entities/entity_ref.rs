@@ -0,0 +1,166 @@
+/// Weak references to entities that are safe to store in user datagroups.
+///
+/// An [EntityRef] remembers both the id of the referenced entity and the generation
+/// of its memory slot, so it will stop resolving once that entity is destroyed,
+/// even if its memory is reused by another entity.
+///
+/// Entity ids are unique across worlds, so references stay valid when worlds are merged.
+/// To keep references valid when entities change ids (like when loading a snapshot),
+/// list the datagroup fields holding references in `register_datagroup!`:
+/// ```ignore
+/// register_datagroup!(Owner, factory, init_style = NoArg, entity_refs = (owned, target));
+/// ```
+/// Then `World::remap_entity_refs` will update those fields.
+use nohash_hasher::IntMap;
+
+use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
+
+use super::entity::{Entity, EntityID, INVALID_ENTITY_ID};
+use super::entity_allocator::EntityPtr;
+
+/// Maps old entity ids to references to the entities that replace them.
+/// Used to fix [EntityRef]s after entities get new ids
+pub type EntityRemap = IntMap<EntityID, EntityRef>;
+
+/// A generation checked reference to an entity.
+///
+/// It does not keep the entity alive, use `get()` to check if the
+/// referenced entity still exists.
+#[derive(Clone, Copy, PartialEq)]
+pub struct EntityRef {
+    id: EntityID,
+    ptr: Option<EntityPtr>,
+}
+
+/// Implemented by types that contain [EntityRef]s, so they can be remapped
+pub trait VisitEntityRefs {
+    fn visit_entity_refs(&mut self, visitor: &mut dyn FnMut(&mut EntityRef));
+}
+
+// -- < Implementations > --------------------------------
+
+impl EntityRef {
+    /// A reference that doesn't point to any entity
+    pub const NULL: EntityRef = EntityRef {
+        id: INVALID_ENTITY_ID,
+        ptr: None,
+    };
+
+    /// Create a reference to the entity pointed by `ptr`.
+    ///
+    /// Don't call this while holding a write lock on the same entity,
+    /// use `from_parts` instead
+    pub fn new(ptr: EntityPtr) -> Self {
+        let id = ptr
+            .try_read()
+            .expect("Trying to reference a dead entity")
+            .get_id();
+        EntityRef { id, ptr: Some(ptr) }
+    }
+
+    /// Create a reference from an entity id and a pointer to the same entity
+    pub fn from_parts(id: EntityID, ptr: EntityPtr) -> Self {
+        EntityRef { id, ptr: Some(ptr) }
+    }
+
+    /// Id of the referenced entity. Note that the id is returned even
+    /// if the entity is no longer live
+    #[inline(always)]
+    pub fn get_id(&self) -> EntityID {
+        self.id
+    }
+
+    /// If this reference points to nothing
+    #[inline(always)]
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_none()
+    }
+
+    /// If the referenced entity still exists
+    #[inline(always)]
+    pub fn is_live(&self) -> bool {
+        self.ptr.is_some_and(|ptr| ptr.is_live())
+    }
+
+    /// Get a pointer to the referenced entity, or `None` if it was destroyed
+    #[inline(always)]
+    pub fn get(&self) -> Option<EntityPtr> {
+        self.ptr.filter(|ptr| ptr.is_live())
+    }
+
+    /// Lock the referenced entity for reading, or `None` if it was destroyed
+    #[inline]
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, Entity>> {
+        self.ptr.as_ref()?.try_read()
+    }
+
+    /// Lock the referenced entity for writing, or `None` if it was destroyed
+    #[inline]
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, Entity>> {
+        self.ptr.as_ref()?.try_write()
+    }
+
+    /// Make this reference point to nothing
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        *self = EntityRef::NULL;
+    }
+
+    /// Point to the entity that replaces the currently referenced one in `remap`.
+    /// References to entities that are not in `remap` are left untouched
+    pub fn remap(&mut self, remap: &EntityRemap) {
+        if self.is_null() {
+            return;
+        }
+
+        if let Some(new_ref) = remap.get(&self.id) {
+            *self = *new_ref;
+        }
+    }
+}
+
+impl Default for EntityRef {
+    fn default() -> Self {
+        EntityRef::NULL
+    }
+}
+
+impl std::fmt::Debug for EntityRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't deref the pointer, the entity might be dead or locked
+        f.debug_struct("EntityRef")
+            .field("id", &self.id)
+            .field("is_live", &self.is_live())
+            .finish()
+    }
+}
+
+impl VisitEntityRefs for EntityRef {
+    fn visit_entity_refs(&mut self, visitor: &mut dyn FnMut(&mut EntityRef)) {
+        visitor(self)
+    }
+}
+
+impl<T: VisitEntityRefs> VisitEntityRefs for Option<T> {
+    fn visit_entity_refs(&mut self, visitor: &mut dyn FnMut(&mut EntityRef)) {
+        if let Some(value) = self {
+            value.visit_entity_refs(visitor);
+        }
+    }
+}
+
+impl<T: VisitEntityRefs> VisitEntityRefs for Vec<T> {
+    fn visit_entity_refs(&mut self, visitor: &mut dyn FnMut(&mut EntityRef)) {
+        for value in self.iter_mut() {
+            value.visit_entity_refs(visitor);
+        }
+    }
+}
+
+impl<T: VisitEntityRefs, const N: usize> VisitEntityRefs for [T; N] {
+    fn visit_entity_refs(&mut self, visitor: &mut dyn FnMut(&mut EntityRef)) {
+        for value in self.iter_mut() {
+            value.visit_entity_refs(visitor);
+        }
+    }
+}
@@ -183,91 +183,226 @@ impl EntitySpawnDescription {
         self.get_global_system_by_id(get_id!(S))
     }
 
-    /// Checks if the datagroups of this entity make sense, else panic
-    pub fn check_datagroups_panic(&self) {
-        assert!(
-            self.get_datagroups().len() <= MAX_DATAGROUP_LEN as usize,
-            "More datagroups than what the indexing type can support: {} (limit {})",
-            self.get_datagroups().len(),
-            MAX_DATAGROUP_LEN
-        );
+    /// Check every problem that would prevent this entity from spawning, like missing
+    /// dependencies, wrong init params, or ids that were never registered
+    pub fn validate(&self) -> Result<(), SpawnError> {
+        let mut problems = Vec::new();
+        self.validate_datagroups(&mut problems);
+        self.validate_local_systems(&mut problems);
+        self.validate_global_systems(&mut problems);
+        SpawnError::from_problems(&self.name, problems)
+    }
 
-        let registry = DataGroupRegistry::get_global_registry().read();
+    fn validate_datagroups(&self, problems: &mut Vec<SpawnProblem>) {
+        if self.get_datagroups().len() > MAX_DATAGROUP_LEN as usize {
+            problems.push(SpawnProblem::TooManyDataGroups {
+                count: self.get_datagroups().len(),
+                limit: MAX_DATAGROUP_LEN as usize,
+            });
+        }
 
-        self.get_datagroups().iter().for_each(|(id, init_param)| {
-            let entry = registry.get_entry_by_id(*id);
+        let registry = DataGroupRegistry::get_global_registry().read();
 
-            helpers::check_init_params_panic(init_param, entry)
-        });
+        // Sort ids so problems are always reported in the same order
+        let mut ids: Vec<DataGroupID> = self.get_datagroups().keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            let init_param = &self.get_datagroups()[&id];
+            match registry.try_get_entry_by_id(id) {
+                Some(entry) => {
+                    if let Err(problem) = helpers::check_init_params(init_param, entry) {
+                        problems.push(problem);
+                    }
+                }
+                None => problems.push(SpawnProblem::UnknownDataGroup(id)),
+            }
+        }
     }
 
-    /// Checks if the local systems of this entity have their dependencies met
-    pub fn check_local_systems_panic(&self) {
+    fn validate_local_systems(&self, problems: &mut Vec<SpawnProblem>) {
         let registry = LocalSystemRegistry::get_global_registry().read();
+        let dg_registry = DataGroupRegistry::get_global_registry().read();
 
-        self.get_local_systems().iter().for_each(|id| {
-            let entry = registry.get_entry_by_id(*id);
+        let mut ids: Vec<SystemClassID> = self.get_local_systems().iter().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            let Some(entry) = registry.try_get_entry_by_id(id) else {
+                problems.push(SpawnProblem::UnknownLocalSystem(id));
+                continue;
+            };
 
-            entry.dependencies.iter().for_each(|dep| {
+            for dep in &entry.dependencies {
                 let dg_id = match dep {
                     Dependency::DataGroup(id) => id,
-                    Dependency::OptionalDG(_) => return,
+                    Dependency::OptionalDG(_) => continue,
                 };
 
-                if self.get_datagroups().contains_key(dg_id) {
-                    return;
+                if !self.get_datagroups().contains_key(dg_id) {
+                    problems.push(SpawnProblem::MissingLocalSystemDependency {
+                        local_system: entry.name,
+                        datagroup: dg_registry.get_entry_by_id(*dg_id).name,
+                    });
                 }
-
-                let dg_registry = DataGroupRegistry::get_global_registry().read();
-
-                panic!(
-                    "Local System '{}' is missing dependency Datagroup '{}'",
-                    entry.name,
-                    dg_registry.get_entry_by_id(*dg_id).name
-                );
-            });
-        });
+            }
+        }
     }
 
     // Checks if the datagroups required by the global systems requested
     // by this entity are present
-    fn check_global_systems_panic(&self) {
-        let global_system_registry = GlobalSystemRegistry::get_global_registry().read();
-        for &global_system in &self.global_systems {
-            let gs_entry = global_system_registry.get_entry_by_id(global_system);
-            for &datagroup in &gs_entry.dependencies {
-                let dg_id = match datagroup {
+    fn validate_global_systems(&self, problems: &mut Vec<SpawnProblem>) {
+        let registry = GlobalSystemRegistry::get_global_registry().read();
+        let dg_registry = DataGroupRegistry::get_global_registry().read();
+
+        let mut ids: Vec<GlobalSystemID> = self.get_global_systems().iter().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            let Some(entry) = registry.try_get_entry_by_id(id) else {
+                problems.push(SpawnProblem::UnknownGlobalSystem(id));
+                continue;
+            };
+
+            for dep in &entry.dependencies {
+                let dg_id = match dep {
                     Dependency::DataGroup(dg_id) => dg_id,
-                    Dependency::OptionalDG(_) => {
-                        continue;
-                    } // nothing to check if they're optional
+                    Dependency::OptionalDG(_) => continue, // nothing to check if they're optional
                 };
 
-                if self.get_datagroups().contains_key(&dg_id) {
-                    // Everything ok, this entity has the required datagroup
-                    continue;
+                if !self.get_datagroups().contains_key(dg_id) {
+                    problems.push(SpawnProblem::MissingGlobalSystemDependency {
+                        global_system: entry.name,
+                        datagroup: dg_registry.get_entry_by_id(*dg_id).name,
+                    });
                 }
-
-                let dg_name = DataGroupRegistry::get_global_registry()
-                    .read()
-                    .get_entry_by_id(dg_id)
-                    .name;
-                let gs_name = gs_entry.name;
-                panic!(
-                    "Entity doesn't have the datagroup '{dg_name}' required by the global system '{gs_name}', which is requested by the entity"
-                );
             }
         }
     }
 
+    /// Checks if the datagroups of this entity make sense, else panic
+    pub fn check_datagroups_panic(&self) {
+        let mut problems = Vec::new();
+        self.validate_datagroups(&mut problems);
+        SpawnError::from_problems(&self.name, problems).unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Checks if the local systems of this entity have their dependencies met
+    pub fn check_local_systems_panic(&self) {
+        let mut problems = Vec::new();
+        self.validate_local_systems(&mut problems);
+        SpawnError::from_problems(&self.name, problems).unwrap_or_else(|err| panic!("{err}"));
+    }
+
     /// Check if the entity to be spawned makes sense, else panic
     pub fn check_panic(&self) {
-        self.check_datagroups_panic();
-        self.check_local_systems_panic();
-        self.check_global_systems_panic();
+        self.validate().unwrap_or_else(|err| panic!("{err}"));
     }
 }
 
+/// Something that prevents an entity from spawning
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpawnProblem {
+    /// More datagroups than what the datagroup indexing type can support
+    TooManyDataGroups { count: usize, limit: usize },
+    /// No datagroup is registered with this id
+    UnknownDataGroup(DataGroupID),
+    /// No local system is registered with this id
+    UnknownLocalSystem(SystemClassID),
+    /// No global system is registered with this id
+    UnknownGlobalSystem(GlobalSystemID),
+    /// A datagroup added by a system's `simple_prepare` was never given its init arg
+    UninitializedDataGroup {
+        datagroup: &'static str,
+        message: &'static str,
+    },
+    /// The init param doesn't match the init style of the datagroup
+    WrongInitStyle {
+        datagroup: &'static str,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// A local system requires a datagroup the entity doesn't have
+    MissingLocalSystemDependency {
+        local_system: &'static str,
+        datagroup: &'static str,
+    },
+    /// A global system requires a datagroup the entity doesn't have
+    MissingGlobalSystemDependency {
+        global_system: &'static str,
+        datagroup: &'static str,
+    },
+}
+
+/// Every problem found in a spawn description, see [EntitySpawnDescription::validate]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnError {
+    pub entity_name: String,
+    pub problems: Vec<SpawnProblem>,
+}
+
+impl SpawnError {
+    fn from_problems(entity_name: &str, problems: Vec<SpawnProblem>) -> Result<(), SpawnError> {
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        Err(SpawnError {
+            entity_name: entity_name.to_owned(),
+            problems,
+        })
+    }
+}
+
+impl std::fmt::Display for SpawnProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpawnProblem::TooManyDataGroups { count, limit } => write!(
+                f,
+                "More datagroups than what the indexing type can support: {count} (limit {limit})"
+            ),
+            SpawnProblem::UnknownDataGroup(id) => write!(f, "Unknown datagroup id {id}"),
+            SpawnProblem::UnknownLocalSystem(id) => write!(f, "Unknown local system id {id}"),
+            SpawnProblem::UnknownGlobalSystem(id) => write!(f, "Unknown global system id {id}"),
+            SpawnProblem::UninitializedDataGroup { datagroup, message } => write!(
+                f,
+                "Found Uninitialized init param for DataGroup '{datagroup}' params: {message}"
+            ),
+            SpawnProblem::WrongInitStyle {
+                datagroup,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Datagroup '{datagroup}' expects a {expected} param, but found: {found}"
+            ),
+            SpawnProblem::MissingLocalSystemDependency {
+                local_system,
+                datagroup,
+            } => write!(
+                f,
+                "Local System '{local_system}' is missing dependency Datagroup '{datagroup}'"
+            ),
+            SpawnProblem::MissingGlobalSystemDependency {
+                global_system,
+                datagroup,
+            } => write!(
+                f,
+                "Entity doesn't have the datagroup '{datagroup}' required by the global system '{global_system}', which is requested by the entity"
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Can't spawn entity '{}':", self.entity_name)?;
+        for problem in &self.problems {
+            write!(f, "\n - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SpawnError {}
+
 /// Helpers to handle common uses cases for entity spawn descriptions
 pub mod helpers {
     use crate::{
@@ -279,7 +414,7 @@ pub mod helpers {
         get_id,
     };
 
-    use super::EntitySpawnDescription;
+    use super::{EntitySpawnDescription, SpawnProblem};
 
     /// Add an uninitialized datagroup dependency to the spawn description
     pub fn local_system_try_add_datagroup<D>(
@@ -301,36 +436,50 @@ pub mod helpers {
             .or_insert_with(|| default_init);
     }
 
-    /// Checks if the init params of a DataGroup matches what it expects them to be. If they are not correct, it panics
-    pub fn check_init_params_panic(init_param: &DataGroupInitType, entry: &DataGroupRegistryEntry) {
-        if let DataGroupInitType::Uninitialized(msg) = init_param {
-            panic!(
-                "Found Uninitialized init param for DataGroup '{}' params: {msg}",
-                entry.name
-            );
+    /// Name of the variant of an init param
+    pub fn init_type_name(init_param: &DataGroupInitType) -> &'static str {
+        match init_param {
+            DataGroupInitType::Uninitialized(_) => "Uninitialized",
+            DataGroupInitType::NoInit => "NoInit",
+            DataGroupInitType::NoArg => "NoArg",
+            DataGroupInitType::Arg(_) => "Arg",
+            DataGroupInitType::OptionalArg(_) => "OptionalArg",
         }
+    }
 
-        match entry.init_desc {
-            InitDesc::NoInit => assert!(
-                matches!(init_param, DataGroupInitType::NoInit),
-                "Datagroup '{}' expects a NoInit param, but found: {init_param:?}",
-                entry.name
-            ),
-            InitDesc::NoArg => assert!(
-                matches!(init_param, DataGroupInitType::NoArg),
-                "Datagroup '{}' expects a NoArg param, but found: {init_param:?}",
-                entry.name
-            ),
-            InitDesc::Arg => assert!(
-                matches!(init_param, DataGroupInitType::Arg(_)),
-                "Datagroup '{}' expects a Arg param, but found: {init_param:?}",
-                entry.name
-            ),
-            InitDesc::OptionalArg => assert!(
-                matches!(init_param, DataGroupInitType::OptionalArg(_)),
-                "Datagroup '{}' expects a OptionalArg param, but found: {init_param:?}",
-                entry.name
-            ),
+    /// Checks if the init params of a DataGroup matches what it expects them to be
+    pub fn check_init_params(
+        init_param: &DataGroupInitType,
+        entry: &DataGroupRegistryEntry,
+    ) -> Result<(), SpawnProblem> {
+        if let DataGroupInitType::Uninitialized(message) = init_param {
+            return Err(SpawnProblem::UninitializedDataGroup {
+                datagroup: entry.name,
+                message,
+            });
+        }
+
+        let expected = match entry.init_desc {
+            InitDesc::NoInit => "NoInit",
+            InitDesc::NoArg => "NoArg",
+            InitDesc::Arg => "Arg",
+            InitDesc::OptionalArg => "OptionalArg",
+        };
+        let found = init_type_name(init_param);
+        if expected != found {
+            return Err(SpawnProblem::WrongInitStyle {
+                datagroup: entry.name,
+                expected,
+                found,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks if the init params of a DataGroup matches what it expects them to be. If they are not correct, it panics
+    pub fn check_init_params_panic(init_param: &DataGroupInitType, entry: &DataGroupRegistryEntry) {
+        if let Err(problem) = check_init_params(init_param, entry) {
+            panic!("{problem}");
         }
     }
 }
@@ -7,11 +7,23 @@ use lazy_static::lazy_static;
 use atomic_float::AtomicF64;
 
 use crate::core::ids::IDLocator;
+use crate::core::logging;
+use crate::core::profiling::{LocalSystemTimings, ProfileScope, ENTITY_SYSTEM_CATEGORY};
+use crate::data_group::{DataGroup, DataGroupID};
+use crate::entities::archetype::{ArchetypeStorage, StorageMode};
+use crate::entities::change_detection::{
+    advance_change_tick, current_change_tick, ChangeTick, DataGroupTicks, RemovedDataGroup,
+    RemovedDataGroups,
+};
 use crate::entities::entity::{EntityID, INVALID_ENTITY_ID};
+use crate::entities::entity_id_allocator::EntityIDAllocator;
+use crate::entities::entity_index::EntityIndex;
+use crate::entities::entity_ref::{EntityRef, EntityRemap};
+use crate::entities::relationships::{Relation, Relationships};
 use crate::get_id;
 use crate::systems::engine::rendering::CameraDG;
 
-use super::entity_spawn_desc::EntitySpawnDescription;
+use super::entity_spawn_desc::{EntitySpawnDescription, SpawnError};
 use crate::core::locking::RwLock;
 use crate::entities::entity_allocator::EntityAllocator;
 use crate::systems::common::{StageID, STAGE_COUNT};
@@ -19,26 +31,22 @@ use crate::systems::global_systems::{
     GSLifetime, GlobalSystem, GlobalSystemDesc, GlobalSystemID, GlobalSystemRegistry,
 };
 
+use nohash_hasher::IntSet;
 use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+use tracing::{debug, warn};
 
 pub use crate::entities::entity_allocator::EntityPtr;
 
-/// We just go up. If we ever run out of them we can think of blocks of IDs per thread and a better allocation system
-static ENTITY_COUNT: std::sync::atomic::AtomicU64 =
-    std::sync::atomic::AtomicU64::new(INVALID_ENTITY_ID + 1);
-
 /// Allocate a new Entity ID
+#[inline(always)]
 pub fn allocate_entity_id() -> EntityID {
-    // Note: if we ever need to do something more complex with IDs we can do it here
-
-    ENTITY_COUNT.fetch_add(1, Ordering::AcqRel)
+    EntityIDAllocator::get_global().allocate()
 }
 
 /// Deallocate an Entity ID
+#[inline(always)]
 pub fn deallocate_entity_id(id: EntityID) {
-    assert!(id < ENTITY_COUNT.load(Ordering::Acquire));
-
-    // Note: if we ever need to do something more complex with IDs we can do it here
+    EntityIDAllocator::get_global().deallocate(id);
 }
 
 /// Entity Creation Queue type used by worlds
@@ -51,7 +59,7 @@ pub type EntityDeletionQueue = scc::Queue<EntityID>;
 pub type GlobalSystemQueue = scc::Queue<GlobalSystemID>;
 
 /// Entity Map Type that holds all the entities in a World
-pub type EntityMap = dashmap::DashMap<EntityID, EntityPtr>;
+pub type EntityMap = EntityIndex;
 
 /// Global System Storage inside world
 pub type GlobalSystemStorage = RwLock<Box<dyn GlobalSystem>>;
@@ -60,7 +68,7 @@ pub type GlobalSystemStorage = RwLock<Box<dyn GlobalSystem>>;
 /// it means that it's not loaded
 pub type GlobalSystemMap = RwLock<Vec<Option<GlobalSystemStorage>>>;
 
-/// Vector with all the entities inside a world or inside a stage in a world (for faster iteration).
+/// Vector with entities inside a world (for faster iteration), like the ones required by a global system.
 /// Do not use at the same time as the entity map
 pub type EntitiesVec = RwLock<Vec<EntityPtr>>;
 
@@ -81,25 +89,56 @@ pub type GSEntitiesMap = RwLock<Vec<EntitiesVec>>;
 /// Queue for reparenting operations that'll be executed in a World
 type ReparentingQueue = scc::Queue<ReparentingOps>;
 
+/// Queue for entity duplications that'll be executed in a World
+type DuplicationQueue = scc::Queue<DuplicationOp>;
+
+/// A requested entity duplication
+#[derive(Debug, Clone, Copy)]
+struct DuplicationOp {
+    source: EntityID,
+    new_id: EntityID,
+    recursive: bool,
+}
+
 /// Possible re-parenting operations
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum ReparentingOps {
     SetParent { child: EntityID, parent: EntityID },
     ClearParent(EntityID),
 }
 
+/// Pop every operation in `queue`, pushing back the ones `keep` returns `true` for, in the same order
+fn retain_queue<T: Copy + 'static>(queue: &scc::Queue<T>, mut keep: impl FnMut(T) -> bool) {
+    let mut kept = Vec::new();
+    while let Some(op) = queue.pop() {
+        let op = **op;
+        if keep(op) {
+            kept.push(op);
+        }
+    }
+
+    for op in kept {
+        queue.push(op);
+    }
+}
+
 #[derive(Debug)]
 pub struct World {
     id: WorldID,
     delta_time: DeltaTimeAtomicType,
     fixed_delta_time: DeltaTimeAtomicType,
     delta_time_scaling: DeltaTimeAtomicType,
+    /// Paused worlds skip their steps, unless a step was requested
+    paused: AtomicBool,
+    requested_steps: AtomicUsize,
+    /// If the current step is skipped, decided when the step starts
+    skipping_step: AtomicBool,
+    /// All the entities in this world, and the ones to run per stage
     entities: EntityMap,
-    entities_all: EntitiesVec,
-    entities_stages: [EntitiesVec; STAGE_COUNT],
     creation_queue: EntityCreationQueue,
     deletion_queue: EntityDeletionQueue,
     reparenting_queue: ReparentingQueue,
+    duplication_queue: DuplicationQueue,
 
     global_system_stages: [GlobalSystemIDVec; STAGE_COUNT],
     global_systems: GlobalSystemMap,
@@ -109,6 +148,20 @@ pub struct World {
     /// entities to run per stage per global system
     gs_entity_map: GSEntitiesMap,
 
+    /// Typed relations between entities in this world
+    relationships: Relationships,
+
+    /// Datagroups of the entities destroyed in the last steps
+    removed_datagroups: RemovedDataGroups,
+
+    /// Time spent by each local system in the current stage, only measured while profiling
+    local_system_timings: LocalSystemTimings,
+
+    /// How datagroups are stored in this world
+    storage_mode: StorageMode,
+    /// Datagroups of the entities stored in archetypes
+    archetypes: RwLock<ArchetypeStorage>,
+
     /// Current camera used to render scene
     /// TODO update this variable when the camera entity changes
     current_camera: RwLock<Option<EntityID>>,
@@ -119,7 +172,13 @@ impl World {
     /// Maybe this should be variable based on load
     pub const PAR_CHUNKS_NUM: usize = 20;
 
+    /// World with boxed storage, only used by tests
+    #[cfg(test)]
     pub(crate) fn new(id: WorldID) -> Self {
+        World::with_storage(id, StorageMode::Boxed)
+    }
+
+    pub(crate) fn with_storage(id: WorldID, storage_mode: StorageMode) -> Self {
         let gs_count = GlobalSystemRegistry::get_global_registry()
             .read()
             .get_global_system_count();
@@ -138,18 +197,25 @@ impl World {
             delta_time: Default::default(),
             fixed_delta_time: Default::default(),
             delta_time_scaling: AtomicF64::from(1.0),
+            paused: AtomicBool::new(false),
+            requested_steps: AtomicUsize::ZERO,
+            skipping_step: AtomicBool::new(false),
             entities: Default::default(),
-            entities_all: Default::default(),
-            entities_stages: core::array::from_fn(|_| Default::default()),
             creation_queue: Default::default(),
             deletion_queue: Default::default(),
             reparenting_queue: Default::default(),
+            duplication_queue: Default::default(),
             global_systems: GlobalSystemMap::new(gs_map),
             global_systems_count: gs_count_array,
             global_system_stages: core::array::from_fn(|_| Default::default()),
             gs_creation_queue: Default::default(),
             gs_deletion_queue: Default::default(),
             gs_entity_map: RwLock::new(gs_entity_map),
+            relationships: Default::default(),
+            removed_datagroups: Default::default(),
+            local_system_timings: LocalSystemTimings::new(),
+            storage_mode,
+            archetypes: Default::default(),
             current_camera: RwLock::new(None),
         };
 
@@ -169,6 +235,12 @@ impl World {
         self.id
     }
 
+    /// How datagroups are stored in this world
+    #[inline(always)]
+    pub fn get_storage_mode(&self) -> StorageMode {
+        self.storage_mode
+    }
+
     /// Current scaled delta time
     #[inline(always)]
     pub fn get_delta_time(&self) -> DeltaTimeType {
@@ -193,34 +265,89 @@ impl World {
         new_id
     }
 
+    /// Like `create_entity`, but the spawn description is always validated, and returned problems
+    /// instead of panicking. Use it for entities described by data, like prefabs or mods
+    pub fn try_create_entity(
+        &self,
+        spawn_desc: EntitySpawnDescription,
+    ) -> Result<EntityID, SpawnError> {
+        spawn_desc.validate()?;
+        let new_id = allocate_entity_id();
+        self.creation_queue
+            .push(RwLock::new(Some((new_id, spawn_desc))));
+        Ok(new_id)
+    }
+
+    /// Create a new entity with an id assigned somewhere else, like an id from a range
+    /// reserved with [EntityIDAllocator::reserve_range]. The entity will spawn at the end of
+    /// the current stage. Creation fails when processed if the id is already in use
+    pub fn create_entity_with_id(&self, id: EntityID, spawn_desc: EntitySpawnDescription) {
+        assert!(
+            id != INVALID_ENTITY_ID,
+            "Can't create an entity with the invalid id"
+        );
+        if cfg!(debug_assertions) {
+            spawn_desc.check_panic();
+        }
+        self.creation_queue
+            .push(RwLock::new(Some((id, spawn_desc))));
+    }
+
     /// Create a new entity based on its spawn description
     fn create_entity_internal(&self, id: EntityID, spawn_desc: EntitySpawnDescription) {
-        println!("Creating entity: {}", spawn_desc.name);
+        if self.entities.contains_index(&id) {
+            // Only possible with ids assigned by the user
+            warn!(
+                target: logging::ENTITY_SYSTEM,
+                world_id = self.id,
+                entity_id = id,
+                "Failed to create entity '{}': its id is already in use",
+                spawn_desc.name
+            );
+            return;
+        }
+
+        debug!(
+            target: logging::ENTITY_SYSTEM,
+            world_id = self.id,
+            entity_id = id,
+            "Creating entity: {}",
+            spawn_desc.name
+        );
         // Allocate entity from the global allocator
         let global_allocator = EntityAllocator::get_global();
-        let mut entity_ptr = global_allocator.write().allocate();
+        let mut entity_ptr = global_allocator.read().allocate();
         entity_ptr.init(id, spawn_desc);
 
-        let old = self.entities.insert(id, entity_ptr);
+        self.register_entity_internal(id, entity_ptr);
+    }
+
+    /// Add a freshly initialized entity to this world: its maps, stage lists and
+    /// the global systems it requires
+    fn register_entity_internal(&self, id: EntityID, entity_ptr: EntityPtr) {
+        let inserted = self.entities.insert(id, entity_ptr);
         assert!(
-            old.is_none(),
+            inserted,
             "Duplicated Entity ID, old entity {:?}",
-            old.unwrap()
+            self.entities.get(&id).unwrap()
         );
 
-        // Insert entity for iteration
-        {
-            let mut entities_all = self.entities_all.write();
-            entities_all.push(entity_ptr);
+        // Move its datagroups to an archetype, spatial entities have to run with their hierarchy
+        if self.storage_mode == StorageMode::Archetype {
+            let mut archetypes = self.archetypes.write();
+            let mut entity = entity_ptr.write();
+            if !entity.is_spatial_entity() {
+                entity.move_to_archetype(&mut archetypes);
+            }
         }
 
         let entity_ref = unsafe { &*(*entity_ptr).data_ptr() };
 
         // Schedule this entity to run in the right stage
-        for (stage_id, stage_vec) in self.entities_stages.iter().enumerate() {
+        for stage_id in 0..STAGE_COUNT {
             let stage_id = stage_id as StageID;
             if entity_ref.should_run_in_stage(stage_id) {
-                stage_vec.write().push(entity_ptr);
+                self.entities.add_to_stage(stage_id, id);
             }
         }
 
@@ -245,10 +372,13 @@ impl World {
                         You can fix this by ensuring that `{}` is loaded before creating an entity that requires it", entry.name);
             }
 
-            // Add this entity to the entity vector for each GS it requires
-            let mut entities_per_gs = self.gs_entity_map.write();
-            let gs_entities = &mut entities_per_gs[gs_id as usize];
-            gs_entities.write().push(entity_ptr);
+            // Add this entity to the entity vector for each GS it requires.
+            // Entities in archetypes are given to global systems straight from their chunks
+            if !entity_ref.is_in_archetype() {
+                let mut entities_per_gs = self.gs_entity_map.write();
+                let gs_entities = &mut entities_per_gs[gs_id as usize];
+                gs_entities.write().push(entity_ptr);
+            }
         }
     }
 
@@ -257,15 +387,191 @@ impl World {
         self.deletion_queue.push(id);
     }
 
-    /// Destroy an entity
-    pub fn destroy_entity_internal(&self, id: EntityID) {
-        // Before deleting an entity, we have to check if the entity
-        let prev = self.entities.remove(&id);
-        if prev.is_none() {
-            println!("Failed to destroy Entity {id}, maybe it was already deleted (?)");
+    /// Create a copy of entity `id`, including the state of its datagroups and its systems.
+    /// The copy gets the same parent as the original. If `recursive`, the spatial children
+    /// of the entity are duplicated as well and attached to the copy.
+    ///
+    /// Returns the id of the copy. Like creation, the copy will spawn at the end of the current stage.
+    /// Relations and the ids stored in [EntityRef]s are not changed.
+    ///
+    /// Fails if the entity doesn't exist yet, or if any datagroup of the entities to duplicate
+    /// is not cloneable
+    pub fn duplicate_entity(
+        &self,
+        id: EntityID,
+        recursive: bool,
+    ) -> Result<EntityID, DuplicationError> {
+        let source_ptr = self
+            .entities
+            .get(&id)
+            .ok_or(DuplicationError::EntityNotFound(id))?;
+        Self::check_duplication(source_ptr, recursive)?;
+
+        let new_id = allocate_entity_id();
+        self.duplication_queue.push(DuplicationOp {
+            source: id,
+            new_id,
+            recursive,
+        });
+        Ok(new_id)
+    }
+
+    /// Check that the entity at `entity_ptr` can be duplicated, and its children if `recursive`
+    fn check_duplication(entity_ptr: EntityPtr, recursive: bool) -> Result<(), DuplicationError> {
+        let mut pending = vec![entity_ptr];
+        while let Some(entity_ptr) = pending.pop() {
+            let entity = entity_ptr.read();
+            if let Some(datagroup) = entity.find_uncloneable_datagroup() {
+                return Err(DuplicationError::NotCloneable {
+                    entity_name: entity.get_name().to_owned(),
+                    datagroup,
+                });
+            }
+
+            if let (true, Some(transform)) = (recursive, entity.get_transform()) {
+                pending.extend(transform.children.iter().copied());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Duplicate entity `source` as `new_id`, see `duplicate_entity`
+    fn duplicate_entity_internal(&self, source: EntityID, new_id: EntityID, recursive: bool) {
+        let Some(source_ptr) = self.entities.get(&source) else {
+            warn!(
+                target: logging::ENTITY_SYSTEM,
+                world_id = self.id,
+                entity_id = source,
+                "Failed to duplicate entity: it doesn't exist in this world"
+            );
+            return;
+        };
+
+        // Checked when requested, but its children might have changed since then
+        if let Err(err) = Self::check_duplication(source_ptr, recursive) {
+            warn!(
+                target: logging::ENTITY_SYSTEM,
+                world_id = self.id,
+                entity_id = source,
+                "Failed to duplicate entity: {err}"
+            );
             return;
         }
-        let (_id, entity_ptr) = prev.unwrap();
+
+        let parent = self.duplicate_entity_single(source_ptr, new_id);
+        if let Some(parent_ptr) = parent {
+            let parent_id = parent_ptr.read().get_id();
+            self.set_entity_parent_internal(new_id, parent_id);
+        }
+
+        if recursive {
+            self.duplicate_children_internal(source_ptr, new_id);
+        }
+    }
+
+    /// Duplicate the children of `source_ptr` recursively, attaching the copies to `new_parent`
+    fn duplicate_children_internal(&self, source_ptr: EntityPtr, new_parent: EntityID) {
+        let children: Vec<EntityPtr> = match source_ptr.read().get_transform() {
+            Some(transform) => transform.children.clone(),
+            None => return,
+        };
+
+        for child_ptr in children {
+            let new_child_id = allocate_entity_id();
+            self.duplicate_entity_single(child_ptr, new_child_id);
+            self.set_entity_parent_internal(new_child_id, new_parent);
+            self.duplicate_children_internal(child_ptr, new_child_id);
+        }
+    }
+
+    /// Create and register a copy of a single entity. Returns the parent of the source entity, if any
+    fn duplicate_entity_single(
+        &self,
+        source_ptr: EntityPtr,
+        new_id: EntityID,
+    ) -> Option<EntityPtr> {
+        let global_allocator = EntityAllocator::get_global();
+        let mut entity_ptr = global_allocator.read().allocate();
+
+        let parent = {
+            let source = source_ptr.read();
+            debug!(
+                target: logging::ENTITY_SYSTEM,
+                world_id = self.id,
+                entity_id = new_id,
+                source_id = source.get_id(),
+                "Duplicating entity: {}",
+                source.get_name()
+            );
+            entity_ptr.init_duplicate(new_id, &source);
+            source
+                .get_transform()
+                .and_then(|transform| transform.parent)
+        };
+
+        self.register_entity_internal(new_id, entity_ptr);
+        parent
+    }
+
+    /// Get a weak reference to an entity in this world. The reference will be null
+    /// if the entity doesn't exist, or is not yet created.
+    pub fn get_entity_ref(&self, id: EntityID) -> EntityRef {
+        match self.entities.get(&id) {
+            Some(entity_ptr) => EntityRef::from_parts(id, entity_ptr),
+            None => EntityRef::NULL,
+        }
+    }
+
+    /// Get all the entities in this world, including the children in spatial hierarchies.
+    /// Note that this is only valid if no stage is being executed, or if called from a Local/Global System
+    pub fn get_all_entities(&self) -> Vec<EntityPtr> {
+        self.entities.read_all().to_vec()
+    }
+
+    /// Update every [EntityRef] stored in the datagroups of this world using `remap`.
+    /// Call this after entities get new ids, like when loading a snapshot
+    pub fn remap_entity_refs(&self, remap: &EntityRemap) {
+        self.entities.read_all().par_iter().for_each(|entity_ptr| {
+            let mut entity = entity_ptr.write();
+            for datagroup in entity.get_all_datagroups_mut() {
+                datagroup.__visit_entity_refs__(&mut |entity_ref| entity_ref.remap(remap));
+            }
+        });
+    }
+
+    /// Destroy an entity, its spatial children, and the entities related to it with a cascade policy
+    pub fn destroy_entity_internal(&self, id: EntityID) {
+        // Cascades are followed with a worklist instead of recursion, as chains of
+        // related entities can be arbitrarily long
+        let mut pending = self.destroy_entity_single(id);
+        while let Some(related_id) = pending.pop() {
+            // Some of them might have been destroyed already as part of the spatial hierarchy
+            if self.entities.contains_key(&related_id) {
+                pending.extend(self.destroy_entity_single(related_id));
+            }
+        }
+    }
+
+    /// Destroy an entity and its spatial children.
+    /// Returns the entities related to them with a cascade policy, which should be destroyed too
+    fn destroy_entity_single(&self, id: EntityID) -> Vec<EntityID> {
+        // Before deleting an entity, we have to check if the entity.
+        // Removing it from the entity map also removes it from the stage lists
+        let Some(entity_ptr) = self.entities.remove(&id) else {
+            warn!(
+                target: logging::ENTITY_SYSTEM,
+                world_id = self.id,
+                entity_id = id,
+                "Failed to destroy entity, maybe it was already deleted (?)"
+            );
+            return vec![];
+        };
+
+        // Clean up relations to this entity, collecting the entities that should die with it
+        let mut related_to_destroy = Vec::new();
+        self.relationships
+            .on_entity_destroyed(id, &mut related_to_destroy);
 
         // TODO I'm not sure this implementation is the best option for recursive deletion.
 
@@ -277,28 +583,12 @@ impl World {
             // ? Can this be a problem if we want entities to have a `on_delete` callback? do we want one?
 
             let mut entity = entity_ptr.write();
-            if entity.is_root() {
-                // Remove root from stage lists
-                for (stage_id, stage_vec) in self.entities_stages.iter().enumerate() {
-                    if entity.should_run_in_stage(stage_id as StageID) {
-                        World::remove_entity_from_stage_vec(stage_vec, &entity_ptr);
-                    }
-                }
-            } else {
+            if !entity.is_root() {
                 // Remove prev root from stage lists it no longer needs to be in
                 let prev_parent_ptr = unsafe { entity.get_transform_unsafe() }.parent.unwrap();
 
                 entity.clear_parent();
-
-                let prev_root = prev_parent_ptr.read().get_root();
-                let prev_root_entity = prev_root.read();
-                let prev_stages = &unsafe { prev_root_entity.get_transform_unsafe() }.stage_count;
-
-                for (stage_id, stage_vec) in self.entities_stages.iter().enumerate() {
-                    if prev_stages[stage_id].load(Ordering::Acquire) == 0 {
-                        World::remove_entity_from_stage_vec(stage_vec, &prev_parent_ptr);
-                    }
-                }
+                self.remove_from_finished_stages(prev_parent_ptr);
             }
 
             const RECURSIVE_DELETION_EXPECTED_STACK_LEN: usize = 100;
@@ -328,32 +618,42 @@ impl World {
                 ids_to_delete.push(entity.get_id());
             }
 
-            // delete all entities in the hierarchy. The order doesn't matter
-            ids_to_delete.into_par_iter().for_each(|id| {
-                self.destroy_entity_internal(id);
-            });
+            // delete all entities in the hierarchy. The order doesn't matter.
+            // Their transforms are gone, so this doesn't go deeper than one level
+            let related_to_hierarchy: Vec<EntityID> = ids_to_delete
+                .into_par_iter()
+                .flat_map_iter(|id| self.destroy_entity_single(id))
+                .collect();
+            related_to_destroy.extend(related_to_hierarchy);
         } else {
-            // Easy case, just remove from stage lists
-            for (stage_id, stage_vec) in self.entities_stages.iter().enumerate() {
-                let stage_id = stage_id as StageID;
-                if entity_ptr.read().should_run_in_stage(stage_id) {
-                    World::remove_entity_from_stage_vec(stage_vec, &entity_ptr);
-                }
+            // Easy case, it was already removed from the stage lists.
+            // Take its datagroups out of the archetype, so they are dropped with the entity
+            if self.storage_mode == StorageMode::Archetype {
+                let mut archetypes = self.archetypes.write();
+                entity_ptr.write().move_out_of_archetype(&mut archetypes);
             }
         }
 
-        // Destroy entity from iteration lists
-        {
-            let mut entities_all = self.entities_all.write();
-            for i in 0..entities_all.len() {
-                let vec_ref = entities_all[i];
-                if vec_ref == entity_ptr {
-                    entities_all.swap_remove(i);
-                    break;
-                }
-            }
-        }
+        self.removed_datagroups.push(
+            id,
+            entity_ptr
+                .read()
+                .get_all_datagroups()
+                .map(|datagroup| datagroup.get_id()),
+        );
+        self.unregister_entity_internal(entity_ptr);
 
+        deallocate_entity_id(id);
+        // Actually destroy entity
+        let global_allocator = EntityAllocator::get_global();
+        global_allocator.read().free(&entity_ptr);
+
+        related_to_destroy
+    }
+
+    /// Remove an entity from the global systems it requires.
+    /// Global systems with a `WhenRequired` lifetime are unloaded when no entity requires them anymore
+    fn unregister_entity_internal(&self, entity_ptr: EntityPtr) {
         // Decrease counters for global systems in this entity
         {
             let gs_counts = &self.global_systems_count;
@@ -388,11 +688,262 @@ impl World {
                 }
             }
         }
+    }
 
-        deallocate_entity_id(id);
-        // Actually destroy entity
-        let global_allocator = EntityAllocator::get_global();
-        global_allocator.write().free(&entity_ptr);
+    /// Remove entity `id` and its spatial subtree from this world without destroying them,
+    /// so they can be inserted in another world. The entity is detached from its parent,
+    /// and all the relations of the removed entities are cleared.
+    ///
+    /// Returns the removed entities, parents before their children,
+    /// or `None` if the entity is not in this world
+    pub(super) fn take_entity_internal(&self, id: EntityID) -> Option<Vec<EntityPtr>> {
+        let entity_ptr = self.entities.get(&id)?;
+
+        {
+            // Roots and non-spatial entities leave the stage lists when removed from the entity map
+            let mut entity = entity_ptr.write();
+            if entity.is_spatial_entity() && !entity.is_root() {
+                // Remove prev root from stage lists it no longer needs to be in
+                let prev_parent_ptr = unsafe { entity.get_transform_unsafe() }.parent.unwrap();
+
+                entity.clear_parent();
+                self.remove_from_finished_stages(prev_parent_ptr);
+            }
+        }
+
+        // Children are not in the stage lists, only their root is
+        let taken = Self::collect_hierarchy(entity_ptr);
+
+        let mut current_camera = self.current_camera.write();
+        for &taken_ptr in taken.iter() {
+            let taken_id = taken_ptr.read().get_id();
+            self.entities.remove(&taken_id);
+            self.relationships.forget_entity(taken_id);
+            self.unregister_entity_internal(taken_ptr);
+
+            if self.storage_mode == StorageMode::Archetype {
+                let mut archetypes = self.archetypes.write();
+                taken_ptr.write().move_out_of_archetype(&mut archetypes);
+            }
+
+            if *current_camera == Some(taken_id) {
+                *current_camera = None;
+            }
+        }
+
+        Some(taken)
+    }
+
+    /// The entity at `entity_ptr` and its spatial subtree, parents before their children
+    fn collect_hierarchy(entity_ptr: EntityPtr) -> Vec<EntityPtr> {
+        let mut hierarchy = vec![entity_ptr];
+        let mut next = 0;
+        while next < hierarchy.len() {
+            let children = hierarchy[next]
+                .read()
+                .get_transform()
+                .map(|transform| transform.children.clone());
+            hierarchy.extend(children.unwrap_or_default());
+            next += 1;
+        }
+
+        hierarchy
+    }
+
+    /// Check that entities from another world can be inserted in this one with `insert_entities_internal`:
+    /// their ids are free, and the global systems they require are loaded or can be loaded when required.
+    /// Logs the problem and returns `false` otherwise
+    fn can_insert_entities(&self, entities: &[EntityPtr]) -> bool {
+        let gs_registry = GlobalSystemRegistry::get_global_registry().read();
+        for entity_ptr in entities {
+            let entity = entity_ptr.read();
+            let id = entity.get_id();
+            if self.entities.contains_index(&id) {
+                warn!(
+                    target: logging::ENTITY_SYSTEM,
+                    world_id = self.id,
+                    entity_id = id,
+                    "Can't insert entity '{}': its id is already in use",
+                    entity.get_name()
+                );
+                return false;
+            }
+
+            for &gs_id in entity.get_global_systems() {
+                let entry = gs_registry.get_entry_by_id(gs_id);
+                if entry.lifetime != GSLifetime::WhenRequired
+                    && !self.global_system_is_loaded_by_id(gs_id)
+                {
+                    warn!(
+                        target: logging::ENTITY_SYSTEM,
+                        world_id = self.id,
+                        entity_id = id,
+                        "Can't insert entity '{}': it requires global system `{}`, which is not loaded",
+                        entity.get_name(),
+                        entry.name
+                    );
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Hand the commands queued in this world for the `moved` entities over to `target`.
+    /// Reparenting between a moved entity and one that stayed is dropped, they are in different worlds now
+    fn forward_entity_commands(&self, moved: &[EntityPtr], target: &World) {
+        let moved: IntSet<EntityID> = moved.iter().map(|ptr| ptr.read().get_id()).collect();
+
+        retain_queue(&self.deletion_queue, |id| {
+            if !moved.contains(&id) {
+                return true;
+            }
+            target.deletion_queue.push(id);
+            false
+        });
+
+        retain_queue(&self.duplication_queue, |op| {
+            if !moved.contains(&op.source) {
+                return true;
+            }
+            target.duplication_queue.push(op);
+            false
+        });
+
+        retain_queue(&self.reparenting_queue, |op| {
+            let (child, parent) = match op {
+                ReparentingOps::SetParent { child, parent } => (child, Some(parent)),
+                ReparentingOps::ClearParent(child) => (child, None),
+            };
+            let child_moved = moved.contains(&child);
+            let parent_moved = parent.map_or(child_moved, |parent| moved.contains(&parent));
+            if child_moved != parent_moved {
+                warn!(
+                    target: logging::ENTITY_SYSTEM,
+                    world_id = self.id,
+                    entity_id = child,
+                    "Dropping reparenting between an entity moved to another world and one that stayed"
+                );
+            } else if child_moved {
+                target.reparenting_queue.push(op);
+            } else {
+                return true;
+            }
+            false
+        });
+    }
+
+    /// Insert entities taken from another world with `take_entity_internal`.
+    /// Global systems with a `WhenRequired` lifetime are loaded if needed
+    pub(super) fn insert_entities_internal(&self, entities: &[EntityPtr]) {
+        // Parents go first, so only the root of the hierarchy ends up in the stage lists
+        for &entity_ptr in entities {
+            let id = entity_ptr.read().get_id();
+            self.register_entity_internal(id, entity_ptr);
+        }
+    }
+
+    /// Relate `source` to `target` with relation `R`. Unlike parenting, relations take effect immediately.
+    /// Returns whether the relation was added, or `false` if it already existed or one of
+    /// the entities is not in this world
+    pub fn add_relation<R: Relation>(&self, source: EntityID, target: EntityID) -> bool {
+        if !self.entities.contains_key(&source) || !self.entities.contains_key(&target) {
+            warn!(
+                target: logging::ENTITY_SYSTEM,
+                world_id = self.id,
+                entity_id = source,
+                "Can't relate entity {source} to entity {target}: they are not both in this world"
+            );
+            return false;
+        }
+
+        self.relationships.add::<R>(source, target)
+    }
+
+    /// Remove the relation `R` from `source` to `target`.
+    /// Returns whether the relation existed
+    pub fn remove_relation<R: Relation>(&self, source: EntityID, target: EntityID) -> bool {
+        self.relationships.remove::<R>(source, target)
+    }
+
+    /// If `source` is related to `target` with relation `R`
+    pub fn has_relation<R: Relation>(&self, source: EntityID, target: EntityID) -> bool {
+        self.relationships.contains::<R>(source, target)
+    }
+
+    /// All the entities that `source` is related to with relation `R`
+    pub fn related<R: Relation>(&self, source: EntityID) -> Vec<EntityID> {
+        self.relationships.related::<R>(source)
+    }
+
+    /// All the entities related to `target` with relation `R`
+    pub fn related_reverse<R: Relation>(&self, target: EntityID) -> Vec<EntityID> {
+        self.relationships.related_reverse::<R>(target)
+    }
+
+    /// Tick of the stage currently running, to compare datagroup changes against.
+    /// See [crate::entities::change_detection]
+    #[inline(always)]
+    pub fn get_change_tick(&self) -> ChangeTick {
+        current_change_tick()
+    }
+
+    /// Entities in this world whose `DG` datagroup changed at `tick` or later
+    pub fn query_changed_since<DG>(&self, tick: ChangeTick) -> Vec<EntityID>
+    where
+        DG: IDLocator + DataGroup + 'static,
+    {
+        self.query_ticks(get_id!(DG), |ticks| ticks.changed_since(tick))
+    }
+
+    /// Entities in this world whose `DG` datagroup was added at `tick` or later
+    pub fn query_added_since<DG>(&self, tick: ChangeTick) -> Vec<EntityID>
+    where
+        DG: IDLocator + DataGroup + 'static,
+    {
+        self.query_ticks(get_id!(DG), |ticks| ticks.added_since(tick))
+    }
+
+    /// Entities destroyed at `tick` or later that had a `DG` datagroup.
+    /// Removals are only kept until the end of the step after they happened
+    pub fn query_removed_since<DG>(&self, tick: ChangeTick) -> Vec<EntityID>
+    where
+        DG: IDLocator + DataGroup + 'static,
+    {
+        let dg_id = get_id!(DG);
+        self.removed_datagroups
+            .since(tick)
+            .into_iter()
+            .filter(|removed| removed.datagroup == dg_id)
+            .map(|removed| removed.entity)
+            .collect()
+    }
+
+    /// All the datagroups of entities destroyed at `tick` or later.
+    /// Removals are only kept until the end of the step after they happened
+    pub fn removed_since(&self, tick: ChangeTick) -> Vec<RemovedDataGroup> {
+        self.removed_datagroups.since(tick)
+    }
+
+    /// Entities with a datagroup `dg_id` whose ticks match `predicate`
+    fn query_ticks(
+        &self,
+        dg_id: DataGroupID,
+        predicate: impl Fn(&DataGroupTicks) -> bool,
+    ) -> Vec<EntityID> {
+        // Don't hold the index lock while locking entities
+        let entities = self.entities.read_all().to_vec();
+        entities
+            .iter()
+            .filter_map(|entity_ptr| {
+                let entity = entity_ptr.read();
+                entity
+                    .get_datagroup_ticks_by_id(dg_id)
+                    .filter(|ticks| predicate(ticks))
+                    .map(|_| entity.get_id())
+            })
+            .collect()
     }
 
     /// Request to make `parent_id` the parent of `entity_id`.
@@ -437,28 +988,16 @@ impl World {
 
             if child_entity.is_root() {
                 // Remove child from execution lists
-                for (stage_id, stage_vec) in self.entities_stages.iter().enumerate() {
-                    if child_entity.should_run_in_stage(stage_id as StageID) {
-                        World::remove_entity_from_stage_vec(stage_vec, &child_ptr);
-                    }
-                }
+                self.entities.remove_from_all_stages(entity_id);
             }
 
             let maybe_prev_parent_ptr = unsafe { child_entity.get_transform_unsafe() }.parent;
 
-            child_entity.set_parent(*parent_ptr);
+            child_entity.set_parent(parent_ptr);
 
             if let Some(prev_parent_ptr) = maybe_prev_parent_ptr {
                 // Remove prev root from execution lists it no longer needs to be in
-                let prev_root = prev_parent_ptr.read().get_root();
-                let prev_root_entity = prev_root.read();
-                let prev_stages = &unsafe { prev_root_entity.get_transform_unsafe() }.stage_count;
-
-                for (stage_id, stage_vec) in self.entities_stages.iter().enumerate() {
-                    if prev_stages[stage_id].load(Ordering::Acquire) == 0 {
-                        World::remove_entity_from_stage_vec(stage_vec, &prev_parent_ptr);
-                    }
-                }
+                self.remove_from_finished_stages(prev_parent_ptr);
             }
         }
 
@@ -467,10 +1006,10 @@ impl World {
         let root = parent_ptr.read().get_root();
 
         let root_entity = root.read();
-        for (stage_id, stage_vec) in self.entities_stages.iter().enumerate() {
-            if root_entity.should_run_in_stage(stage_id as StageID) && !old_stages_to_run[stage_id]
-            {
-                stage_vec.write().push(root);
+        for (stage_id, old_stage_to_run) in old_stages_to_run.iter().enumerate() {
+            let stage_id = stage_id as StageID;
+            if root_entity.should_run_in_stage(stage_id) && !old_stage_to_run {
+                self.entities.add_to_stage(stage_id, root_entity.get_id());
             }
         }
     }
@@ -498,18 +1037,13 @@ impl World {
         entity.clear_parent();
 
         // Remove prev root from execution lists it no longer needs to be in
-        let prev_root = prev_parent_ptr.read().get_root();
-        let prev_root_entity = prev_root.read();
-        let prev_stages = &unsafe { prev_root_entity.get_transform_unsafe() }.stage_count;
+        self.remove_from_finished_stages(prev_parent_ptr);
 
         // And add this entity to execution lists
-        for (stage_id, stage_vec) in self.entities_stages.iter().enumerate() {
-            if prev_stages[stage_id].load(Ordering::Acquire) == 0 {
-                World::remove_entity_from_stage_vec(stage_vec, &prev_parent_ptr);
-            }
-
-            if entity.should_run_in_stage(stage_id as StageID) {
-                stage_vec.write().push(*entity_ptr);
+        for stage_id in 0..STAGE_COUNT {
+            let stage_id = stage_id as StageID;
+            if entity.should_run_in_stage(stage_id) {
+                self.entities.add_to_stage(stage_id, entity_id);
             }
         }
     }
@@ -534,6 +1068,51 @@ impl World {
             .store(scaling_factor, Ordering::Release);
     }
 
+    /// Pause or resume this world. Paused worlds don't run their stages,
+    /// nor process their entity and global system commands, until resumed
+    /// or stepped with `World::request_step`
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Release);
+        if !paused {
+            self.requested_steps.store(0, Ordering::Release);
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    /// Run a single step of this world while paused. Ignored if the world is not paused
+    pub fn request_step(&self) {
+        if self.is_paused() {
+            self.requested_steps.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
+    /// Decide if the step about to start is skipped, consuming a requested step if paused
+    pub(super) fn begin_step_internal(&self) {
+        let skip = self.is_paused()
+            && self
+                .requested_steps
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |steps| {
+                    steps.checked_sub(1)
+                })
+                .is_err();
+        self.skipping_step.store(skip, Ordering::Release);
+    }
+
+    #[inline(always)]
+    pub(super) fn get_local_system_timings(&self) -> &LocalSystemTimings {
+        &self.local_system_timings
+    }
+
+    /// If this world doesn't run the current step because it's paused
+    #[inline(always)]
+    pub(super) fn is_skipping_step(&self) -> bool {
+        self.skipping_step.load(Ordering::Acquire)
+    }
+
     fn process_global_systems_commands(&self) {
         let mut changed = false;
         // Delete global systems scheduled for deletion
@@ -593,6 +1172,16 @@ impl World {
             });
         }
 
+        // Process duplications. Sequential, as recursive duplications reparent the copies
+        while let Some(op) = self.duplication_queue.pop() {
+            let DuplicationOp {
+                source,
+                new_id,
+                recursive,
+            } = **op;
+            self.duplicate_entity_internal(source, new_id, recursive);
+        }
+
         // Process re-parenting
         if !self.reparenting_queue.is_empty() {
             // No parallelism allowed here, reparenting operations
@@ -610,22 +1199,37 @@ impl World {
 
     /// Process a stage in this world
     fn run_stage(&self, stage_id: StageID) {
+        let mut stage_scope = ProfileScope::new("World::run_stage", ENTITY_SYSTEM_CATEGORY);
+        stage_scope.add_arg("world", self.id as f64);
+        stage_scope.add_arg("stage", stage_id as f64);
+
+        // Changes from now on are part of a new tick
+        let tick = advance_change_tick();
+        if stage_id == 0 {
+            self.removed_datagroups.on_step_start(tick);
+        }
+
         // Process all the entity and global systems commands before the stage
-        self.process_global_systems_commands();
-        self.process_entity_commands();
+        {
+            let _scope = ProfileScope::new("World commands", ENTITY_SYSTEM_CATEGORY);
+            self.process_global_systems_commands();
+            self.process_entity_commands();
+        }
 
         {
             // Run Stage in all entities
-            let entities_stage = self.entities_stages[stage_id as usize].read();
+            let entities_stage = self.entities.read_stage(stage_id);
+            let archetypes = self.archetypes.read();
             {
                 let gs_stage = self.global_system_stages[stage_id as usize].read();
-                if entities_stage.is_empty() && gs_stage.is_empty() {
+                if entities_stage.is_empty() && gs_stage.is_empty() && archetypes.is_empty() {
                     // Nothing to do, no more commands can be created
                     // TODO: Check this for Global Systems. They might need to execute?
                     return;
                 }
             }
 
+            let mut entities_scope = ProfileScope::new("Entities", ENTITY_SYSTEM_CATEGORY);
             entities_stage
                 .par_chunks(World::PAR_CHUNKS_NUM)
                 .for_each(|map_refs| {
@@ -644,6 +1248,14 @@ impl World {
                         }
                     }
                 });
+
+            // Run Stage in all entities stored in archetypes, chunk by chunk
+            archetypes.run_stage(self, stage_id);
+
+            // Local systems are too many to record one by one, report their totals instead
+            if entities_scope.is_recording() {
+                self.local_system_timings.drain_into(&mut entities_scope);
+            }
         }
 
         // Run all global systems
@@ -651,14 +1263,27 @@ impl World {
             let gs_stage = self.global_system_stages[stage_id as usize].read();
             let gs_registry = GlobalSystemRegistry::get_global_registry().read();
             let gs_storages = self.global_systems.read();
+            let mut chunk_entities = vec![];
             for &gs_id in gs_stage.iter() {
                 let entry = gs_registry.get_entry_by_id(gs_id);
+                let _scope = ProfileScope::new(entry.name, ENTITY_SYSTEM_CATEGORY);
                 let mut storage = gs_storages[gs_id as usize].as_ref().unwrap().write();
                 let current_fn = entry.functions[stage_id as usize]
                     .expect("This global system should have a function for the current stage");
 
-                let mut stage_entities = self.gs_entity_map.write();
-                let current_stage_entities = &mut stage_entities[gs_id as usize];
+                let stage_entities = self.gs_entity_map.read();
+                let boxed_entities = stage_entities[gs_id as usize].read();
+                let current_stage_entities = if self.storage_mode == StorageMode::Archetype {
+                    // Boxed entities go first, then the ones in archetypes chunk by chunk
+                    chunk_entities.clear();
+                    chunk_entities.extend_from_slice(&boxed_entities);
+                    self.archetypes
+                        .read()
+                        .extend_global_system_entities(gs_id, &mut chunk_entities);
+                    &chunk_entities
+                } else {
+                    &*boxed_entities
+                };
 
                 (current_fn)(&mut storage, self, &self.entities, current_stage_entities);
             }
@@ -666,6 +1291,7 @@ impl World {
 
         // TODO move entity and global systems commands to frame start and frame end functions
         // Process all the entity commands created in the stage
+        let _scope = ProfileScope::new("World commands", ENTITY_SYSTEM_CATEGORY);
         self.process_entity_commands();
         self.process_global_systems_commands();
     }
@@ -678,6 +1304,49 @@ impl World {
         self.global_system_is_loaded_by_id(get_id!(GS))
     }
 
+    /// Ids of the global systems loaded in this world, in running order
+    pub fn get_loaded_global_systems(&self) -> Vec<GlobalSystemID> {
+        self.global_systems
+            .read()
+            .iter()
+            .enumerate()
+            .filter(|(_, storage)| storage.is_some())
+            .map(|(id, _)| id as GlobalSystemID)
+            .collect()
+    }
+
+    /// Ids of the global systems this world runs in a stage, in running order
+    pub fn get_global_systems_in_stage(&self, stage_id: StageID) -> Vec<GlobalSystemID> {
+        self.global_system_stages[stage_id as usize].read().clone()
+    }
+
+    /// Number of entities that run local systems in each stage,
+    /// including the ones stored in archetypes
+    pub fn get_stage_entity_counts(&self) -> [usize; STAGE_COUNT] {
+        let mut counts = [0; STAGE_COUNT];
+        for entity_ptr in self.entities.read_all().iter() {
+            let entity = entity_ptr.read();
+            if entity.is_in_archetype() {
+                continue;
+            }
+
+            for (stage_id, count) in counts.iter_mut().enumerate() {
+                if entity.is_stage_enabled(stage_id as StageID) {
+                    *count += 1;
+                }
+            }
+        }
+
+        for archetype in self.archetypes.read().get_archetypes() {
+            for (stage_id, count) in counts.iter_mut().enumerate() {
+                if archetype.runs_in_stage(stage_id as StageID) {
+                    *count += archetype.len();
+                }
+            }
+        }
+        counts
+    }
+
     /// Requests a Global system load. It will be done by the start of the next frame.
     fn load_global_system_by_id(&self, global_system_id: GlobalSystemID) {
         self.gs_creation_queue.push(global_system_id);
@@ -790,6 +1459,20 @@ impl World {
         todo!("Implement world merge!")
     }
 
+    /// Call `f` with the entity ids and the `DG` column of every archetype chunk storing datagroup `DG`.
+    /// Only entities stored in archetypes are visited, see [StorageMode].
+    /// All the visited datagroups are marked as changed.
+    ///
+    /// The entities of each chunk are locked for writing while `f` visits it,
+    /// so don't hold locks of entities in this world while calling this function
+    pub fn for_each_chunk_column<DG, F>(&self, f: F)
+    where
+        DG: IDLocator + DataGroup + 'static,
+        F: FnMut(&[EntityID], &mut [DG]),
+    {
+        self.archetypes.read().for_each_column(f);
+    }
+
     /// Get a reference to the entity map.
     ///
     /// This function is intended to be used for tests
@@ -814,15 +1497,19 @@ impl World {
         &self.global_systems
     }
 
-    /// Helper to remove an entity from a stage vec
-    #[inline(always)]
-    fn remove_entity_from_stage_vec(stage_vec: &EntitiesVec, entity_ptr: &EntityPtr) {
-        let mut stage = stage_vec.write();
+    /// Helper to remove a previous parent from the stage lists that its hierarchy
+    /// doesn't need to run anymore, after one of its children was detached
+    fn remove_from_finished_stages(&self, prev_parent_ptr: EntityPtr) {
+        // Only roots are in the stage lists
+        let prev_root = prev_parent_ptr.read().get_root();
+        let prev_root_entity = prev_root.read();
+        let prev_root_id = prev_root_entity.get_id();
+        let prev_stages = &unsafe { prev_root_entity.get_transform_unsafe() }.stage_count;
 
-        for i in 0..stage.len() {
-            if stage[i] == *entity_ptr {
-                stage.swap_remove(i);
-                break;
+        for (stage_id, stage_count) in prev_stages.iter().enumerate() {
+            if stage_count.load(Ordering::Acquire) == 0 {
+                self.entities
+                    .remove_from_stage(stage_id as StageID, prev_root_id);
             }
         }
     }
@@ -841,7 +1528,6 @@ impl World {
     /// The entity related to this id should provide a camera datagroup.
     /// If not, this function **will crash**
     pub fn set_current_camera(&self, entity_id: EntityID) {
-
         #[cfg(debug_assertions)]
         { // Check that the entity is a valid camera
             let entity_map = self.get_entities();
@@ -870,6 +1556,9 @@ pub type WorldDestroyQueue = scc::Queue<WorldID>;
 /// Entity System queue type for merge world commands
 pub type WorldMergeQueue = scc::Queue<(WorldID, WorldID)>;
 
+/// Entity System queue type for moving entities between worlds: (source world, entity, target world)
+pub type EntityMoveQueue = scc::Queue<(WorldID, EntityID, WorldID)>;
+
 /// Entity System atomic type used for deltas
 pub type DeltaTimeAtomicType = AtomicF64;
 
@@ -881,18 +1570,53 @@ pub type DeltaTimeType = f64;
 pub enum EntitySystemError {
     /// Failed to find the specified world
     WorldNotFound,
+    /// The entity can't spawn from its spawn description
+    InvalidSpawn(SpawnError),
+    /// The entity can't be duplicated
+    InvalidDuplication(DuplicationError),
 }
 
 impl std::fmt::Display for EntitySystemError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             EntitySystemError::WorldNotFound => write!(f, "World Not Found"),
+            EntitySystemError::InvalidSpawn(err) => write!(f, "{err}"),
+            EntitySystemError::InvalidDuplication(err) => write!(f, "{err}"),
         }
     }
 }
 
 impl std::error::Error for EntitySystemError {}
 
+/// Reasons an entity can't be duplicated, see [World::duplicate_entity]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DuplicationError {
+    /// There's no entity with this id in the world, or it didn't spawn yet
+    EntityNotFound(EntityID),
+    /// A datagroup of an entity to duplicate doesn't support cloning
+    NotCloneable {
+        entity_name: String,
+        datagroup: &'static str,
+    },
+}
+
+impl std::fmt::Display for DuplicationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DuplicationError::EntityNotFound(id) => write!(f, "Entity {id} not found"),
+            DuplicationError::NotCloneable {
+                entity_name,
+                datagroup,
+            } => write!(
+                f,
+                "Can't duplicate entity '{entity_name}': DataGroup '{datagroup}' is not cloneable"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DuplicationError {}
+
 #[derive(Debug)]
 pub struct EntitySystem {
     pool: ThreadPool,
@@ -903,6 +1627,7 @@ pub struct EntitySystem {
     world_id_counter: AtomicU16,
     destroy_world_queue: WorldDestroyQueue,
     merge_worlds_queue: WorldMergeQueue,
+    move_entity_queue: EntityMoveQueue,
 }
 
 impl EntitySystem {
@@ -924,16 +1649,23 @@ impl EntitySystem {
     }
 
     /// Create a new world
-    fn create_world_internal(&self, new_id: WorldID) {
-        let old = self.worlds.insert(new_id, World::new(new_id));
+    fn create_world_internal(&self, new_id: WorldID, storage_mode: StorageMode) {
+        let old = self
+            .worlds
+            .insert(new_id, World::with_storage(new_id, storage_mode));
         assert!(old.is_none(), "World ID collision! Old : {:?}", old);
     }
 
     /// Create a new world and return its world ID
     pub fn create_world(&self) -> WorldID {
+        self.create_world_with_storage(StorageMode::Boxed)
+    }
+
+    /// Create a new world that stores datagroups using `storage_mode`, and return its world ID
+    pub fn create_world_with_storage(&self, storage_mode: StorageMode) -> WorldID {
         let new_id = self.world_id_counter.fetch_add(1, Ordering::AcqRel) as WorldID;
 
-        self.create_world_internal(new_id);
+        self.create_world_internal(new_id, storage_mode);
 
         new_id
     }
@@ -941,7 +1673,11 @@ impl EntitySystem {
     /// Destroy a world
     fn destroy_world_internal(&self, id: WorldID) {
         if self.worlds.remove(&id).is_none() {
-            println!("Failed to destroy World {id}, maybe it was already destroyed(?)");
+            warn!(
+                target: logging::ENTITY_SYSTEM,
+                world_id = id,
+                "Failed to destroy world, maybe it was already destroyed (?)"
+            );
         }
     }
 
@@ -954,8 +1690,11 @@ impl EntitySystem {
     fn merge_worlds_internal(&self, source: WorldID, target: WorldID) {
         let target_world = self.worlds.get_mut(&target);
         if target_world.is_none() {
-            println!(
-                "Failed to merge World {source} into World {target} due to missing target world!"
+            warn!(
+                target: logging::ENTITY_SYSTEM,
+                world_id = source,
+                target_world_id = target,
+                "Failed to merge worlds due to missing target world!"
             );
             return;
         }
@@ -963,8 +1702,11 @@ impl EntitySystem {
 
         let source_world = self.worlds.remove(&source);
         if source_world.is_none() {
-            println!(
-                "Failed to merge World {source} into World {target} due to missing source world!"
+            warn!(
+                target: logging::ENTITY_SYSTEM,
+                world_id = source,
+                target_world_id = target,
+                "Failed to merge worlds due to missing source world!"
             );
             return;
         }
@@ -978,8 +1720,72 @@ impl EntitySystem {
         self.merge_worlds_queue.push((source, target));
     }
 
-    /// Process destroy and merge world commands
+    /// Move `entity_id` and its spatial subtree from World `source` to World `target`
+    fn move_entity_internal(&self, source: WorldID, entity_id: EntityID, target: WorldID) {
+        if source == target {
+            return;
+        }
+
+        let (Some(source_world), Some(target_world)) =
+            (self.worlds.get(&source), self.worlds.get(&target))
+        else {
+            warn!(
+                target: logging::ENTITY_SYSTEM,
+                world_id = source,
+                entity_id,
+                target_world_id = target,
+                "Failed to move entity between worlds due to missing world!"
+            );
+            return;
+        };
+
+        let Some(entity_ptr) = source_world.entities.get(&entity_id) else {
+            warn!(
+                target: logging::ENTITY_SYSTEM,
+                world_id = source,
+                entity_id,
+                target_world_id = target,
+                "Failed to move entity between worlds, it's not in the source world"
+            );
+            return;
+        };
+
+        // Check before taking the entities out of the source world, so they are not lost
+        if !target_world.can_insert_entities(&World::collect_hierarchy(entity_ptr)) {
+            warn!(
+                target: logging::ENTITY_SYSTEM,
+                world_id = source,
+                entity_id,
+                target_world_id = target,
+                "Failed to move entity between worlds, the target world can't take it"
+            );
+            return;
+        }
+
+        let entities = source_world
+            .take_entity_internal(entity_id)
+            .expect("Entity should be in the source world");
+        source_world.forward_entity_commands(&entities, &target_world);
+        target_world.insert_entities_internal(&entities);
+    }
+
+    /// Move an entity and its spatial subtree from World `source` to World `target`. The entities keep their ids.
+    ///
+    /// The moved entity is detached from its parent, and the relations of all the moved entities are cleared.
+    /// Global systems with a `WhenRequired` lifetime are loaded in the target world if needed.
+    /// The move is done before the next stage starts, and commands still queued for the moved entities
+    /// follow them to the target world.
+    ///
+    /// The entities stay in the source world if the target world requires a global system they need
+    /// that is not loaded, or if one of their ids is in use there
+    pub fn move_entity(&self, source: WorldID, entity_id: EntityID, target: WorldID) {
+        self.move_entity_queue.push((source, entity_id, target));
+    }
+
+    /// Process destroy and merge world commands, and entity moves between worlds
     fn process_world_command_queues(&self) {
+        let _scope = ProfileScope::new("World system commands", ENTITY_SYSTEM_CATEGORY);
+
         // Reset if requested
         if self.requested_reset.load(Ordering::Acquire) {
             self.reset_internal();
@@ -995,17 +1801,29 @@ impl EntitySystem {
             let (source, target) = **value;
             self.merge_worlds_internal(source, target);
         }
+
+        // Finally move entities between the remaining worlds
+        while let Some(value) = self.move_entity_queue.pop() {
+            let (source, entity_id, target) = **value;
+            self.move_entity_internal(source, entity_id, target);
+        }
     }
 
     /// Process a stage for the entity system and all the worlds
     fn process_stage(&self, stage_id: StageID) {
+        let mut stage_scope =
+            ProfileScope::new("EntitySystem::process_stage", ENTITY_SYSTEM_CATEGORY);
+        stage_scope.add_arg("stage", stage_id as f64);
+
         // Process all commands created before the stage
         self.process_world_command_queues();
 
         // Process worlds in parallel
         self.pool.install(|| {
             self.worlds.par_iter().for_each(|world| {
-                world.run_stage(stage_id);
+                if !world.is_skipping_step() {
+                    world.run_stage(stage_id);
+                }
             });
         });
 
@@ -1023,6 +1841,7 @@ impl EntitySystem {
         // Update delta times in parallel
         self.pool.install(|| {
             self.worlds.par_iter().for_each(|world| {
+                world.begin_step_internal();
                 world
                     .update_delta_time_internal(self.get_delta_time(), self.get_fixed_delta_time());
             });
@@ -1043,7 +1862,79 @@ impl EntitySystem {
         match self.worlds.get(&world_id) {
             Some(entry) => Ok(entry.create_entity(spawn_desc)),
             None => {
-                println!("Failed to create entity due to: Couldn't find World {world_id}!");
+                warn!(
+                    target: logging::ENTITY_SYSTEM,
+                    world_id,
+                    "Failed to create entity due to: Couldn't find world!"
+                );
+                Err(EntitySystemError::WorldNotFound)
+            }
+        }
+    }
+
+    /// Create a new entity in World `world_id`, if its spawn description is valid. See `World::try_create_entity`
+    pub fn try_create_entity(
+        &self,
+        world_id: WorldID,
+        spawn_desc: EntitySpawnDescription,
+    ) -> Result<EntityID, EntitySystemError> {
+        match self.worlds.get(&world_id) {
+            Some(entry) => entry
+                .try_create_entity(spawn_desc)
+                .map_err(EntitySystemError::InvalidSpawn),
+            None => {
+                warn!(
+                    target: logging::ENTITY_SYSTEM,
+                    world_id,
+                    "Failed to create entity due to: Couldn't find world!"
+                );
+                Err(EntitySystemError::WorldNotFound)
+            }
+        }
+    }
+
+    /// Create an entity with an id assigned somewhere else. See `World::create_entity_with_id`
+    pub fn create_entity_with_id(
+        &self,
+        world_id: WorldID,
+        id: EntityID,
+        spawn_desc: EntitySpawnDescription,
+    ) -> Result<(), EntitySystemError> {
+        match self.worlds.get(&world_id) {
+            Some(entry) => {
+                entry.create_entity_with_id(id, spawn_desc);
+                Ok(())
+            }
+            None => {
+                warn!(
+                    target: logging::ENTITY_SYSTEM,
+                    world_id,
+                    entity_id = id,
+                    "Failed to create entity due to: Couldn't find world!"
+                );
+                Err(EntitySystemError::WorldNotFound)
+            }
+        }
+    }
+
+    /// Duplicate an entity in World `world_id`. See `World::duplicate_entity`
+    pub fn duplicate_entity(
+        &self,
+        world_id: WorldID,
+        entity_id: EntityID,
+        recursive: bool,
+    ) -> Result<EntityID, EntitySystemError> {
+        match self.worlds.get(&world_id) {
+            Some(entry) => entry
+                .duplicate_entity(entity_id, recursive)
+                .map_err(EntitySystemError::InvalidDuplication),
+            None => {
+                warn!(
+                    target: logging::ENTITY_SYSTEM,
+                    world_id,
+                    entity_id,
+                    "Failed to duplicate entity due to: Couldn't find world!"
+                );
                 Err(EntitySystemError::WorldNotFound)
             }
         }
@@ -1057,8 +1948,11 @@ impl EntitySystem {
                 true
             }
             None => {
-                println!(
-                    "Failed to destroy entity {entity_id} due to: Couldn't find World {world_id}!"
+                warn!(
+                    target: logging::ENTITY_SYSTEM,
+                    world_id,
+                    entity_id,
+                    "Failed to destroy entity due to: Couldn't find world!"
                 );
                 false
             }
@@ -1085,6 +1979,13 @@ impl EntitySystem {
         });
     }
 
+    /// Run `f` with World `world_id`, if it exists. Meant for tools that inspect worlds between steps.
+    ///
+    /// Don't step or destroy worlds inside `f`, the world is locked while it runs
+    pub fn with_world<R>(&self, world_id: WorldID, f: impl FnOnce(&World) -> R) -> Option<R> {
+        self.worlds.get(&world_id).map(|world| f(&world))
+    }
+
     // Resets the entity system. That is, destroys all the worlds and creates the default one. DO NOT call this from an world/system update
     fn reset_internal(&self) {
         // Empty commands
@@ -1096,11 +1997,15 @@ impl EntitySystem {
             self.merge_worlds_queue.pop();
         }
 
+        while !self.move_entity_queue.is_empty() {
+            self.move_entity_queue.pop();
+        }
+
         // Destroy all worlds
         self.worlds.clear();
 
         // Create default world
-        self.create_world_internal(DEFAULT_WORLD); // World 0 is always created
+        self.create_world_internal(DEFAULT_WORLD, StorageMode::Boxed); // World 0 is always created
 
         self.requested_reset.store(false, Ordering::Release);
     }
@@ -1134,8 +2039,7 @@ impl EntitySystem {
     #[cfg(test)]
     pub(super) fn get_entity(&self, world_id: WorldID, entity_id: EntityID) -> EntityPtr {
         let world = self.worlds.get(&world_id).unwrap();
-        let entity = world.entities.get(&entity_id).unwrap();
-        return entity.clone();
+        world.entities.get(&entity_id).unwrap()
     }
 
     /// Get a reference to the worldmap
@@ -1144,7 +2048,7 @@ impl EntitySystem {
     /// DO NOT USE THIS FUNCTION OUTSIDE TESTS
     #[cfg(test)]
     pub(super) fn get_world_map(&self) -> &WorldMap {
-        return &self.worlds;
+        &self.worlds
     }
 
     /// Run a step for the specified world. Specially useful to run a word per test
@@ -1163,10 +2067,10 @@ impl EntitySystem {
 
         self.worlds
             .get(&world_id)
-            .and_then(|world| {
+            .map(|world| {
+                world.begin_step_internal();
                 world
                     .update_delta_time_internal(self.get_delta_time(), self.get_fixed_delta_time());
-                Some(())
             })
             .expect("World not found");
 
@@ -1187,9 +2091,10 @@ impl EntitySystem {
         // Process worlds in parallel
         self.worlds
             .get(&world_id)
-            .and_then(|world| {
-                world.run_stage(stage_id);
-                Some(())
+            .map(|world| {
+                if !world.is_skipping_step() {
+                    world.run_stage(stage_id);
+                }
             })
             .expect("World should exists by now");
 
@@ -1216,6 +2121,7 @@ impl EntitySystem {
             world_id_counter: AtomicU16::new(DEFAULT_WORLD + 1), // Note that the default world has id 0
             destroy_world_queue: Default::default(),
             merge_worlds_queue: Default::default(),
+            move_entity_queue: Default::default(),
         };
 
         new_self.reset_internal();
@@ -0,0 +1,221 @@
+/// Typed relationships between entities in a world, like "owned by", "targets"
+/// or "attached to socket". This is the generic version of the parent/child
+/// relationship in [crate::entities::transform_datagroup::Transform].
+///
+/// Relations go from a source entity to a target entity. To declare a new relation
+/// implement the [Relation] trait on a marker type:
+/// ```ignore
+/// pub struct Owns;
+///
+/// impl Relation for Owns {
+///     const ON_DESTROY: DestroyPolicy = DestroyPolicy::Cascade;
+/// }
+///
+/// world.add_relation::<Owns>(player, sword);
+/// assert_eq!(world.related::<Owns>(player), vec![sword]);
+/// assert_eq!(world.related_reverse::<Owns>(sword), vec![player]);
+/// ```
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use nohash_hasher::IntMap;
+
+use crate::core::locking::RwLock;
+
+use super::entity::EntityID;
+
+/// What to do with a relation when one of its entities is destroyed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DestroyPolicy {
+    /// Destroying the source also destroys all its targets.
+    /// Destroying a target just removes the relation
+    Cascade,
+    /// Destroying any of the entities removes the relation
+    Clear,
+    /// The surviving entity keeps the relation to the destroyed entity.
+    /// Users are responsible of checking if the related entity is still live
+    Keep,
+}
+
+/// A kind of relation between entities
+pub trait Relation: 'static {
+    /// What happens with this relation when one of its entities is destroyed
+    const ON_DESTROY: DestroyPolicy;
+}
+
+/// Storage for all the relations in a world
+#[derive(Debug, Default)]
+pub struct Relationships {
+    storages: RwLock<HashMap<TypeId, RwLock<RelationStorage>>>,
+}
+
+/// Storage for all the relations of a single kind
+#[derive(Debug)]
+struct RelationStorage {
+    policy: DestroyPolicy,
+    /// source -> targets
+    targets: IntMap<EntityID, Vec<EntityID>>,
+    /// target -> sources
+    sources: IntMap<EntityID, Vec<EntityID>>,
+}
+
+// -- < Implementations > --------------------------------
+
+impl Relationships {
+    /// Relate `source` to `target`. Adding an already existing relation does nothing.
+    /// Returns whether the relation was added
+    pub fn add<R: Relation>(&self, source: EntityID, target: EntityID) -> bool {
+        let type_id = TypeId::of::<R>();
+        {
+            let storages = self.storages.read();
+            if let Some(storage) = storages.get(&type_id) {
+                return storage.write().add(source, target);
+            }
+        }
+
+        let mut storages = self.storages.write();
+        let storage = storages
+            .entry(type_id)
+            .or_insert_with(|| RwLock::new(RelationStorage::new(R::ON_DESTROY)));
+        storage.get_mut().add(source, target)
+    }
+
+    /// Remove the relation from `source` to `target`.
+    /// Returns whether the relation existed
+    pub fn remove<R: Relation>(&self, source: EntityID, target: EntityID) -> bool {
+        let storages = self.storages.read();
+        storages
+            .get(&TypeId::of::<R>())
+            .is_some_and(|storage| storage.write().remove(source, target))
+    }
+
+    /// If `source` is related to `target`
+    pub fn contains<R: Relation>(&self, source: EntityID, target: EntityID) -> bool {
+        let storages = self.storages.read();
+        storages.get(&TypeId::of::<R>()).is_some_and(|storage| {
+            storage
+                .read()
+                .targets
+                .get(&source)
+                .is_some_and(|targets| targets.contains(&target))
+        })
+    }
+
+    /// All the targets of `source`
+    pub fn related<R: Relation>(&self, source: EntityID) -> Vec<EntityID> {
+        let storages = self.storages.read();
+        storages
+            .get(&TypeId::of::<R>())
+            .and_then(|storage| storage.read().targets.get(&source).cloned())
+            .unwrap_or_default()
+    }
+
+    /// All the sources related to `target`
+    pub fn related_reverse<R: Relation>(&self, target: EntityID) -> Vec<EntityID> {
+        let storages = self.storages.read();
+        storages
+            .get(&TypeId::of::<R>())
+            .and_then(|storage| storage.read().sources.get(&target).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Apply the destroy policy of every relation to an entity that is being destroyed.
+    /// Entities that should be destroyed as a consequence are added to `to_destroy`
+    pub(super) fn on_entity_destroyed(&self, id: EntityID, to_destroy: &mut Vec<EntityID>) {
+        let storages = self.storages.read();
+        for storage in storages.values() {
+            storage.write().on_entity_destroyed(id, to_destroy);
+        }
+    }
+
+    /// Remove every relation of an entity that leaves the world, ignoring destroy policies
+    pub(super) fn forget_entity(&self, id: EntityID) {
+        let storages = self.storages.read();
+        for storage in storages.values() {
+            storage.write().forget_entity(id);
+        }
+    }
+}
+
+impl RelationStorage {
+    fn new(policy: DestroyPolicy) -> Self {
+        RelationStorage {
+            policy,
+            targets: Default::default(),
+            sources: Default::default(),
+        }
+    }
+
+    fn add(&mut self, source: EntityID, target: EntityID) -> bool {
+        let targets = self.targets.entry(source).or_default();
+        if targets.contains(&target) {
+            return false;
+        }
+
+        targets.push(target);
+        self.sources.entry(target).or_default().push(source);
+        true
+    }
+
+    fn remove(&mut self, source: EntityID, target: EntityID) -> bool {
+        let removed = remove_from_map(&mut self.targets, source, target);
+        if removed {
+            remove_from_map(&mut self.sources, target, source);
+        }
+        removed
+    }
+
+    fn on_entity_destroyed(&mut self, id: EntityID, to_destroy: &mut Vec<EntityID>) {
+        let targets = self.targets.remove(&id).unwrap_or_default();
+        let sources = self.sources.remove(&id).unwrap_or_default();
+
+        if self.policy == DestroyPolicy::Keep {
+            // The other entities keep their relation to this entity
+            return;
+        }
+
+        for &target in targets.iter() {
+            remove_from_map(&mut self.sources, target, id);
+        }
+
+        for &source in sources.iter() {
+            remove_from_map(&mut self.targets, source, id);
+        }
+
+        if self.policy == DestroyPolicy::Cascade {
+            to_destroy.extend(targets);
+        }
+    }
+
+    fn forget_entity(&mut self, id: EntityID) {
+        for target in self.targets.remove(&id).unwrap_or_default() {
+            remove_from_map(&mut self.sources, target, id);
+        }
+
+        for source in self.sources.remove(&id).unwrap_or_default() {
+            remove_from_map(&mut self.targets, source, id);
+        }
+    }
+}
+
+/// Remove `value` from the list of `key` in `map`, and the list itself if it's empty.
+/// Returns whether the value was found
+fn remove_from_map(
+    map: &mut IntMap<EntityID, Vec<EntityID>>,
+    key: EntityID,
+    value: EntityID,
+) -> bool {
+    let Some(values) = map.get_mut(&key) else {
+        return false;
+    };
+
+    let Some(pos) = values.iter().position(|&v| v == value) else {
+        return false;
+    };
+
+    values.swap_remove(pos);
+    if values.is_empty() {
+        map.remove(&key);
+    }
+    true
+}
@@ -0,0 +1,153 @@
+use crate::app::App;
+use crate::entities::archetype::StorageMode;
+use crate::entities::change_detection::*;
+use crate::entities::entity::EntityID;
+use crate::entities::entity_allocator::{EntityAllocator, EntityPtr};
+use crate::entities::entity_spawn_desc::EntitySpawnDescription;
+use crate::entities::entity_system::{EntitySystem, WorldID};
+use crate::tests::shared_datagroups::sdg::{
+    AnimationDataGroup, MeshDataGroup, TestNumberDataGroup, TestNumberDataGroupArg,
+};
+use crate::tests::shared_local_systems::sls::TestAdder;
+
+#[test]
+fn test_datagroup_ticks() {
+    if !App::is_initialized() {
+        App::initialize().expect("Failed to initialize the app");
+    }
+
+    let mut spawn_desc = EntitySpawnDescription::default();
+    let init_params = Box::new(AnimationDataGroup {
+        clip_name: "clip".to_string(),
+        duration: 1.0,
+    });
+    AnimationDataGroup::prepare_spawn(&mut spawn_desc, init_params);
+    MeshDataGroup::prepare_spawn(&mut spawn_desc);
+
+    let global_allocator = EntityAllocator::get_global();
+    let mut entity_ptr = global_allocator.read().allocate();
+    entity_ptr.init(1, spawn_desc);
+
+    let tick = advance_change_tick();
+    let mut entity = entity_ptr.write();
+    let ticks = entity.get_datagroup_ticks::<MeshDataGroup>().unwrap();
+    assert_eq!(ticks.added(), ticks.changed());
+    assert!(!entity.changed_since(tick));
+
+    // Shared access doesn't count as a change
+    entity.get_datagroup::<MeshDataGroup>().unwrap();
+    assert!(!entity.datagroup_changed_since::<MeshDataGroup>(tick));
+
+    entity.get_datagroup_mut::<MeshDataGroup>().unwrap();
+    assert!(entity.datagroup_changed_since::<MeshDataGroup>(tick));
+    assert!(!entity.datagroup_changed_since::<AnimationDataGroup>(tick));
+    assert!(!entity.datagroup_added_since::<MeshDataGroup>(tick));
+    assert!(entity.changed_since(tick));
+
+    drop(entity);
+    global_allocator.read().free(&entity_ptr);
+}
+
+fn spawn_number(es: &EntitySystem, world_id: WorldID, with_adder: bool) -> EntityID {
+    let mut spawn_desc = EntitySpawnDescription::default();
+    TestNumberDataGroup::prepare_spawn(
+        &mut spawn_desc,
+        Box::new(TestNumberDataGroupArg { num: 0 }),
+    );
+    if with_adder {
+        TestAdder::simple_prepare(&mut spawn_desc);
+    }
+    es.create_entity(world_id, spawn_desc)
+        .expect("Creation should be successful")
+}
+
+#[test]
+fn test_change_detection_in_world() {
+    if !App::is_initialized() {
+        App::initialize().expect("Failed to initialize the app");
+    }
+
+    let es = EntitySystem::get();
+    for storage_mode in [StorageMode::Boxed, StorageMode::Archetype] {
+        let world_id = es.create_world_with_storage(storage_mode);
+        es.step_world(0.0, 0.0, world_id); // Process world creation
+
+        let start = current_change_tick();
+        let changing = spawn_number(es, world_id, true);
+        let still = spawn_number(es, world_id, false);
+        es.step_world(0.0, 0.0, world_id); // Process creation and run local systems
+
+        let tick = {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&world_id).unwrap();
+            let mut added = world.query_added_since::<TestNumberDataGroup>(start);
+            added.sort();
+            assert_eq!(added, vec![changing, still]);
+            world.get_change_tick()
+        };
+
+        // Only the entity with a local system using the datagroup changes
+        es.step_world(0.0, 0.0, world_id);
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&world_id).unwrap();
+            assert_eq!(
+                world.query_changed_since::<TestNumberDataGroup>(tick),
+                vec![changing]
+            );
+            assert!(world
+                .query_added_since::<TestNumberDataGroup>(tick)
+                .is_empty());
+
+            let entities: Vec<EntityPtr> = [changing, still]
+                .iter()
+                .map(|&id| es.get_entity(world_id, id))
+                .collect();
+            let changed: Vec<&EntityPtr> = entities
+                .iter()
+                .changed_since::<TestNumberDataGroup>(tick)
+                .collect();
+            assert_eq!(changed, vec![&entities[0]]);
+        }
+
+        // Mutable access marks the datagroup as changed
+        let tick = current_change_tick();
+        es.get_entity(world_id, still)
+            .write()
+            .get_datagroup_mut::<TestNumberDataGroup>()
+            .unwrap()
+            .num = 10;
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&world_id).unwrap();
+            assert_eq!(
+                world.query_changed_since::<TestNumberDataGroup>(tick),
+                vec![still]
+            );
+        }
+
+        let tick = current_change_tick();
+        es.destroy_entity(world_id, changing);
+        es.step_world(0.0, 0.0, world_id); // Process destruction
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&world_id).unwrap();
+            assert_eq!(
+                world.query_removed_since::<TestNumberDataGroup>(tick),
+                vec![changing]
+            );
+            assert!(world.query_removed_since::<MeshDataGroup>(tick).is_empty());
+        }
+
+        // Removals are forgotten after the step following them
+        es.step_world(0.0, 0.0, world_id);
+        es.step_world(0.0, 0.0, world_id);
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&world_id).unwrap();
+            assert!(world.removed_since(tick).is_empty());
+        }
+
+        es.destroy_world(world_id);
+    }
+}
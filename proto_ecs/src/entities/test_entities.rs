@@ -8,17 +8,27 @@ mod test {
         app::App,
         core::casting::cast,
         core::ids::{HasID, IDLocator},
+        core::profiling::{ProfileScope, Profiler},
+        data_group::DataGroupInitType,
         entities::{
+            archetype::StorageMode,
+            entity::{entity_id_index, make_entity_id, EntityID, INVALID_ENTITY_ID},
             entity_allocator::EntityAllocator,
-            entity_spawn_desc::EntitySpawnDescription,
-            entity_system::{EntitySystem, World},
+            entity_id_allocator::EntityIDAllocator,
+            entity_ref::EntityRemap,
+            entity_spawn_desc::{EntitySpawnDescription, SpawnProblem},
+            entity_system::{DuplicationError, EntitySystem, EntitySystemError, World},
+            relationships::{DestroyPolicy, Relation},
             transform_datagroup::Transform,
         },
         get_id,
         systems::common::STAGE_COUNT,
+        systems::local_systems::SystemClassID,
+        systems::schedule::WorldSchedule,
         tests::{
             shared_datagroups::sdg::{
-                AnimationDataGroup, MeshDataGroup, TestNumberDataGroup, TestNumberDataGroupArg,
+                AnimationDataGroup, MeshDataGroup, TestDropDataGroup, TestEntityRefDataGroup,
+                TestNumberDataGroup, TestNumberDataGroupArg, TEST_DROP_LIVE_COUNT,
             },
             shared_global_systems::sgs::Test as gs_Test,
             shared_global_systems::sgs::{
@@ -32,7 +42,7 @@ mod test {
     #[test]
     fn test_entity_creation() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let mut spawn_desc = EntitySpawnDescription::default();
@@ -51,7 +61,7 @@ mod test {
         spawn_desc.set_name("Test Name".to_owned());
 
         let global_allocator = EntityAllocator::get_global();
-        let mut entity_ptr = global_allocator.write().allocate();
+        let mut entity_ptr = global_allocator.read().allocate();
         entity_ptr.init(1, spawn_desc);
 
         let entity = entity_ptr.read();
@@ -76,7 +86,7 @@ mod test {
     #[test]
     fn test_entity_stage_run() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let world = World::new(0);
@@ -92,7 +102,7 @@ mod test {
         spawn_desc.set_name("Test Name".to_owned());
 
         let global_allocator = EntityAllocator::get_global();
-        let mut entity_ptr = global_allocator.write().allocate();
+        let mut entity_ptr = global_allocator.read().allocate();
         entity_ptr.init(1, spawn_desc);
 
         let mut entity = entity_ptr.write();
@@ -107,7 +117,7 @@ mod test {
     #[test]
     fn test_entity_system_basic() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let es = EntitySystem::get();
@@ -175,7 +185,7 @@ mod test {
     #[test]
     fn test_parenting() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let es = EntitySystem::get();
@@ -184,7 +194,7 @@ mod test {
 
         let get_spawn_desc = || {
             let mut desc = EntitySpawnDescription::default();
-            Transform::prepare_spawn(&mut desc, Box::new(Transform::default()));
+            Transform::prepare_spawn(&mut desc, Box::default());
             AllLive::simple_prepare(&mut desc);
             desc
         };
@@ -275,7 +285,7 @@ mod test {
     #[test]
     fn test_hierarchy_update() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         // Test hierarchical updates
@@ -285,7 +295,7 @@ mod test {
 
         fn get_new_desc() -> EntitySpawnDescription {
             let mut desc = EntitySpawnDescription::default();
-            Transform::prepare_spawn(&mut desc, Box::new(Transform::default()));
+            Transform::prepare_spawn(&mut desc, Box::default());
             desc
         }
 
@@ -440,4 +450,1048 @@ mod test {
         // Should panic here
         es.step_world(0.0, 0.0, new_world_id);
     }
+
+    #[test]
+    fn test_entity_refs() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world();
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+
+        let mut holder_desc = EntitySpawnDescription::default();
+        TestEntityRefDataGroup::prepare_spawn(&mut holder_desc);
+        let holder_id = es
+            .create_entity(new_world_id, holder_desc)
+            .expect("Creation should be successful");
+        let target_id = es
+            .create_entity(new_world_id, EntitySpawnDescription::default())
+            .expect("Creation should be successful");
+        let replacement_id = es
+            .create_entity(new_world_id, EntitySpawnDescription::default())
+            .expect("Creation should be successful");
+        es.step_world(0.0, 0.0, new_world_id); // Process entity creation
+
+        let holder_ptr = es.get_entity(new_world_id, holder_id);
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            let mut holder = holder_ptr.write();
+            let refs_dg = holder
+                .get_datagroup_mut::<TestEntityRefDataGroup>()
+                .expect("Holder should provide the entity ref datagroup");
+            refs_dg.target = world.get_entity_ref(target_id);
+            refs_dg.others.push(world.get_entity_ref(target_id));
+
+            assert!(refs_dg.target.get().is_some());
+            assert!(world.get_entity_ref(INVALID_ENTITY_ID).is_null());
+        }
+
+        {
+            // Check that references can be remapped to other entities
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            let mut remap = EntityRemap::default();
+            remap.insert(target_id, world.get_entity_ref(replacement_id));
+            world.remap_entity_refs(&remap);
+
+            let holder = holder_ptr.read();
+            let refs_dg = holder.get_datagroup::<TestEntityRefDataGroup>().unwrap();
+            assert_eq!(refs_dg.target.get_id(), replacement_id);
+            assert_eq!(refs_dg.others[0].get_id(), replacement_id);
+        }
+
+        // References should not resolve after the entity is destroyed
+        es.destroy_entity(new_world_id, replacement_id);
+        es.step_world(0.0, 0.0, new_world_id);
+        {
+            let holder = holder_ptr.read();
+            let refs_dg = holder.get_datagroup::<TestEntityRefDataGroup>().unwrap();
+            assert!(refs_dg.target.get().is_none());
+            assert!(!refs_dg.others[0].is_live());
+            assert_eq!(refs_dg.target.get_id(), replacement_id);
+        }
+
+        es.destroy_world(new_world_id);
+    }
+
+    #[test]
+    fn test_entity_duplication() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world();
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+
+        let mut root_desc = EntitySpawnDescription::default();
+        root_desc.set_name("Root entity".into());
+        Transform::prepare_spawn(&mut root_desc, Box::default());
+        let root_id = es
+            .create_entity(new_world_id, root_desc)
+            .expect("Creation should be successful");
+
+        let mut node_desc = EntitySpawnDescription::default();
+        node_desc.set_name("Node entity".into());
+        Transform::prepare_spawn(&mut node_desc, Box::default());
+        TestNumberDataGroup::prepare_spawn(
+            &mut node_desc,
+            Box::new(TestNumberDataGroupArg { num: 5 }),
+        );
+        let node_id = es
+            .create_entity(new_world_id, node_desc)
+            .expect("Creation should be successful");
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            world.set_entity_parent(node_id, root_id);
+        }
+        es.step_world(0.0, 0.0, new_world_id); // Process entity creation
+
+        // Change the state after creation, the copies should keep it
+        let node_ptr = es.get_entity(new_world_id, node_id);
+        node_ptr
+            .write()
+            .get_datagroup_mut::<TestNumberDataGroup>()
+            .unwrap()
+            .num = 9;
+
+        let root_copy_id = es
+            .duplicate_entity(new_world_id, root_id, true)
+            .expect("Duplication should be successful");
+        let node_copy_id = es
+            .duplicate_entity(new_world_id, node_id, false)
+            .expect("Duplication should be successful");
+        es.step_world(0.0, 0.0, new_world_id); // Process duplication
+
+        {
+            // The recursive copy is a new root with a copy of the node as child
+            let root_copy_ptr = es.get_entity(new_world_id, root_copy_id);
+            let root_copy = root_copy_ptr.read();
+            assert_eq!(root_copy.get_name(), "Root entity");
+            assert!(root_copy.is_root());
+
+            let root_copy_transform = root_copy.get_transform().unwrap();
+            assert_eq!(root_copy_transform.n_nodes, 2);
+            assert_eq!(root_copy_transform.children.len(), 1);
+
+            let child_copy = root_copy_transform.children[0].read();
+            assert_ne!(child_copy.get_id(), node_id);
+            assert_eq!(child_copy.get_name(), "Node entity");
+            assert_eq!(
+                child_copy
+                    .get_datagroup::<TestNumberDataGroup>()
+                    .unwrap()
+                    .num,
+                9
+            );
+        }
+
+        {
+            // The single copy shares the parent of the original node
+            let root_ptr = es.get_entity(new_world_id, root_id);
+            let root = root_ptr.read();
+            assert_eq!(root.get_transform().unwrap().n_nodes, 3);
+
+            let node_copy_ptr = es.get_entity(new_world_id, node_copy_id);
+            let node_copy = node_copy_ptr.read();
+            assert!(!node_copy.is_root());
+            assert_eq!(
+                node_copy
+                    .get_datagroup::<TestNumberDataGroup>()
+                    .unwrap()
+                    .num,
+                9
+            );
+        }
+
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            assert_eq!(world.get_entities().len(), 5);
+        }
+
+        es.destroy_world(new_world_id);
+    }
+
+    #[test]
+    fn test_entity_datagroup_block() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world();
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+
+        let mut spawn_desc = EntitySpawnDescription::default();
+        spawn_desc.set_name("Block entity".into());
+        TestDropDataGroup::prepare_spawn(&mut spawn_desc);
+        MeshDataGroup::prepare_spawn(&mut spawn_desc);
+        TestNumberDataGroup::prepare_spawn(
+            &mut spawn_desc,
+            Box::new(TestNumberDataGroupArg { num: 3 }),
+        );
+        let entity_id = es
+            .create_entity(new_world_id, spawn_desc)
+            .expect("Creation should be successful");
+        es.step_world(0.0, 0.0, new_world_id); // Process entity creation
+
+        assert_eq!(TEST_DROP_LIVE_COUNT.load(Ordering::Acquire), 1);
+        {
+            let entity_ptr = es.get_entity(new_world_id, entity_id);
+            let entity = entity_ptr.read();
+
+            // Datagroups constructed in place, sorted by id
+            let datagroups = entity.get_datagroups();
+            assert_eq!(datagroups.len(), 3);
+            assert!(datagroups
+                .iter()
+                .zip(datagroups.iter().skip(1))
+                .all(|(a, b)| a.get_id() < b.get_id()));
+
+            assert_eq!(
+                entity.get_datagroup::<TestNumberDataGroup>().unwrap().num,
+                3
+            );
+            assert_eq!(
+                entity.get_datagroup::<TestDropDataGroup>().unwrap().payload,
+                [7; 4]
+            );
+            assert!(entity.get_datagroup::<MeshDataGroup>().is_some());
+        }
+
+        // Datagroups in the block are dropped along with the entity
+        es.destroy_entity(new_world_id, entity_id);
+        es.step_world(0.0, 0.0, new_world_id); // Process entity destruction
+        assert_eq!(TEST_DROP_LIVE_COUNT.load(Ordering::Acquire), 0);
+
+        es.destroy_world(new_world_id);
+    }
+
+    #[test]
+    fn test_archetype_storage() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world_with_storage(StorageMode::Archetype);
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+
+        // More than a chunk worth of entities
+        const ENTITIES_NUM: u32 = 300;
+        let ids: Vec<_> = (0..ENTITIES_NUM)
+            .map(|i| {
+                let mut spawn_desc = EntitySpawnDescription::default();
+                spawn_desc.set_name(format!("Archetype entity {i}"));
+                TestNumberDataGroup::prepare_spawn(
+                    &mut spawn_desc,
+                    Box::new(TestNumberDataGroupArg { num: i }),
+                );
+                TestAdder::simple_prepare(&mut spawn_desc);
+                TestMultiplier::simple_prepare(&mut spawn_desc);
+                es.create_entity(new_world_id, spawn_desc)
+                    .expect("Creation should be successful")
+            })
+            .collect();
+        es.step_world(0.0, 0.0, new_world_id); // Process entity creation
+
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            assert_eq!(world.get_storage_mode(), StorageMode::Archetype);
+            let entity_ptr = es.get_entity(new_world_id, ids[0]);
+            assert!(entity_ptr.read().is_in_archetype());
+        }
+
+        es.step_world(0.0, 0.0, new_world_id); // Run local systems chunk by chunk
+
+        // Entities are created at the end of the first stage, so they already ran once
+        // in the creation step
+        let run_systems = |num: u32| (num + 1) * 2;
+        for (i, &id) in ids.iter().enumerate() {
+            let entity_ptr = es.get_entity(new_world_id, id);
+            let entity = entity_ptr.read();
+            let num = entity.get_datagroup::<TestNumberDataGroup>().unwrap().num;
+            assert_eq!(num, run_systems(run_systems(i as u32)));
+        }
+
+        // The last entity fills the gap left by the first one
+        es.destroy_entity(new_world_id, ids[0]);
+        es.step_world(0.0, 0.0, new_world_id); // Process destruction and run again
+
+        {
+            let last_ptr = es.get_entity(new_world_id, *ids.last().unwrap());
+            let last = last_ptr.read();
+            let num = last.get_datagroup::<TestNumberDataGroup>().unwrap().num;
+            assert_eq!(
+                num,
+                run_systems(run_systems(run_systems(ENTITIES_NUM - 1)))
+            );
+        }
+
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            let mut visited = 0;
+            world.for_each_chunk_column::<TestNumberDataGroup, _>(|chunk_ids, values| {
+                assert_eq!(chunk_ids.len(), values.len());
+                assert!(!chunk_ids.contains(&ids[0]));
+                visited += values.len();
+            });
+            assert_eq!(visited, ENTITIES_NUM as usize - 1);
+        }
+
+        es.destroy_world(new_world_id);
+    }
+
+    #[test]
+    fn test_archetype_global_system_flow() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world_with_storage(StorageMode::Archetype);
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+
+        // More than a chunk worth of entities stored in archetypes
+        const ENTITIES_NUM: usize = 300;
+        let mut ids: Vec<_> = (0..ENTITIES_NUM)
+            .map(|_| {
+                let mut spawn_desc = EntitySpawnDescription::default();
+                GSFlowDG::prepare_spawn(&mut spawn_desc);
+                GSFlowTester::simple_prepare(&mut spawn_desc);
+                es.create_entity(new_world_id, spawn_desc)
+                    .expect("Creation should be successful")
+            })
+            .collect();
+
+        // A spatial entity keeps its datagroups boxed
+        let mut spawn_desc = EntitySpawnDescription::default();
+        GSFlowDG::prepare_spawn(&mut spawn_desc);
+        Transform::prepare_spawn(&mut spawn_desc, Box::default());
+        GSFlowTester::simple_prepare(&mut spawn_desc);
+        ids.push(
+            es.create_entity(new_world_id, spawn_desc)
+                .expect("Creation should be successful"),
+        );
+
+        es.step_world(0.0, 0.0, new_world_id); // Process creation
+        es.step_world(0.0, 0.0, new_world_id);
+
+        let world = es.get_worlds().get(&new_world_id).unwrap();
+        {
+            let global_systems_lock = world.get_global_systems().read();
+            let gs_storage_lock = global_systems_lock[get_id!(GSFlowTester) as usize]
+                .as_ref()
+                .expect("This global system should have storage loaded right")
+                .read();
+
+            let gs_storage: &GSFlowTester = cast(&*gs_storage_lock);
+            assert_eq!(gs_storage.n_entities, ENTITIES_NUM + 1);
+        }
+
+        // Every entity should be visited exactly once
+        let entities = world.get_entities();
+        let mut visited: Vec<usize> = ids
+            .iter()
+            .map(|id| {
+                entities
+                    .get(id)
+                    .unwrap()
+                    .read()
+                    .get_datagroup::<GSFlowDG>()
+                    .unwrap()
+                    .id
+            })
+            .collect();
+        visited.sort_unstable();
+        assert_eq!(visited, (1..=ENTITIES_NUM + 1).collect::<Vec<_>>());
+
+        es.destroy_world(new_world_id);
+    }
+
+    #[test]
+    fn test_move_entity_between_worlds() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let source_world_id = es.create_world();
+        let target_world_id = es.create_world();
+        es.step_world(0.0, 0.0, source_world_id); // Process world creation
+
+        let mut root_desc = EntitySpawnDescription::default();
+        Transform::prepare_spawn(&mut root_desc, Box::default());
+        WhenRequiredGS::simple_prepare(&mut root_desc);
+        let root_id = es
+            .create_entity(source_world_id, root_desc)
+            .expect("Creation should be successful");
+
+        let mut child_desc = EntitySpawnDescription::default();
+        Transform::prepare_spawn(&mut child_desc, Box::default());
+        TestNumberDataGroup::prepare_spawn(
+            &mut child_desc,
+            Box::new(TestNumberDataGroupArg { num: 3 }),
+        );
+        let child_id = es
+            .create_entity(source_world_id, child_desc)
+            .expect("Creation should be successful");
+
+        let other_id = es
+            .create_entity(source_world_id, EntitySpawnDescription::default())
+            .expect("Creation should be successful");
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&source_world_id).unwrap();
+            world.set_entity_parent(child_id, root_id);
+        }
+        es.step_world(0.0, 0.0, source_world_id); // Process entity creation
+
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&source_world_id).unwrap();
+            world.add_relation::<Targets>(other_id, root_id);
+        }
+
+        es.move_entity(source_world_id, root_id, target_world_id);
+        es.step_world(0.0, 0.0, target_world_id); // Process the move
+        es.step_world(0.0, 0.0, target_world_id); // Process global system loading
+
+        {
+            let worlds = es.get_world_map();
+            let source_world = worlds.get(&source_world_id).unwrap();
+            let target_world = worlds.get(&target_world_id).unwrap();
+
+            // The whole hierarchy moved, keeping its ids
+            assert!(!source_world.get_entities().contains_key(&root_id));
+            assert!(!source_world.get_entities().contains_key(&child_id));
+            assert!(source_world.get_entities().contains_key(&other_id));
+            assert!(target_world.get_entities().contains_key(&root_id));
+            assert!(target_world.get_entities().contains_key(&child_id));
+
+            // Relations don't cross worlds
+            assert!(source_world.related::<Targets>(other_id).is_empty());
+
+            assert!(
+                target_world.global_system_is_loaded::<WhenRequiredGS>(),
+                "WhenRequired global system should be loaded by the moved entity"
+            );
+        }
+
+        {
+            let root_ptr = es.get_entity(target_world_id, root_id);
+            let root = root_ptr.read();
+            assert!(root.is_root());
+            assert_eq!(root.get_transform().unwrap().n_nodes, 2);
+
+            let child_ptr = es.get_entity(target_world_id, child_id);
+            let child = child_ptr.read();
+            assert!(!child.is_root());
+            assert_eq!(child.get_datagroup::<TestNumberDataGroup>().unwrap().num, 3);
+        }
+
+        es.destroy_world(source_world_id);
+        es.destroy_world(target_world_id);
+    }
+
+    #[test]
+    fn test_move_child_entity_between_worlds() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let source_world_id = es.create_world();
+        let target_world_id = es.create_world();
+        es.step_world(0.0, 0.0, source_world_id); // Process world creation
+
+        let mut root_desc = EntitySpawnDescription::default();
+        Transform::prepare_spawn(&mut root_desc, Box::default());
+        let root_id = es
+            .create_entity(source_world_id, root_desc)
+            .expect("Creation should be successful");
+
+        // Only the child has systems to run, so the root is in the stage list because of it
+        let mut child_desc = EntitySpawnDescription::default();
+        Transform::prepare_spawn(&mut child_desc, Box::default());
+        TestNumberDataGroup::prepare_spawn(
+            &mut child_desc,
+            Box::new(TestNumberDataGroupArg { num: 0 }),
+        );
+        TestAdder::simple_prepare(&mut child_desc);
+        let child_id = es
+            .create_entity(source_world_id, child_desc)
+            .expect("Creation should be successful");
+
+        let mut grandchild_desc = EntitySpawnDescription::default();
+        Transform::prepare_spawn(&mut grandchild_desc, Box::default());
+        let grandchild_id = es
+            .create_entity(source_world_id, grandchild_desc)
+            .expect("Creation should be successful");
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&source_world_id).unwrap();
+            world.set_entity_parent(child_id, root_id);
+            world.set_entity_parent(grandchild_id, child_id);
+        }
+        es.step_world(0.0, 0.0, source_world_id); // Process entity creation
+
+        let root_ptr = es.get_entity(source_world_id, root_id);
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&source_world_id).unwrap();
+            assert_eq!(&*world.get_entities().read_stage(0), &[root_ptr]);
+
+            // Commands still queued for the moved entities
+            world.clear_entity_parent(grandchild_id);
+            world.set_entity_parent(child_id, root_id);
+        }
+
+        es.move_entity(source_world_id, child_id, target_world_id);
+        es.step_world(0.0, 0.0, target_world_id); // Process the move
+        es.step_world(0.0, 0.0, source_world_id); // Process the commands left in the source world
+
+        {
+            let worlds = es.get_world_map();
+            let source_world = worlds.get(&source_world_id).unwrap();
+            let target_world = worlds.get(&target_world_id).unwrap();
+
+            assert!(source_world.get_entities().contains_key(&root_id));
+            assert!(!source_world.get_entities().contains_key(&child_id));
+            assert!(!source_world.get_entities().contains_key(&grandchild_id));
+            assert!(target_world.get_entities().contains_key(&child_id));
+            assert!(target_world.get_entities().contains_key(&grandchild_id));
+
+            // The root has nothing to run anymore
+            assert!(source_world.get_entities().read_stage(0).is_empty());
+        }
+
+        {
+            let root = root_ptr.read();
+            assert_eq!(root.get_transform().unwrap().n_nodes, 1);
+            assert!(root.get_transform().unwrap().children.is_empty());
+
+            // The child is a root in the target world, and keeps running there:
+            // once in the creation step, and once in the target world.
+            // The grandchild was detached by the forwarded command
+            let child_ptr = es.get_entity(target_world_id, child_id);
+            let child = child_ptr.read();
+            assert!(child.is_root());
+            assert_eq!(child.get_transform().unwrap().n_nodes, 1);
+            assert_eq!(child.get_datagroup::<TestNumberDataGroup>().unwrap().num, 2);
+
+            let grandchild_ptr = es.get_entity(target_world_id, grandchild_id);
+            assert!(grandchild_ptr.read().is_root());
+        }
+
+        es.destroy_world(source_world_id);
+        es.destroy_world(target_world_id);
+    }
+
+    #[test]
+    fn test_move_entity_missing_global_system() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let source_world_id = es.create_world();
+        let target_world_id = es.create_world();
+        es.step_world(0.0, 0.0, source_world_id); // Process world creation
+
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&source_world_id).unwrap();
+            world.load_global_system::<ManualLifetimeGS>();
+        }
+        es.step_world(0.0, 0.0, source_world_id); // Process GS creation
+
+        let mut spawn_desc = EntitySpawnDescription::default();
+        ManualLifetimeGS::simple_prepare(&mut spawn_desc);
+        let entity_id = es
+            .create_entity(source_world_id, spawn_desc)
+            .expect("Creation should be successful");
+        es.step_world(0.0, 0.0, source_world_id); // Process entity creation
+
+        // The target world doesn't have the global system, so the entity stays where it is
+        es.move_entity(source_world_id, entity_id, target_world_id);
+        es.step_world(0.0, 0.0, target_world_id); // Process the move
+
+        {
+            let worlds = es.get_world_map();
+            let source_world = worlds.get(&source_world_id).unwrap();
+            let target_world = worlds.get(&target_world_id).unwrap();
+            assert!(source_world.get_entities().contains_key(&entity_id));
+            assert!(!target_world.get_entities().contains_key(&entity_id));
+        }
+
+        es.destroy_world(source_world_id);
+        es.destroy_world(target_world_id);
+    }
+
+    struct Owns;
+    impl Relation for Owns {
+        const ON_DESTROY: DestroyPolicy = DestroyPolicy::Cascade;
+    }
+
+    struct Targets;
+    impl Relation for Targets {
+        const ON_DESTROY: DestroyPolicy = DestroyPolicy::Clear;
+    }
+
+    struct LastHitBy;
+    impl Relation for LastHitBy {
+        const ON_DESTROY: DestroyPolicy = DestroyPolicy::Keep;
+    }
+
+    #[test]
+    fn test_entity_duplication_not_cloneable() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world();
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+
+        let mut root_desc = EntitySpawnDescription::default();
+        root_desc.set_name("Root entity".into());
+        Transform::prepare_spawn(&mut root_desc, Box::default());
+        let root_id = es
+            .create_entity(new_world_id, root_desc)
+            .expect("Creation should be successful");
+
+        let mut node_desc = EntitySpawnDescription::default();
+        node_desc.set_name("Node entity".into());
+        Transform::prepare_spawn(&mut node_desc, Box::default());
+        MeshDataGroup::prepare_spawn(&mut node_desc);
+        let node_id = es
+            .create_entity(new_world_id, node_desc)
+            .expect("Creation should be successful");
+
+        // Entities that didn't spawn yet can't be duplicated
+        assert!(matches!(
+            es.duplicate_entity(new_world_id, root_id, false),
+            Err(EntitySystemError::InvalidDuplication(
+                DuplicationError::EntityNotFound(id)
+            )) if id == root_id
+        ));
+
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            world.set_entity_parent(node_id, root_id);
+        }
+        es.step_world(0.0, 0.0, new_world_id); // Process entity creation
+
+        // The node, or the root with its children, can't be copied
+        let expected_err = DuplicationError::NotCloneable {
+            entity_name: "Node entity".into(),
+            datagroup: "MeshDataGroup",
+        };
+        for (id, recursive) in [(node_id, false), (root_id, true)] {
+            match es.duplicate_entity(new_world_id, id, recursive) {
+                Err(EntitySystemError::InvalidDuplication(err)) => assert_eq!(err, expected_err),
+                other => panic!("Duplication should fail, got {other:?}"),
+            }
+        }
+
+        // The root alone can still be copied
+        es.duplicate_entity(new_world_id, root_id, false)
+            .expect("Duplication should be successful");
+        es.step_world(0.0, 0.0, new_world_id); // Process duplication
+
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            assert_eq!(world.get_entities().len(), 3);
+        }
+
+        es.destroy_world(new_world_id);
+    }
+
+    #[test]
+    fn test_relationships() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world();
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+
+        let mut ids = vec![];
+        for _ in 0..4 {
+            let id = es
+                .create_entity(new_world_id, EntitySpawnDescription::default())
+                .expect("Creation should be successful");
+            ids.push(id);
+        }
+        let (player, sword, shield, enemy) = (ids[0], ids[1], ids[2], ids[3]);
+        es.step_world(0.0, 0.0, new_world_id); // Process entity creation
+
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            assert!(world.add_relation::<Owns>(player, sword));
+            assert!(world.add_relation::<Owns>(player, shield));
+            assert!(!world.add_relation::<Owns>(player, shield));
+            assert!(world.add_relation::<Targets>(enemy, player));
+            assert!(world.add_relation::<LastHitBy>(enemy, player));
+
+            assert_eq!(world.related::<Owns>(player), vec![sword, shield]);
+            assert_eq!(world.related_reverse::<Owns>(sword), vec![player]);
+            assert!(world.has_relation::<Targets>(enemy, player));
+            assert!(!world.has_relation::<Targets>(player, enemy));
+
+            assert!(world.remove_relation::<Owns>(player, shield));
+            assert!(world.related_reverse::<Owns>(shield).is_empty());
+        }
+
+        // Destroying the owner destroys the owned entities, clears `Targets` and keeps `LastHitBy`
+        es.destroy_entity(new_world_id, player);
+        es.step_world(0.0, 0.0, new_world_id);
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            let entities = world.get_entities();
+            assert!(!entities.contains_key(&player));
+            assert!(!entities.contains_key(&sword));
+            assert!(entities.contains_key(&shield));
+            assert!(entities.contains_key(&enemy));
+
+            assert!(world.related::<Owns>(player).is_empty());
+            assert!(world.related::<Targets>(enemy).is_empty());
+            assert_eq!(world.related::<LastHitBy>(enemy), vec![player]);
+
+            // Destroyed entities can't be related anymore
+            assert!(!world.add_relation::<Owns>(player, shield));
+            assert!(!world.add_relation::<Targets>(enemy, player));
+            assert!(world.related::<Owns>(player).is_empty());
+        }
+
+        es.destroy_world(new_world_id);
+    }
+
+    #[test]
+    fn test_long_cascade_destruction() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world();
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+
+        // Long enough to overflow the stack if cascades were destroyed recursively
+        const CHAIN_LEN: usize = 10_000;
+        let ids: Vec<EntityID> = (0..CHAIN_LEN)
+            .map(|_| {
+                es.create_entity(new_world_id, EntitySpawnDescription::default())
+                    .expect("Creation should be successful")
+            })
+            .collect();
+        es.step_world(0.0, 0.0, new_world_id); // Process entity creation
+
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            for pair in ids.windows(2) {
+                world.add_relation::<Owns>(pair[0], pair[1]);
+            }
+        }
+
+        es.destroy_entity(new_world_id, ids[0]);
+        es.step_world(0.0, 0.0, new_world_id);
+
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            assert!(world.get_entities().is_empty());
+        }
+
+        es.destroy_world(new_world_id);
+    }
+
+    #[test]
+    fn test_create_entity_with_reserved_id() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world();
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+
+        // Ids reserved up front are never handed out by the allocator
+        let reserved = EntityIDAllocator::get_global().reserve(2).unwrap();
+        let auto_id = es
+            .create_entity(new_world_id, EntitySpawnDescription::default())
+            .expect("Creation should be successful");
+        assert!(!reserved.contains(&auto_id));
+
+        es.create_entity_with_id(
+            new_world_id,
+            reserved.start,
+            EntitySpawnDescription::default(),
+        )
+        .expect("Creation should be successful");
+        // Creating twice with the same id fails without spawning a second entity
+        es.create_entity_with_id(
+            new_world_id,
+            reserved.start,
+            EntitySpawnDescription::default(),
+        )
+        .expect("Creation should be successful");
+        es.step_world(0.0, 0.0, new_world_id); // Process entity creation
+
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            assert!(world.get_entities().contains_key(&reserved.start));
+            assert!(world.get_entities().contains_key(&auto_id));
+            assert_eq!(world.get_entities().len(), 2);
+
+            // Ids with the same index but another generation don't match the entity
+            let stale_id = make_entity_id(entity_id_index(reserved.start), 1);
+            assert!(!world.get_entities().contains_key(&stale_id));
+            assert!(world.get_entity_ref(stale_id).is_null());
+        }
+
+        es.destroy_world(new_world_id);
+    }
+
+    #[test]
+    fn test_world_pause() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world();
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+
+        let mut spawn_desc = EntitySpawnDescription::default();
+        TestNumberDataGroup::prepare_spawn(
+            &mut spawn_desc,
+            Box::new(TestNumberDataGroupArg { num: 0 }),
+        );
+        TestAdder::simple_prepare(&mut spawn_desc);
+        let entity_id = es
+            .create_entity(new_world_id, spawn_desc)
+            .expect("Creation should be successful");
+        es.step_world(0.0, 0.0, new_world_id); // Process creation and run the adder once
+
+        let get_num = || {
+            es.get_entity(new_world_id, entity_id)
+                .read()
+                .get_datagroup::<TestNumberDataGroup>()
+                .unwrap()
+                .num
+        };
+        assert_eq!(get_num(), 1);
+
+        let pause = |paused: bool| {
+            es.with_world(new_world_id, |world| world.set_paused(paused))
+                .expect("World should exist");
+        };
+        let request_step = || {
+            es.with_world(new_world_id, |world| world.request_step())
+                .expect("World should exist");
+        };
+
+        // Paused worlds skip their steps
+        pause(true);
+        es.step_world(0.0, 0.0, new_world_id);
+        es.step_world(0.0, 0.0, new_world_id);
+        assert_eq!(get_num(), 1);
+
+        // Each requested step runs once
+        request_step();
+        request_step();
+        es.step_world(0.0, 0.0, new_world_id);
+        es.step_world(0.0, 0.0, new_world_id);
+        es.step_world(0.0, 0.0, new_world_id);
+        assert_eq!(get_num(), 3);
+
+        // Resuming forgets the pending steps, and steps are ignored when not paused
+        request_step();
+        pause(false);
+        request_step();
+        es.step_world(0.0, 0.0, new_world_id);
+        assert_eq!(get_num(), 4);
+        pause(true);
+        es.step_world(0.0, 0.0, new_world_id);
+        assert_eq!(get_num(), 4);
+
+        es.destroy_world(new_world_id);
+    }
+
+    #[test]
+    fn test_profiling() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world();
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+
+        for _ in 0..3 {
+            let mut spawn_desc = EntitySpawnDescription::default();
+            TestNumberDataGroup::prepare_spawn(
+                &mut spawn_desc,
+                Box::new(TestNumberDataGroupArg { num: 0 }),
+            );
+            TestAdder::simple_prepare(&mut spawn_desc);
+            es.create_entity(new_world_id, spawn_desc)
+                .expect("Creation should be successful");
+        }
+        es.step_world(0.0, 0.0, new_world_id); // Process entity creation
+
+        Profiler::set_enabled(true);
+        es.step_world(0.0, 0.0, new_world_id);
+        {
+            let _scope = ProfileScope::new("test_profiling \"scope\"", "test");
+        }
+        Profiler::set_enabled(false);
+
+        let mut trace = Vec::new();
+        Profiler::write_chrome_trace_to(&mut trace).unwrap();
+        let trace = String::from_utf8(trace).unwrap();
+
+        assert!(trace.starts_with("{\"traceEvents\":["));
+        assert!(trace.contains("\"name\":\"World::run_stage\""));
+        assert!(trace.contains(&format!("\"world\":{new_world_id}")));
+        // Local systems are reported by the entity pass, added up over all the entities
+        assert!(trace.contains("\"name\":\"Entities\""));
+        assert!(trace.contains("\"TestAdder entities\":3"));
+        assert!(trace.contains("\"name\":\"test_profiling \\\"scope\\\"\""));
+        assert!(trace.contains("\"name\":\"thread_name\""));
+
+        es.destroy_world(new_world_id);
+    }
+
+    #[test]
+    fn test_spawn_validation() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let invalid_spawn_desc = || {
+            let mut spawn_desc = EntitySpawnDescription::default();
+            spawn_desc.set_name("Invalid".to_string());
+            TestAdder::simple_prepare(&mut spawn_desc); // TestNumberDataGroup is left uninitialized
+            spawn_desc.add_datagroup::<AnimationDataGroup>(DataGroupInitType::NoInit);
+            gs_Test::simple_prepare(&mut spawn_desc); // Requires MeshDataGroup, which is missing
+            spawn_desc.add_local_system_by_id(SystemClassID::MAX);
+            spawn_desc
+        };
+
+        // Every problem is reported, not just the first one
+        let err = invalid_spawn_desc().validate().unwrap_err();
+        assert_eq!(err.entity_name, "Invalid");
+        assert_eq!(err.problems.len(), 4, "{err}");
+        let has_problem = |check: fn(&SpawnProblem) -> bool| err.problems.iter().any(check);
+        assert!(has_problem(|problem| matches!(
+            problem,
+            SpawnProblem::UninitializedDataGroup { .. }
+        )));
+        assert!(has_problem(|problem| matches!(
+            problem,
+            SpawnProblem::WrongInitStyle {
+                expected: "Arg",
+                found: "NoInit",
+                ..
+            }
+        )));
+        assert!(has_problem(|problem| matches!(
+            problem,
+            SpawnProblem::MissingGlobalSystemDependency { .. }
+        )));
+        assert!(has_problem(|problem| matches!(
+            problem,
+            SpawnProblem::UnknownLocalSystem(SystemClassID::MAX)
+        )));
+        assert!(err.to_string().starts_with("Can't spawn entity 'Invalid':"));
+
+        // Invalid entities are rejected without being queued
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world();
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+        assert!(matches!(
+            es.try_create_entity(new_world_id, invalid_spawn_desc()),
+            Err(EntitySystemError::InvalidSpawn(_))
+        ));
+
+        let mut spawn_desc = EntitySpawnDescription::default();
+        TestNumberDataGroup::prepare_spawn(
+            &mut spawn_desc,
+            Box::new(TestNumberDataGroupArg { num: 0 }),
+        );
+        TestAdder::simple_prepare(&mut spawn_desc);
+        let entity_id = es
+            .try_create_entity(new_world_id, spawn_desc)
+            .expect("The spawn description is valid");
+        es.step_world(0.0, 0.0, new_world_id); // Process entity creation
+
+        {
+            let worlds = es.get_world_map();
+            let world = worlds.get(&new_world_id).unwrap();
+            assert_eq!(world.get_entities().len(), 1);
+            assert!(world.get_entities().contains_key(&entity_id));
+        }
+
+        es.destroy_world(new_world_id);
+    }
+
+    #[test]
+    fn test_world_schedule() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let es = EntitySystem::get();
+        let new_world_id = es.create_world();
+        es.step_world(0.0, 0.0, new_world_id); // Process world creation
+
+        for _ in 0..3 {
+            let mut spawn_desc = EntitySpawnDescription::default();
+            TestNumberDataGroup::prepare_spawn(
+                &mut spawn_desc,
+                Box::new(TestNumberDataGroupArg { num: 0 }),
+            );
+            TestAdder::simple_prepare(&mut spawn_desc);
+            es.create_entity(new_world_id, spawn_desc).unwrap();
+        }
+        es.step_world(0.0, 0.0, new_world_id); // Process entity creation
+
+        let schedule = WorldSchedule::from_world_id(new_world_id).unwrap();
+        assert_eq!(schedule.world_id, new_world_id);
+        let stage_0 = schedule
+            .stages
+            .iter()
+            .find(|stage| stage.stage == 0)
+            .unwrap();
+        assert_eq!(stage_0.entity_count, 3);
+        for stage in schedule.stages.iter() {
+            for global_system in stage.global_systems.iter() {
+                assert!(schedule.loaded_global_systems.contains(global_system));
+            }
+        }
+
+        let mut json = Vec::new();
+        schedule.write_json_to(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains("{\"stage\":0,\"entity_count\":3,"));
+
+        es.destroy_world(new_world_id);
+    }
 }
@@ -5,10 +5,10 @@ use proto_ecs::entities::entity_allocator::*;
 #[test]
 fn test_allocation() {
     if !App::is_initialized() {
-        App::initialize();
+        App::initialize().expect("Failed to initialize the app");
     }
 
-    let mut alloc = EntityAllocator::new();
+    let alloc = EntityAllocator::new();
     let mut entity_ptr = alloc.allocate();
     let mut spawn_desc = EntitySpawnDescription::default();
     spawn_desc.set_name("hello".to_owned());
@@ -29,10 +29,10 @@ fn test_allocation() {
 #[test]
 fn test_free() {
     if !App::is_initialized() {
-        App::initialize();
+        App::initialize().expect("Failed to initialize the app");
     }
 
-    let mut alloc = EntityAllocator::new();
+    let alloc = EntityAllocator::new();
     let entity_ptr = alloc.allocate();
 
     // Check that you can free without initializing
@@ -54,10 +54,10 @@ fn test_free() {
 #[should_panic]
 fn test_panic_use_after_free() {
     if !App::is_initialized() {
-        App::initialize();
+        App::initialize().expect("Failed to initialize the app");
     }
 
-    let mut alloc = EntityAllocator::new();
+    let alloc = EntityAllocator::new();
     let mut entity_ptr = alloc.allocate();
     let spawn_desc = EntitySpawnDescription::default();
 
@@ -65,3 +65,90 @@ fn test_panic_use_after_free() {
     alloc.free(&entity_ptr);
     entity_ptr.read().get_id();
 }
+
+#[test]
+fn test_checked_access() {
+    if !App::is_initialized() {
+        App::initialize().expect("Failed to initialize the app");
+    }
+
+    let alloc = EntityAllocator::new();
+    let mut entity_ptr = alloc.allocate();
+
+    // Uninitialized entities can't be accessed
+    assert!(entity_ptr.try_read().is_none());
+
+    entity_ptr.init(420, EntitySpawnDescription::default());
+    assert_eq!(entity_ptr.try_read().unwrap().get_id(), 420);
+    assert!(entity_ptr.try_write().is_some());
+
+    // Stale pointers are detected, even if their memory is reused. Free entries are
+    // reused in order, so the freed one comes back after the rest of its chunk
+    alloc.free(&entity_ptr);
+    let _rest_of_chunk: Vec<EntityPtr> = (1..EntityAllocator::CHUNK_LEN)
+        .map(|_| alloc.allocate())
+        .collect();
+    let mut new_ptr = alloc.allocate();
+    new_ptr.init(421, EntitySpawnDescription::default());
+    assert!(entity_ptr.try_read().is_none());
+    assert!(entity_ptr.try_write().is_none());
+    assert_eq!(new_ptr.try_read().unwrap().get_id(), 421);
+
+    // Formatting a stale pointer reports what is in its slot instead of reading it
+    let debug = format!("{entity_ptr:?}");
+    assert!(debug.contains("slot_entity_id: 421"), "{debug}");
+}
+
+#[test]
+fn test_trim_and_stats() {
+    if !App::is_initialized() {
+        App::initialize().expect("Failed to initialize the app");
+    }
+
+    let mut alloc = EntityAllocator::new();
+    assert_eq!(alloc.stats(), EntityAllocatorStats::default());
+
+    // Fill two chunks and a bit of a third one
+    let count = EntityAllocator::CHUNK_LEN * 2 + 10;
+    let mut ptrs: Vec<EntityPtr> = (0..count).map(|_| alloc.allocate()).collect();
+    for (i, ptr) in ptrs.iter_mut().enumerate() {
+        ptr.init(i as u64 + 1, EntitySpawnDescription::default());
+    }
+
+    let stats = alloc.stats();
+    assert_eq!(stats.live_entries, count);
+    assert_eq!(stats.chunks, 3);
+    assert_eq!(stats.total_entries, EntityAllocator::CHUNK_LEN * 3);
+    assert_eq!(stats.free_entries, stats.total_entries - count);
+    assert!(stats.total_bytes >= stats.live_bytes);
+
+    // Nothing to release while every chunk has live entities
+    assert_eq!(alloc.trim(), 0);
+
+    // Free everything except the last entity, which keeps its chunk alive
+    let last = ptrs.pop().unwrap();
+    for ptr in ptrs.iter() {
+        alloc.free(ptr);
+    }
+    assert_eq!(alloc.trim(), 2);
+
+    let stats = alloc.stats();
+    assert_eq!(stats.live_entries, 1);
+    assert_eq!(stats.chunks, 1);
+    assert_eq!(stats.trimmed_chunks, 2);
+    assert_eq!(stats.total_entries, EntityAllocator::CHUNK_LEN);
+
+    // Stale pointers to trimmed chunks are still detected, and the live entity is untouched
+    assert!(!ptrs[0].is_live());
+    assert!(ptrs[0].try_read().is_none());
+    assert_eq!(last.read().get_id(), count as u64);
+
+    // Trimmed chunks are reused when allocating again
+    let new_ptrs: Vec<EntityPtr> = (0..EntityAllocator::CHUNK_LEN * 2)
+        .map(|_| alloc.allocate())
+        .collect();
+    let stats = alloc.stats();
+    assert_eq!(stats.chunks, 3);
+    assert_eq!(stats.trimmed_chunks, 0);
+    assert_eq!(stats.live_entries, new_ptrs.len() + 1);
+}
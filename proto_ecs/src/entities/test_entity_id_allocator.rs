@@ -0,0 +1,87 @@
+use crate::entities::entity::*;
+use crate::entities::entity_id_allocator::*;
+
+#[test]
+fn test_id_allocation() {
+    let alloc = EntityIDAllocator::new();
+
+    let ids: Vec<EntityID> = (0..1000).map(|_| alloc.allocate()).collect();
+    for (i, id) in ids.iter().enumerate() {
+        assert_ne!(*id, INVALID_ENTITY_ID);
+        assert_eq!(entity_id_generation(*id), 0);
+        assert!(!ids[..i].contains(id), "Ids should be unique");
+    }
+
+    // Without recycling, ids are never handed out again
+    alloc.deallocate(ids[0]);
+    assert!((0..100).all(|_| alloc.allocate() != ids[0]));
+}
+
+#[test]
+fn test_id_allocation_threads() {
+    let alloc = EntityIDAllocator::new();
+
+    let mut ids: Vec<EntityID> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|_| scope.spawn(|| (0..1000).map(|_| alloc.allocate()).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    let len = ids.len();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), len, "Ids should be unique across threads");
+}
+
+#[test]
+fn test_id_recycling() {
+    let alloc = EntityIDAllocator::new();
+    alloc.set_recycling(true);
+
+    let id = alloc.allocate();
+    alloc.deallocate(id);
+    let recycled = alloc.allocate();
+    assert_ne!(recycled, id);
+    assert_eq!(entity_id_index(recycled), entity_id_index(id));
+    assert_eq!(entity_id_generation(recycled), entity_id_generation(id) + 1);
+
+    // Indices that run out of generations are retired
+    let last = make_entity_id(entity_id_index(recycled), MAX_ENTITY_GENERATION);
+    alloc.deallocate(last);
+    assert_ne!(entity_id_index(alloc.allocate()), entity_id_index(id));
+}
+
+#[test]
+fn test_id_reservation() {
+    let server = EntityIDAllocator::new();
+    let client = EntityIDAllocator::new();
+
+    let range = server.reserve(100).unwrap();
+    assert_eq!(range.end - range.start, 100);
+    assert!((0..500).all(|_| !range.contains(&server.allocate())));
+
+    // The client already handed out some ids that overlap with the server range
+    client.allocate();
+    assert!(matches!(
+        client.reserve_range(range.clone()),
+        Err(EntityIDError::AlreadyAllocated { .. })
+    ));
+
+    // A range past everything the client handed out is fine
+    let range = server.reserve(100).unwrap();
+    client.reserve_range(range.clone()).unwrap();
+    assert!((0..500).all(|_| !range.contains(&client.allocate())));
+    assert!(matches!(
+        client.reserve_range(range),
+        Err(EntityIDError::AlreadyAllocated { .. })
+    ));
+
+    assert!(matches!(
+        client.reserve_range(INVALID_ENTITY_ID..10),
+        Err(EntityIDError::InvalidRange(_))
+    ));
+}
@@ -0,0 +1,60 @@
+use crate::entities::entity_index::*;
+use proto_ecs::entities::entity_allocator::*;
+
+#[test]
+fn test_index_insert_remove() {
+    let alloc = EntityAllocator::new();
+    let index = EntityIndex::new();
+
+    // Ids far apart live in different sparse pages
+    let ids = [1, 2, 3, 5_000, 1_000_000];
+    let ptrs: Vec<EntityPtr> = ids.iter().map(|_| alloc.allocate()).collect();
+    for (&id, &ptr) in ids.iter().zip(ptrs.iter()) {
+        assert!(index.insert(id, ptr));
+    }
+    assert!(!index.insert(1, ptrs[1]), "Ids should be unique");
+    assert_eq!(index.len(), ids.len());
+
+    // Removing swaps the last entity in, which should still be found
+    assert_eq!(index.remove(&1), Some(ptrs[0]));
+    assert_eq!(index.remove(&1), None);
+    assert!(!index.contains_key(&1));
+    for (id, ptr) in ids.iter().zip(ptrs.iter()).skip(1) {
+        assert_eq!(index.get(id), Some(*ptr));
+    }
+    assert_eq!(index.len(), ids.len() - 1);
+    assert_eq!(index.read_all().len(), ids.len() - 1);
+}
+
+#[test]
+fn test_index_stages() {
+    let alloc = EntityAllocator::new();
+    let index = EntityIndex::new();
+
+    let ids = [10, 11, 12, 13];
+    let ptrs: Vec<EntityPtr> = ids.iter().map(|_| alloc.allocate()).collect();
+    for (&id, &ptr) in ids.iter().zip(ptrs.iter()) {
+        index.insert(id, ptr);
+        index.add_to_stage(0, id);
+        index.add_to_stage(0, id); // Adding twice does nothing
+    }
+    index.add_to_stage(3, 12);
+    assert_eq!(index.read_stage(0).len(), ids.len());
+    assert_eq!(&*index.read_stage(3), &[ptrs[2]]);
+
+    // Removing from a stage swaps the last one in, which should still be removable
+    index.remove_from_stage(0, 10);
+    index.remove_from_stage(0, 10);
+    assert_eq!(index.read_stage(0).len(), 3);
+    index.remove_from_stage(0, 13);
+    assert_eq!(&*index.read_stage(0), &[ptrs[2], ptrs[1]]);
+
+    // Removing from the index removes it from every stage
+    index.remove(&12);
+    assert_eq!(&*index.read_stage(0), &[ptrs[1]]);
+    assert!(index.read_stage(3).is_empty());
+
+    index.remove_from_all_stages(11);
+    assert!(index.read_stage(0).is_empty());
+    assert!(index.contains_key(&11));
+}
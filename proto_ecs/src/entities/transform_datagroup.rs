@@ -3,14 +3,15 @@ use ecs_macros::{register_datagroup, CanCast};
 /// A Transform datagroup that represents the spatial information about an entity and
 /// its spatial relationships to other entities.
 ///
-/// This is the only datagroup allowed to have references to other entities,
-/// and those reference are strictly controlled.
+/// This is the only datagroup allowed to have raw pointers to other entities,
+/// and those reference are strictly controlled. Other datagroups should use
+/// [crate::entities::entity_ref::EntityRef] instead.
 ///
 /// Users should not have access to this datagroup.
 use std::sync::atomic::AtomicUsize;
 
 use crate::{
-    data_group::{DataGroup, GenericDataGroupInitArgTrait},
+    data_group::GenericDataGroupInitArgTrait,
     entities::entity_allocator::EntityPtr,
     systems::common::STAGE_COUNT,
 };
@@ -61,7 +62,12 @@ pub struct Transform {
 }
 
 impl GenericDataGroupInitArgTrait for Transform {}
-register_datagroup!(Transform, factory, init_style = Arg(Transform));
+register_datagroup!(
+    Transform,
+    factory,
+    init_style = Arg(Transform),
+    cloneable = true
+);
 
 impl TransformDesc for Transform {
     fn init(&mut self, init_data: Box<Transform>) {
@@ -86,6 +92,18 @@ impl Transform {
         self.parent.is_none()
     }
 
+    /// Parent of this node in its spatial hierarchy, if any
+    #[inline(always)]
+    pub fn get_parent(&self) -> Option<EntityPtr> {
+        self.parent
+    }
+
+    /// Direct children of this node in its spatial hierarchy
+    #[inline(always)]
+    pub fn get_children(&self) -> &[EntityPtr] {
+        &self.children
+    }
+
     /// Set the local transform matrix
     #[inline(always)]
     pub fn get_local_transform_mat(&self) -> TransformMatrix {
@@ -172,8 +190,25 @@ impl Transform {
     }
 }
 
-fn factory() -> Box<dyn DataGroup> {
-    Box::<Transform>::default()
+fn factory() -> Transform {
+    Transform::default()
+}
+
+/// Cloning a transform keeps its local and world position, but not its place
+/// in the hierarchy: the copy has no parent nor children, and its stage counts
+/// are zeroed so the owning entity can initialize them
+impl Clone for Transform {
+    fn clone(&self) -> Self {
+        Self {
+            cached_parent_world_transform: self.cached_parent_world_transform,
+            cached_inverse_parent_world_transform: self.cached_inverse_parent_world_transform,
+            cached_world_position: self.cached_world_position,
+            local_position: self.local_position,
+            local_rotation: self.local_rotation,
+            local_scale: self.local_scale,
+            ..Default::default()
+        }
+    }
 }
 
 impl Default for Transform {
@@ -1,3 +1,6 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+
 use proto_ecs::data_group::DataGroupID;
 
 pub type StageID = u8;
@@ -22,3 +25,235 @@ impl Dependency {
         }
     }
 }
+
+/// Kind of the systems being ordered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemKind {
+    Local,
+    Global,
+}
+
+/// Problem found while ordering systems by their `before` and `after` dependencies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SystemOrderProblem {
+    /// Systems whose dependencies form a cycle, each one running before the next.
+    /// The first system is repeated at the end
+    Cycle(Vec<&'static str>),
+    /// A system should run before a system that is not registered
+    UnknownBefore { system: &'static str, crc: u32 },
+    /// A system should run after a system that is not registered
+    UnknownAfter { system: &'static str, crc: u32 },
+    /// Two systems have names with the same crc, so their dependencies can't be told apart
+    CrcCollision {
+        first: &'static str,
+        second: &'static str,
+        crc: u32,
+    },
+}
+
+/// Every problem found while ordering the systems of a registry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemOrderError {
+    pub kind: SystemKind,
+    pub problems: Vec<SystemOrderProblem>,
+}
+
+/// A system to order with [sort_systems]
+#[derive(Debug)]
+pub(crate) struct SystemOrderNode<'a> {
+    pub name: &'static str,
+    pub name_crc: u32,
+    pub before: &'a [u32],
+    pub after: &'a [u32],
+    /// Id this system should keep if possible, from a registry manifest
+    pub pinned_id: Option<u32>,
+}
+
+// -- < Implementations > --------------------------------
+
+impl std::fmt::Display for SystemKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SystemKind::Local => write!(f, "local"),
+            SystemKind::Global => write!(f, "global"),
+        }
+    }
+}
+
+impl std::fmt::Display for SystemOrderProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SystemOrderProblem::Cycle(systems) => {
+                write!(f, "Cyclic dependencies: {}", systems.join(" -> "))
+            }
+            SystemOrderProblem::UnknownBefore { system, crc } => write!(
+                f,
+                "'{system}' should run before an unregistered system (name crc {crc:#010x})"
+            ),
+            SystemOrderProblem::UnknownAfter { system, crc } => write!(
+                f,
+                "'{system}' should run after an unregistered system (name crc {crc:#010x})"
+            ),
+            SystemOrderProblem::CrcCollision { first, second, crc } => write!(
+                f,
+                "'{first}' and '{second}' have the same name crc {crc:#010x}"
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for SystemOrderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Can't order the {} systems:", self.kind)?;
+        for problem in self.problems.iter() {
+            write!(f, "\n - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SystemOrderError {}
+
+/// Position of each system in an order where every system runs after the systems
+/// in its `after` list and before the systems in its `before` list. Systems that
+/// don't depend on each other are ordered by pinned id and then by name crc,
+/// so the order is always the same
+pub(crate) fn sort_systems(
+    kind: SystemKind,
+    systems: &[SystemOrderNode],
+) -> Result<Vec<u32>, SystemOrderError> {
+    let mut problems = vec![];
+
+    let mut names: HashMap<u32, &'static str> = HashMap::with_capacity(systems.len());
+    for system in systems.iter() {
+        match names.entry(system.name_crc) {
+            Entry::Occupied(first) => problems.push(SystemOrderProblem::CrcCollision {
+                first: first.get(),
+                second: system.name,
+                crc: system.name_crc,
+            }),
+            Entry::Vacant(entry) => {
+                entry.insert(system.name);
+            }
+        }
+    }
+
+    // Edges go from the system that runs first to the system that runs after it.
+    // Unknown systems are reported instead of being added to the graph
+    let mut edges = vec![];
+    for system in systems.iter() {
+        for &crc in system.before.iter() {
+            if names.contains_key(&crc) {
+                edges.push((system.name_crc, crc));
+            } else {
+                problems.push(SystemOrderProblem::UnknownBefore {
+                    system: system.name,
+                    crc,
+                });
+            }
+        }
+
+        for &crc in system.after.iter() {
+            if names.contains_key(&crc) {
+                edges.push((crc, system.name_crc));
+            } else {
+                problems.push(SystemOrderProblem::UnknownAfter {
+                    system: system.name,
+                    crc,
+                });
+            }
+        }
+    }
+
+    let mut predecessors: HashMap<u32, usize> = names.keys().map(|&crc| (crc, 0)).collect();
+    let mut successors: HashMap<u32, Vec<u32>> = HashMap::new();
+    for &(first, then) in edges.iter() {
+        successors.entry(first).or_default().push(then);
+        *predecessors.get_mut(&then).unwrap() += 1;
+    }
+
+    // Pick the system with the lowest pinned id and name crc among the ones that can
+    // run next. If the pinned ids are a valid order, this reproduces it exactly
+    let pinned_ids: HashMap<u32, u32> = systems
+        .iter()
+        .filter_map(|system| Some((system.name_crc, system.pinned_id?)))
+        .collect();
+    let sort_key = |crc: u32| (pinned_ids.get(&crc).copied().unwrap_or(u32::MAX), crc);
+    let mut ready: BTreeSet<(u32, u32)> = predecessors
+        .iter()
+        .filter(|(_, &count)| count == 0)
+        .map(|(&crc, _)| sort_key(crc))
+        .collect();
+
+    let mut order = Vec::with_capacity(names.len());
+    while let Some((_, crc)) = ready.pop_first() {
+        order.push(crc);
+        for then in successors.get(&crc).into_iter().flatten() {
+            let count = predecessors.get_mut(then).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                ready.insert(sort_key(*then));
+            }
+        }
+    }
+
+    // Systems in a cycle never get all their predecessors ordered
+    if order.len() < names.len() {
+        problems.push(SystemOrderProblem::Cycle(find_cycle(
+            &edges, &order, &names,
+        )));
+    }
+
+    if !problems.is_empty() {
+        return Err(SystemOrderError { kind, problems });
+    }
+
+    Ok(systems
+        .iter()
+        .map(|system| {
+            order
+                .iter()
+                .position(|&crc| crc == system.name_crc)
+                .expect("Every system should be ordered") as u32
+        })
+        .collect())
+}
+
+/// Names of the systems in one of the cycles left after sorting, in running order
+fn find_cycle(
+    edges: &[(u32, u32)],
+    sorted: &[u32],
+    names: &HashMap<u32, &'static str>,
+) -> Vec<&'static str> {
+    let is_left = |crc: &u32| !sorted.contains(crc);
+
+    // Every system left runs after some other system left, so walking
+    // back through them always ends up going around a cycle
+    let run_before = |crc: u32| {
+        edges
+            .iter()
+            .filter(|(first, then)| *then == crc && is_left(first))
+            .map(|(first, _)| *first)
+            .min()
+            .expect("Systems left after sorting should depend on another system left")
+    };
+
+    let mut current = names
+        .keys()
+        .copied()
+        .filter(is_left)
+        .min()
+        .expect("There should be systems left after sorting");
+    let mut path = vec![];
+    while !path.contains(&current) {
+        path.push(current);
+        current = run_before(current);
+    }
+
+    // The path is in reverse running order, and the cycle closes where it found a system twice
+    let start = path.iter().position(|&crc| crc == current).unwrap();
+    let mut cycle = vec![names[&current]];
+    cycle.extend(path[start + 1..].iter().rev().map(|crc| names[crc]));
+    cycle.push(names[&current]);
+    cycle
+}
@@ -10,7 +10,7 @@ use crate::{
             render_thread::{RenderProxy, RenderThread},
         }, windowing::window_manager::WindowManager,
     },
-    data_group::{DataGroup, GenericDataGroupInitArgTrait},
+    data_group::GenericDataGroupInitArgTrait,
     entities::{
         entity::EntityID,
         entity_system::{EntityMap, EntityPtr, World},
@@ -127,11 +127,11 @@ pub struct MeshRenderer {
     models: Vec<ModelHandle>,
 }
 
-fn mesh_renderer_factory() -> Box<dyn DataGroup> {
-    Box::new(MeshRenderer {
+fn mesh_renderer_factory() -> MeshRenderer {
+    MeshRenderer {
         materials: vec![],
         models: vec![],
-    })
+    }
 }
 
 register_datagroup! {
@@ -164,10 +164,10 @@ pub struct CameraDG {
 }
 
 
-fn camera_factory() -> Box<dyn DataGroup> {
-    Box::new(CameraDG {
+fn camera_factory() -> CameraDG {
+    CameraDG {
         camera: Camera::default(),
-    })
+    }
 }
 
 register_datagroup! {
@@ -3,10 +3,11 @@ use parking_lot::RwLock;
 use proto_ecs::core::casting::CanCast;
 use proto_ecs::core::common::InitDesc;
 use proto_ecs::core::ids;
-use proto_ecs::entities::entity_system::{EntitiesVec, EntityMap};
+use proto_ecs::core::registry_manifest::{RegistryKind, RegistryManifest};
+use proto_ecs::entities::entity_allocator::EntityPtr;
+use proto_ecs::entities::entity_system::EntityMap;
 use proto_ecs::get_id;
 use proto_ecs::systems::common::*;
-use topological_sort::TopologicalSort;
 
 pub use ecs_macros::register_global_system;
 
@@ -18,7 +19,7 @@ pub type GlobalSystemID = u32;
 pub const INVALID_GLOBAL_SYSTEM_CLASS_ID: GlobalSystemID = GlobalSystemID::MAX;
 
 /// A function to run to update a global system
-pub type GSStageFn = fn(&mut Box<dyn GlobalSystem>, &World, &EntityMap, &EntitiesVec);
+pub type GSStageFn = fn(&mut Box<dyn GlobalSystem>, &World, &EntityMap, &Vec<EntityPtr>);
 
 /// Maps from stage to Global System function
 pub type GSStageMap = StageMap<GSStageFn>;
@@ -120,8 +121,16 @@ impl GlobalSystemRegistry {
         self.is_initialized
     }
 
-    /// Initialize the global registry
-    pub fn initialize() {
+    /// Initialize the global registry, failing if its systems can't be ordered
+    pub fn initialize() -> Result<(), SystemOrderError> {
+        GlobalSystemRegistry::initialize_with_manifest(None)
+    }
+
+    /// Initialize the global registry, keeping the ids of `manifest` when the
+    /// `before` and `after` dependencies allow it
+    pub fn initialize_with_manifest(
+        manifest: Option<&RegistryManifest>,
+    ) -> Result<(), SystemOrderError> {
         let mut registry = GlobalSystemRegistry::get_global_registry().write();
         assert!(
             !registry.is_initialized,
@@ -134,18 +143,27 @@ impl GlobalSystemRegistry {
         // Clear globals
         std::mem::swap(&mut locals_register_fns, &mut globals_register_fns);
 
-        registry.init(locals_register_fns);
+        registry.init_with_manifest(locals_register_fns, manifest)
     }
 
     /// Initialize this registry entry
-    pub fn init(&mut self, registry_fns: TempRegistryLambdas) {
+    pub fn init(&mut self, registry_fns: TempRegistryLambdas) -> Result<(), SystemOrderError> {
+        self.init_with_manifest(registry_fns, None)
+    }
+
+    /// Initialize this registry entry, keeping the ids of `manifest` when possible
+    pub fn init_with_manifest(
+        &mut self,
+        registry_fns: TempRegistryLambdas,
+        manifest: Option<&RegistryManifest>,
+    ) -> Result<(), SystemOrderError> {
         registry_fns.into_iter().for_each(|lambda| lambda(self));
-        self.set_toposort_ids();
+        self.set_toposort_ids(manifest)?;
 
-        self.entries
-            .sort_unstable_by(|this, other| this.id.cmp(&other.id));
+        self.entries.sort_unstable_by_key(|entry| entry.id);
 
         self.is_initialized = true;
+        Ok(())
     }
 
     #[inline]
@@ -154,70 +172,39 @@ impl GlobalSystemRegistry {
         &self.entries[id as usize]
     }
 
-    /// Set ids for local systems based on the topological ordering
-    /// generated by the `before` and `after` dependencies. Local systems
-    /// can then be sorted by id to get the order in which they should be run
-    fn set_toposort_ids(&mut self) {
-        if self.entries.is_empty() {
-            return;
-        }
-
-        let mut ts: TopologicalSort<GlobalSystemID> = TopologicalSort::new();
-        let source_node = GlobalSystemID::default();
-
-        for entry in self.entries.iter() {
-            let entry_crc = entry.name_crc;
-            ts.add_dependency(source_node, entry_crc);
-
-            // Sanity check
-            debug_assert!(
-                source_node != entry.name_crc,
-                "Source node should be a value never reachable by the crc"
-            );
-            for &other_crc in entry.before.iter() {
-                ts.add_dependency(entry_crc, other_crc);
-            }
-
-            for &other_crc in entry.after.iter() {
-                ts.add_dependency(other_crc, entry_crc);
-            }
-        }
-
-        let source_node_vec = ts.pop_all();
-        debug_assert!(
-            source_node_vec.len() == 1,
-            "The first dependency should be only the source node"
-        );
-        debug_assert!(
-            source_node_vec[0] == source_node,
-            "The first dependency should be the source node"
-        );
-        let mut dependency_order = vec![];
-        while !ts.is_empty() {
-            let mut non_dependents = ts.pop_all();
-            if non_dependents.is_empty() && !ts.is_empty() {
-                // If there's cyclic dependencies,
-                // then the popped list is empty and ts.len > 0,
-                // See: https://docs.rs/topological-sort/latest/topological_sort/struct.TopologicalSort.html#method.pop_all
-                // TODO: better error handling, report cyclic dependencies
-                panic!("Cyclic dependencies between local systems!");
-            }
-
-            // Non-dependents are elements that do not depend on anything else.
-            // Sort them by value to get a deterministic ordering each time.
-            // Since they don't depend on each other, the actual order doesn't matter.
-            non_dependents.sort();
-            dependency_order.extend(non_dependents);
-        }
+    /// Entry of global system `id`, or `None` if no global system is registered with that id
+    #[inline]
+    pub fn try_get_entry_by_id(&self, id: GlobalSystemID) -> Option<&GlobalSystemRegistryEntry> {
+        self.entries.get(id as usize)
+    }
 
-        for entry in self.entries.iter_mut() {
-            let id = dependency_order
-                .iter()
-                .position(|&crc| entry.name_crc == crc)
-                .unwrap();
+    /// Set ids for global systems based on the topological ordering
+    /// generated by the `before` and `after` dependencies. Global systems
+    /// can then be sorted by id to get the order in which they should be run
+    fn set_toposort_ids(
+        &mut self,
+        manifest: Option<&RegistryManifest>,
+    ) -> Result<(), SystemOrderError> {
+        let nodes: Vec<SystemOrderNode> = self
+            .entries
+            .iter()
+            .map(|entry| SystemOrderNode {
+                name: entry.name,
+                name_crc: entry.name_crc,
+                before: &entry.before,
+                after: &entry.after,
+                pinned_id: manifest.and_then(|manifest| {
+                    manifest.get_id(RegistryKind::GlobalSystem, entry.name_crc)
+                }),
+            })
+            .collect();
+        let ids = sort_systems(SystemKind::Global, &nodes)?;
+
+        for (entry, id) in self.entries.iter_mut().zip(ids) {
             entry.id = id as GlobalSystemID;
             (entry.set_id_fn)(id as GlobalSystemID);
         }
+        Ok(())
     }
 
     /// Get the entry for a specific LocalSystem
@@ -265,6 +252,21 @@ impl GlobalSystemRegistry {
     pub fn get_entries(&self) -> &Vec<GlobalSystemRegistryEntry> {
         &self.entries
     }
+
+    /// Entry of the global system named `name`, if it's registered
+    pub fn get_entry_by_name(&self, name: &str) -> Option<&GlobalSystemRegistryEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Global systems with a function for `stage_id`, in running order
+    pub fn get_systems_in_stage(
+        &self,
+        stage_id: StageID,
+    ) -> impl Iterator<Item = &GlobalSystemRegistryEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.functions[stage_id as usize].is_some())
+    }
 }
 
 pub type TempRegistryLambda = Box<dyn FnOnce(&mut GlobalSystemRegistry) + Sync + Send + 'static>;
@@ -1,20 +1,19 @@
 use crate::data_group::DataGroup;
 use crate::entities::entity::{DataGroupIndexingType, EntityID};
 use crate::entities::entity_system::World;
-pub use ecs_macros::register_local_system;
+pub use ecs_macros::{local_system, register_local_system};
 /// Local systems are basically functions that operate on datagroups from
-/// an entity. To define a local system, the user should be able to
-/// write a function with datagroups it expects as parameters and
-/// annotate a macro attribute that will register that system. E.g:
+/// an entity. To define a local system, write a function with the datagroups
+/// it expects as parameters and annotate it with the attribute that registers it. E.g:
 ///
-/// #[local_system]
+/// #[local_system(stage = 0)]
 /// pub fn MySystem(animation : &mut AnimationDatagroup, mesh : &mut MeshDatagroup)
 /// { ... }
 use lazy_static::lazy_static;
 use proto_ecs::core::casting::CanCast;
+use proto_ecs::core::registry_manifest::{RegistryKind, RegistryManifest};
 use proto_ecs::core::{ids, locking::RwLock};
 use proto_ecs::get_id;
-use topological_sort::TopologicalSort;
 
 use proto_ecs::systems::common::*;
 
@@ -36,7 +35,9 @@ pub trait LocalSystemMeta {
 
 pub type LocalSystemFactory = fn() -> Box<dyn LocalSystem>;
 
-pub type SystemFn = fn(&World, EntityID, &[DataGroupIndexingType], &mut [Box<dyn DataGroup>]) -> ();
+/// Function to run a local system stage on an entity.
+/// Receives the indices of its dependencies in the entity datagroups, and the entity datagroups
+pub type SystemFn = fn(&World, EntityID, &[DataGroupIndexingType], &[*mut (dyn DataGroup + 'static)]) -> ();
 
 /// Stage Map type
 pub type LSStageMap = StageMap<SystemFn>;
@@ -44,6 +45,17 @@ pub type LSStageMap = StageMap<SystemFn>;
 /// Empty stage map
 pub const EMPTY_STAGE_MAP: LSStageMap = [None; STAGE_COUNT];
 
+/// Function to run a local system over every entity of an archetype chunk.
+/// Receives the ids of the entities in the chunk and the start of the column
+/// of each dependency, or null for missing optional dependencies
+pub type ChunkSystemFn = fn(&World, &[EntityID], &[*mut u8]) -> ();
+
+/// Stage Map type for chunk functions
+pub type LSChunkStageMap = StageMap<ChunkSystemFn>;
+
+/// Empty chunk stage map
+pub const EMPTY_CHUNK_STAGE_MAP: LSChunkStageMap = [None; STAGE_COUNT];
+
 pub trait LocalSystemDesc {
     const NAME: &'static str;
     const NAME_CRC: u32;
@@ -56,6 +68,7 @@ pub struct LocalSystemRegistryEntry {
     pub name_crc: u32,
     pub dependencies: Vec<Dependency>,
     pub functions: LSStageMap,
+    pub chunk_functions: LSChunkStageMap,
     pub before: Vec<SystemClassID>,
     pub after: Vec<SystemClassID>,
     pub set_id_fn: fn(SystemClassID), // Only used for init, don't use it manually
@@ -98,8 +111,16 @@ impl LocalSystemRegistry {
         self.is_initialized
     }
 
-    /// Initialize the global registry
-    pub fn initialize() {
+    /// Initialize the global registry, failing if its systems can't be ordered
+    pub fn initialize() -> Result<(), SystemOrderError> {
+        LocalSystemRegistry::initialize_with_manifest(None)
+    }
+
+    /// Initialize the global registry, keeping the ids of `manifest` when the
+    /// `before` and `after` dependencies allow it
+    pub fn initialize_with_manifest(
+        manifest: Option<&RegistryManifest>,
+    ) -> Result<(), SystemOrderError> {
         let mut registry = LocalSystemRegistry::get_global_registry().write();
         assert!(
             !registry.is_initialized,
@@ -112,18 +133,27 @@ impl LocalSystemRegistry {
         // Clear globals
         std::mem::swap(&mut locals_register_fns, &mut globals_register_fns);
 
-        registry.init(locals_register_fns);
+        registry.init_with_manifest(locals_register_fns, manifest)
     }
 
     /// Initialize this registry entry
-    pub fn init(&mut self, registry_fns: TempRegistryLambdas) {
+    pub fn init(&mut self, registry_fns: TempRegistryLambdas) -> Result<(), SystemOrderError> {
+        self.init_with_manifest(registry_fns, None)
+    }
+
+    /// Initialize this registry entry, keeping the ids of `manifest` when possible
+    pub fn init_with_manifest(
+        &mut self,
+        registry_fns: TempRegistryLambdas,
+        manifest: Option<&RegistryManifest>,
+    ) -> Result<(), SystemOrderError> {
         registry_fns.into_iter().for_each(|lambda| lambda(self));
-        self.set_toposort_ids();
+        self.set_toposort_ids(manifest)?;
 
-        self.entries
-            .sort_unstable_by(|this, other| this.id.cmp(&other.id));
+        self.entries.sort_unstable_by_key(|entry| entry.id);
 
         self.is_initialized = true;
+        Ok(())
     }
 
     #[inline]
@@ -132,70 +162,64 @@ impl LocalSystemRegistry {
         &self.entries[id as usize]
     }
 
-    /// Set ids for local systems based on the topological ordering
-    /// generated by the `before` and `after` dependencies. Local systems
-    /// can then be sorted by id to get the order in which they should be run
-    fn set_toposort_ids(&mut self) {
-        if self.entries.is_empty() {
-            return; // Nothing to do if there are no entries
-        }
+    /// Entry of local system `id`, or `None` if no local system is registered with that id
+    #[inline]
+    pub fn try_get_entry_by_id(&self, id: SystemClassID) -> Option<&LocalSystemRegistryEntry> {
+        self.entries.get(id as usize)
+    }
 
-        let mut ts: TopologicalSort<SystemClassID> = TopologicalSort::new();
-        let source_node = SystemClassID::default();
-
-        for entry in self.entries.iter() {
-            let entry_crc = entry.name_crc;
-            ts.add_dependency(source_node, entry_crc);
-
-            // Sanity check
-            debug_assert!(
-                source_node != entry.name_crc,
-                "Source node should be a value never reachable by the crc"
-            );
-            for &other_crc in entry.before.iter() {
-                ts.add_dependency(entry_crc, other_crc);
-            }
-
-            for &other_crc in entry.after.iter() {
-                ts.add_dependency(other_crc, entry_crc);
-            }
-        }
+    #[inline(always)]
+    pub fn get_local_system_count(&self) -> usize {
+        self.entries.len()
+    }
 
-        let source_node_vec = ts.pop_all();
-        debug_assert!(
-            source_node_vec.len() == 1,
-            "The first dependency should be only the source node"
-        );
-        debug_assert!(
-            source_node_vec[0] == source_node,
-            "The first dependency should be the source node"
-        );
-        let mut dependency_order = vec![];
-        while !ts.is_empty() {
-            let mut non_dependents = ts.pop_all();
-            if non_dependents.is_empty() && !ts.is_empty() {
-                // If there's cyclic dependencies,
-                // then the popped list is empty and ts.len > 0,
-                // See: https://docs.rs/topological-sort/latest/topological_sort/struct.TopologicalSort.html#method.pop_all
-                // TODO: better error handling, report cyclic dependencies
-                panic!("Cyclic dependencies between local systems!");
-            }
-
-            // Non-dependents are elements that do not depend on anything else.
-            // Sort them by value to get a deterministic ordering each time.
-            // Since they don't depend on each other, the actual order doesn't matter.
-            non_dependents.sort();
-            dependency_order.extend(non_dependents);
-        }
+    #[inline(always)]
+    pub fn get_entries(&self) -> &Vec<LocalSystemRegistryEntry> {
+        &self.entries
+    }
 
-        for entry in self.entries.iter_mut() {
-            let id = dependency_order
-                .iter()
-                .position(|&crc| entry.name_crc == crc)
-                .unwrap();
+    /// Entry of the local system named `name`, if it's registered
+    pub fn get_entry_by_name(&self, name: &str) -> Option<&LocalSystemRegistryEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Local systems with a function for `stage_id`, in running order
+    pub fn get_systems_in_stage(
+        &self,
+        stage_id: StageID,
+    ) -> impl Iterator<Item = &LocalSystemRegistryEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.functions[stage_id as usize].is_some())
+    }
+
+    /// Set ids for local systems based on the topological ordering
+    /// generated by the `before` and `after` dependencies. Local systems
+    /// can then be sorted by id to get the order in which they should be run
+    fn set_toposort_ids(
+        &mut self,
+        manifest: Option<&RegistryManifest>,
+    ) -> Result<(), SystemOrderError> {
+        let nodes: Vec<SystemOrderNode> = self
+            .entries
+            .iter()
+            .map(|entry| SystemOrderNode {
+                name: entry.name,
+                name_crc: entry.name_crc,
+                before: &entry.before,
+                after: &entry.after,
+                pinned_id: manifest.and_then(|manifest| {
+                    manifest.get_id(RegistryKind::LocalSystem, entry.name_crc)
+                }),
+            })
+            .collect();
+        let ids = sort_systems(SystemKind::Local, &nodes)?;
+
+        for (entry, id) in self.entries.iter_mut().zip(ids) {
             entry.id = id as SystemClassID;
             (entry.set_id_fn)(id as SystemClassID);
         }
+        Ok(())
     }
 
     /// Get the entry for a specific LocalSystem
@@ -1,4 +1,5 @@
 pub mod common;
 pub mod global_systems;
 pub mod local_systems;
-pub mod engine;
\ No newline at end of file
+pub mod engine;
+pub mod schedule;
\ No newline at end of file
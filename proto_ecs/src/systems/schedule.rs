@@ -0,0 +1,484 @@
+/// Answers "what runs when?" from the system registries and the worlds.
+///
+/// A [Schedule] describes the registered local and global systems: the stages they run in,
+/// their running order, their datagroup dependencies and their `before` and `after` edges.
+/// A [WorldSchedule] describes what a single world is running right now: its loaded global
+/// systems and how many entities run each stage.
+///
+/// Both can be written as Graphviz DOT, to render with `dot -Tsvg`, or as JSON,
+/// so they can be kept as build artifacts and compared between versions.
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::core::utils::json::{escape_json, write_json_strings};
+use crate::data_group::DataGroupRegistry;
+use crate::entities::entity_system::{EntitySystem, World, WorldID};
+use crate::systems::common::{Dependency, StageID, StageMap, STAGE_COUNT};
+use crate::systems::global_systems::{GlobalSystemID, GlobalSystemRegistry};
+use crate::systems::local_systems::LocalSystemRegistry;
+
+/// Name used for ids that are not in their registry
+const UNREGISTERED: &str = "<unregistered>";
+
+/// A datagroup required by a system
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataGroupDependency {
+    pub datagroup: &'static str,
+    /// If the system still runs on entities without this datagroup
+    pub optional: bool,
+}
+
+/// A registered system and its ordering constraints
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemInfo {
+    /// Id of the system, which is also its position in the running order
+    pub id: u32,
+    pub name: &'static str,
+    /// Stages this system has a function for
+    pub stages: Vec<StageID>,
+    pub dependencies: Vec<DataGroupDependency>,
+    /// Systems that should run after this one
+    pub before: Vec<&'static str>,
+    /// Systems that should run before this one
+    pub after: Vec<&'static str>,
+}
+
+/// Systems with a function for a stage, in running order.
+/// Local systems run before global systems
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageSchedule {
+    pub stage: StageID,
+    pub local_systems: Vec<&'static str>,
+    pub global_systems: Vec<&'static str>,
+}
+
+/// What runs in each stage according to the registries
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Schedule {
+    /// Local systems sorted by id
+    pub local_systems: Vec<SystemInfo>,
+    /// Global systems sorted by id
+    pub global_systems: Vec<SystemInfo>,
+    /// Stages with at least one system, sorted by stage
+    pub stages: Vec<StageSchedule>,
+}
+
+/// What a world runs in a stage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorldStageSchedule {
+    pub stage: StageID,
+    /// Entities running local systems in this stage
+    pub entity_count: usize,
+    /// Loaded global systems running in this stage, in running order
+    pub global_systems: Vec<&'static str>,
+}
+
+/// What a world is running right now
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorldSchedule {
+    pub world_id: WorldID,
+    /// Global systems loaded in the world, in running order
+    pub loaded_global_systems: Vec<&'static str>,
+    /// Stages with entities or global systems to run, sorted by stage
+    pub stages: Vec<WorldStageSchedule>,
+}
+
+// -- < Implementations > --------------------------------
+
+impl Schedule {
+    /// Schedule of the global registries. The app should be initialized
+    pub fn from_global_registries() -> Schedule {
+        Schedule::from_registries(
+            &DataGroupRegistry::get_global_registry().read(),
+            &LocalSystemRegistry::get_global_registry().read(),
+            &GlobalSystemRegistry::get_global_registry().read(),
+        )
+    }
+
+    /// Schedule of initialized registries
+    pub fn from_registries(
+        dg_registry: &DataGroupRegistry,
+        ls_registry: &LocalSystemRegistry,
+        gs_registry: &GlobalSystemRegistry,
+    ) -> Schedule {
+        let local_names: Vec<(u32, &'static str)> = ls_registry
+            .get_entries()
+            .iter()
+            .map(|entry| (entry.name_crc, entry.name))
+            .collect();
+        let local_systems = ls_registry
+            .get_entries()
+            .iter()
+            .map(|entry| SystemInfo {
+                id: entry.id,
+                name: entry.name,
+                stages: function_stages(&entry.functions),
+                dependencies: dependencies_info(dg_registry, &entry.dependencies),
+                before: names_by_crc(&local_names, &entry.before),
+                after: names_by_crc(&local_names, &entry.after),
+            })
+            .collect();
+
+        let global_names: Vec<(u32, &'static str)> = gs_registry
+            .get_entries()
+            .iter()
+            .map(|entry| (entry.name_crc, entry.name))
+            .collect();
+        let global_systems = gs_registry
+            .get_entries()
+            .iter()
+            .map(|entry| SystemInfo {
+                id: entry.id,
+                name: entry.name,
+                stages: function_stages(&entry.functions),
+                dependencies: dependencies_info(dg_registry, &entry.dependencies),
+                before: names_by_crc(&global_names, &entry.before),
+                after: names_by_crc(&global_names, &entry.after),
+            })
+            .collect();
+
+        let stages = (0..STAGE_COUNT)
+            .map(|stage_id| {
+                let stage = stage_id as StageID;
+                StageSchedule {
+                    stage,
+                    local_systems: ls_registry
+                        .get_systems_in_stage(stage)
+                        .map(|entry| entry.name)
+                        .collect(),
+                    global_systems: gs_registry
+                        .get_systems_in_stage(stage)
+                        .map(|entry| entry.name)
+                        .collect(),
+                }
+            })
+            .filter(|stage| !stage.local_systems.is_empty() || !stage.global_systems.is_empty())
+            .collect();
+
+        Schedule {
+            local_systems,
+            global_systems,
+            stages,
+        }
+    }
+
+    /// Write this schedule as a Graphviz graph with a cluster per stage. Systems are
+    /// numbered in running order, labeled with their datagroups (optional ones end in `?`),
+    /// and `before`/`after` constraints are edges from the system that runs first
+    pub fn write_dot_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "digraph schedule {{")?;
+        writeln!(writer, "    rankdir=LR;")?;
+        writeln!(writer, "    node [shape=box];")?;
+
+        for stage in self.stages.iter() {
+            writeln!(writer, "    subgraph cluster_stage_{} {{", stage.stage)?;
+            writeln!(writer, "        label=\"Stage {}\";", stage.stage)?;
+
+            let systems = stage
+                .local_systems
+                .iter()
+                .map(|name| ("local", find_system(&self.local_systems, name)))
+                .chain(
+                    stage
+                        .global_systems
+                        .iter()
+                        .map(|name| ("global", find_system(&self.global_systems, name))),
+                );
+            for (position, (kind, system)) in systems.enumerate() {
+                let dependencies: Vec<String> = system
+                    .dependencies
+                    .iter()
+                    .map(|dependency| {
+                        let optional = if dependency.optional { "?" } else { "" };
+                        format!("{}{}", dependency.datagroup, optional)
+                    })
+                    .collect();
+                writeln!(
+                    writer,
+                    "        \"{}:{}:{}\" [label=\"{}. {}\\n{}\"{}];",
+                    stage.stage,
+                    kind,
+                    system.name,
+                    position + 1,
+                    system.name,
+                    dependencies.join(", "),
+                    if kind == "global" {
+                        ", shape=ellipse"
+                    } else {
+                        ""
+                    }
+                )?;
+            }
+
+            write_dot_edges(
+                &mut writer,
+                stage.stage,
+                "local",
+                &stage.local_systems,
+                &self.local_systems,
+            )?;
+            write_dot_edges(
+                &mut writer,
+                stage.stage,
+                "global",
+                &stage.global_systems,
+                &self.global_systems,
+            )?;
+
+            // Local systems run before the global systems of the same stage
+            if let (Some(last_local), Some(first_global)) =
+                (stage.local_systems.last(), stage.global_systems.first())
+            {
+                writeln!(
+                    writer,
+                    "        \"{0}:local:{1}\" -> \"{0}:global:{2}\" [style=dashed];",
+                    stage.stage, last_local, first_global
+                )?;
+            }
+            writeln!(writer, "    }}")?;
+        }
+        writeln!(writer, "}}")?;
+        writer.flush()
+    }
+
+    /// Write this schedule as a Graphviz graph file at `path`, see [Schedule::write_dot_to]
+    pub fn write_dot<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        self.write_dot_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Write this schedule as JSON
+    pub fn write_json_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(b"{\n\"local_systems\":[")?;
+        write_json_systems(&mut writer, &self.local_systems)?;
+        writer.write_all(b"\n],\n\"global_systems\":[")?;
+        write_json_systems(&mut writer, &self.global_systems)?;
+        writer.write_all(b"\n],\n\"stages\":[")?;
+        for (i, stage) in self.stages.iter().enumerate() {
+            if i > 0 {
+                writer.write_all(b",")?;
+            }
+            write!(writer, "\n{{\"stage\":{},\"local_systems\":", stage.stage)?;
+            write_json_strings(&mut writer, &stage.local_systems)?;
+            writer.write_all(b",\"global_systems\":")?;
+            write_json_strings(&mut writer, &stage.global_systems)?;
+            writer.write_all(b"}")?;
+        }
+        writer.write_all(b"\n]\n}\n")?;
+        writer.flush()
+    }
+
+    /// Write this schedule as a JSON file at `path`
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        self.write_json_to(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl WorldSchedule {
+    /// What `world` is running right now
+    pub fn from_world(world: &World) -> WorldSchedule {
+        let gs_registry = GlobalSystemRegistry::get_global_registry().read();
+        let name = |id: GlobalSystemID| {
+            gs_registry
+                .try_get_entry_by_id(id)
+                .map_or(UNREGISTERED, |entry| entry.name)
+        };
+
+        let entity_counts = world.get_stage_entity_counts();
+        let stages = (0..STAGE_COUNT)
+            .map(|stage_id| WorldStageSchedule {
+                stage: stage_id as StageID,
+                entity_count: entity_counts[stage_id],
+                global_systems: world
+                    .get_global_systems_in_stage(stage_id as StageID)
+                    .into_iter()
+                    .map(name)
+                    .collect(),
+            })
+            .filter(|stage| stage.entity_count > 0 || !stage.global_systems.is_empty())
+            .collect();
+
+        WorldSchedule {
+            world_id: world.get_id(),
+            loaded_global_systems: world
+                .get_loaded_global_systems()
+                .into_iter()
+                .map(name)
+                .collect(),
+            stages,
+        }
+    }
+
+    /// What world `world_id` is running right now, or `None` if it doesn't exist
+    pub fn from_world_id(world_id: WorldID) -> Option<WorldSchedule> {
+        EntitySystem::get().with_world(world_id, WorldSchedule::from_world)
+    }
+
+    /// Write this schedule as a Graphviz graph with a cluster per stage, holding the
+    /// number of entities that run in it followed by its global systems in running order
+    pub fn write_dot_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "digraph world_{} {{", self.world_id)?;
+        writeln!(writer, "    rankdir=LR;")?;
+        writeln!(writer, "    node [shape=ellipse];")?;
+
+        for stage in self.stages.iter() {
+            writeln!(writer, "    subgraph cluster_stage_{} {{", stage.stage)?;
+            writeln!(writer, "        label=\"Stage {}\";", stage.stage)?;
+            let mut previous = format!("{}:entities", stage.stage);
+            writeln!(
+                writer,
+                "        \"{}\" [label=\"{} entities\", shape=box];",
+                previous, stage.entity_count
+            )?;
+            for name in stage.global_systems.iter() {
+                let node = format!("{}:global:{}", stage.stage, name);
+                writeln!(writer, "        \"{}\" [label=\"{}\"];", node, name)?;
+                writeln!(writer, "        \"{}\" -> \"{}\";", previous, node)?;
+                previous = node;
+            }
+            writeln!(writer, "    }}")?;
+        }
+        writeln!(writer, "}}")?;
+        writer.flush()
+    }
+
+    /// Write this schedule as a Graphviz graph file at `path`, see [WorldSchedule::write_dot_to]
+    pub fn write_dot<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        self.write_dot_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Write this schedule as JSON
+    pub fn write_json_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write!(
+            writer,
+            "{{\n\"world_id\":{},\n\"loaded_global_systems\":",
+            self.world_id
+        )?;
+        write_json_strings(&mut writer, &self.loaded_global_systems)?;
+        writer.write_all(b",\n\"stages\":[")?;
+        for (i, stage) in self.stages.iter().enumerate() {
+            if i > 0 {
+                writer.write_all(b",")?;
+            }
+            write!(
+                writer,
+                "\n{{\"stage\":{},\"entity_count\":{},\"global_systems\":",
+                stage.stage, stage.entity_count
+            )?;
+            write_json_strings(&mut writer, &stage.global_systems)?;
+            writer.write_all(b"}")?;
+        }
+        writer.write_all(b"\n]\n}\n")?;
+        writer.flush()
+    }
+
+    /// Write this schedule as a JSON file at `path`
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        self.write_json_to(BufWriter::new(File::create(path)?))
+    }
+}
+
+/// Stages with a function in `functions`
+fn function_stages<F>(functions: &StageMap<F>) -> Vec<StageID> {
+    functions
+        .iter()
+        .enumerate()
+        .filter(|(_, function)| function.is_some())
+        .map(|(stage_id, _)| stage_id as StageID)
+        .collect()
+}
+
+fn dependencies_info(
+    dg_registry: &DataGroupRegistry,
+    dependencies: &[Dependency],
+) -> Vec<DataGroupDependency> {
+    dependencies
+        .iter()
+        .map(|dependency| DataGroupDependency {
+            datagroup: dg_registry
+                .try_get_entry_by_id(dependency.unwrap())
+                .map_or(UNREGISTERED, |entry| entry.name),
+            optional: matches!(dependency, Dependency::OptionalDG(_)),
+        })
+        .collect()
+}
+
+/// Names of the systems with name crcs `crcs`
+fn names_by_crc(names: &[(u32, &'static str)], crcs: &[u32]) -> Vec<&'static str> {
+    crcs.iter()
+        .map(|crc| {
+            names
+                .iter()
+                .find(|(name_crc, _)| name_crc == crc)
+                .map_or(UNREGISTERED, |(_, name)| *name)
+        })
+        .collect()
+}
+
+fn find_system<'a>(systems: &'a [SystemInfo], name: &str) -> &'a SystemInfo {
+    systems
+        .iter()
+        .find(|system| system.name == name)
+        .expect("Systems in a stage should be in the schedule")
+}
+
+/// Write the `before` and `after` constraints between the systems of a stage
+fn write_dot_edges<W: Write>(
+    writer: &mut W,
+    stage: StageID,
+    kind: &str,
+    stage_systems: &[&'static str],
+    systems: &[SystemInfo],
+) -> std::io::Result<()> {
+    let mut edges = vec![];
+    for &name in stage_systems.iter() {
+        let system = find_system(systems, name);
+        edges.extend(system.before.iter().map(|&other| (name, other)));
+        edges.extend(system.after.iter().map(|&other| (other, name)));
+    }
+    edges.retain(|(first, then)| stage_systems.contains(first) && stage_systems.contains(then));
+    edges.sort_unstable();
+    edges.dedup();
+
+    for (first, then) in edges {
+        writeln!(
+            writer,
+            "        \"{0}:{1}:{2}\" -> \"{0}:{1}:{3}\";",
+            stage, kind, first, then
+        )?;
+    }
+    Ok(())
+}
+
+/// Write `systems` as JSON objects, separated by commas
+fn write_json_systems<W: Write>(writer: &mut W, systems: &[SystemInfo]) -> std::io::Result<()> {
+    for (i, system) in systems.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        write!(
+            writer,
+            "\n{{\"id\":{},\"name\":\"{}\",\"stages\":{:?},\"dependencies\":[",
+            system.id,
+            escape_json(system.name),
+            system.stages
+        )?;
+        for (j, dependency) in system.dependencies.iter().enumerate() {
+            if j > 0 {
+                writer.write_all(b",")?;
+            }
+            write!(
+                writer,
+                "{{\"datagroup\":\"{}\",\"optional\":{}}}",
+                escape_json(dependency.datagroup),
+                dependency.optional
+            )?;
+        }
+        writer.write_all(b"],\"before\":")?;
+        write_json_strings(writer, &system.before)?;
+        writer.write_all(b",\"after\":")?;
+        write_json_strings(writer, &system.after)?;
+        writer.write_all(b"}")?;
+    }
+    Ok(())
+}
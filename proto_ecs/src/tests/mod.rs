@@ -4,3 +4,4 @@ pub mod shared_local_systems;
 mod test_datagroups;
 mod test_global_systems;
 mod test_local_systems;
+mod test_schedule;
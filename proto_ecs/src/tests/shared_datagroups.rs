@@ -3,6 +3,8 @@ pub mod sdg {
     use proto_ecs::data_group::*;
 
     use crate::core::casting::CanCast;
+    use crate::core::reflection::Reflect;
+    use crate::entities::entity_ref::EntityRef;
     // -- first example datagroup
     #[derive(CanCast, Debug)]
     pub struct AnimationDataGroup {
@@ -18,11 +20,11 @@ pub mod sdg {
 
     impl GenericDataGroupInitArgTrait for AnimationDataGroup {}
 
-    fn animation_factory() -> Box<dyn DataGroup> {
-        return Box::new(AnimationDataGroup {
+    fn animation_factory() -> AnimationDataGroup {
+        AnimationDataGroup {
             clip_name: "Hello world".to_string(),
             duration: 12.4,
-        });
+        }
     }
 
     impl AnimationDataGroupDesc for AnimationDataGroup {
@@ -37,8 +39,8 @@ pub mod sdg {
     #[derive(CanCast, Debug)]
     pub struct MeshDataGroup {}
 
-    fn mesh_factory() -> Box<dyn DataGroup> {
-        return Box::new(MeshDataGroup {});
+    fn mesh_factory() -> MeshDataGroup {
+        MeshDataGroup {}
     }
 
     register_datagroup!(MeshDataGroup, mesh_factory, init_style = NoArg);
@@ -47,7 +49,7 @@ pub mod sdg {
         fn init(&mut self) {}
     }
 
-    #[derive(CanCast, Default, Debug)]
+    #[derive(CanCast, Default, Debug, Clone)]
     pub struct TestNumberDataGroup {
         pub num: u32,
     }
@@ -59,14 +61,15 @@ pub mod sdg {
 
     impl GenericDataGroupInitArgTrait for TestNumberDataGroupArg {}
 
-    fn test_num_factory() -> Box<dyn DataGroup> {
-        return Box::new(TestNumberDataGroup::default());
+    fn test_num_factory() -> TestNumberDataGroup {
+        TestNumberDataGroup::default()
     }
 
     register_datagroup!(
         TestNumberDataGroup,
         test_num_factory,
-        init_style = Arg(TestNumberDataGroupArg)
+        init_style = Arg(TestNumberDataGroupArg),
+        cloneable = true
     );
 
     impl TestNumberDataGroupDesc for TestNumberDataGroup {
@@ -74,4 +77,150 @@ pub mod sdg {
             self.num = init_data.num;
         }
     }
+
+    // -- Datagroup storing references to other entities
+    #[derive(CanCast, Default, Debug)]
+    pub struct TestEntityRefDataGroup {
+        pub target: EntityRef,
+        pub others: Vec<EntityRef>,
+    }
+
+    fn test_entity_ref_factory() -> TestEntityRefDataGroup {
+        TestEntityRefDataGroup::default()
+    }
+
+    register_datagroup!(
+        TestEntityRefDataGroup,
+        test_entity_ref_factory,
+        init_style = NoArg,
+        entity_refs = (target, others)
+    );
+
+    impl TestEntityRefDataGroupDesc for TestEntityRefDataGroup {
+        fn init(&mut self) {}
+    }
+
+    // -- Datagroup counting its live instances
+    pub static TEST_DROP_LIVE_COUNT: std::sync::atomic::AtomicI32 =
+        std::sync::atomic::AtomicI32::new(0);
+
+    #[derive(CanCast, Debug)]
+    pub struct TestDropDataGroup {
+        pub payload: [u64; 4],
+    }
+
+    impl Default for TestDropDataGroup {
+        fn default() -> Self {
+            TEST_DROP_LIVE_COUNT.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
+            Self { payload: [7; 4] }
+        }
+    }
+
+    impl Drop for TestDropDataGroup {
+        fn drop(&mut self) {
+            let prev = TEST_DROP_LIVE_COUNT.fetch_sub(1, std::sync::atomic::Ordering::AcqRel);
+            assert!(prev > 0, "TestDropDataGroup dropped too many times!");
+        }
+    }
+
+    fn test_drop_factory() -> TestDropDataGroup {
+        TestDropDataGroup::default()
+    }
+
+    register_datagroup!(TestDropDataGroup, test_drop_factory, init_style = NoArg);
+
+    impl TestDropDataGroupDesc for TestDropDataGroup {
+        fn init(&mut self) {}
+    }
+
+    // -- Datagroup with reflected fields
+    #[derive(Reflect, Debug, Clone, Copy, PartialEq, Default)]
+    pub enum TestReflectMode {
+        #[default]
+        Idle,
+        Running,
+    }
+
+    #[derive(Reflect, Debug, Default)]
+    pub struct TestReflectSettings {
+        pub position: macaw::Vec3,
+        pub mode: TestReflectMode,
+    }
+
+    #[derive(CanCast, Reflect, Debug, Default)]
+    pub struct TestReflectDataGroup {
+        pub name: String,
+        pub speed: f32,
+        pub visible: bool,
+        pub settings: TestReflectSettings,
+        #[reflect(skip)]
+        pub target: EntityRef,
+    }
+
+    fn test_reflect_factory() -> TestReflectDataGroup {
+        TestReflectDataGroup::default()
+    }
+
+    register_datagroup!(TestReflectDataGroup, test_reflect_factory, reflect = true);
+
+    impl TestReflectDataGroupDesc for TestReflectDataGroup {}
+
+    // -- Datagroup registered with the derive macro
+    #[derive(DataGroup, Default, Debug, Clone)]
+    #[datagroup(init = OptionalArg(TestDerivedDataGroupArg), cloneable)]
+    pub struct TestDerivedDataGroup {
+        pub num: u32,
+        #[datagroup(entity_ref)]
+        pub target: EntityRef,
+    }
+
+    #[derive(CanCast, Debug)]
+    pub struct TestDerivedDataGroupArg {
+        pub num: u32,
+    }
+
+    impl GenericDataGroupInitArgTrait for TestDerivedDataGroupArg {}
+
+    impl TestDerivedDataGroupDesc for TestDerivedDataGroup {
+        fn init(&mut self, init_data: Option<Box<TestDerivedDataGroupArg>>) {
+            if let Some(init_data) = init_data {
+                self.num = init_data.num;
+            }
+        }
+    }
+
+    // -- Derived datagroup sharing its init arg type with another one
+    #[derive(DataGroup, Default, Debug)]
+    #[datagroup(init = Arg(TestDerivedDataGroupArg))]
+    pub struct TestDerivedSharedArgDataGroup {
+        pub num: u32,
+    }
+
+    impl TestDerivedSharedArgDataGroupDesc for TestDerivedSharedArgDataGroup {
+        fn init(&mut self, init_data: Box<TestDerivedDataGroupArg>) {
+            self.num = init_data.num * 2;
+        }
+    }
+
+    // -- Generic datagroup, registered for a concrete type
+    #[derive(CanCast, Default, Debug)]
+    pub struct TestGenericDataGroup<T> {
+        pub values: Vec<T>,
+    }
+
+    fn test_generic_factory() -> TestGenericDataGroup<u64> {
+        TestGenericDataGroup::<u64>::default()
+    }
+
+    register_datagroup!(
+        self::TestGenericDataGroup<u64>,
+        test_generic_factory,
+        init_style = NoArg
+    );
+
+    impl TestGenericDataGroupU64Desc for TestGenericDataGroup<u64> {
+        fn init(&mut self) {
+            self.values.push(42);
+        }
+    }
 }
@@ -1,6 +1,6 @@
 #[cfg(test)]
 pub mod sgs {
-    use crate::data_group::{DataGroup, GenericDataGroupInitArgTrait};
+    use crate::data_group::GenericDataGroupInitArgTrait;
     use crate::entities::entity_system::*;
     use crate::systems::global_systems::*;
     use crate::tests::shared_datagroups::sdg::{AnimationDataGroup, MeshDataGroup};
@@ -125,8 +125,8 @@ pub mod sgs {
 
     impl GenericDataGroupInitArgTrait for GSFlowDG {}
 
-    fn gs_flow_factory() -> Box<dyn DataGroup> {
-        return Box::new(GSFlowDG { id: 0 });
+    fn gs_flow_factory() -> GSFlowDG {
+        GSFlowDG { id: 0 }
     }
 
     register_datagroup!(GSFlowDG, gs_flow_factory, init_style = NoArg);
@@ -143,7 +143,7 @@ pub mod sgs {
     }
 
     fn gs_flow_tester_factory() -> Box<dyn GlobalSystem> {
-        return Box::new(GSFlowTester { n_entities: 0 });
+        Box::new(GSFlowTester { n_entities: 0 })
     }
 
     register_global_system! {
@@ -174,7 +174,7 @@ pub mod sgs {
     #[derive(Debug, CanCast)]
     pub struct AllLive;
     fn all_live_gs_factory() -> Box<dyn GlobalSystem> {
-        return Box::new(AllLive);
+        Box::new(AllLive)
     }
 
     register_global_system! {
@@ -198,9 +198,9 @@ pub mod sgs {
                 );
             }
 
-            for entry in _entity_map.iter() {
+            for entity in _entity_map.read_all().iter() {
                 assert!(
-                    entry.value().is_live(),
+                    entity.is_live(),
                     "Entities in the entity map should all be live"
                 );
             }
@@ -212,7 +212,7 @@ pub mod sgs {
     pub struct AlwaysLive;
 
     fn always_live_factory() -> Box<dyn GlobalSystem> {
-        return Box::new(AlwaysLive {});
+        Box::new(AlwaysLive {})
     }
 
     register_global_system!(
@@ -228,7 +228,7 @@ pub mod sgs {
     pub struct WhenRequiredGS;
 
     fn when_required_factory() -> Box<dyn GlobalSystem> {
-        return Box::new(WhenRequiredGS {});
+        Box::new(WhenRequiredGS {})
     }
 
     register_global_system!(
@@ -244,7 +244,7 @@ pub mod sgs {
     pub struct ManualLifetimeGS;
 
     fn manual_lifetime_factory() -> Box<dyn GlobalSystem> {
-        return Box::new(ManualLifetimeGS {});
+        Box::new(ManualLifetimeGS {})
     }
 
     register_global_system!(
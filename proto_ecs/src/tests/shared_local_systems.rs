@@ -5,7 +5,7 @@ pub mod sls {
         AnimationDataGroup, MeshDataGroup, TestNumberDataGroup,
     };
     use proto_ecs::entities::entity::EntityID;
-    use proto_ecs::systems::local_systems::register_local_system;
+    use proto_ecs::systems::local_systems::{local_system, register_local_system};
 
     // -- Local system creation
     pub struct Test;
@@ -68,7 +68,7 @@ pub mod sls {
             _entity_id: EntityID,
             test_number_data_group: &mut TestNumberDataGroup,
         ) {
-            test_number_data_group.num = test_number_data_group.num + 1
+            test_number_data_group.num += 1
         }
     }
 
@@ -87,7 +87,7 @@ pub mod sls {
             _entity_id: EntityID,
             test_number_data_group: &mut TestNumberDataGroup,
         ) {
-            test_number_data_group.num = test_number_data_group.num * 2
+            test_number_data_group.num *= 2
         }
     }
 
@@ -115,4 +115,14 @@ pub mod sls {
             assert_eq!(test_number_data_group.num, TestAssertNumber4::NUM)
         }
     }
+
+    // -- Local system declared on a function
+    #[local_system(stages = (0, 1), after = (TestAdder))]
+    pub fn TestFunctionSystem(
+        mesh_data_group: Option<&mut MeshDataGroup>,
+        entity_id: EntityID,
+        test_number_data_group: &mut TestNumberDataGroup,
+    ) {
+        test_number_data_group.num = entity_id as u32 + mesh_data_group.map_or(0, |_| 100);
+    }
 }
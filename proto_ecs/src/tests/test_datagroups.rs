@@ -2,9 +2,11 @@
 #[cfg(test)]
 pub mod datagroup_test {
     use crate::core::common::InitDesc;
+    use crate::core::registry_manifest::RegistryManifest;
     use crate::{
         app::App,
         core::casting::{cast, cast_mut},
+        core::reflection::{ReflectError, ReflectValue, TypeInfo},
         create_datagroup,
         entities::entity_spawn_desc::EntitySpawnDescription,
         get_id,
@@ -16,7 +18,7 @@ pub mod datagroup_test {
     #[test]
     fn test_datagroup_registration() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let global_registry = DataGroupRegistry::get_global_registry().read();
@@ -40,14 +42,20 @@ pub mod datagroup_test {
         assert_eq!(anim_entry.name_crc, AnimationDataGroup::NAME_CRC);
         assert_eq!(mesh_entry.name_crc, MeshDataGroup::NAME_CRC);
 
-        assert_eq!(anim_entry.factory_func, AnimationDataGroup::FACTORY);
-        assert_eq!(mesh_entry.factory_func, MeshDataGroup::FACTORY);
+        assert!(std::ptr::fn_addr_eq(
+            anim_entry.factory_func,
+            AnimationDataGroup::FACTORY
+        ));
+        assert!(std::ptr::fn_addr_eq(
+            mesh_entry.factory_func,
+            MeshDataGroup::FACTORY
+        ));
     }
 
     #[test]
     fn test_construction_workflow() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let anim_datagroup = create_datagroup!(AnimationDataGroup);
@@ -72,7 +80,7 @@ pub mod datagroup_test {
     #[test]
     fn test_init_registry() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let global_registry = DataGroupRegistry::get_global_registry().read();
@@ -87,7 +95,7 @@ pub mod datagroup_test {
     #[test]
     fn test_datagroup_initialization() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         assert_eq!(AnimationDataGroup::INIT_DESC, InitDesc::Arg);
@@ -118,7 +126,7 @@ pub mod datagroup_test {
     #[test]
     fn test_datagroup_entity_spawn_desc() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let mut spawn_desc = EntitySpawnDescription::default();
@@ -153,7 +161,7 @@ pub mod datagroup_test {
     #[should_panic]
     fn test_datagroup_wrong_init_data() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let mut spawn_desc = EntitySpawnDescription::default();
@@ -166,7 +174,7 @@ pub mod datagroup_test {
     #[should_panic]
     fn test_datagroup_uninitialized_init_data() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let mut spawn_desc = EntitySpawnDescription::default();
@@ -175,4 +183,174 @@ pub mod datagroup_test {
             .add_datagroup::<AnimationDataGroup>(DataGroupInitType::Uninitialized("Test message"));
         spawn_desc.check_datagroups_panic();
     }
+
+    #[test]
+    fn test_datagroup_reflection() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let global_registry = DataGroupRegistry::get_global_registry().read();
+        assert!(global_registry
+            .get_entry::<AnimationDataGroup>()
+            .reflection
+            .is_none());
+
+        let reflection = global_registry
+            .get_entry::<TestReflectDataGroup>()
+            .reflection
+            .expect("Datagroup should be reflected");
+        let type_info = (reflection.type_info)();
+        let field_names: Vec<&str> = type_info.fields().iter().map(|field| field.name).collect();
+        assert_eq!(field_names, ["name", "speed", "visible", "settings"]);
+        assert_eq!(type_info.fields()[1].type_name, "f32");
+        assert_eq!(
+            type_info.fields()[1].offset,
+            std::mem::offset_of!(TestReflectDataGroup, speed)
+        );
+        let mode_info = (type_info.fields()[3].type_info)().fields()[1].type_info;
+        assert!(matches!(
+            mode_info(),
+            TypeInfo::Enum { variants, .. } if *variants == ["Idle", "Running"]
+        ));
+
+        let mut datagroup = create_datagroup!(TestReflectDataGroup);
+        {
+            let reflected = global_registry.reflect_mut(datagroup.as_mut()).unwrap();
+            reflected
+                .set_path("name", ReflectValue::String("Reflected".to_owned()))
+                .unwrap();
+            reflected.set_path("speed", ReflectValue::Int(2)).unwrap();
+            reflected
+                .set_path("settings.position", ReflectValue::Vec3(macaw::Vec3::X))
+                .unwrap();
+            reflected
+                .set_path("settings.mode", ReflectValue::Enum("Running".to_owned()))
+                .unwrap();
+
+            assert!(matches!(
+                reflected.set_path("settings.mode", ReflectValue::Enum("Flying".to_owned())),
+                Err(ReflectError::UnknownVariant { .. })
+            ));
+            assert!(matches!(
+                reflected.set_path("visible", ReflectValue::Float(1.0)),
+                Err(ReflectError::TypeMismatch { .. })
+            ));
+            assert!(matches!(
+                reflected.get_path("target"),
+                Err(ReflectError::UnknownField(_))
+            ));
+            assert!(matches!(
+                reflected.get_path("settings"),
+                Err(ReflectError::NotAValue(_))
+            ));
+        }
+
+        let reflected = global_registry.reflect(datagroup.as_ref()).unwrap();
+        assert_eq!(reflected.get_path("speed"), Ok(ReflectValue::Float(2.0)));
+        assert_eq!(
+            reflected.get_path("settings.mode"),
+            Ok(ReflectValue::Enum("Running".to_owned()))
+        );
+
+        let datagroup: &TestReflectDataGroup = cast(datagroup.as_ref());
+        assert_eq!(datagroup.name, "Reflected");
+        assert_eq!(datagroup.settings.position, macaw::Vec3::X);
+        assert_eq!(datagroup.settings.mode, TestReflectMode::Running);
+        // Skipped fields are left alone
+        assert!(datagroup.target.is_null());
+    }
+
+    #[test]
+    fn test_derived_datagroup() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let global_registry = DataGroupRegistry::get_global_registry().read();
+        let entry = global_registry.get_entry::<TestDerivedDataGroup>();
+        assert_eq!(entry.name, "TestDerivedDataGroup");
+        assert_eq!(entry.init_desc, InitDesc::OptionalArg);
+        assert!(entry.clone_func.is_some());
+
+        // The factory defaults to `Default`
+        let mut datagroup = create_datagroup!(TestDerivedDataGroup);
+        assert_eq!(cast::<_, TestDerivedDataGroup>(&datagroup).num, 0);
+
+        datagroup.__init__(Some(Box::new(TestDerivedDataGroupArg { num: 3 })));
+        let copy = (entry.clone_func.unwrap())(datagroup.as_ref());
+        assert_eq!(cast::<_, TestDerivedDataGroup>(&copy).num, 3);
+
+        let mut visited_refs = 0;
+        datagroup.__visit_entity_refs__(&mut |_| visited_refs += 1);
+        assert_eq!(visited_refs, 1);
+
+        let mut spawn_desc = EntitySpawnDescription::default();
+        TestDerivedDataGroup::prepare_spawn(&mut spawn_desc, None);
+        assert!(matches!(
+            spawn_desc.get_datagroup::<TestDerivedDataGroup>(),
+            Some(DataGroupInitType::OptionalArg(None))
+        ));
+
+        // Both derived datagroups take the same init arg type
+        let mut datagroup = create_datagroup!(TestDerivedSharedArgDataGroup);
+        datagroup.__init__(Some(Box::new(TestDerivedDataGroupArg { num: 3 })));
+        assert_eq!(cast::<_, TestDerivedSharedArgDataGroup>(&datagroup).num, 6);
+
+        // Datagroups registered with a generic type are named after it
+        let entry = global_registry.get_entry::<TestGenericDataGroup<u64>>();
+        assert_eq!(entry.name, "TestGenericDataGroup<u64>");
+        assert_eq!(entry.id, get_id!(TestGenericDataGroup<u64>));
+
+        let mut datagroup = create_datagroup!(TestGenericDataGroup<u64>);
+        datagroup.__init__(None);
+        assert_eq!(
+            cast::<_, TestGenericDataGroup<u64>>(&datagroup).values,
+            [42]
+        );
+    }
+
+    fn manifest_test_entry(name: &'static str, name_crc: u32) -> DataGroupRegistryEntry {
+        DataGroupRegistryEntry {
+            name,
+            name_crc,
+            factory_func: <AnimationDataGroup as DatagroupDesc>::FACTORY,
+            in_place_factory_func: <AnimationDataGroup as DatagroupDesc>::IN_PLACE_FACTORY,
+            clone_func: None,
+            reflection: None,
+            layout: DataGroupLayout::new::<AnimationDataGroup>(),
+            init_desc: InitDesc::Arg,
+            id: 0,
+            set_id_fn: |_| {},
+        }
+    }
+
+    #[test]
+    fn test_datagroup_manifest_ids() {
+        let manifest = RegistryManifest::read_from(
+            "[datagroups]
+0 0x0000001e C
+1 0x0000000a A
+2 0x00000014 B"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        // B is gone and D is new
+        let mut registry = DataGroupRegistry::from_entries(vec![
+            manifest_test_entry("A", 10),
+            manifest_test_entry("C", 30),
+            manifest_test_entry("D", 40),
+        ]);
+        registry.init_with_manifest(Some(&manifest));
+
+        // The others keep their ids, leaving a hole where B was, and D goes after them
+        let id_of = |name| registry.get_entry_by_name(name).unwrap().id;
+        assert_eq!(id_of("C"), 0);
+        assert_eq!(id_of("A"), 1);
+        assert_eq!(id_of("D"), 3);
+        assert!(registry.try_get_entry_by_id(2).is_none());
+        assert_eq!(registry.get_entry_by_id(3).name, "D");
+        assert_eq!(registry.get_datagroup_count(), 3);
+    }
 }
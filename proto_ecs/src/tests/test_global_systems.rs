@@ -3,7 +3,7 @@ mod global_system_test {
     use crate::app::App;
     use crate::core::casting::cast_mut;
     use crate::entities::entity_spawn_desc::EntitySpawnDescription;
-    use crate::entities::entity_system::{EntitiesVec, EntityMap, World};
+    use crate::entities::entity_system::{EntityMap, World};
     use crate::get_id;
     use crate::systems::global_systems::GlobalSystemRegistry;
     use crate::tests::shared_datagroups::sdg::{AnimationDataGroup, MeshDataGroup};
@@ -12,7 +12,7 @@ mod global_system_test {
     #[test]
     fn test_global_system_registration() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
         let gs_registry = GlobalSystemRegistry::get_global_registry().read();
 
@@ -44,7 +44,7 @@ mod global_system_test {
     #[test]
     fn test_global_system_initialization() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let gs_registry = GlobalSystemRegistry::get_global_registry().read();
@@ -74,7 +74,7 @@ mod global_system_test {
     #[test]
     fn test_global_system_run() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let gs_registry = GlobalSystemRegistry::get_global_registry().read();
@@ -82,14 +82,11 @@ mod global_system_test {
         let mut test_gs = gs_registry.create::<Test>();
         let test_gs_entry = gs_registry.get_entry::<Test>();
         let entity_map = EntityMap::new();
-        let entity_vec = EntitiesVec::default();
+        let entity_vec = vec![];
         let world = World::new(69);
 
-        for f in test_gs_entry.functions {
-            match f {
-                Some(f) => (f)(&mut test_gs, &world, &entity_map, &entity_vec),
-                _ => {}
-            }
+        for f in test_gs_entry.functions.into_iter().flatten() {
+            (f)(&mut test_gs, &world, &entity_map, &entity_vec);
         }
 
         let test_gs: &mut Test = cast_mut(&mut test_gs);
@@ -100,7 +97,7 @@ mod global_system_test {
     #[should_panic]
     fn test_simple_prepare_should_panic() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         // check that you can register a global system with simple prepare and
@@ -114,7 +111,7 @@ mod global_system_test {
     #[test]
     fn test_simple_prepare_should_not_panic() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         // check that you can register a global system with simple prepare and
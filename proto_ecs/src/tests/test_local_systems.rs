@@ -1,11 +1,16 @@
 #[cfg(test)]
 mod local_system_test {
     use super::super::shared_datagroups::sdg::*;
+    use crate::core::registry_manifest::RegistryManifest;
     use crate::entities::entity::DataGroupIndexingType;
     use crate::entities::entity_spawn_desc::EntitySpawnDescription;
     use crate::entities::entity_system::World;
-    use crate::systems::local_systems::LocalSystemDesc;
-    use crate::tests::shared_local_systems::sls::{Test, TestOpt};
+    use crate::systems::common::{Dependency, SystemKind, SystemOrderError, SystemOrderProblem};
+    use crate::systems::local_systems::{
+        LocalSystemDesc, LocalSystemRegistryEntry, SystemClassID, TempRegistryLambda,
+        EMPTY_CHUNK_STAGE_MAP, EMPTY_STAGE_MAP, INVALID_SYSTEM_CLASS_ID,
+    };
+    use crate::tests::shared_local_systems::sls::{Test, TestAdder, TestFunctionSystem, TestOpt};
     use crate::{
         app::App, core::casting::cast, get_id, systems::local_systems::LocalSystemRegistry,
     };
@@ -14,24 +19,22 @@ mod local_system_test {
     #[test]
     fn test_local_system_registration() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let dg_registry = DataGroupRegistry::get_global_registry().read();
         let ls_registry = LocalSystemRegistry::get_global_registry().read();
         let mesh = dg_registry.create::<MeshDataGroup>();
         let anim = dg_registry.create::<AnimationDataGroup>();
-        let mut dgs = vec![anim, mesh];
+        let mut dgs = [anim, mesh];
+        let dg_ptrs: Vec<*mut dyn DataGroup> = dgs.iter_mut().map(|dg| dg.as_mut() as _).collect();
         let indices: [DataGroupIndexingType; 2] = [0, 1];
         let entry = ls_registry.get_entry::<Test>();
 
         assert_eq!(entry.id, get_id!(Test));
 
-        for f in entry.functions {
-            match f {
-                Some(f) => (f)(&World::new(0), 0, &indices, &mut dgs),
-                _ => {}
-            }
+        for f in entry.functions.into_iter().flatten() {
+            (f)(&World::new(0), 0, &indices, &dg_ptrs);
         }
 
         let anim: &AnimationDataGroup = cast(&dgs[0]);
@@ -54,7 +57,7 @@ mod local_system_test {
     #[test]
     fn test_local_system_entity_spawn_desc() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         {
@@ -108,7 +111,7 @@ mod local_system_test {
     #[should_panic]
     fn test_local_system_missing_dependency() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
 
         let mut spawn_desc = EntitySpawnDescription::default();
@@ -119,7 +122,7 @@ mod local_system_test {
     #[test]
     fn test_local_system_before_after() {
         if !App::is_initialized() {
-            App::initialize();
+            App::initialize().expect("Failed to initialize the app");
         }
         let global_registry = LocalSystemRegistry::get_global_registry().read();
         let entry = global_registry.get_entry::<Test>();
@@ -135,4 +138,182 @@ mod local_system_test {
             "Wrong number of `before` dependencies"
         );
     }
+
+    #[test]
+    fn test_local_system_attribute() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let dg_registry = DataGroupRegistry::get_global_registry().read();
+        let ls_registry = LocalSystemRegistry::get_global_registry().read();
+        let entry = ls_registry.get_entry::<TestFunctionSystem>();
+
+        assert_eq!(entry.id, get_id!(TestFunctionSystem));
+        assert_eq!(entry.name, "TestFunctionSystem");
+        assert_eq!(entry.after, [<TestAdder as LocalSystemDesc>::NAME_CRC]);
+        assert!(entry.before.is_empty());
+        assert!(matches!(
+            entry.dependencies[..],
+            [Dependency::OptionalDG(mesh), Dependency::DataGroup(number)]
+                if mesh == get_id!(MeshDataGroup) && number == get_id!(TestNumberDataGroup)
+        ));
+        assert!(entry.functions[0].is_some() && entry.functions[1].is_some());
+        assert!(entry.functions[2].is_none());
+
+        // Parameters are passed in the order of the function, not of the dependencies
+        let mut dgs = [
+            dg_registry.create::<TestNumberDataGroup>(),
+            dg_registry.create::<MeshDataGroup>(),
+        ];
+        let dg_ptrs: Vec<*mut dyn DataGroup> = dgs.iter_mut().map(|dg| dg.as_mut() as _).collect();
+        let indices: [DataGroupIndexingType; 2] = [1, 0];
+        (entry.functions[0].unwrap())(&World::new(0), 7, &indices, &dg_ptrs);
+
+        let number: &TestNumberDataGroup = cast(&dgs[0]);
+        assert_eq!(number.num, 107);
+
+        // Only required dependencies are added to spawn descriptions
+        let mut spawn_desc = EntitySpawnDescription::default();
+        TestFunctionSystem::simple_prepare(&mut spawn_desc);
+        assert!(spawn_desc.get_local_system::<TestFunctionSystem>());
+        assert!(spawn_desc.get_datagroup::<TestNumberDataGroup>().is_some());
+        assert!(spawn_desc.get_datagroup::<MeshDataGroup>().is_none());
+    }
+
+    /// Entry for a registry built by hand, with its name crc given instead of computed
+    fn ordering_entry(
+        name: &'static str,
+        name_crc: u32,
+        before: Vec<SystemClassID>,
+        after: Vec<SystemClassID>,
+    ) -> LocalSystemRegistryEntry {
+        LocalSystemRegistryEntry {
+            id: INVALID_SYSTEM_CLASS_ID,
+            name,
+            name_crc,
+            dependencies: vec![],
+            functions: EMPTY_STAGE_MAP,
+            chunk_functions: EMPTY_CHUNK_STAGE_MAP,
+            before,
+            after,
+            set_id_fn: |_| {},
+        }
+    }
+
+    fn init_registry(
+        entries: Vec<LocalSystemRegistryEntry>,
+        manifest: Option<&RegistryManifest>,
+    ) -> Result<LocalSystemRegistry, SystemOrderError> {
+        let mut registry = LocalSystemRegistry::new();
+        registry.init_with_manifest(
+            entries
+                .into_iter()
+                .map(|entry| {
+                    Box::new(move |registry: &mut LocalSystemRegistry| registry.register(entry))
+                        as TempRegistryLambda
+                })
+                .collect(),
+            manifest,
+        )?;
+        Ok(registry)
+    }
+
+    fn registry_names(registry: &LocalSystemRegistry) -> Vec<&'static str> {
+        registry
+            .get_entries()
+            .iter()
+            .map(|entry| entry.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_local_system_ordering() {
+        // C runs after A and B runs before A, so the order is B, A, C
+        let registry = init_registry(
+            vec![
+                ordering_entry("A", 10, vec![], vec![]),
+                ordering_entry("B", 20, vec![10], vec![]),
+                ordering_entry("C", 30, vec![], vec![10]),
+            ],
+            None,
+        )
+        .unwrap();
+        let names: Vec<&str> = (0..3).map(|id| registry.get_entry_by_id(id).name).collect();
+        assert_eq!(names, ["B", "A", "C"]);
+        assert!(registry.is_initialized());
+    }
+
+    #[test]
+    fn test_local_system_ordering_errors() {
+        let err = init_registry(
+            vec![
+                // A -> B -> C -> A is a cycle
+                ordering_entry("A", 10, vec![20], vec![]),
+                ordering_entry("B", 20, vec![30], vec![]),
+                ordering_entry("C", 30, vec![10], vec![]),
+                // D depends on systems that are not registered
+                ordering_entry("D", 40, vec![99], vec![98]),
+                // C2 has the same crc as C
+                ordering_entry("C2", 30, vec![], vec![]),
+            ],
+            None,
+        )
+        .unwrap_err();
+
+        assert_eq!(err.kind, SystemKind::Local);
+        assert_eq!(
+            err.problems,
+            [
+                SystemOrderProblem::CrcCollision {
+                    first: "C",
+                    second: "C2",
+                    crc: 30
+                },
+                SystemOrderProblem::UnknownBefore {
+                    system: "D",
+                    crc: 99
+                },
+                SystemOrderProblem::UnknownAfter {
+                    system: "D",
+                    crc: 98
+                },
+                SystemOrderProblem::Cycle(vec!["A", "B", "C", "A"]),
+            ]
+        );
+        assert!(err
+            .to_string()
+            .contains("Cyclic dependencies: A -> B -> C -> A"));
+    }
+
+    #[test]
+    fn test_local_system_manifest_ids() {
+        let entries = |c_after_a: bool| {
+            let c_after = if c_after_a { vec![10] } else { vec![] };
+            vec![
+                ordering_entry("A", 10, vec![], vec![]),
+                ordering_entry("B", 20, vec![], vec![]),
+                ordering_entry("C", 30, vec![], c_after),
+            ]
+        };
+
+        // Without a manifest, independent systems are sorted by name crc
+        let registry = init_registry(entries(false), None).unwrap();
+        assert_eq!(registry_names(&registry), ["A", "B", "C"]);
+
+        let manifest = RegistryManifest::read_from(
+            "[local_systems]
+0 0x0000001e C
+1 0x0000000a A
+2 0x00000014 B"
+                .as_bytes(),
+        )
+        .unwrap();
+        let registry = init_registry(entries(false), Some(&manifest)).unwrap();
+        assert_eq!(registry_names(&registry), ["C", "A", "B"]);
+
+        // Dependencies win over the manifest
+        let registry = init_registry(entries(true), Some(&manifest)).unwrap();
+        assert_eq!(registry_names(&registry), ["A", "C", "B"]);
+    }
 }
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod schedule_test {
+    use crate::app::App;
+    use crate::systems::schedule::{DataGroupDependency, Schedule};
+
+    #[test]
+    fn test_schedule() {
+        if !App::is_initialized() {
+            App::initialize().expect("Failed to initialize the app");
+        }
+
+        let schedule = Schedule::from_global_registries();
+        let test = schedule
+            .local_systems
+            .iter()
+            .find(|system| system.name == "Test")
+            .unwrap();
+        assert_eq!(test.stages, [0, 1]);
+        assert_eq!(test.before, ["TestOpt"]);
+        assert!(test.after.is_empty());
+
+        let test_opt = schedule
+            .local_systems
+            .iter()
+            .find(|system| system.name == "TestOpt")
+            .unwrap();
+        assert_eq!(
+            test_opt.dependencies,
+            [
+                DataGroupDependency {
+                    datagroup: "AnimationDataGroup",
+                    optional: false
+                },
+                DataGroupDependency {
+                    datagroup: "MeshDataGroup",
+                    optional: true
+                }
+            ]
+        );
+
+        // Systems of a stage are listed in running order
+        let stage_0 = schedule
+            .stages
+            .iter()
+            .find(|stage| stage.stage == 0)
+            .unwrap();
+        let position = |name: &str| {
+            stage_0
+                .local_systems
+                .iter()
+                .position(|system| *system == name)
+                .unwrap()
+        };
+        assert!(position("Test") < position("TestOpt"));
+        assert!(position("TestAdder") < position("TestMultiplier"));
+        assert!(position("TestMultiplier") < position("TestAssertNumber4"));
+
+        let stage_42 = schedule
+            .stages
+            .iter()
+            .find(|stage| stage.stage == 42)
+            .unwrap();
+        let gs_position = |name: &str| {
+            stage_42
+                .global_systems
+                .iter()
+                .position(|system| *system == name)
+                .unwrap()
+        };
+        assert!(gs_position("TestBefore") < gs_position("Test"));
+        assert!(gs_position("Test") < gs_position("TestAfter"));
+
+        let mut dot = Vec::new();
+        schedule.write_dot_to(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph schedule {"));
+        assert!(dot.contains("\"0:local:Test\" -> \"0:local:TestOpt\";"));
+        assert!(dot.contains("\"42:global:TestBefore\" -> \"42:global:Test\";"));
+
+        let mut json = Vec::new();
+        schedule.write_json_to(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains("\"name\":\"Test\",\"stages\":[0, 1]"));
+        assert!(json.contains("\"before\":[\"TestOpt\"]"));
+    }
+}
@@ -0,0 +1,57 @@
+/// Initializes an app whose global systems can't be ordered. The registries are
+/// globals that can only be initialized once, so this is a single test in its own binary
+use proto_ecs::core::casting::CanCast;
+use proto_ecs::data_group::DataGroupRegistry;
+use proto_ecs::prelude::*;
+use proto_ecs::systems::common::SystemOrderProblem;
+use proto_ecs::systems::global_systems::*;
+use proto_ecs::systems::local_systems::LocalSystemRegistry;
+
+// -- First and Second have to run before each other
+#[derive(Debug, CanCast)]
+pub struct First;
+
+fn first_factory() -> Box<dyn GlobalSystem> {
+    Box::new(First)
+}
+
+register_global_system! {
+    First,
+    factory = first_factory,
+    before = (Second)
+}
+
+impl FirstGlobalSystem for First {}
+
+#[derive(Debug, CanCast)]
+pub struct Second;
+
+fn second_factory() -> Box<dyn GlobalSystem> {
+    Box::new(Second)
+}
+
+register_global_system! {
+    Second,
+    factory = second_factory,
+    before = (First)
+}
+
+impl SecondGlobalSystem for Second {}
+
+#[test]
+fn test_app_initialize_error() {
+    // Failing again instead of panicking on registries that were already initialized
+    for _ in 0..2 {
+        let err = App::initialize().expect_err("Global systems can't be ordered");
+        assert!(matches!(
+            err.problems.as_slice(),
+            [SystemOrderProblem::Cycle(_)]
+        ));
+
+        assert!(!App::is_initialized());
+        assert!(!GlobalSystemRegistry::get_global_registry().read().is_initialized());
+        // Registries initialized before the failure keep their ids
+        assert!(DataGroupRegistry::get_global_registry().read().is_initialized());
+        assert!(LocalSystemRegistry::get_global_registry().read().is_initialized());
+    }
+}
@@ -0,0 +1,118 @@
+/// Runs the whole app loop with the headless platform. The app is a global that can
+/// only run once, so this is a single test in its own binary
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use lazy_static::lazy_static;
+use proto_ecs::core::locking::RwLock;
+use proto_ecs::core::platform::headless::headless_window::HeadlessWindow;
+use proto_ecs::core::rendering::render_api::API;
+use proto_ecs::core::windowing::events::{Event, Type};
+use proto_ecs::core::windowing::window_manager::WindowManager;
+use proto_ecs::prelude::*;
+
+static UPDATES: AtomicU32 = AtomicU32::new(0);
+static DETACHED: AtomicU32 = AtomicU32::new(0);
+
+lazy_static! {
+    static ref EVENTS: RwLock<Vec<(u32, Type)>> = RwLock::new(vec![]);
+    static ref OVERLAY_EVENTS: RwLock<Vec<(u32, Type)>> = RwLock::new(vec![]);
+}
+
+struct CountingLayer;
+
+impl Layer for CountingLayer {
+    fn on_attach(&mut self) {}
+
+    fn on_detach(&mut self) {
+        DETACHED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn update(&mut self, _delta_time: f32) {
+        let frame = UPDATES.fetch_add(1, Ordering::SeqCst) + 1;
+
+        // Events can be pushed while the app runs too
+        if frame == 3 {
+            HeadlessWindow::with_instance(|window| {
+                assert_eq!(window.get_frame_count(), 3);
+                window.push_event(Event::new(Type::MouseMoved { x: 1.0, y: 2.0 }));
+            });
+        }
+    }
+
+    fn on_event(&mut self, event: &mut Event) {
+        // Events are sent before updating layers
+        let frame = UPDATES.load(Ordering::SeqCst) + 1;
+        EVENTS.write().push((frame, event.get_type().clone()));
+    }
+}
+
+struct OverlayLayer;
+
+impl Layer for OverlayLayer {
+    fn on_attach(&mut self) {}
+
+    fn on_detach(&mut self) {
+        DETACHED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn update(&mut self, _delta_time: f32) {}
+
+    fn on_event(&mut self, event: &mut Event) {
+        let frame = UPDATES.load(Ordering::SeqCst) + 1;
+        OVERLAY_EVENTS.write().push((frame, event.get_type().clone()));
+    }
+}
+
+#[test]
+fn test_headless_app() {
+    App::initialize().expect("Failed to initialize the app");
+    WindowManager::init(
+        WindowBuilder::new().with_width(640).with_height(480),
+        Platforms::Headless,
+    );
+    Render::init();
+
+    App::add_layer(Box::new(CountingLayer));
+    App::add_overlay(Box::new(OverlayLayer));
+    // Layers are attached after sending the events of the first frame,
+    // so they only get events from the second frame on
+    HeadlessWindow::with_instance(|window| {
+        window.set_frame_limit(Some(5));
+        window.push_event_at_frame(2, Event::new(Type::WindowFocus));
+        window.push_event_at_frame(
+            3,
+            Event::new(Type::WindowResize {
+                new_width: 800,
+                new_height: 600,
+            }),
+        );
+    });
+
+    App::run_application();
+    // Headless apps don't wait for the render thread, it might stop before starting
+    Render::shutdown();
+    assert!(matches!(Render::get_current_api(), API::None));
+
+    assert_eq!(UPDATES.load(Ordering::SeqCst), 5);
+    assert_eq!(DETACHED.load(Ordering::SeqCst), 2);
+    let expected_events = vec![
+        (2, Type::WindowFocus),
+        (
+            3,
+            Type::WindowResize {
+                new_width: 800,
+                new_height: 600,
+            },
+        ),
+        (4, Type::MouseMoved { x: 1.0, y: 2.0 }),
+        (5, Type::WindowClose),
+    ];
+    // Every layer and overlay gets each event once
+    assert_eq!(*EVENTS.read(), expected_events);
+    assert_eq!(*OVERLAY_EVENTS.read(), expected_events);
+
+    let window_manager = WindowManager::get().read();
+    let window = window_manager.get_window();
+    assert_eq!(window.get_width(), 800);
+    assert_eq!(window.get_heigth(), 600);
+}
@@ -149,7 +149,7 @@ impl ModelRotatorLSLocalSystem for ModelRotatorLS {
 }
 
 fn main() {
-    App::initialize();
+    App::initialize().unwrap_or_else(|err| panic!("{err}"));
     WindowManager::init(
         WindowBuilder::new()
             .with_height(720)
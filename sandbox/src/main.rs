@@ -130,7 +130,15 @@ impl Layer for MyLayer {
 }
 
 fn main() {
-    App::initialize();
+    // Show engine diagnostics, filter them with RUST_LOG
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .init();
+
+    App::initialize().unwrap_or_else(|err| panic!("{err}"));
     WindowManager::init(
         WindowBuilder::new()
             .with_height(720)